@@ -0,0 +1,581 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sequence::{reverse_complementary, Direction, Topology};
+
+/// Affine gap scores for local alignment.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AlignmentScoring {
+    pub match_score: i32,
+    pub mismatch: i32,
+    pub gap_open: i32,
+    pub gap_extend: i32,
+}
+
+impl Default for AlignmentScoring {
+    fn default() -> Self {
+        Self {
+            match_score: 2,
+            mismatch: -3,
+            gap_open: -5,
+            gap_extend: -2,
+        }
+    }
+}
+
+/// One column of an alignment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlignOp {
+    Match,
+    Mismatch,
+    /// Query base missing from the reference.
+    Insertion,
+    /// Reference base missing from the query.
+    Deletion,
+}
+
+/// Local alignment of a query to a reference.
+#[derive(Debug, Clone)]
+pub struct Alignment {
+    pub score: i32,
+    /// Aligned part of the query (0-based, end exclusive).
+    pub query_span: [usize; 2],
+    /// 0-based reference position of the first aligned base.
+    pub reference_start: usize,
+    pub ops: Vec<AlignOp>,
+}
+
+/// Alignment column with the positions it covers. Insertions point at the
+/// reference base they are inserted in front of.
+#[derive(Debug, Clone, Copy)]
+pub struct AlignedColumn {
+    pub op: AlignOp,
+    pub query: Option<usize>,
+    pub reference: usize,
+}
+
+impl Alignment {
+    /// Columns with query and reference positions, wrapping reference positions
+    /// around the origin of a reference of the given length.
+    pub fn columns(&self, reference_length: usize) -> Vec<AlignedColumn> {
+        let mut query = self.query_span[0];
+        let mut reference = self.reference_start;
+        self.ops
+            .iter()
+            .map(|&op| {
+                let column = AlignedColumn {
+                    op,
+                    query: (op != AlignOp::Deletion).then_some(query),
+                    reference: reference % reference_length,
+                };
+                if op != AlignOp::Deletion {
+                    query += 1;
+                }
+                if op != AlignOp::Insertion {
+                    reference += 1;
+                }
+                column
+            })
+            .collect()
+    }
+
+    /// Number of reference bases covered.
+    pub fn reference_length(&self) -> usize {
+        self.ops
+            .iter()
+            .filter(|op| **op != AlignOp::Insertion)
+            .count()
+    }
+
+    /// Fraction of columns that are matches.
+    pub fn identity(&self) -> f64 {
+        if self.ops.is_empty() {
+            return 0.0;
+        }
+        let matches = self.ops.iter().filter(|op| **op == AlignOp::Match).count();
+        matches as f64 / self.ops.len() as f64
+    }
+}
+
+// Traceback bits: 2 bits for the source of H, one bit each for whether E and F
+// were extended rather than opened.
+const FROM_STOP: u8 = 0;
+const FROM_DIAGONAL: u8 = 1;
+const FROM_E: u8 = 2;
+const FROM_F: u8 = 3;
+const E_EXTENDED: u8 = 4;
+const F_EXTENDED: u8 = 8;
+
+/// Banded Smith-Waterman with affine gaps (Gotoh).
+///
+/// Only cells with `j - i` within `band` of `diagonal` are filled, where `i`
+/// indexes the query and `j` the reference. Returns `None` if nothing aligns.
+pub fn banded_smith_waterman(
+    query: &[u8],
+    reference: &[u8],
+    diagonal: i64,
+    band: usize,
+    scoring: &AlignmentScoring,
+) -> Option<Alignment> {
+    let n = query.len();
+    let m = reference.len() as i64;
+    let width = 2 * band + 1;
+    const NEG: i32 = i32::MIN / 4;
+
+    // Column k of row i is reference position j = i + diagonal - band + k (1-based DP indices)
+    let to_j = |i: usize, k: usize| i as i64 + diagonal - band as i64 + k as i64;
+
+    let mut h = vec![NEG; (n + 1) * width];
+    let mut e = vec![NEG; (n + 1) * width];
+    let mut f = vec![NEG; (n + 1) * width];
+    let mut trace = vec![FROM_STOP; (n + 1) * width];
+    let at = |i: usize, k: usize| i * width + k;
+
+    for k in 0..width {
+        let j = to_j(0, k);
+        if (0..=m).contains(&j) {
+            h[at(0, k)] = 0;
+        }
+    }
+
+    let mut best = (0, 0, 0);
+    for i in 1..=n {
+        for k in 0..width {
+            let j = to_j(i, k);
+            if j < 0 || j > m {
+                continue;
+            }
+            if j == 0 {
+                h[at(i, k)] = 0;
+                continue;
+            }
+            let mut bits = 0;
+
+            // Gap in the query, coming from the left (same row, k - 1)
+            if k > 0 {
+                let open = h[at(i, k - 1)] + scoring.gap_open;
+                let extend = e[at(i, k - 1)] + scoring.gap_extend;
+                if extend > open {
+                    e[at(i, k)] = extend;
+                    bits |= E_EXTENDED;
+                } else {
+                    e[at(i, k)] = open;
+                }
+            }
+            // Gap in the reference, coming from above (previous row, k + 1)
+            if k + 1 < width {
+                let open = h[at(i - 1, k + 1)] + scoring.gap_open;
+                let extend = f[at(i - 1, k + 1)] + scoring.gap_extend;
+                if extend > open {
+                    f[at(i, k)] = extend;
+                    bits |= F_EXTENDED;
+                } else {
+                    f[at(i, k)] = open;
+                }
+            }
+
+            let substitution = if query[i - 1] == reference[j as usize - 1] {
+                scoring.match_score
+            } else {
+                scoring.mismatch
+            };
+            let diagonal_score = h[at(i - 1, k)] + substitution;
+
+            let (score, source) = [
+                (0, FROM_STOP),
+                (diagonal_score, FROM_DIAGONAL),
+                (e[at(i, k)], FROM_E),
+                (f[at(i, k)], FROM_F),
+            ]
+            .into_iter()
+            .fold((0, FROM_STOP), |best, candidate| {
+                if candidate.0 > best.0 {
+                    candidate
+                } else {
+                    best
+                }
+            });
+            h[at(i, k)] = score;
+            trace[at(i, k)] = bits | source;
+            if score > best.0 {
+                best = (score, i, k);
+            }
+        }
+    }
+
+    let (score, mut i, mut k) = best;
+    if score <= 0 {
+        return None;
+    }
+    let query_end = i;
+    let reference_end = to_j(i, k) as usize;
+
+    // Walk back, tracking which matrix we are in
+    let mut ops = Vec::new();
+    let mut state = trace[at(i, k)] & 3;
+    while i > 0 && state != FROM_STOP {
+        let bits = trace[at(i, k)];
+        match state {
+            FROM_DIAGONAL => {
+                let j = to_j(i, k) as usize;
+                ops.push(if query[i - 1] == reference[j - 1] {
+                    AlignOp::Match
+                } else {
+                    AlignOp::Mismatch
+                });
+                i -= 1;
+                state = trace[at(i, k)] & 3;
+            }
+            FROM_E => {
+                ops.push(AlignOp::Deletion);
+                k -= 1;
+                state = if bits & E_EXTENDED != 0 {
+                    FROM_E
+                } else {
+                    trace[at(i, k)] & 3
+                };
+            }
+            _ => {
+                ops.push(AlignOp::Insertion);
+                i -= 1;
+                k += 1;
+                state = if bits & F_EXTENDED != 0 {
+                    FROM_F
+                } else {
+                    trace[at(i, k)] & 3
+                };
+            }
+        }
+    }
+    ops.reverse();
+
+    Some(Alignment {
+        score,
+        query_span: [i, query_end],
+        reference_start: reference_end - ops.iter().filter(|op| **op != AlignOp::Insertion).count(),
+        ops,
+    })
+}
+
+/// Global alignment with affine gaps (Gotoh) over the full matrix, so only
+/// meant for short sequences. End gaps are scored like any other gap.
+pub fn global_alignment(
+    query: &[u8],
+    reference: &[u8],
+    scoring: &AlignmentScoring,
+) -> Vec<AlignOp> {
+    let n = query.len();
+    let m = reference.len();
+    let width = m + 1;
+    const NEG: i32 = i32::MIN / 4;
+    let at = |i: usize, j: usize| i * width + j;
+
+    let mut h = vec![NEG; (n + 1) * width];
+    let mut e = vec![NEG; (n + 1) * width];
+    let mut f = vec![NEG; (n + 1) * width];
+    let mut trace = vec![FROM_STOP; (n + 1) * width];
+    h[0] = 0;
+    for j in 1..=m {
+        e[at(0, j)] = scoring.gap_open + (j as i32 - 1) * scoring.gap_extend;
+        h[at(0, j)] = e[at(0, j)];
+        trace[at(0, j)] = FROM_E | if j > 1 { E_EXTENDED } else { 0 };
+    }
+    for i in 1..=n {
+        f[at(i, 0)] = scoring.gap_open + (i as i32 - 1) * scoring.gap_extend;
+        h[at(i, 0)] = f[at(i, 0)];
+        trace[at(i, 0)] = FROM_F | if i > 1 { F_EXTENDED } else { 0 };
+    }
+
+    for i in 1..=n {
+        for j in 1..=m {
+            let mut bits = 0;
+            let open = h[at(i, j - 1)] + scoring.gap_open;
+            let extend = e[at(i, j - 1)] + scoring.gap_extend;
+            e[at(i, j)] = if extend > open {
+                bits |= E_EXTENDED;
+                extend
+            } else {
+                open
+            };
+            let open = h[at(i - 1, j)] + scoring.gap_open;
+            let extend = f[at(i - 1, j)] + scoring.gap_extend;
+            f[at(i, j)] = if extend > open {
+                bits |= F_EXTENDED;
+                extend
+            } else {
+                open
+            };
+            let substitution = if query[i - 1] == reference[j - 1] {
+                scoring.match_score
+            } else {
+                scoring.mismatch
+            };
+            let (score, source) = [(e[at(i, j)], FROM_E), (f[at(i, j)], FROM_F)]
+                .into_iter()
+                .fold(
+                    (h[at(i - 1, j - 1)] + substitution, FROM_DIAGONAL),
+                    |best, candidate| {
+                        if candidate.0 > best.0 {
+                            candidate
+                        } else {
+                            best
+                        }
+                    },
+                );
+            h[at(i, j)] = score;
+            trace[at(i, j)] = bits | source;
+        }
+    }
+
+    let (mut i, mut j) = (n, m);
+    let mut ops = Vec::with_capacity(n.max(m));
+    let mut state = trace[at(i, j)] & 3;
+    while i > 0 || j > 0 {
+        let bits = trace[at(i, j)];
+        match state {
+            FROM_DIAGONAL => {
+                ops.push(if query[i - 1] == reference[j - 1] {
+                    AlignOp::Match
+                } else {
+                    AlignOp::Mismatch
+                });
+                i -= 1;
+                j -= 1;
+                state = trace[at(i, j)] & 3;
+            }
+            FROM_E => {
+                ops.push(AlignOp::Deletion);
+                j -= 1;
+                state = if bits & E_EXTENDED != 0 {
+                    FROM_E
+                } else {
+                    trace[at(i, j)] & 3
+                };
+            }
+            _ => {
+                ops.push(AlignOp::Insertion);
+                i -= 1;
+                state = if bits & F_EXTENDED != 0 {
+                    FROM_F
+                } else {
+                    trace[at(i, j)] & 3
+                };
+            }
+        }
+    }
+    ops.reverse();
+    ops
+}
+
+/// 2-bit encoding of a k-mer, `None` if it contains anything but A, C, G, T.
+pub fn encode_kmer(kmer: &[u8]) -> Option<u64> {
+    kmer.iter().try_fold(0u64, |acc, b| {
+        let code = match b {
+            b'A' => 0,
+            b'C' => 1,
+            b'G' => 2,
+            b'T' | b'U' => 3,
+            _ => return None,
+        };
+        Some(acc << 2 | code)
+    })
+}
+
+/// K-mer index of a reference, used to seed banded alignments.
+pub struct ReferenceIndex {
+    sequence: Vec<u8>,
+    topology: Topology,
+    k: usize,
+    kmers: HashMap<u64, Vec<usize>>,
+}
+
+/// Alignment of a query to one strand of an indexed reference. Query positions
+/// refer to the query as aligned, reverse complemented for the reverse strand.
+#[derive(Debug, Clone)]
+pub struct ReferenceHit {
+    pub strand: Direction,
+    pub query: Vec<u8>,
+    pub alignment: Alignment,
+}
+
+impl ReferenceIndex {
+    pub fn new(sequence: &str, topology: Topology, k: usize) -> Self {
+        let sequence = sequence.to_ascii_uppercase().into_bytes();
+        let k = k.clamp(4, 32);
+        let mut kmers: HashMap<u64, Vec<usize>> = HashMap::new();
+        let starts = match topology {
+            Topology::Linear => sequence.len().saturating_sub(k - 1),
+            Topology::Circular => sequence.len(),
+        };
+        for start in 0..starts {
+            let kmer: Vec<u8> = (start..start + k)
+                .map(|i| sequence[i % sequence.len()])
+                .collect();
+            if let Some(code) = encode_kmer(&kmer) {
+                kmers.entry(code).or_default().push(start);
+            }
+        }
+        Self {
+            sequence,
+            topology,
+            k,
+            kmers,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.sequence.len()
+    }
+
+    /// Reference offsets (`reference - query`) of shared k-mers, one per
+    /// cluster within a band, with the number of k-mers supporting it. Most
+    /// supported first.
+    fn seed_diagonals(&self, query: &[u8], band: usize) -> Vec<(i64, usize)> {
+        let length = self.sequence.len() as i64;
+        let bucket_size = band.max(1) as i64;
+        let mut buckets: HashMap<i64, Vec<i64>> = HashMap::new();
+        for (i, kmer) in query.windows(self.k).enumerate() {
+            let Some(hits) = encode_kmer(kmer).and_then(|code| self.kmers.get(&code)) else {
+                continue;
+            };
+            for &hit in hits {
+                let diagonal = match self.topology {
+                    Topology::Linear => hit as i64 - i as i64,
+                    Topology::Circular => (hit as i64 - i as i64).rem_euclid(length),
+                };
+                buckets
+                    .entry(diagonal.div_euclid(bucket_size))
+                    .or_default()
+                    .push(diagonal);
+            }
+        }
+        let mut diagonals: Vec<(i64, usize)> = buckets
+            .into_values()
+            .map(|mut diagonals| {
+                diagonals.sort_unstable();
+                (diagonals[diagonals.len() / 2], diagonals.len())
+            })
+            .collect();
+        diagonals.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        diagonals
+    }
+
+    /// Align a query on one strand around a diagonal. On circular references
+    /// the alignment may run across the origin.
+    fn align_at(
+        &self,
+        query: &[u8],
+        diagonal: i64,
+        strand: Direction,
+        band: usize,
+        scoring: &AlignmentScoring,
+    ) -> Option<ReferenceHit> {
+        let length = self.sequence.len() as i64;
+        let window_start = match self.topology {
+            Topology::Linear => (diagonal - band as i64).max(0),
+            Topology::Circular => diagonal - band as i64,
+        };
+        let window_end = match self.topology {
+            Topology::Linear => (diagonal + query.len() as i64 + band as i64).min(length),
+            Topology::Circular => diagonal + query.len() as i64 + band as i64,
+        };
+        let window: Vec<u8> = (window_start..window_end)
+            .map(|j| self.sequence[j.rem_euclid(length) as usize])
+            .collect();
+
+        let mut alignment =
+            banded_smith_waterman(query, &window, diagonal - window_start, band, scoring)?;
+        alignment.reference_start =
+            (window_start + alignment.reference_start as i64).rem_euclid(length) as usize;
+        Some(ReferenceHit {
+            strand,
+            query: query.to_vec(),
+            alignment,
+        })
+    }
+
+    /// Align a query on one strand around its most supported diagonal.
+    fn align_strand(
+        &self,
+        query: Vec<u8>,
+        strand: Direction,
+        band: usize,
+        scoring: &AlignmentScoring,
+    ) -> Option<ReferenceHit> {
+        let (diagonal, _) = *self.seed_diagonals(&query, band).first()?;
+        self.align_at(&query, diagonal, strand, band, scoring)
+    }
+
+    /// True if the reference bases of two hits overlap.
+    fn overlaps(&self, a: &Alignment, b: &Alignment) -> bool {
+        let length = self.sequence.len();
+        let offset = |from: usize, to: usize| (to + length - from) % length;
+        offset(a.reference_start, b.reference_start) < a.reference_length()
+            || offset(b.reference_start, a.reference_start) < b.reference_length()
+    }
+
+    /// Align a query to every place on both strands seeded by at least
+    /// `min_kmers` shared k-mers, best scoring first. Hits overlapping a
+    /// better one, on either strand, are dropped.
+    pub fn align_all(
+        &self,
+        query: &str,
+        band: usize,
+        scoring: &AlignmentScoring,
+        min_kmers: usize,
+    ) -> Vec<ReferenceHit> {
+        let query = query.to_ascii_uppercase();
+        let reverse = reverse_complementary(&query);
+        let mut hits: Vec<ReferenceHit> = Vec::new();
+        for (strand, bases) in [(Direction::Fwd, &query), (Direction::Rev, &reverse)] {
+            let bases = bases.as_bytes();
+            for (diagonal, support) in self.seed_diagonals(bases, band) {
+                if support < min_kmers.max(1) {
+                    break;
+                }
+                if let Some(hit) = self.align_at(bases, diagonal, strand, band, scoring) {
+                    hits.push(hit);
+                }
+            }
+        }
+        hits.sort_by_key(|hit| std::cmp::Reverse(hit.alignment.score));
+        let mut kept: Vec<ReferenceHit> = Vec::new();
+        for hit in hits {
+            let overlapping = kept
+                .iter()
+                .any(|k| self.overlaps(&k.alignment, &hit.alignment));
+            if !overlapping {
+                kept.push(hit);
+            }
+        }
+        kept
+    }
+
+    /// Align a query to both strands and keep the better scoring hit.
+    pub fn align(
+        &self,
+        query: &str,
+        band: usize,
+        scoring: &AlignmentScoring,
+    ) -> Option<ReferenceHit> {
+        let query = query.to_ascii_uppercase();
+        let forward = self.align_strand(query.clone().into_bytes(), Direction::Fwd, band, scoring);
+        let reverse = self.align_strand(
+            reverse_complementary(&query).into_bytes(),
+            Direction::Rev,
+            band,
+            scoring,
+        );
+        match (forward, reverse) {
+            (Some(f), Some(r)) => Some(if r.alignment.score > f.alignment.score {
+                r
+            } else {
+                f
+            }),
+            (f, r) => f.or(r),
+        }
+    }
+}
@@ -0,0 +1,573 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::alignment::{AlignOp, AlignmentScoring, ReferenceIndex};
+use crate::plasmid_file::{read_plasmid_file, Feature, PlasmidFile};
+use crate::sequence::{
+    complement_base, reverse_complementary, sanitize_sequence, Direction, Topology,
+};
+use crate::translation::{feature_sequence, translate_feature};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TransferSettings {
+    pub kmer_length: usize,
+    /// Allowed drift from the seeded diagonal, in bases.
+    pub band: usize,
+    /// Reference bases aligned on each side of a feature, so that short
+    /// features can be placed too.
+    pub flank: usize,
+    /// Features below this identity are not transferred.
+    pub min_identity: f64,
+    /// Smallest fraction of a feature that has to align to transfer it.
+    pub min_coverage: f64,
+    pub scoring: AlignmentScoring,
+}
+
+impl Default for TransferSettings {
+    fn default() -> Self {
+        Self {
+            kmer_length: 12,
+            band: 40,
+            flank: 20,
+            min_identity: 0.8,
+            min_coverage: 0.5,
+            scoring: AlignmentScoring::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferRequest {
+    /// Plasmid to annotate. Features it already has are not transferred again.
+    pub plasmid: PlasmidFile,
+    /// Annotated references, open in the app.
+    #[serde(default)]
+    pub references: Vec<PlasmidFile>,
+    /// Annotated references read from files.
+    #[serde(default)]
+    pub reference_paths: Vec<PathBuf>,
+    #[serde(default)]
+    pub settings: TransferSettings,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransferStatus {
+    /// The feature's bases are unchanged.
+    Exact,
+    /// Point differences or in-frame indels.
+    Edited,
+    /// Only part of the feature aligns, or a CDS lost its reading frame.
+    Broken,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferredFeature {
+    /// Feature placed on the plasmid.
+    pub feature: Feature,
+    /// Name of the reference the feature was taken from.
+    pub reference: String,
+    /// Span of the feature in the reference.
+    pub reference_span: [usize; 2],
+    /// Fraction of alignment columns over the feature that are matches.
+    /// Joined features count the bases between their parts too.
+    pub identity: f64,
+    /// Fraction of the feature's bases that align.
+    pub coverage: f64,
+    pub status: TransferStatus,
+    /// Why the feature is edited or broken.
+    pub issues: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferenceSummary {
+    pub name: String,
+    pub transferred: usize,
+    pub broken: usize,
+    /// Labels of the features that could not be placed.
+    pub missing: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferResult {
+    pub features: Vec<TransferredFeature>,
+    pub references: Vec<ReferenceSummary>,
+}
+
+/// Number of bases in a 1-based, inclusive span, which may run across the
+/// origin.
+fn span_length(span: [usize; 2], length: usize) -> usize {
+    if span[0] <= span[1] {
+        span[1] + 1 - span[0]
+    } else {
+        length + span[1] + 1 - span[0]
+    }
+}
+
+/// Top strand segments of a feature, the span if it is not joined.
+fn feature_segments(feature: &Feature) -> Vec<[usize; 2]> {
+    if feature.segments.is_empty() {
+        vec![feature.span]
+    } else {
+        feature.segments.clone()
+    }
+}
+
+/// Where a reference feature aligns in the plasmid.
+struct Projection {
+    /// Plasmid position (0-based) of every coding base of the feature that
+    /// aligns to a base.
+    positions: Vec<Option<usize>>,
+    /// True if the coding strand of the feature (the top strand for features
+    /// without direction) lies on the plasmid's bottom strand.
+    reversed: bool,
+    identity: f64,
+}
+
+/// Shared k-mers needed before a place of the plasmid is aligned to.
+const MIN_SEED_KMERS: usize = 2;
+
+/// Align a feature with flanking bases to every place of the plasmid it
+/// matches, so that features present more than once are all found.
+fn project_feature(
+    reference: &PlasmidFile,
+    feature: &Feature,
+    index: &ReferenceIndex,
+    settings: &TransferSettings,
+) -> Vec<Projection> {
+    let length = reference.sequence.len();
+    let bases = reference.sequence.as_bytes();
+    let circular = reference.topology == Topology::Circular;
+    let rev = feature.directionality == Some(Direction::Rev);
+    let segments = feature_segments(feature);
+    if segments.iter().flatten().any(|&i| i == 0 || i > length) {
+        return Vec::new();
+    }
+
+    // 0-based reference positions of the whole span and of the coding bases,
+    // in reading direction. Joined features are aligned with the bases
+    // between their parts, so that these do not show up as deletions.
+    let expand = |span: [usize; 2]| {
+        (span[0] - 1..span[0] - 1 + span_length(span, length)).map(move |i| i % length)
+    };
+    let mut region: Vec<usize> = expand(feature.span).collect();
+    let mut coding: Vec<usize> = segments.iter().flat_map(|s| expand(*s)).collect();
+    if rev {
+        region.reverse();
+        coding.reverse();
+    }
+    let (Some(&first), Some(&last)) = (region.first(), region.last()) else {
+        return Vec::new();
+    };
+
+    // Flanks in reading direction, cut at the ends of linear references
+    let step = |position: usize, forward: bool, n: usize| -> Option<usize> {
+        let target = if forward {
+            position as i64 + n as i64
+        } else {
+            position as i64 - n as i64
+        };
+        if circular {
+            Some(target.rem_euclid(length as i64) as usize)
+        } else {
+            (0..length as i64)
+                .contains(&target)
+                .then_some(target as usize)
+        }
+    };
+    let flank = settings.flank.min(length.saturating_sub(region.len()) / 2);
+    let upstream: Vec<usize> = (1..=flank)
+        .rev()
+        .filter_map(|n| step(first, rev, n))
+        .collect();
+    let downstream: Vec<usize> = (1..=flank).filter_map(|n| step(last, !rev, n)).collect();
+    let query: String = upstream
+        .iter()
+        .chain(&region)
+        .chain(&downstream)
+        .map(|&i| {
+            if rev {
+                complement_base(bases[i]) as char
+            } else {
+                bases[i] as char
+            }
+        })
+        .collect();
+
+    let region_range = upstream.len()..upstream.len() + region.len();
+    index
+        .align_all(&query, settings.band, &settings.scoring, MIN_SEED_KMERS)
+        .into_iter()
+        .filter_map(|hit| {
+            let query_length = query.len();
+            let hit_rev = hit.strand == Direction::Rev;
+            let mut mapped: HashMap<usize, usize> = HashMap::new();
+            let mut matches = 0;
+            let mut columns = 0;
+            let mut inside = false;
+            let mut pending = 0;
+            for column in hit.alignment.columns(index.len()) {
+                // Alignment columns in query order, which runs against the
+                // feature on the reverse strand
+                let original = column
+                    .query
+                    .map(|q| if hit_rev { query_length - 1 - q } else { q });
+                match original {
+                    Some(i) if region_range.contains(&i) => {
+                        // Plasmid bases inserted between two feature bases count too
+                        columns += 1 + if inside { pending } else { 0 };
+                        inside = true;
+                        pending = 0;
+                        if column.op == AlignOp::Match {
+                            matches += 1;
+                        }
+                        if column.op != AlignOp::Insertion {
+                            mapped.insert(region[i - region_range.start], column.reference);
+                        }
+                    }
+                    Some(_) => {
+                        inside = false;
+                        pending = 0;
+                    }
+                    None => pending += 1,
+                }
+            }
+            if columns == 0 {
+                return None;
+            }
+            let positions = coding.iter().map(|i| mapped.get(i).copied()).collect();
+            Some(Projection {
+                positions,
+                reversed: hit_rev,
+                identity: matches as f64 / columns as f64,
+            })
+        })
+        .collect()
+}
+
+/// Place a reference feature on the plasmid along its projection.
+fn placed_feature(feature: &Feature, projection: &Projection, length: usize) -> Option<Feature> {
+    let rev = feature.directionality == Some(Direction::Rev);
+    let mut segments = feature_segments(feature);
+    if rev {
+        segments.reverse();
+    }
+    let mut placed = Vec::new();
+    let mut offset = 0;
+    for segment in segments {
+        let size = span_length(segment, length);
+        let part = &projection.positions[offset..offset + size];
+        offset += size;
+        let first = part.iter().flatten().next();
+        let last = part.iter().rev().flatten().next();
+        if let (Some(&first), Some(&last)) = (first, last) {
+            placed.push(if projection.reversed {
+                [last + 1, first + 1]
+            } else {
+                [first + 1, last + 1]
+            });
+        }
+    }
+    if projection.reversed {
+        placed.reverse();
+    }
+    let span = [placed.first()?[0], placed.last()?[1]];
+    let directionality = feature.directionality.map(|_| {
+        if projection.reversed {
+            Direction::Rev
+        } else {
+            Direction::Fwd
+        }
+    });
+    Some(Feature {
+        span,
+        directionality,
+        segments: if feature.segments.is_empty() {
+            Vec::new()
+        } else {
+            placed
+        },
+        ..feature.clone()
+    })
+}
+
+/// Check a projection of a reference feature and describe how the copy on the
+/// plasmid differs, `None` if it does not match well enough.
+fn transfer_projection(
+    plasmid: &PlasmidFile,
+    reference: &PlasmidFile,
+    feature: &Feature,
+    projection: &Projection,
+    settings: &TransferSettings,
+) -> Option<TransferredFeature> {
+    let placed = placed_feature(feature, projection, plasmid.sequence.len())?;
+    let aligned = projection.positions.iter().flatten().count();
+    let coverage = aligned as f64 / projection.positions.len() as f64;
+    if projection.identity < settings.min_identity || coverage < settings.min_coverage {
+        return None;
+    }
+
+    let mut issues = Vec::new();
+    let ends_aligned = projection.positions.first().is_some_and(|p| p.is_some())
+        && projection.positions.last().is_some_and(|p| p.is_some());
+    if !ends_aligned {
+        issues.push(format!(
+            "Only {:.0}% of the feature aligns.",
+            100.0 * coverage
+        ));
+    }
+    let original = feature_sequence(&reference.sequence, feature);
+    let mut copy = feature_sequence(&plasmid.sequence, &placed);
+    if placed.directionality.is_none() && projection.reversed {
+        copy = reverse_complementary(&copy);
+    }
+    let mut frame_lost = false;
+    if feature.feature_type.eq_ignore_ascii_case("CDS") {
+        if original.len() % 3 != copy.len() % 3 {
+            issues.push("The reading frame is shifted.".to_string());
+            frame_lost = true;
+        }
+        let internal_stops = |sequence: &str, feature: &Feature| {
+            translate_feature(sequence, feature, None)
+                .map(|t| t.protein.trim_end_matches('*').matches('*').count())
+                .unwrap_or(0)
+        };
+        if internal_stops(&plasmid.sequence, &placed) > internal_stops(&reference.sequence, feature)
+        {
+            issues.push("The coding sequence has a premature stop codon.".to_string());
+            frame_lost = true;
+        }
+    }
+    let status = if !ends_aligned || frame_lost {
+        TransferStatus::Broken
+    } else if copy != original {
+        if copy.len() != original.len() {
+            issues.push(format!(
+                "{} bases instead of {}.",
+                copy.len(),
+                original.len()
+            ));
+        }
+        TransferStatus::Edited
+    } else {
+        TransferStatus::Exact
+    };
+    Some(TransferredFeature {
+        feature: placed,
+        reference: reference.name.clone(),
+        reference_span: feature.span,
+        identity: projection.identity,
+        coverage,
+        status,
+        issues,
+    })
+}
+
+/// Transfer the features of one reference to the plasmid.
+fn transfer_reference(
+    plasmid: &PlasmidFile,
+    index: &ReferenceIndex,
+    reference: &PlasmidFile,
+    settings: &TransferSettings,
+) -> (Vec<TransferredFeature>, ReferenceSummary) {
+    let mut transferred = Vec::new();
+    let mut missing = Vec::new();
+    for feature in &reference.features {
+        let transfers: Vec<TransferredFeature> =
+            project_feature(reference, feature, index, settings)
+                .iter()
+                .filter_map(|p| transfer_projection(plasmid, reference, feature, p, settings))
+                .collect();
+        if transfers.is_empty() {
+            missing.push(feature.label.clone());
+        }
+        transferred.extend(transfers);
+    }
+    let summary = ReferenceSummary {
+        name: reference.name.clone(),
+        transferred: transferred.len(),
+        broken: transferred
+            .iter()
+            .filter(|t| t.status == TransferStatus::Broken)
+            .count(),
+        missing,
+    };
+    (transferred, summary)
+}
+
+/// Copy the features of annotated references onto a plasmid. Features that
+/// several references place at the same position are kept once, from the
+/// reference matching best.
+pub fn transfer_annotations(request: &TransferRequest) -> Result<TransferResult, String> {
+    let mut plasmid = request.plasmid.clone();
+    plasmid.sequence = sanitize_sequence(&plasmid.sequence);
+    if plasmid.sequence.is_empty() {
+        return Err("The plasmid has no sequence.".to_string());
+    }
+    let mut references = request.references.clone();
+    for path in &request.reference_paths {
+        references.push(read_plasmid_file(path)?);
+    }
+    if references.is_empty() {
+        return Err("No reference given.".to_string());
+    }
+
+    let index = ReferenceIndex::new(
+        &plasmid.sequence,
+        plasmid.topology,
+        request.settings.kmer_length,
+    );
+    let mut features: Vec<TransferredFeature> = Vec::new();
+    let mut summaries = Vec::new();
+    for mut reference in references {
+        reference.sequence = sanitize_sequence(&reference.sequence);
+        if reference.sequence.is_empty() {
+            continue;
+        }
+        let (transferred, summary) =
+            transfer_reference(&plasmid, &index, &reference, &request.settings);
+        summaries.push(summary);
+        features.extend(transferred);
+    }
+
+    let same_place = |a: &Feature, b: &Feature| {
+        a.label == b.label && a.span == b.span && a.directionality == b.directionality
+    };
+    features.sort_by(|a, b| b.identity.total_cmp(&a.identity));
+    let mut kept: Vec<TransferredFeature> = Vec::new();
+    for transfer in features {
+        let annotated = plasmid
+            .features
+            .iter()
+            .any(|f| same_place(f, &transfer.feature));
+        let duplicate = kept
+            .iter()
+            .any(|k| same_place(&k.feature, &transfer.feature));
+        if !annotated && !duplicate {
+            kept.push(transfer);
+        }
+    }
+    kept.sort_by_key(|t| t.feature.span[0]);
+    Ok(TransferResult {
+        features: kept,
+        references: summaries,
+    })
+}
+
+#[tauri::command]
+/// Copy features from annotated reference plasmids onto a plasmid.
+pub fn transfer_reference_annotations(request: TransferRequest) -> Result<TransferResult, String> {
+    transfer_annotations(&request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequence::tests::RANDOM_PLASMID;
+
+    const LOXP: &str = "ATAACTTCGTATAGCATACATTATACGAAGTTAT";
+
+    fn plasmid(name: &str, sequence: String, features: Vec<Feature>) -> PlasmidFile {
+        PlasmidFile {
+            name: name.to_string(),
+            sequence,
+            topology: Topology::Circular,
+            features,
+        }
+    }
+
+    /// Reference with a loxP site after the first 80 bases.
+    fn reference() -> PlasmidFile {
+        let random = RANDOM_PLASMID.concat();
+        let loxp = Feature {
+            label: "loxP".to_string(),
+            feature_type: "protein_bind".to_string(),
+            span: [81, 114],
+            directionality: Some(Direction::Fwd),
+            segments: Vec::new(),
+            qualifiers: Vec::new(),
+        };
+        plasmid(
+            "reference",
+            format!("{}{}{}", &random[..80], LOXP, &random[80..200]),
+            vec![loxp],
+        )
+    }
+
+    fn transfer(sequence: String) -> Vec<(String, [usize; 2], Option<Direction>, TransferStatus)> {
+        let request = TransferRequest {
+            plasmid: plasmid("plasmid", sequence, Vec::new()),
+            references: vec![reference()],
+            reference_paths: Vec::new(),
+            settings: TransferSettings::default(),
+        };
+        transfer_annotations(&request)
+            .unwrap()
+            .features
+            .into_iter()
+            .map(|t| {
+                (
+                    t.feature.label,
+                    t.feature.span,
+                    t.feature.directionality,
+                    t.status,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn identical_copy() {
+        assert_eq!(
+            transfer(reference().sequence),
+            [(
+                "loxP".to_string(),
+                [81, 114],
+                Some(Direction::Fwd),
+                TransferStatus::Exact
+            )]
+        );
+    }
+
+    #[test]
+    fn reverse_complement_copy() {
+        assert_eq!(
+            transfer(reverse_complementary(&reference().sequence)),
+            [(
+                "loxP".to_string(),
+                [121, 154],
+                Some(Direction::Rev),
+                TransferStatus::Exact
+            )]
+        );
+    }
+
+    #[test]
+    fn duplicated_sites_are_all_transferred() {
+        let random = RANDOM_PLASMID.concat();
+        let sequence = format!(
+            "{}{}{}{}{}",
+            &random[..80],
+            LOXP,
+            &random[80..200],
+            LOXP,
+            &random[200..300]
+        );
+        let loxp = |span| {
+            (
+                "loxP".to_string(),
+                span,
+                Some(Direction::Fwd),
+                TransferStatus::Exact,
+            )
+        };
+        assert_eq!(transfer(sequence), [loxp([81, 114]), loxp([235, 268])]);
+    }
+}
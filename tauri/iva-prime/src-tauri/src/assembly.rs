@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+use crate::primers::Primer;
+use crate::sequence::{
+    reverse_complementary, sanitize_sequence, span_sequence, Direction, Topology,
+};
+
+/// One part of a multi-fragment assembly, either a region of an open plasmid
+/// or a raw linear sequence.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssemblyPart {
+    pub name: String,
+    /// Source sequence (the whole plasmid or the raw fragment).
+    pub sequence: String,
+    #[serde(default = "default_topology")]
+    pub topology: Topology,
+    /// Region of the source to use (1-based, inclusive). The whole source if
+    /// missing. Spans where the end lies before the start run across the origin.
+    #[serde(default)]
+    pub span: Option<[usize; 2]>,
+    /// Orientation of the part in the assembled construct.
+    #[serde(default = "default_direction")]
+    pub direction: Direction,
+}
+
+fn default_topology() -> Topology {
+    Topology::Linear
+}
+
+fn default_direction() -> Direction {
+    Direction::Fwd
+}
+
+/// Primers amplifying one part of an assembly.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartPrimers {
+    pub name: String,
+    pub primers: Vec<Primer>,
+    /// PCR product of this part, including the overlaps.
+    pub amplicon: String,
+}
+
+impl AssemblyPart {
+    /// Part sequence as it will read in the assembled construct.
+    pub fn oriented_sequence(&self) -> Result<String, String> {
+        let source = sanitize_sequence(&self.sequence);
+        let region = match self.span {
+            None => source,
+            Some([start, end]) => {
+                let in_bounds = |i: usize| i >= 1 && i <= source.len();
+                if !in_bounds(start) || !in_bounds(end) {
+                    return Err(format!(
+                        "Span [{}, {}] of part \"{}\" is out of bounds.",
+                        start, end, self.name
+                    ));
+                }
+                if end < start && self.topology == Topology::Linear {
+                    return Err(format!(
+                        "Span [{}, {}] of part \"{}\" crosses the end of a linear sequence.",
+                        start, end, self.name
+                    ));
+                }
+                span_sequence(&source, [start, end])
+            }
+        };
+        if region.is_empty() {
+            return Err(format!("Part \"{}\" is empty.", self.name));
+        }
+        Ok(match self.direction {
+            Direction::Fwd => region,
+            Direction::Rev => reverse_complementary(&region),
+        })
+    }
+}
@@ -0,0 +1,619 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::collection::{with_binding_candidates, IndexSummary};
+use crate::jobs::{spawn_job, JobReporter};
+use crate::melting::{melting_temperature, TmSettings};
+use crate::plasmid_file::Feature;
+use crate::sequence::{
+    iupac_compatible, reverse_complementary, sanitize_sequence, Direction, Topology,
+};
+
+/// Matching bases needed at the 5' end of the annealed part, so a random
+/// tail base does not count as annealed.
+const ANNEAL_CLAMP: usize = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Oligo {
+    pub name: String,
+    pub sequence: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BindingSettings {
+    /// Bases at the 3' end that have to anneal, mismatches included.
+    pub min_anneal: usize,
+    /// Mismatches allowed in the annealed part.
+    pub max_mismatches: usize,
+    /// Bases at the 3' end that have to match without mismatch.
+    pub exact_3_prime: usize,
+    /// Sites whose annealed part melts below this temperature are dropped.
+    pub min_tm: f64,
+    /// Sites kept per oligo and plasmid, best first.
+    pub max_sites: usize,
+    pub tm: TmSettings,
+}
+
+impl Default for BindingSettings {
+    fn default() -> Self {
+        Self {
+            min_anneal: 15,
+            max_mismatches: 1,
+            exact_3_prime: 3,
+            min_tm: 40.0,
+            max_sites: 100,
+            tm: TmSettings::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BindingSiteRequest {
+    pub oligos: Vec<Oligo>,
+    pub sequence: String,
+    pub topology: Topology,
+    #[serde(default)]
+    pub settings: BindingSettings,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionBindingRequest {
+    pub oligos: Vec<Oligo>,
+    #[serde(default)]
+    pub settings: BindingSettings,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BindingSite {
+    pub oligo: String,
+    pub strand: Direction,
+    /// Annealed part on the plasmid, 1-based, inclusive. Spans where the end
+    /// lies before the start run across the origin.
+    pub span: [usize; 2],
+    pub anneal_length: usize,
+    /// Bases 5' of the annealed part.
+    pub tail_length: usize,
+    /// Plasmid positions (1-based) of mismatches in the annealed part.
+    pub mismatches: Vec<usize>,
+    /// Melting temperature of the annealed part of the oligo, not lowered by
+    /// mismatches.
+    pub tm: f64,
+    /// The site as primer feature, ready to add to the plasmid.
+    pub feature: Feature,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BindingSiteResult {
+    pub sites: Vec<BindingSite>,
+    /// Oligos without any binding site.
+    pub unbound: Vec<String>,
+    /// Plain text version of the result.
+    pub report: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionBindingSite {
+    pub path: PathBuf,
+    pub plasmid: String,
+    #[serde(flatten)]
+    pub site: BindingSite,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionBindingResult {
+    pub sites: Vec<CollectionBindingSite>,
+    pub unbound: Vec<String>,
+    pub index: IndexSummary,
+}
+
+/// Where the oligo anneals on one strand: template index of its 3' base and
+/// index of the first annealed oligo base.
+struct Anneal {
+    end: usize,
+    start: usize,
+}
+
+/// Parse oligos pasted or read from a file, either FASTA or one oligo per
+/// line as `<name> <sequence>` or just the sequence.
+pub fn parse_oligos(text: &str) -> Vec<Oligo> {
+    let mut oligos: Vec<Oligo> = Vec::new();
+    if text.trim_start().starts_with('>') {
+        for line in text.lines() {
+            if let Some(header) = line.strip_prefix('>') {
+                oligos.push(Oligo {
+                    name: header.trim().to_string(),
+                    sequence: String::new(),
+                });
+            } else if let Some(oligo) = oligos.last_mut() {
+                oligo.sequence.push_str(line.trim());
+            }
+        }
+    } else {
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let fields: Vec<&str> = line
+                .split(['\t', ',', ';', ' '])
+                .filter(|f| !f.is_empty())
+                .collect();
+            let (name, sequence) = match fields.as_slice() {
+                [sequence] => (String::new(), sequence.to_string()),
+                [name, sequence, ..] => (name.to_string(), sequence.to_string()),
+                [] => continue,
+            };
+            oligos.push(Oligo { name, sequence });
+        }
+    }
+    oligos.retain(|o| !oligo_bases(&o.sequence).is_empty());
+    name_oligos(&mut oligos);
+    oligos
+}
+
+/// Name unnamed oligos after their position and number repeated names, so
+/// that sites and reports tell all oligos apart.
+pub fn name_oligos(oligos: &mut [Oligo]) {
+    let given: HashSet<String> = oligos
+        .iter()
+        .map(|o| o.name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect();
+    let mut used: HashSet<String> = HashSet::new();
+    for (i, oligo) in oligos.iter_mut().enumerate() {
+        let name = oligo.name.trim();
+        let base = if name.is_empty() {
+            format!("Oligo {}", i + 1)
+        } else {
+            name.to_string()
+        };
+        // Made up names must not take one given to a later oligo
+        let free = |candidate: &String| {
+            !used.contains(candidate)
+                && (!given.contains(candidate) || (candidate == name && !name.is_empty()))
+        };
+        let mut candidate = base.clone();
+        let mut n = 2;
+        while !free(&candidate) {
+            candidate = format!("{} ({})", base, n);
+            n += 1;
+        }
+        used.insert(candidate.clone());
+        oligo.name = candidate;
+    }
+}
+
+/// Oligo bases without gaps.
+pub fn oligo_bases(sequence: &str) -> String {
+    sanitize_sequence(sequence).replace(['.', '-'], "")
+}
+
+/// Sites on the top strand of `template` where the 3' end of `oligo` anneals.
+fn anneal_sites(
+    template: &[u8],
+    topology: Topology,
+    oligo: &[u8],
+    settings: &BindingSettings,
+) -> Vec<Anneal> {
+    let length = template.len();
+    let oligo_length = oligo.len();
+    let seed = settings.min_anneal.clamp(1, oligo_length);
+    let exact = settings.exact_3_prime.min(seed);
+    if length < seed {
+        return Vec::new();
+    }
+
+    let mut sites = Vec::new();
+    for end in 0..length {
+        if topology == Topology::Linear && end + 1 < seed {
+            continue;
+        }
+        // Template index paired with oligo base `j`, if there is one
+        let position = |j: usize| {
+            let back = oligo_length - 1 - j;
+            match topology {
+                Topology::Linear => end.checked_sub(back),
+                Topology::Circular => (back < length).then(|| (end + length - back) % length),
+            }
+        };
+        let matches =
+            |j: usize| position(j).is_some_and(|p| iupac_compatible(template[p], oligo[j]));
+
+        let seed_start = oligo_length - seed;
+        if !(oligo_length - exact..oligo_length).all(matches) {
+            continue;
+        }
+        let mut mismatches = (seed_start..oligo_length - exact)
+            .filter(|&j| !matches(j))
+            .count();
+        if mismatches > settings.max_mismatches {
+            continue;
+        }
+
+        let mut start = seed_start;
+        while start > 0 && position(start - 1).is_some() {
+            if !matches(start - 1) {
+                if mismatches == settings.max_mismatches {
+                    break;
+                }
+                mismatches += 1;
+            }
+            start -= 1;
+        }
+        while start < seed_start
+            && (start..(start + ANNEAL_CLAMP).min(oligo_length)).any(|j| !matches(j))
+        {
+            start += 1;
+        }
+        sites.push(Anneal { end, start });
+    }
+    sites
+}
+
+/// Binding sites of one oligo on both strands of a plasmid, best first.
+pub fn oligo_binding_sites(
+    sequence: &str,
+    topology: Topology,
+    oligo: &Oligo,
+    settings: &BindingSettings,
+) -> Vec<BindingSite> {
+    let bases = oligo_bases(&oligo.sequence);
+    let sequence = sanitize_sequence(sequence);
+    let length = sequence.len();
+    if bases.is_empty() || length == 0 {
+        return Vec::new();
+    }
+    let bottom = reverse_complementary(&sequence);
+
+    let mut sites = Vec::new();
+    for (strand, template) in [(Direction::Fwd, &sequence), (Direction::Rev, &bottom)] {
+        let template = template.as_bytes();
+        for anneal in anneal_sites(template, topology, bases.as_bytes(), settings) {
+            let annealed = &bases[anneal.start..];
+            let tm = melting_temperature(annealed, None, &settings.tm);
+            if tm < settings.min_tm {
+                continue;
+            }
+            // Template index of the oligo base `j` and its top strand position
+            let template_index =
+                |j: usize| (anneal.end + length - (bases.len() - 1 - j) % length) % length;
+            let top_position = |index: usize| match strand {
+                Direction::Fwd => index + 1,
+                Direction::Rev => length - index,
+            };
+            let first = top_position(template_index(anneal.start));
+            let last = top_position(anneal.end);
+            let span = match strand {
+                Direction::Fwd => [first, last],
+                Direction::Rev => [last, first],
+            };
+            let mut mismatches: Vec<usize> = (anneal.start..bases.len())
+                .filter(|&j| !iupac_compatible(template[template_index(j)], bases.as_bytes()[j]))
+                .map(|j| top_position(template_index(j)))
+                .collect();
+            mismatches.sort_unstable();
+
+            let mut note = format!("Tm {:.1} °C", tm);
+            if anneal.start > 0 {
+                note.push_str(&format!(", {} nt 5' tail", anneal.start));
+            }
+            if !mismatches.is_empty() {
+                note.push_str(&format!(", {} mismatch(es)", mismatches.len()));
+            }
+            sites.push(BindingSite {
+                oligo: oligo.name.clone(),
+                strand,
+                span,
+                anneal_length: annealed.len(),
+                tail_length: anneal.start,
+                mismatches,
+                tm,
+                feature: Feature {
+                    label: oligo.name.clone(),
+                    feature_type: "primer_bind".to_string(),
+                    span,
+                    directionality: Some(strand),
+                    segments: Vec::new(),
+                    qualifiers: vec![("note".to_string(), note)],
+                },
+            });
+        }
+    }
+    sites.sort_by(|a, b| {
+        (a.mismatches.len(), b.anneal_length, a.span).cmp(&(
+            b.mismatches.len(),
+            a.anneal_length,
+            b.span,
+        ))
+    });
+    sites.truncate(settings.max_sites);
+    sites
+}
+
+/// Binding sites of all oligos on an open plasmid.
+pub fn find_binding_sites(request: &BindingSiteRequest) -> Result<BindingSiteResult, String> {
+    if request.oligos.is_empty() {
+        return Err("No oligos given.".to_string());
+    }
+    let mut oligos = request.oligos.clone();
+    name_oligos(&mut oligos);
+    let mut sites = Vec::new();
+    let mut unbound = Vec::new();
+    for oligo in &oligos {
+        let found = oligo_binding_sites(
+            &request.sequence,
+            request.topology,
+            oligo,
+            &request.settings,
+        );
+        if found.is_empty() {
+            unbound.push(oligo.name.clone());
+        }
+        sites.extend(found);
+    }
+    let mut result = BindingSiteResult {
+        sites,
+        unbound,
+        report: String::new(),
+    };
+    result.report = binding_report_text(&result);
+    Ok(result)
+}
+
+/// Binding sites of all oligos across the indexed plasmid collection.
+pub fn find_collection_binding_sites(
+    request: &CollectionBindingRequest,
+    reporter: Option<&JobReporter>,
+) -> Result<CollectionBindingResult, String> {
+    if request.oligos.is_empty() {
+        return Err("No oligos given.".to_string());
+    }
+    let mut oligos = request.oligos.clone();
+    name_oligos(&mut oligos);
+    with_binding_candidates(
+        reporter,
+        &oligos,
+        &request.settings,
+        |plasmids, candidates, index| {
+            let mut sites = Vec::new();
+            let mut unbound = Vec::new();
+            for (i, (oligo, candidates)) in oligos.iter().zip(&candidates).enumerate() {
+                if let Some(reporter) = reporter {
+                    if reporter.is_cancelled() {
+                        return Err("Binding site search cancelled.".to_string());
+                    }
+                    reporter.progress(i, oligos.len(), format!("Searching {}", oligo.name));
+                }
+                let mut bound = false;
+                for &n in candidates {
+                    let plasmid = &plasmids[n];
+                    let found = oligo_binding_sites(
+                        &plasmid.sequence,
+                        plasmid.topology,
+                        oligo,
+                        &request.settings,
+                    );
+                    bound |= !found.is_empty();
+                    sites.extend(found.into_iter().map(|site| CollectionBindingSite {
+                        path: plasmid.path.clone(),
+                        plasmid: plasmid.name.clone(),
+                        site,
+                    }));
+                }
+                if !bound {
+                    unbound.push(oligo.name.clone());
+                }
+            }
+            Ok(CollectionBindingResult {
+                sites,
+                unbound,
+                index,
+            })
+        },
+    )
+}
+
+/// One line of a binding site report.
+pub fn binding_site_line(site: &BindingSite) -> String {
+    let mut line = format!(
+        "{}\t{}..{}\t{}\t{} nt annealed\tTm {:.1} °C",
+        site.oligo,
+        site.span[0],
+        site.span[1],
+        match site.strand {
+            Direction::Fwd => "fwd",
+            Direction::Rev => "rev",
+        },
+        site.anneal_length,
+        site.tm
+    );
+    if site.tail_length > 0 {
+        line.push_str(&format!("\t{} nt tail", site.tail_length));
+    }
+    if !site.mismatches.is_empty() {
+        let positions: Vec<String> = site.mismatches.iter().map(|p| p.to_string()).collect();
+        line.push_str(&format!("\tmismatches at {}", positions.join(", ")));
+    }
+    line
+}
+
+/// Plain text report of the binding sites on a plasmid.
+pub fn binding_report_text(result: &BindingSiteResult) -> String {
+    let mut lines = vec![format!("{} binding site(s)", result.sites.len())];
+    lines.extend(result.sites.iter().map(binding_site_line));
+    if !result.unbound.is_empty() {
+        lines.push(String::new());
+        lines.push(format!("No binding site: {}", result.unbound.join(", ")));
+    }
+    lines.join("\n")
+}
+
+#[tauri::command]
+/// Find where oligos bind on the open plasmid.
+pub fn find_primer_binding_sites(request: BindingSiteRequest) -> Result<BindingSiteResult, String> {
+    find_binding_sites(&request)
+}
+
+#[tauri::command]
+/// Start looking for binding sites of oligos across the plasmid collection in
+/// the background. Returns the job id, results arrive through the
+/// `job-finished` event.
+pub fn start_collection_binding_job(
+    app: tauri::AppHandle,
+    request: CollectionBindingRequest,
+) -> u64 {
+    spawn_job(app, "collection-binding", move |reporter| {
+        find_collection_binding_sites(&request, Some(reporter))
+    })
+}
+
+#[tauri::command]
+/// Split pasted text into oligos.
+pub fn parse_oligo_list(text: String) -> Vec<Oligo> {
+    parse_oligos(&text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequence::tests::RANDOM_PLASMID;
+
+    /// A base that never pairs like `base` does.
+    fn mismatch(base: u8) -> char {
+        match base {
+            b'A' => 'C',
+            b'C' => 'A',
+            b'G' => 'T',
+            _ => 'G',
+        }
+    }
+
+    /// Oligo matching `template[from..to]` with `tail` mismatching bases
+    /// in front and the bases at `changed` oligo indices mismatched.
+    fn oligo(template: &str, from: usize, to: usize, tail: usize, changed: &[usize]) -> String {
+        let template = template.as_bytes();
+        let mut bases: Vec<char> = template[from - tail..to]
+            .iter()
+            .map(|&b| b as char)
+            .collect();
+        for i in (0..tail).chain(changed.iter().map(|i| tail + i)) {
+            bases[i] = mismatch(template[from - tail + i]);
+        }
+        bases.into_iter().collect()
+    }
+
+    fn sites(sequence: &str, topology: Topology, oligo: &str, exact: usize) -> Vec<BindingSite> {
+        let oligo = Oligo {
+            name: "P1".to_string(),
+            sequence: oligo.to_string(),
+        };
+        let settings = BindingSettings {
+            exact_3_prime: exact,
+            ..BindingSettings::default()
+        };
+        oligo_binding_sites(sequence, topology, &oligo, &settings)
+    }
+
+    fn summary(sites: &[BindingSite]) -> Vec<(Direction, [usize; 2], usize, Vec<usize>)> {
+        sites
+            .iter()
+            .map(|s| (s.strand, s.span, s.tail_length, s.mismatches.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn anneal_sites_stop_at_the_5_prime_tail() {
+        let plasmid = RANDOM_PLASMID.concat();
+        let settings = BindingSettings::default();
+        let tailed = oligo(&plasmid, 100, 122, 12, &[]);
+        let found = anneal_sites(
+            plasmid.as_bytes(),
+            Topology::Circular,
+            tailed.as_bytes(),
+            &settings,
+        );
+        let found: Vec<(usize, usize)> = found.iter().map(|a| (a.end, a.start)).collect();
+        assert_eq!(found, [(121, 12)]);
+
+        // Across the origin only on circular templates
+        let origin = format!("{}{}", &plasmid[390..], &plasmid[..12]);
+        let circular = anneal_sites(
+            plasmid.as_bytes(),
+            Topology::Circular,
+            origin.as_bytes(),
+            &settings,
+        );
+        let circular: Vec<(usize, usize)> = circular.iter().map(|a| (a.end, a.start)).collect();
+        assert_eq!(circular, [(11, 0)]);
+        assert!(anneal_sites(
+            plasmid.as_bytes(),
+            Topology::Linear,
+            origin.as_bytes(),
+            &settings
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn binding_sites_on_both_strands() {
+        let plasmid = RANDOM_PLASMID.concat();
+        let circular = Topology::Circular;
+
+        let tailed = oligo(&plasmid, 100, 122, 12, &[]);
+        assert_eq!(
+            summary(&sites(&plasmid, circular, &tailed, 3)),
+            [(Direction::Fwd, [101, 122], 12, vec![])]
+        );
+
+        let mismatched = oligo(&plasmid, 200, 225, 0, &[10]);
+        assert_eq!(
+            summary(&sites(&plasmid, circular, &mismatched, 3)),
+            [(Direction::Fwd, [201, 225], 0, vec![211])]
+        );
+        let reverse = reverse_complementary(&mismatched);
+        assert_eq!(
+            summary(&sites(&plasmid, circular, &reverse, 3)),
+            [(Direction::Rev, [201, 225], 0, vec![211])]
+        );
+
+        let origin = format!("{}{}", &plasmid[390..], &plasmid[..12]);
+        assert_eq!(
+            summary(&sites(&plasmid, circular, &origin, 3)),
+            [(Direction::Fwd, [391, 12], 0, vec![])]
+        );
+        assert_eq!(
+            summary(&sites(
+                &plasmid,
+                circular,
+                &reverse_complementary(&origin),
+                3
+            )),
+            [(Direction::Rev, [391, 12], 0, vec![])]
+        );
+        assert!(sites(&plasmid, Topology::Linear, &origin, 3).is_empty());
+    }
+
+    #[test]
+    fn exact_3_prime_bases_reject_mismatches() {
+        let plasmid = RANDOM_PLASMID.concat();
+        let last_base = oligo(&plasmid, 300, 325, 0, &[24]);
+        assert!(sites(&plasmid, Topology::Circular, &last_base, 3).is_empty());
+        assert_eq!(
+            summary(&sites(&plasmid, Topology::Circular, &last_base, 0)),
+            [(Direction::Fwd, [301, 325], 0, vec![325])]
+        );
+    }
+
+    #[test]
+    fn oligo_names_are_unique() {
+        let oligos = parse_oligos(">\nACGTACGTAC\n>Oligo 1\nGGGCCCAAAT\n>x\nTTTTGG\n>x\nAAAACC\n");
+        let names: Vec<&str> = oligos.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(names, ["Oligo 1 (2)", "Oligo 1", "x", "x (2)"]);
+    }
+}
@@ -0,0 +1,702 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde::de::DeserializeOwned;
+
+use crate::binding_sites::{
+    binding_site_line, find_binding_sites, find_collection_binding_sites, parse_oligos,
+    BindingSettings, BindingSiteRequest, CollectionBindingRequest, Oligo,
+};
+use crate::common_features::{find_library_features, AnnotationRequest, AnnotationSettings};
+use crate::duplicates::{
+    check_collection, find_duplicates, CollectionCheckRequest, DuplicateSettings,
+};
+use crate::export::MixedBaseFormat;
+use crate::feature_library::annotation_library;
+use crate::insertion_presets::{find_preset, preset_dna};
+use crate::nanopore::{verify_whole_plasmid, NanoporeSettings};
+use crate::plasmid_diff::{diff_plasmids, DiffRequest, DiffSettings};
+use crate::plasmid_file::{read_plasmid_file, write_genbank, RegionSelection};
+use crate::primers::DesignSettings;
+use crate::reads::read_sequencing_reads;
+use crate::sequence::Direction;
+use crate::subcloning::{subclone_from_files, SubcloningFileRequest};
+use crate::verification::{verify_reads, VerificationSettings};
+
+const SUBCLONE_USAGE: &str = "\
+Usage: iva-prime subclone --recipient <file> --donor <file>
+                          (--span <start>..<end> | --feature <label>)
+                          --at <position>[..<end>] --output <file.gb> [options]
+
+Subclone a region of the donor plasmid into the recipient plasmid and write
+the resulting plasmid and its primers, without opening the app.
+
+Options:
+  --recipient <file>     Target vector (.gb, .gbk, .dna, .fasta)
+  --donor <file>         Plasmid containing the region to subclone
+  --span <start>..<end>  Donor region (1-based, inclusive)
+  --feature <label>      Donor feature to subclone, instead of a span
+  --reverse              Subclone the reverse complement of the region
+  --at <pos>[..<end>]    Insert before <pos>, or replace <pos>..<end> of the recipient
+  --5prime <seq>         Bases added in front of the region
+  --3prime <seq>         Bases added behind the region
+  --5prime-preset <name> Insertion preset added in front of the region
+  --3prime-preset <name> Insertion preset added behind the region
+  --organism <name>      Codon usage for AA presets [default: Escherichia coli]
+  --output <file.gb>     GenBank file for the resulting plasmid
+  --primers <file.csv>   Primer order sheet [default: <output>_primers.csv]
+  --settings <file>      JSON primer design settings
+  --mixed-bases <format> iupac | idtHandMixed [default: iupac]";
+
+const VERIFY_USAGE: &str = "\
+Usage: iva-prime verify --plasmid <file> --reads <file>... [options]
+
+Align Sanger reads to the expected plasmid and report whether they confirm it.
+
+Options:
+  --plasmid <file>       Expected construct (.gb, .gbk, .dna, .fasta)
+  --reads <file>...      Reads (.ab1, .fasta, .fastq, .seq)
+  --span <start>..<end>  Region that must be confirmed [default: whole plasmid]
+  --feature <label>      Feature that must be confirmed, instead of a span
+  --settings <file>      JSON verification settings
+  --json                 Print the result as JSON instead of text
+  --output <file>        Write the report to a file instead of printing it";
+
+const NANOPORE_USAGE: &str = "\
+Usage: iva-prime nanopore --plasmid <file> --reads <file.fastq> [options]
+
+Build a consensus from whole-plasmid nanopore reads and report variants,
+coverage and mixed populations against the expected plasmid.
+
+Options:
+  --plasmid <file>       Expected construct (.gb, .gbk, .dna, .fasta)
+  --reads <file>         Reads (.fastq, .fasta)
+  --settings <file>      JSON nanopore settings
+  --consensus <file>     Write the consensus sequence as FASTA
+  --json                 Print the result as JSON instead of text
+  --output <file>        Write the report to a file instead of printing it";
+
+const IMPORT_USAGE: &str = "\
+Usage: iva-prime import --input <file> --output <file.gb> [options]
+
+Read a plasmid file and write it as GenBank, optionally annotating common
+features such as promoters, origins, resistance genes and tags.
+
+Options:
+  --input <file>            Plasmid file (.gb, .gbk, .dna, .fasta)
+  --output <file.gb>        GenBank file to write
+  --annotate                Add common features that are not annotated yet
+  --min-identity <fraction> Lowest identity of annotated features [default: 0.95]
+  --min-coverage <fraction> Shortest annotated part of a feature [default: 1]";
+
+const DIFF_USAGE: &str = "\
+Usage: iva-prime diff --from <file> --to <file> [options]
+
+Compare two versions of a plasmid, e.g. a parent vector and the designed
+product, and report sequence edits and feature changes. Circular plasmids are
+compared independently of their origin and orientation.
+
+Options:
+  --from <file>          First plasmid (.gb, .gbk, .dna, .fasta)
+  --to <file>            Second plasmid
+  --settings <file>      JSON comparison settings
+  --json                 Print the result as JSON instead of text
+  --output <file>        Write the report to a file instead of printing it";
+
+const DUPLICATES_USAGE: &str = "\
+Usage: iva-prime duplicates --dir <folder> [options]
+
+Find plasmid files in a folder that hold the same or nearly the same plasmid,
+also when saved with another origin or on the other strand.
+
+Options:
+  --dir <folder>            Folder to scan, including subfolders
+  --check <file>            Only list the copies of this plasmid
+  --min-similarity <fraction> Shared k-mers of similar plasmids [default: 0.9]
+  --top-level               Do not scan subfolders
+  --json                    Print the result as JSON instead of text
+  --output <file>           Write the report to a file instead of printing it";
+
+const BIND_USAGE: &str = "\
+Usage: iva-prime bind (--oligo <sequence> | --oligos <file>) (--plasmid <file> | --collection) [options]
+
+Find where oligos bind on a plasmid or across the indexed plasmid collection,
+on both strands. The 3' end has to anneal, 5' tails are allowed.
+
+Options:
+  --oligo <sequence>        Oligo to look for, can be given more than once
+  --oligos <file>           FASTA file or one oligo per line as <name> <sequence>
+  --plasmid <file>          Plasmid to search (.gb, .gbk, .dna, .fasta)
+  --collection              Search the plasmid collection set up in the app
+  --min-anneal <bases>      Bases at the 3' end that have to anneal [default: 15]
+  --max-mismatches <count>  Mismatches allowed in the annealed part [default: 1]
+  --settings <file>         JSON binding site settings
+  --json                    Print the result as JSON instead of text
+  --output <file>           Write the report to a file instead of printing it";
+
+/// Parse `<start>..<end>` or a single position.
+fn parse_range(value: &str) -> Option<(i64, Option<i64>)> {
+    match value.split_once("..") {
+        Some((start, end)) => Some((start.trim().parse().ok()?, Some(end.trim().parse().ok()?))),
+        None => Some((value.trim().parse().ok()?, None)),
+    }
+}
+
+/// Parse a `--span` value, both ends must be given.
+fn parse_span(value: &str) -> Result<[usize; 2], String> {
+    match parse_range(value) {
+        Some((start, Some(end))) if start > 0 && end > 0 => Ok([start as usize, end as usize]),
+        _ => Err(format!("Invalid span \"{}\".", value)),
+    }
+}
+
+/// Parse a value between 0 and 1.
+fn parse_fraction(value: &str) -> Result<f64, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|f| (0.0..=1.0).contains(f))
+        .ok_or_else(|| format!("Invalid fraction \"{}\".", value))
+}
+
+/// Read a JSON settings file given with `--settings`.
+fn read_settings<T: DeserializeOwned>(path: &str) -> Result<T, String> {
+    let json =
+        fs::read_to_string(path).map_err(|e| format!("Could not read \"{}\": {}", path, e))?;
+    serde_json::from_str(&json).map_err(|e| format!("Invalid settings file \"{}\": {}", path, e))
+}
+
+fn parse_subclone_args(args: &[String]) -> Result<SubcloningFileRequest, String> {
+    let mut recipient_path = None;
+    let mut donor_path = None;
+    let mut region = None;
+    let mut direction = Direction::Fwd;
+    let mut operation = None;
+    let mut seq_5_prime = String::new();
+    let mut seq_3_prime = String::new();
+    let mut preset_5_prime = None;
+    let mut preset_3_prime = None;
+    let mut organism = "Escherichia coli".to_string();
+    let mut output_path: Option<PathBuf> = None;
+    let mut primers_path = None;
+    let mut settings = DesignSettings::default();
+    let mut mixed_base_format = MixedBaseFormat::default();
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        if flag == "--reverse" {
+            direction = Direction::Rev;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for \"{}\".", flag))?;
+        match flag.as_str() {
+            "--recipient" => recipient_path = Some(PathBuf::from(value)),
+            "--donor" => donor_path = Some(PathBuf::from(value)),
+            "--span" => region = Some(RegionSelection::Span(parse_span(value)?)),
+            "--feature" => region = Some(RegionSelection::Feature(value.clone())),
+            "--at" => {
+                operation = Some(
+                    parse_range(value).ok_or_else(|| format!("Invalid position \"{}\".", value))?,
+                );
+            }
+            "--5prime" => seq_5_prime = value.clone(),
+            "--3prime" => seq_3_prime = value.clone(),
+            "--5prime-preset" => preset_5_prime = Some(value.clone()),
+            "--3prime-preset" => preset_3_prime = Some(value.clone()),
+            "--organism" => organism = value.clone(),
+            "--output" => output_path = Some(PathBuf::from(value)),
+            "--primers" => primers_path = Some(PathBuf::from(value)),
+            "--settings" => settings = read_settings(value)?,
+            "--mixed-bases" => {
+                mixed_base_format =
+                    serde_json::from_value(serde_json::Value::String(value.clone()))
+                        .map_err(|_| format!("Unknown mixed base format \"{}\".", value))?;
+            }
+            other => return Err(format!("Unknown option \"{}\".", other)),
+        }
+    }
+
+    let output_path = output_path.ok_or("Missing --output.")?;
+    let primers_path = primers_path.unwrap_or_else(|| {
+        let stem = output_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        output_path.with_file_name(format!("{}_primers.csv", stem))
+    });
+    let (operation_start, operation_end) = operation.ok_or("Missing --at.")?;
+    // Presets go next to the region, outside of them any given bases
+    if let Some(name) = preset_5_prime {
+        seq_5_prime.push_str(&preset_dna(&find_preset(&name)?, &organism)?);
+    }
+    if let Some(name) = preset_3_prime {
+        seq_3_prime = preset_dna(&find_preset(&name)?, &organism)? + &seq_3_prime;
+    }
+
+    Ok(SubcloningFileRequest {
+        recipient_path: recipient_path.ok_or("Missing --recipient.")?,
+        donor_path: donor_path.ok_or("Missing --donor.")?,
+        region: region.ok_or("Missing --span or --feature.")?,
+        direction,
+        operation_start,
+        operation_end,
+        seq_5_prime,
+        seq_3_prime,
+        output_path: Some(output_path),
+        primers_path: Some(primers_path),
+        mixed_base_format,
+        settings,
+    })
+}
+
+fn run_subclone(args: &[String]) -> Result<(), String> {
+    let request = parse_subclone_args(args)?;
+    let result = subclone_from_files(&request)?;
+    for primer in &result.primer_set.primers {
+        println!("{}\t{}", primer.label, primer.sequence());
+    }
+    Ok(())
+}
+
+fn run_verify(args: &[String]) -> Result<(), String> {
+    let mut plasmid_path = None;
+    let mut read_paths: Vec<PathBuf> = Vec::new();
+    let mut target = None;
+    let mut settings = VerificationSettings::default();
+    let mut json = false;
+    let mut output_path = None;
+
+    let mut args = args.iter().peekable();
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--json" => json = true,
+            "--reads" => {
+                while let Some(path) = args.next_if(|a| !a.starts_with("--")) {
+                    read_paths.push(PathBuf::from(path));
+                }
+            }
+            _ => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("Missing value for \"{}\".", flag))?;
+                match flag.as_str() {
+                    "--plasmid" => plasmid_path = Some(PathBuf::from(value)),
+                    "--span" => target = Some(RegionSelection::Span(parse_span(value)?)),
+                    "--feature" => target = Some(RegionSelection::Feature(value.clone())),
+                    "--settings" => settings = read_settings(value)?,
+                    "--output" => output_path = Some(PathBuf::from(value)),
+                    other => return Err(format!("Unknown option \"{}\".", other)),
+                }
+            }
+        }
+    }
+
+    let plasmid = read_plasmid_file(&plasmid_path.ok_or("Missing --plasmid.")?)?;
+    if read_paths.is_empty() {
+        return Err("Missing --reads.".to_string());
+    }
+    let mut reads = Vec::new();
+    for path in &read_paths {
+        reads.extend(read_sequencing_reads(path)?);
+    }
+
+    let result = verify_reads(&plasmid, &reads, target.as_ref(), &settings)?;
+    let report = if json {
+        serde_json::to_string_pretty(&result).map_err(|e| e.to_string())?
+    } else {
+        result.report.clone()
+    };
+    write_report(&report, output_path)
+}
+
+/// Print a report, or write it to a file if a path was given.
+fn write_report(report: &str, output_path: Option<PathBuf>) -> Result<(), String> {
+    match output_path {
+        Some(path) => fs::write(&path, report)
+            .map_err(|e| format!("Could not write \"{}\": {}", path.display(), e)),
+        None => {
+            println!("{}", report);
+            Ok(())
+        }
+    }
+}
+
+fn run_nanopore(args: &[String]) -> Result<(), String> {
+    let mut plasmid_path = None;
+    let mut reads_path = None;
+    let mut settings = NanoporeSettings::default();
+    let mut consensus_path: Option<PathBuf> = None;
+    let mut json = false;
+    let mut output_path = None;
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        if flag == "--json" {
+            json = true;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for \"{}\".", flag))?;
+        match flag.as_str() {
+            "--plasmid" => plasmid_path = Some(PathBuf::from(value)),
+            "--reads" => reads_path = Some(PathBuf::from(value)),
+            "--settings" => settings = read_settings(value)?,
+            "--consensus" => consensus_path = Some(PathBuf::from(value)),
+            "--output" => output_path = Some(PathBuf::from(value)),
+            other => return Err(format!("Unknown option \"{}\".", other)),
+        }
+    }
+
+    let plasmid = read_plasmid_file(&plasmid_path.ok_or("Missing --plasmid.")?)?;
+    let reads = read_sequencing_reads(&reads_path.ok_or("Missing --reads.")?)?;
+    let result = verify_whole_plasmid(&plasmid, &reads, &settings, None)?;
+
+    if let Some(path) = consensus_path {
+        let lines: Vec<&str> = result
+            .consensus
+            .as_bytes()
+            .chunks(70)
+            .map(|chunk| std::str::from_utf8(chunk).unwrap_or_default())
+            .collect();
+        let fasta = format!(">{} consensus\n{}\n", plasmid.name, lines.join("\n"));
+        fs::write(&path, fasta)
+            .map_err(|e| format!("Could not write \"{}\": {}", path.display(), e))?;
+    }
+
+    let report = if json {
+        serde_json::to_string_pretty(&result).map_err(|e| e.to_string())?
+    } else {
+        result.report.clone()
+    };
+    write_report(&report, output_path)
+}
+
+fn run_diff(args: &[String]) -> Result<(), String> {
+    let mut from_path = None;
+    let mut to_path = None;
+    let mut settings = DiffSettings::default();
+    let mut json = false;
+    let mut output_path = None;
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        if flag == "--json" {
+            json = true;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for \"{}\".", flag))?;
+        match flag.as_str() {
+            "--from" => from_path = Some(PathBuf::from(value)),
+            "--to" => to_path = Some(PathBuf::from(value)),
+            "--settings" => settings = read_settings(value)?,
+            "--output" => output_path = Some(PathBuf::from(value)),
+            other => return Err(format!("Unknown option \"{}\".", other)),
+        }
+    }
+
+    let request = DiffRequest {
+        from: read_plasmid_file(&from_path.ok_or("Missing --from.")?)?,
+        to: read_plasmid_file(&to_path.ok_or("Missing --to.")?)?,
+        settings,
+    };
+    let result = diff_plasmids(&request)?;
+    let report = if json {
+        serde_json::to_string_pretty(&result).map_err(|e| e.to_string())?
+    } else {
+        result.report.clone()
+    };
+    write_report(&report, output_path)
+}
+
+fn run_duplicates(args: &[String]) -> Result<(), String> {
+    let mut directory = None;
+    let mut check_path = None;
+    let mut settings = DuplicateSettings::default();
+    let mut json = false;
+    let mut output_path = None;
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--json" => json = true,
+            "--top-level" => settings.recursive = false,
+            _ => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("Missing value for \"{}\".", flag))?;
+                match flag.as_str() {
+                    "--dir" => directory = Some(PathBuf::from(value)),
+                    "--check" => check_path = Some(PathBuf::from(value)),
+                    "--min-similarity" => settings.min_similarity = parse_fraction(value)?,
+                    "--output" => output_path = Some(PathBuf::from(value)),
+                    other => return Err(format!("Unknown option \"{}\".", other)),
+                }
+            }
+        }
+    }
+    let directory = directory.ok_or("Missing --dir.")?;
+
+    let report = match check_path {
+        Some(path) => {
+            let plasmid = read_plasmid_file(&path)?;
+            let request = CollectionCheckRequest {
+                sequence: plasmid.sequence,
+                topology: plasmid.topology,
+                directory,
+                settings,
+            };
+            let matches = check_collection(&request, None)?;
+            if json {
+                serde_json::to_string_pretty(&matches).map_err(|e| e.to_string())?
+            } else if matches.is_empty() {
+                format!("No copy of \"{}\" found.", path.display())
+            } else {
+                matches
+                    .iter()
+                    .map(|m| format!("{} ({:.1}%)", m.path.display(), 100.0 * m.similarity))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+        }
+        None => {
+            let result = find_duplicates(&directory, &settings, None)?;
+            if json {
+                serde_json::to_string_pretty(&result).map_err(|e| e.to_string())?
+            } else {
+                result.report
+            }
+        }
+    };
+    write_report(&report, output_path)
+}
+
+fn run_bind(args: &[String]) -> Result<(), String> {
+    let mut oligos: Vec<Oligo> = Vec::new();
+    let mut plasmid_path = None;
+    let mut collection = false;
+    let mut settings = BindingSettings::default();
+    let mut min_anneal = None;
+    let mut max_mismatches = None;
+    let mut json = false;
+    let mut output_path = None;
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--json" => json = true,
+            "--collection" => collection = true,
+            _ => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("Missing value for \"{}\".", flag))?;
+                match flag.as_str() {
+                    "--oligo" => oligos.push(Oligo {
+                        name: String::new(),
+                        sequence: value.clone(),
+                    }),
+                    "--oligos" => {
+                        let text = fs::read_to_string(value)
+                            .map_err(|e| format!("Could not read \"{}\": {}", value, e))?;
+                        oligos.extend(parse_oligos(&text));
+                    }
+                    "--plasmid" => plasmid_path = Some(PathBuf::from(value)),
+                    "--min-anneal" => {
+                        min_anneal = Some(
+                            value
+                                .parse()
+                                .map_err(|_| format!("Invalid length \"{}\".", value))?,
+                        );
+                    }
+                    "--max-mismatches" => {
+                        max_mismatches = Some(
+                            value
+                                .parse()
+                                .map_err(|_| format!("Invalid count \"{}\".", value))?,
+                        );
+                    }
+                    "--settings" => settings = read_settings(value)?,
+                    "--output" => output_path = Some(PathBuf::from(value)),
+                    other => return Err(format!("Unknown option \"{}\".", other)),
+                }
+            }
+        }
+    }
+    // Flags win over the settings file
+    settings.min_anneal = min_anneal.unwrap_or(settings.min_anneal);
+    settings.max_mismatches = max_mismatches.unwrap_or(settings.max_mismatches);
+
+    let report = match (plasmid_path, collection) {
+        (Some(path), false) => {
+            let plasmid = read_plasmid_file(&path)?;
+            let request = BindingSiteRequest {
+                oligos,
+                sequence: plasmid.sequence,
+                topology: plasmid.topology,
+                settings,
+            };
+            let result = find_binding_sites(&request)?;
+            if json {
+                serde_json::to_string_pretty(&result).map_err(|e| e.to_string())?
+            } else {
+                result.report
+            }
+        }
+        (None, true) => {
+            let request = CollectionBindingRequest { oligos, settings };
+            let result = find_collection_binding_sites(&request, None)?;
+            if json {
+                serde_json::to_string_pretty(&result).map_err(|e| e.to_string())?
+            } else {
+                let mut lines = vec![format!(
+                    "{} binding site(s) in {} plasmid(s)",
+                    result.sites.len(),
+                    result.index.plasmids
+                )];
+                lines.extend(
+                    result
+                        .sites
+                        .iter()
+                        .map(|s| format!("{}\t{}", s.path.display(), binding_site_line(&s.site))),
+                );
+                if !result.unbound.is_empty() {
+                    lines.push(String::new());
+                    lines.push(format!("No binding site: {}", result.unbound.join(", ")));
+                }
+                lines.join("\n")
+            }
+        }
+        (Some(_), true) => return Err("Give either --plasmid or --collection.".to_string()),
+        (None, false) => return Err("Missing --plasmid or --collection.".to_string()),
+    };
+    write_report(&report, output_path)
+}
+
+fn run_import(args: &[String]) -> Result<(), String> {
+    let mut input_path = None;
+    let mut output_path = None;
+    let mut annotate = false;
+    let mut settings = AnnotationSettings::default();
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        if flag == "--annotate" {
+            annotate = true;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for \"{}\".", flag))?;
+        match flag.as_str() {
+            "--input" => input_path = Some(PathBuf::from(value)),
+            "--output" => output_path = Some(PathBuf::from(value)),
+            "--min-identity" => settings.min_identity = parse_fraction(value)?,
+            "--min-coverage" => settings.min_coverage = parse_fraction(value)?,
+            other => return Err(format!("Unknown option \"{}\".", other)),
+        }
+    }
+
+    let mut plasmid = read_plasmid_file(&input_path.ok_or("Missing --input.")?)?;
+    let output_path = output_path.ok_or("Missing --output.")?;
+    if annotate {
+        let request = AnnotationRequest {
+            sequence: plasmid.sequence.clone(),
+            topology: plasmid.topology,
+            span: None,
+            features: plasmid.features.clone(),
+            settings,
+        };
+        let hits = find_library_features(&request, &annotation_library())?;
+        for hit in &hits {
+            println!(
+                "{}\t{}..{}\t{:.1}%",
+                hit.feature.label,
+                hit.feature.span[0],
+                hit.feature.span[1],
+                100.0 * hit.identity
+            );
+        }
+        plasmid
+            .features
+            .extend(hits.into_iter().map(|hit| hit.feature));
+        plasmid.features.sort_by_key(|f| f.span[0]);
+    }
+    write_genbank(&output_path, &plasmid)
+}
+
+/// A command line subcommand: its name, usage text, the message printed in
+/// front of its errors and the function running it.
+struct Subcommand {
+    name: &'static str,
+    usage: &'static str,
+    failure: &'static str,
+    run: fn(&[String]) -> Result<(), String>,
+}
+
+const SUBCOMMANDS: &[Subcommand] = &[
+    Subcommand {
+        name: "subclone",
+        usage: SUBCLONE_USAGE,
+        failure: "Subcloning failed",
+        run: run_subclone,
+    },
+    Subcommand {
+        name: "verify",
+        usage: VERIFY_USAGE,
+        failure: "Verification failed",
+        run: run_verify,
+    },
+    Subcommand {
+        name: "diff",
+        usage: DIFF_USAGE,
+        failure: "Comparison failed",
+        run: run_diff,
+    },
+    Subcommand {
+        name: "duplicates",
+        usage: DUPLICATES_USAGE,
+        failure: "Duplicate search failed",
+        run: run_duplicates,
+    },
+    Subcommand {
+        name: "bind",
+        usage: BIND_USAGE,
+        failure: "Binding site search failed",
+        run: run_bind,
+    },
+    Subcommand {
+        name: "import",
+        usage: IMPORT_USAGE,
+        failure: "Import failed",
+        run: run_import,
+    },
+    Subcommand {
+        name: "nanopore",
+        usage: NANOPORE_USAGE,
+        failure: "Nanopore verification failed",
+        run: run_nanopore,
+    },
+];
+
+/// Run a command line subcommand instead of the app. Returns the exit code, or
+/// `None` if the arguments do not name a subcommand and the app should start.
+pub fn run_cli(args: &[String]) -> Option<i32> {
+    let name = args.get(1)?;
+    let subcommand = SUBCOMMANDS.iter().find(|s| s.name == name)?;
+    let args = &args[2..];
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        println!("{}", subcommand.usage);
+        return Some(0);
+    }
+    Some(match (subcommand.run)(args) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}: {}\n\n{}", subcommand.failure, e, subcommand.usage);
+            1
+        }
+    })
+}
@@ -0,0 +1,498 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::codons::{codon_adaptation_index, relative_adaptiveness, CODON_WEIGHTS};
+use crate::export::table_to_csv;
+use crate::optimization::{SHINE_DALGARNO, SHINE_DALGARNO_SPACING};
+use crate::plasmid_file::Feature;
+use crate::sequence::{fraction_gc, sanitize_sequence, Direction, Topology};
+use crate::translation::{feature_genetic_code, feature_sequence, genetic_code};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CodonUsageSettings {
+    /// Codons below this relative adaptiveness count as rare.
+    pub rare_codon_threshold: f64,
+    /// Window (codons) searched for rare codon clusters.
+    pub cluster_window: usize,
+    /// Rare codons within a window that make a cluster.
+    pub cluster_min_rare: usize,
+    /// Codons below this relative adaptiveness draw on scarce tRNAs when
+    /// used over and over.
+    pub trna_threshold: f64,
+    /// Window (codons) searched for tRNA-limited stretches.
+    pub trna_window: usize,
+    /// Uses of the same scarce codon within a window that make a stretch.
+    pub trna_min_repeats: usize,
+    /// Window (bases) of the GC profile.
+    pub gc_window: usize,
+}
+
+impl Default for CodonUsageSettings {
+    fn default() -> Self {
+        Self {
+            rare_codon_threshold: 0.1,
+            cluster_window: 10,
+            cluster_min_rare: 3,
+            trna_threshold: 0.5,
+            trna_window: 20,
+            trna_min_repeats: 4,
+            gc_window: 51,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodonUsageRequest {
+    pub sequence: String,
+    pub topology: Topology,
+    /// Features of the plasmid, only CDS features are analyzed.
+    pub features: Vec<Feature>,
+    /// Expression host, key of the codon usage tables.
+    pub organism: String,
+    #[serde(default)]
+    pub settings: CodonUsageSettings,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CdsWarningKind {
+    /// Length is not a multiple of three.
+    Frame,
+    MissingStart,
+    MissingStop,
+    InternalStop,
+    /// In-frame start codon behind a Shine-Dalgarno-like motif.
+    InternalStart,
+    /// Bases that are not A, C, G or T.
+    AmbiguousBases,
+    /// Translation table other than the standard code, which the host tables
+    /// are for.
+    GeneticCode,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CdsWarning {
+    pub kind: CdsWarningKind,
+    /// Residue the warning refers to (1-based), if any.
+    pub residue: Option<usize>,
+    pub message: String,
+}
+
+/// Stretch of codons flagged by the analysis.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodonStretch {
+    /// First and last residue (1-based, inclusive).
+    pub residues: [usize; 2],
+    /// Bases on the plasmid (1-based, inclusive). Spans where the end lies
+    /// before the start run across the origin.
+    pub span: [usize; 2],
+    pub codons: Vec<String>,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CdsAnalysis {
+    pub label: String,
+    pub span: [usize; 2],
+    pub directionality: Direction,
+    pub length: usize,
+    pub protein: String,
+    /// `None` for CDS read with a code other than the standard one.
+    pub cai: Option<f64>,
+    pub gc: f64,
+    /// GC content at the third codon position.
+    pub gc3: f64,
+    /// Relative adaptiveness of every codon, empty without a CAI.
+    pub codon_scores: Vec<f64>,
+    /// GC content of the window centered on every codon.
+    pub gc_profile: Vec<f64>,
+    pub rare_codons: usize,
+    pub rare_clusters: Vec<CodonStretch>,
+    pub trna_limited: Vec<CodonStretch>,
+    pub warnings: Vec<CdsWarning>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodonUsageReport {
+    pub analyses: Vec<CdsAnalysis>,
+    /// One row per CDS, for saving as a spreadsheet.
+    pub summary_csv: String,
+}
+
+/// Plasmid span of residues of a CDS, wrapping around the origin.
+fn residue_span(
+    feature_span: [usize; 2],
+    direction: Direction,
+    length: usize,
+    residues: [usize; 2],
+) -> [usize; 2] {
+    let wrap = |position: usize| (position - 1) % length + 1;
+    let [start, end] = feature_span;
+    let end = if end < start { end + length } else { end };
+    let [first, last] = residues;
+    match direction {
+        Direction::Fwd => [wrap(start + 3 * (first - 1)), wrap(start + 3 * last - 1)],
+        Direction::Rev => [wrap(end + 1 - 3 * last), wrap(end - 3 * (first - 1))],
+    }
+}
+
+/// Merge overlapping windows (0-based codon indices, inclusive) into stretches.
+fn merge_windows(windows: Vec<[usize; 2]>) -> Vec<[usize; 2]> {
+    let mut merged: Vec<[usize; 2]> = Vec::new();
+    for window in windows {
+        match merged.last_mut() {
+            Some(last) if window[0] <= last[1] + 1 => last[1] = last[1].max(window[1]),
+            _ => merged.push(window),
+        }
+    }
+    merged
+}
+
+/// Analyze the codon usage of a single CDS against a host table.
+pub fn analyze_cds(
+    sequence: &str,
+    feature: &Feature,
+    organism: &str,
+    settings: &CodonUsageSettings,
+) -> CdsAnalysis {
+    let direction = feature.directionality.unwrap_or(Direction::Fwd);
+    // Features with an unknown table are read with the bacterial code
+    let code = feature_genetic_code(feature)
+        .unwrap_or_else(|_| genetic_code(11).expect("Table 11 is defined"));
+    let cds = feature_sequence(sequence, feature);
+    let codons: Vec<&str> = cds
+        .as_bytes()
+        .chunks_exact(3)
+        .map(|c| std::str::from_utf8(c).unwrap_or("NNN"))
+        .collect();
+    let protein: String = codons.iter().map(|c| code.translate_codon(c)).collect();
+    let aas: Vec<char> = protein.chars().collect();
+
+    let mut warnings = Vec::new();
+    if !cds.len().is_multiple_of(3) {
+        warnings.push(CdsWarning {
+            kind: CdsWarningKind::Frame,
+            residue: None,
+            message: format!(
+                "Length of {} bp is not a multiple of three, {} trailing bases.",
+                cds.len(),
+                cds.len() % 3
+            ),
+        });
+    }
+    if !codons.first().is_some_and(|c| code.is_start(c)) {
+        warnings.push(CdsWarning {
+            kind: CdsWarningKind::MissingStart,
+            residue: Some(1),
+            message: "Does not begin with a start codon.".to_string(),
+        });
+    }
+    if aas.last() != Some(&'*') {
+        warnings.push(CdsWarning {
+            kind: CdsWarningKind::MissingStop,
+            residue: None,
+            message: "Does not end with a stop codon.".to_string(),
+        });
+    }
+    for (i, aa) in aas.iter().enumerate() {
+        if *aa == '*' && i + 1 < aas.len() {
+            warnings.push(CdsWarning {
+                kind: CdsWarningKind::InternalStop,
+                residue: Some(i + 1),
+                message: format!("In-frame stop codon {} at residue {}.", codons[i], i + 1),
+            });
+        } else if *aa == 'X' {
+            warnings.push(CdsWarning {
+                kind: CdsWarningKind::AmbiguousBases,
+                residue: Some(i + 1),
+                message: format!(
+                    "Codon {} at residue {} cannot be translated.",
+                    codons[i],
+                    i + 1
+                ),
+            });
+        }
+    }
+    let [min_spacing, max_spacing] = SHINE_DALGARNO_SPACING;
+    for (i, codon) in codons.iter().enumerate().skip(1) {
+        if !matches!(*codon, "ATG" | "GTG") {
+            continue;
+        }
+        let start = 3 * i;
+        let has_sd = SHINE_DALGARNO.iter().any(|core| {
+            (min_spacing..=max_spacing).any(|spacing| {
+                start
+                    .checked_sub(spacing + core.len())
+                    .is_some_and(|from| &cds[from..from + core.len()] == *core)
+            })
+        });
+        if has_sd {
+            warnings.push(CdsWarning {
+                kind: CdsWarningKind::InternalStart,
+                residue: Some(i + 1),
+                message: format!(
+                    "{} at residue {} follows a Shine-Dalgarno-like motif and may start translation.",
+                    codon,
+                    i + 1
+                ),
+            });
+        }
+    }
+
+    // Host tables give weights to the codons of the standard code, they do not
+    // say how a host reads codons that other codes assign differently
+    let standard = genetic_code(1).expect("Table 1 is defined");
+    let host_scored = code.amino_acids == standard.amino_acids;
+    if !host_scored {
+        warnings.push(CdsWarning {
+            kind: CdsWarningKind::GeneticCode,
+            residue: None,
+            message: format!(
+                "Codons are not scored against the host for translation table {}.",
+                code.id
+            ),
+        });
+    }
+
+    // Met, Trp and stops have no synonymous choice and never count as rare
+    let scores: Vec<f64> = if host_scored {
+        aas.iter()
+            .zip(&codons)
+            .map(|(aa, codon)| relative_adaptiveness(organism, *aa, codon))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let has_choice = |i: usize| !matches!(aas[i], 'M' | 'W' | '*' | 'X');
+    let scored_below = |i: usize, threshold: f64| {
+        has_choice(i) && scores.get(i).is_some_and(|&score| score < threshold)
+    };
+    let is_rare = |i: usize| scored_below(i, settings.rare_codon_threshold);
+    let rare_codons = (0..codons.len()).filter(|&i| is_rare(i)).count();
+
+    let stretch = |[first, last]: [usize; 2], detail: String| CodonStretch {
+        residues: [first + 1, last + 1],
+        span: residue_span(
+            feature.span,
+            direction,
+            sequence.len(),
+            [first + 1, last + 1],
+        ),
+        codons: codons[first..=last].iter().map(|c| c.to_string()).collect(),
+        detail,
+    };
+
+    let window = settings.cluster_window.clamp(1, codons.len().max(1));
+    let mut cluster_windows = Vec::new();
+    for start in 0..=codons.len().saturating_sub(window) {
+        let rare: Vec<usize> = (start..(start + window).min(codons.len()))
+            .filter(|&i| is_rare(i))
+            .collect();
+        if rare.len() >= settings.cluster_min_rare.max(1) {
+            cluster_windows.push([rare[0], rare[rare.len() - 1]]);
+        }
+    }
+    let rare_clusters = merge_windows(cluster_windows)
+        .into_iter()
+        .map(|range| {
+            let count = (range[0]..=range[1]).filter(|&i| is_rare(i)).count();
+            stretch(range, format!("{} rare codons", count))
+        })
+        .collect();
+
+    let window = settings.trna_window.clamp(1, codons.len().max(1));
+    // Windows in order of their start, so every codon's windows stay sorted
+    let mut limited_windows: BTreeMap<&str, Vec<[usize; 2]>> = BTreeMap::new();
+    for start in 0..=codons.len().saturating_sub(window) {
+        let mut uses: HashMap<&str, Vec<usize>> = HashMap::new();
+        for i in (start..(start + window).min(codons.len()))
+            .filter(|&i| scored_below(i, settings.trna_threshold))
+        {
+            uses.entry(codons[i]).or_default().push(i);
+        }
+        for (codon, positions) in uses {
+            if positions.len() >= settings.trna_min_repeats.max(2) {
+                limited_windows
+                    .entry(codon)
+                    .or_default()
+                    .push([positions[0], positions[positions.len() - 1]]);
+            }
+        }
+    }
+    let mut trna_limited: Vec<CodonStretch> = Vec::new();
+    for (codon, ranges) in limited_windows {
+        for range in merge_windows(ranges) {
+            let count = (range[0]..=range[1])
+                .filter(|&i| codons[i] == codon)
+                .count();
+            trna_limited.push(stretch(range, format!("{} x {}", count, codon)));
+        }
+    }
+    trna_limited.sort_by(|a, b| a.residues.cmp(&b.residues).then(a.codons.cmp(&b.codons)));
+
+    let half = settings.gc_window / 2;
+    let gc_profile = (0..codons.len())
+        .map(|i| {
+            let centre = 3 * i + 1;
+            let from = centre.saturating_sub(half);
+            let to = (centre + half + 1).min(cds.len());
+            fraction_gc(&cds[from..to])
+        })
+        .collect();
+    let third_positions: String = codons.iter().map(|c| &c[2..]).collect();
+
+    CdsAnalysis {
+        label: feature.label.clone(),
+        span: feature.span,
+        directionality: direction,
+        length: cds.len(),
+        cai: host_scored.then(|| codon_adaptation_index(organism, &cds)),
+        gc: fraction_gc(&cds),
+        gc3: fraction_gc(&third_positions),
+        protein,
+        codon_scores: scores,
+        gc_profile,
+        rare_codons,
+        rare_clusters,
+        trna_limited,
+        warnings,
+    }
+}
+
+/// Analyze every CDS feature of a plasmid.
+pub fn analyze_codon_usage(request: &CodonUsageRequest) -> Result<Vec<CdsAnalysis>, String> {
+    let sequence = sanitize_sequence(&request.sequence);
+    if sequence.is_empty() {
+        return Err("The sequence is empty.".to_string());
+    }
+    if !CODON_WEIGHTS.contains_key(&request.organism) {
+        return Err(format!("No codon table for \"{}\".", request.organism));
+    }
+    let in_bounds = |i: usize| i >= 1 && i <= sequence.len();
+    Ok(request
+        .features
+        .iter()
+        .filter(|f| f.feature_type.eq_ignore_ascii_case("CDS"))
+        .filter(|f| in_bounds(f.span[0]) && in_bounds(f.span[1]))
+        .filter(|f| request.topology == Topology::Circular || f.span[0] <= f.span[1])
+        .map(|f| analyze_cds(&sequence, f, &request.organism, &request.settings))
+        .collect())
+}
+
+/// Summary table of an analysis for CSV export.
+pub fn codon_usage_table(analyses: &[CdsAnalysis]) -> Vec<Vec<String>> {
+    let mut table = vec![[
+        "Feature",
+        "Span",
+        "Length",
+        "CAI",
+        "GC",
+        "GC3",
+        "Rare codons",
+        "Rare clusters",
+        "tRNA-limited stretches",
+        "Warnings",
+    ]
+    .iter()
+    .map(|h| h.to_string())
+    .collect()];
+    for analysis in analyses {
+        let stretches = |stretches: &[CodonStretch]| {
+            stretches
+                .iter()
+                .map(|s| format!("{}-{} ({})", s.residues[0], s.residues[1], s.detail))
+                .collect::<Vec<_>>()
+                .join("; ")
+        };
+        table.push(vec![
+            analysis.label.clone(),
+            format!("{}..{}", analysis.span[0], analysis.span[1]),
+            analysis.length.to_string(),
+            analysis
+                .cai
+                .map(|cai| format!("{:.3}", cai))
+                .unwrap_or_default(),
+            format!("{:.3}", analysis.gc),
+            format!("{:.3}", analysis.gc3),
+            analysis.rare_codons.to_string(),
+            stretches(&analysis.rare_clusters),
+            stretches(&analysis.trna_limited),
+            analysis
+                .warnings
+                .iter()
+                .map(|w| w.message.as_str())
+                .collect::<Vec<_>>()
+                .join(" "),
+        ]);
+    }
+    table
+}
+
+#[tauri::command]
+/// Codon usage of every CDS feature against an expression host.
+pub fn analyze_cds_codon_usage(request: CodonUsageRequest) -> Result<CodonUsageReport, String> {
+    let analyses = analyze_codon_usage(&request)?;
+    let summary_csv = table_to_csv(&codon_usage_table(&analyses));
+    Ok(CodonUsageReport {
+        analyses,
+        summary_csv,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Start, four AGG and four ATA codons taking turns, lysines and a stop.
+    const CDS: &str = "ATGAGGATAAGGATAAGGATAAGGATAAAAAAAAAATAA";
+
+    fn cds_feature(qualifiers: Vec<(String, String)>) -> Feature {
+        Feature {
+            label: "test CDS".to_string(),
+            feature_type: "CDS".to_string(),
+            span: [1, CDS.len()],
+            directionality: Some(Direction::Fwd),
+            segments: Vec::new(),
+            qualifiers,
+        }
+    }
+
+    #[test]
+    fn scarce_codons_are_reported_in_order() {
+        let settings = CodonUsageSettings::default();
+        let analysis = analyze_cds(CDS, &cds_feature(Vec::new()), "Escherichia coli", &settings);
+        assert_eq!(analysis.protein, "MRIRIRIRIKKK*");
+        assert!(analysis.warnings.is_empty());
+        assert!(analysis.cai.is_some_and(|cai| cai < 0.5));
+        assert_eq!(analysis.codon_scores.len(), 13);
+        assert_eq!(analysis.rare_codons, 8);
+        let limited: Vec<([usize; 2], &str)> = analysis
+            .trna_limited
+            .iter()
+            .map(|s| (s.residues, s.detail.as_str()))
+            .collect();
+        assert_eq!(limited, [([2, 8], "4 x AGG"), ([3, 9], "4 x ATA")]);
+        assert_eq!(analysis.trna_limited[0].span, [4, 24]);
+    }
+
+    #[test]
+    fn other_codes_are_not_scored_against_the_host() {
+        let feature = cds_feature(vec![("transl_table".to_string(), "4".to_string())]);
+        let settings = CodonUsageSettings::default();
+        let analysis = analyze_cds(CDS, &feature, "Escherichia coli", &settings);
+        assert_eq!(analysis.protein, "MRIRIRIRIKKK*");
+        assert_eq!(analysis.cai, None);
+        assert!(analysis.codon_scores.is_empty());
+        assert_eq!(analysis.rare_codons, 0);
+        assert!(analysis.trna_limited.is_empty());
+        let kinds: Vec<CdsWarningKind> = analysis.warnings.iter().map(|w| w.kind).collect();
+        assert_eq!(kinds, [CdsWarningKind::GeneticCode]);
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+
+/// Codon frequency tables bundled with the frontend, organism -> amino acid -> codon -> weight.
+pub type CodonWeights = HashMap<String, HashMap<String, HashMap<String, f64>>>;
+
+pub static CODON_WEIGHTS: Lazy<CodonWeights> = Lazy::new(|| {
+    serde_json::from_str(include_str!(
+        "../../../../docker/app/ivaprime/static/data/codonWeights.json"
+    ))
+    .expect("Bundled codonWeights.json is malformed")
+});
+
+/// Standard codon table. DNA -> AA
+#[rustfmt::skip]
+const CODON_TABLE: [(&str, char); 64] = [
+    ("GCT", 'A'), ("GCC", 'A'), ("GCA", 'A'), ("GCG", 'A'),
+    ("TGT", 'C'), ("TGC", 'C'),
+    ("GAT", 'D'), ("GAC", 'D'),
+    ("GAA", 'E'), ("GAG", 'E'),
+    ("TTT", 'F'), ("TTC", 'F'),
+    ("GGT", 'G'), ("GGC", 'G'), ("GGA", 'G'), ("GGG", 'G'),
+    ("CAT", 'H'), ("CAC", 'H'),
+    ("ATT", 'I'), ("ATC", 'I'), ("ATA", 'I'),
+    ("AAA", 'K'), ("AAG", 'K'),
+    ("TTA", 'L'), ("TTG", 'L'), ("CTT", 'L'), ("CTC", 'L'), ("CTA", 'L'), ("CTG", 'L'),
+    ("ATG", 'M'),
+    ("AAT", 'N'), ("AAC", 'N'),
+    ("CCT", 'P'), ("CCC", 'P'), ("CCA", 'P'), ("CCG", 'P'),
+    ("CAA", 'Q'), ("CAG", 'Q'),
+    ("CGT", 'R'), ("CGC", 'R'), ("CGA", 'R'), ("CGG", 'R'), ("AGA", 'R'), ("AGG", 'R'),
+    ("TCT", 'S'), ("TCC", 'S'), ("TCA", 'S'), ("TCG", 'S'), ("AGT", 'S'), ("AGC", 'S'),
+    ("ACT", 'T'), ("ACC", 'T'), ("ACA", 'T'), ("ACG", 'T'),
+    ("GTT", 'V'), ("GTC", 'V'), ("GTA", 'V'), ("GTG", 'V'),
+    ("TGG", 'W'),
+    ("TAT", 'Y'), ("TAC", 'Y'),
+    ("TAA", '*'), ("TAG", '*'), ("TGA", '*'),
+];
+
+/// Translate a single codon with the standard code, `?` for anything unknown.
+pub fn translate_codon(codon: &str) -> char {
+    let codon = codon.replace('U', "T");
+    CODON_TABLE
+        .iter()
+        .find(|(c, _)| *c == codon)
+        .map(|(_, aa)| *aa)
+        .unwrap_or('?')
+}
+
+/// All codons of the standard code encoding an amino acid.
+pub fn synonymous_codons(aa: char) -> Vec<&'static str> {
+    CODON_TABLE
+        .iter()
+        .filter(|(_, a)| *a == aa)
+        .map(|(c, _)| *c)
+        .collect()
+}
+
+/// Codon usage weight of a codon for an organism, 0 if unknown.
+pub fn codon_weight(organism: &str, aa: char, codon: &str) -> f64 {
+    CODON_WEIGHTS
+        .get(organism)
+        .and_then(|table| table.get(&aa.to_string()))
+        .and_then(|codons| codons.get(codon))
+        .copied()
+        .unwrap_or(0.0)
+}
+
+/// Relative adaptiveness of a codon, its usage weight over the best weight
+/// for the amino acid.
+pub fn relative_adaptiveness(organism: &str, aa: char, codon: &str) -> f64 {
+    let best = synonymous_codons(aa)
+        .into_iter()
+        .map(|c| codon_weight(organism, aa, c))
+        .fold(0.0, f64::max);
+    if best > 0.0 {
+        codon_weight(organism, aa, codon) / best
+    } else {
+        0.0
+    }
+}
+
+/// Codon adaptation index of a coding sequence: geometric mean of the relative
+/// adaptiveness of its codons, leaving out Met, Trp and stop codons.
+pub fn codon_adaptation_index(organism: &str, cds: &str) -> f64 {
+    let mut log_sum = 0.0;
+    let mut count = 0;
+    for codon in cds.as_bytes().chunks_exact(3) {
+        let codon = String::from_utf8_lossy(codon);
+        let aa = translate_codon(&codon);
+        if matches!(aa, 'M' | 'W' | '*' | '?') {
+            continue;
+        }
+        // Codons missing from the table would zero the whole index
+        log_sum += relative_adaptiveness(organism, aa, &codon).max(0.01).ln();
+        count += 1;
+    }
+    if count == 0 {
+        0.0
+    } else {
+        (log_sum / count as f64).exp()
+    }
+}
+
+/// Number of differing bases between two codons.
+pub fn codon_distance(a: &str, b: &str) -> usize {
+    a.bytes().zip(b.bytes()).filter(|(x, y)| x != y).count()
+}
@@ -0,0 +1,792 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::alignment::encode_kmer;
+use crate::binding_sites::{oligo_bases, BindingSettings, Oligo};
+use crate::duplicates::{
+    fingerprint, plasmid_files, similarity, DuplicateSettings, Fingerprint, SkippedFile,
+};
+use crate::jobs::{spawn_job, JobReporter};
+use crate::plasmid_file::{read_plasmid_file, Feature};
+use crate::sequence::{
+    iupac_compatible, reverse_complementary, sanitize_sequence, Direction, Topology,
+};
+use crate::store::{Store, Versioned};
+
+/// Length of the k-mers used to seed sequence searches. Short enough that
+/// primer searches and binding-site searches with their default anneal
+/// length and one mismatch are seeded from the index.
+const KMER_LENGTH: usize = 7;
+
+/// Bases at the 3' end of a primer that have to anneal by default.
+const DEFAULT_ANNEAL_LENGTH: usize = 18;
+
+/// Qualifiers searched besides the feature label.
+const NAME_QUALIFIERS: [&str; 3] = ["gene", "product", "standard_name"];
+
+/// Index loaded from disk together with its k-mer positions, shared between
+/// commands. The lock also serializes changes to the index file. The k-mer
+/// positions are only rebuilt when indexed files change.
+static COLLECTION: Lazy<Mutex<Option<LoadedCollection>>> = Lazy::new(|| Mutex::new(None));
+
+/// The index file, in the app data directory.
+static INDEX: Store<CollectionIndex> = Store::new("collection_index.json", false);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CollectionSettings {
+    /// Folders holding the user's plasmid files.
+    pub folders: Vec<PathBuf>,
+    /// Also index subfolders.
+    pub recursive: bool,
+    /// Searches stop after this many hits.
+    pub max_hits: usize,
+}
+
+impl Default for CollectionSettings {
+    fn default() -> Self {
+        Self {
+            folders: Vec::new(),
+            recursive: true,
+            max_hits: 500,
+        }
+    }
+}
+
+/// Plasmid file of the collection. `modified` and `size` tell whether the
+/// file has to be read again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedPlasmid {
+    pub path: PathBuf,
+    /// Milliseconds since the Unix epoch.
+    pub modified: u64,
+    pub size: u64,
+    pub name: String,
+    pub topology: Topology,
+    pub sequence: String,
+    pub features: Vec<Feature>,
+    pub fingerprint: Fingerprint,
+}
+
+/// Index of the user's plasmid collection, stored in the app data directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionIndex {
+    pub format: u32,
+    #[serde(default)]
+    pub settings: CollectionSettings,
+    #[serde(default)]
+    pub plasmids: Vec<IndexedPlasmid>,
+}
+
+impl Default for CollectionIndex {
+    fn default() -> Self {
+        Self {
+            format: Self::FORMAT,
+            settings: CollectionSettings::default(),
+            plasmids: Vec::new(),
+        }
+    }
+}
+
+impl Versioned for CollectionIndex {
+    const FORMAT: u32 = 1;
+    const DESCRIPTION: &'static str = "collection index";
+
+    fn format(&self) -> u32 {
+        self.format
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexSummary {
+    pub plasmids: usize,
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    /// Files that could not be read and folders that could not be listed.
+    pub skipped: Vec<SkippedFile>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CollectionSearchKind {
+    /// DNA sequence on either strand.
+    Sequence,
+    /// Feature label, gene or product, matched in part and ignoring case.
+    Feature,
+    /// Primer whose 3' end anneals on either strand. Bases beyond
+    /// `annealLength` are treated as 5' tail.
+    Primer,
+    /// Whole plasmid sequence, finds copies and close relatives.
+    Plasmid,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionSearchRequest {
+    pub kind: CollectionSearchKind,
+    pub query: String,
+    #[serde(default)]
+    pub max_mismatches: usize,
+    /// Bases at the 3' end a primer needs to anneal with, 18 if not given.
+    #[serde(default)]
+    pub anneal_length: Option<usize>,
+    /// Topology of the query for plasmid searches.
+    #[serde(default)]
+    pub topology: Option<Topology>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionHit {
+    pub path: PathBuf,
+    pub name: String,
+    pub length: usize,
+    /// 1-based, inclusive, `None` for plasmid searches.
+    pub span: Option<[usize; 2]>,
+    pub strand: Option<Direction>,
+    pub mismatches: usize,
+    /// Matching feature for feature searches.
+    pub feature: Option<String>,
+    pub feature_type: Option<String>,
+    /// Estimated similarity for plasmid searches.
+    pub similarity: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionSearchResult {
+    pub hits: Vec<CollectionHit>,
+    /// More hits were found than `maxHits`.
+    pub truncated: bool,
+    pub index: IndexSummary,
+}
+
+/// Positions of every k-mer on the top strand of the indexed plasmids.
+///
+/// Only kept in memory: it takes several times the space of the sequences in
+/// the index file and is rebuilt from them faster than it could be read back.
+struct KmerIndex {
+    /// `(plasmid, start)` pairs by k-mer code.
+    positions: Vec<Vec<(u32, u32)>>,
+}
+
+impl KmerIndex {
+    fn build(plasmids: &[IndexedPlasmid]) -> Self {
+        let mut positions = vec![Vec::new(); 1 << (2 * KMER_LENGTH)];
+        let mut kmer = [0u8; KMER_LENGTH];
+        for (n, plasmid) in plasmids.iter().enumerate() {
+            let bases = plasmid.sequence.as_bytes();
+            let length = bases.len();
+            let starts = match plasmid.topology {
+                Topology::Circular if length >= KMER_LENGTH => length,
+                _ => (length + 1).saturating_sub(KMER_LENGTH),
+            };
+            for start in 0..starts {
+                for (i, base) in kmer.iter_mut().enumerate() {
+                    *base = bases[(start + i) % length];
+                }
+                if let Some(code) = encode_kmer(&kmer) {
+                    positions[code as usize].push((n as u32, start as u32));
+                }
+            }
+        }
+        Self { positions }
+    }
+
+    fn get(&self, code: u32) -> &[(u32, u32)] {
+        &self.positions[code as usize]
+    }
+}
+
+struct LoadedCollection {
+    index: CollectionIndex,
+    kmers: KmerIndex,
+}
+
+/// Modification time in milliseconds and size of a file.
+fn file_stamp(path: &Path) -> Option<(u64, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_millis() as u64;
+    Some((modified, metadata.len()))
+}
+
+/// Bring the index up to date with the configured folders, reading only
+/// files that are new or changed since they were indexed.
+pub fn update_index(
+    index: &mut CollectionIndex,
+    reporter: Option<&JobReporter>,
+) -> Result<IndexSummary, String> {
+    let mut summary = IndexSummary::default();
+    let mut paths = Vec::new();
+    for folder in &index.settings.folders {
+        match plasmid_files(folder, index.settings.recursive) {
+            Ok(files) => paths.extend(files),
+            Err(error) => summary.skipped.push(SkippedFile {
+                path: folder.clone(),
+                error,
+            }),
+        }
+    }
+    paths.sort();
+    paths.dedup();
+
+    let fingerprint_settings = DuplicateSettings::default();
+    let mut previous: HashMap<PathBuf, IndexedPlasmid> = index
+        .plasmids
+        .drain(..)
+        .map(|plasmid| (plasmid.path.clone(), plasmid))
+        .collect();
+    for (i, path) in paths.iter().enumerate() {
+        if let Some(reporter) = reporter {
+            if reporter.is_cancelled() {
+                // Keep what is done so far, the rest is picked up next time
+                index.plasmids.extend(previous.into_values());
+                return Err("Indexing cancelled.".to_string());
+            }
+            reporter.progress(i, paths.len(), format!("Indexing {}", path.display()));
+        }
+        let Some((modified, size)) = file_stamp(path) else {
+            continue;
+        };
+        let known = previous.remove(path);
+        if let Some(plasmid) = known
+            .as_ref()
+            .filter(|p| p.modified == modified && p.size == size)
+        {
+            index.plasmids.push(plasmid.clone());
+            continue;
+        }
+        match read_plasmid_file(path) {
+            Ok(plasmid) => {
+                let sequence = sanitize_sequence(&plasmid.sequence);
+                index.plasmids.push(IndexedPlasmid {
+                    path: path.clone(),
+                    modified,
+                    size,
+                    name: plasmid.name,
+                    topology: plasmid.topology,
+                    fingerprint: fingerprint(&sequence, plasmid.topology, &fingerprint_settings),
+                    sequence,
+                    features: plasmid.features,
+                });
+                if known.is_some() {
+                    summary.updated += 1;
+                } else {
+                    summary.added += 1;
+                }
+            }
+            Err(error) => {
+                if known.is_some() {
+                    summary.removed += 1;
+                }
+                summary.skipped.push(SkippedFile {
+                    path: path.clone(),
+                    error,
+                });
+            }
+        }
+    }
+    summary.removed += previous.len();
+    index.plasmids.sort_by(|a, b| a.path.cmp(&b.path));
+    summary.plasmids = index.plasmids.len();
+    Ok(summary)
+}
+
+/// The loaded collection, read from disk on first use.
+fn loaded_collection(
+    collection: &mut Option<LoadedCollection>,
+) -> Result<&mut LoadedCollection, String> {
+    let loaded = match collection.take() {
+        Some(loaded) => loaded,
+        None => {
+            let index = INDEX.load()?;
+            LoadedCollection {
+                kmers: KmerIndex::build(&index.plasmids),
+                index,
+            }
+        }
+    };
+    Ok(collection.insert(loaded))
+}
+
+/// Run `work` on the up to date collection, saving the index if files were
+/// added, changed or removed.
+fn with_collection<T>(
+    reporter: Option<&JobReporter>,
+    work: impl FnOnce(&LoadedCollection, IndexSummary) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut collection = COLLECTION.lock().map_err(|e| e.to_string())?;
+    let loaded = loaded_collection(&mut collection)?;
+    let outcome = update_index(&mut loaded.index, reporter);
+    let changed = match &outcome {
+        Ok(summary) => summary.added + summary.updated + summary.removed > 0,
+        Err(_) => true,
+    };
+    if changed {
+        loaded.kmers = KmerIndex::build(&loaded.index.plasmids);
+        INDEX.save(&loaded.index)?;
+    }
+    work(loaded, outcome?)
+}
+
+/// Run `work` on the settings and plasmids of the up to date collection.
+pub fn with_indexed_plasmids<T>(
+    reporter: Option<&JobReporter>,
+    work: impl FnOnce(&CollectionSettings, &[IndexedPlasmid]) -> Result<T, String>,
+) -> Result<T, String> {
+    with_collection(reporter, |collection, _| {
+        work(&collection.index.settings, &collection.index.plasmids)
+    })
+}
+
+/// Run `work` on the plasmids of the up to date collection, together with
+/// the indices of the plasmids each oligo may bind.
+pub fn with_binding_candidates<T>(
+    reporter: Option<&JobReporter>,
+    oligos: &[Oligo],
+    settings: &BindingSettings,
+    work: impl FnOnce(&[IndexedPlasmid], Vec<Vec<usize>>, IndexSummary) -> Result<T, String>,
+) -> Result<T, String> {
+    with_collection(reporter, |collection, summary| {
+        let candidates = oligos
+            .iter()
+            .map(|oligo| collection.anneal_candidates(&oligo_bases(&oligo.sequence), settings))
+            .collect();
+        work(&collection.index.plasmids, candidates, summary)
+    })
+}
+
+/// Mismatches between `query` and a plasmid from `start` on, `None` once
+/// more than `max_mismatches` are found.
+fn count_mismatches(
+    bases: &[u8],
+    start: usize,
+    query: &[u8],
+    max_mismatches: usize,
+) -> Option<usize> {
+    let mut mismatches = 0;
+    for (i, &q) in query.iter().enumerate() {
+        if !iupac_compatible(bases[(start + i) % bases.len()], q) {
+            mismatches += 1;
+            if mismatches > max_mismatches {
+                return None;
+            }
+        }
+    }
+    Some(mismatches)
+}
+
+/// Seeds of a query matched with at most `max_mismatches` mismatches.
+///
+/// Splitting the query into `max_mismatches + 1` pieces, every match holds at
+/// least one of them unchanged, so the pieces' k-mers give all candidates.
+/// Returns the offset and k-mer code of each piece's first bases, `None` for
+/// queries too short for that or with degenerate bases in a seed.
+fn seeds(query: &[u8], max_mismatches: usize) -> Option<Vec<(usize, u32)>> {
+    let pieces = max_mismatches + 1;
+    let piece_length = query.len() / pieces;
+    if piece_length < KMER_LENGTH {
+        return None;
+    }
+    (0..pieces)
+        .map(|p| {
+            let offset = p * piece_length;
+            let code = encode_kmer(&query[offset..offset + KMER_LENGTH])?;
+            Some((offset, code as u32))
+        })
+        .collect()
+}
+
+/// Top strand start positions (0-based) where `query` matches with at most
+/// `max_mismatches` mismatches, as `(plasmid, start, mismatches)`. Queries
+/// that cannot be seeded are compared against every position.
+fn approximate_matches(
+    collection: &LoadedCollection,
+    query: &[u8],
+    max_mismatches: usize,
+) -> Vec<(usize, usize, usize)> {
+    let plasmids = &collection.index.plasmids;
+    let fits = |n: usize, start: usize| {
+        let plasmid = &plasmids[n];
+        let length = plasmid.sequence.len();
+        query.len() <= length
+            && match plasmid.topology {
+                Topology::Circular => start < length,
+                Topology::Linear => start + query.len() <= length,
+            }
+    };
+
+    let verify = |(n, start): (usize, usize)| {
+        if !fits(n, start) {
+            return None;
+        }
+        let bases = plasmids[n].sequence.as_bytes();
+        count_mismatches(bases, start, query, max_mismatches)
+            .map(|mismatches| (n, start, mismatches))
+    };
+    match seeds(query, max_mismatches) {
+        Some(seeds) => {
+            let mut candidates = HashSet::new();
+            for (offset, code) in seeds {
+                for &(n, position) in collection.kmers.get(code) {
+                    let (n, position) = (n as usize, position as usize);
+                    if plasmids[n].topology == Topology::Linear && position < offset {
+                        continue;
+                    }
+                    let length = plasmids[n].sequence.len();
+                    candidates.insert((n, (position + length - offset % length) % length));
+                }
+            }
+            let mut candidates: Vec<(usize, usize)> = candidates.into_iter().collect();
+            candidates.sort_unstable();
+            candidates.into_iter().filter_map(verify).collect()
+        }
+        None => plasmids
+            .iter()
+            .enumerate()
+            .flat_map(|(n, plasmid)| (0..plasmid.sequence.len()).map(move |start| (n, start)))
+            .filter_map(verify)
+            .collect(),
+    }
+}
+
+impl LoadedCollection {
+    /// Plasmids where the 3' end of `oligo` anneals on either strand with
+    /// at most the allowed mismatches, looked up in the k-mer index. Only
+    /// these can hold a binding site of the oligo.
+    fn anneal_candidates(&self, oligo: &str, settings: &BindingSettings) -> Vec<usize> {
+        if oligo.is_empty() {
+            return Vec::new();
+        }
+        let seed = &oligo[oligo.len() - settings.min_anneal.clamp(1, oligo.len())..];
+        let reverse = reverse_complementary(seed);
+        let mut candidates: Vec<usize> = [seed, reverse.as_str()]
+            .iter()
+            .flat_map(|bases| approximate_matches(self, bases.as_bytes(), settings.max_mismatches))
+            .map(|(n, _, _)| n)
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates
+    }
+}
+
+fn span_of(plasmid: &IndexedPlasmid, start: usize, length: usize) -> [usize; 2] {
+    let total = plasmid.sequence.len();
+    [start + 1, (start + length - 1) % total + 1]
+}
+
+fn hit(plasmid: &IndexedPlasmid) -> CollectionHit {
+    CollectionHit {
+        path: plasmid.path.clone(),
+        name: plasmid.name.clone(),
+        length: plasmid.sequence.len(),
+        span: None,
+        strand: None,
+        mismatches: 0,
+        feature: None,
+        feature_type: None,
+        similarity: None,
+    }
+}
+
+/// Hits of a DNA query on both strands. `annealed` is the part of the query
+/// that has to match, counted from its 3' end.
+fn sequence_hits(
+    collection: &LoadedCollection,
+    query: &str,
+    annealed: usize,
+    max_mismatches: usize,
+) -> Vec<CollectionHit> {
+    let query = &query[query.len() - annealed..];
+    let reverse = reverse_complementary(query);
+    let mut hits = Vec::new();
+    for (strand, bases) in [(Direction::Fwd, query), (Direction::Rev, reverse.as_str())] {
+        for (n, start, mismatches) in
+            approximate_matches(collection, bases.as_bytes(), max_mismatches)
+        {
+            let plasmid = &collection.index.plasmids[n];
+            hits.push(CollectionHit {
+                span: Some(span_of(plasmid, start, bases.len())),
+                strand: Some(strand),
+                mismatches,
+                ..hit(plasmid)
+            });
+        }
+    }
+    hits.sort_by(|a, b| (&a.path, a.mismatches, a.span).cmp(&(&b.path, b.mismatches, b.span)));
+    hits
+}
+
+fn feature_hits(collection: &LoadedCollection, name: &str) -> Vec<CollectionHit> {
+    let name = name.trim().to_lowercase();
+    let matches = |value: &str| value.to_lowercase().contains(&name);
+    collection
+        .index
+        .plasmids
+        .iter()
+        .flat_map(|plasmid| {
+            plasmid
+                .features
+                .iter()
+                .filter(|feature| {
+                    matches(&feature.label)
+                        || feature.qualifiers.iter().any(|(key, value)| {
+                            NAME_QUALIFIERS.contains(&key.as_str()) && matches(value)
+                        })
+                })
+                .map(|feature| CollectionHit {
+                    span: Some(feature.span),
+                    strand: feature.directionality,
+                    feature: Some(feature.label.clone()),
+                    feature_type: Some(feature.feature_type.clone()),
+                    ..hit(plasmid)
+                })
+        })
+        .collect()
+}
+
+fn plasmid_hits(
+    collection: &LoadedCollection,
+    sequence: &str,
+    topology: Topology,
+) -> Vec<CollectionHit> {
+    let settings = DuplicateSettings::default();
+    let query = fingerprint(sequence, topology, &settings);
+    let mut hits: Vec<CollectionHit> = collection
+        .index
+        .plasmids
+        .iter()
+        .filter_map(|plasmid| {
+            let similarity = similarity(&query, &plasmid.fingerprint, &settings);
+            (similarity >= settings.min_similarity).then_some(CollectionHit {
+                similarity: Some(similarity),
+                ..hit(plasmid)
+            })
+        })
+        .collect();
+    hits.sort_by(|a, b| {
+        b.similarity
+            .unwrap_or(0.0)
+            .total_cmp(&a.similarity.unwrap_or(0.0))
+    });
+    hits
+}
+
+/// Search the collection, re-indexing changed files first.
+pub fn search_collection_index(
+    request: &CollectionSearchRequest,
+) -> Result<CollectionSearchResult, String> {
+    let dna = sanitize_sequence(&request.query);
+    match request.kind {
+        CollectionSearchKind::Feature if request.query.trim().is_empty() => {
+            return Err("No feature name given.".to_string());
+        }
+        CollectionSearchKind::Feature => {}
+        _ if dna.is_empty() => return Err("No sequence given.".to_string()),
+        _ => {}
+    }
+    let annealed = match request.kind {
+        CollectionSearchKind::Primer => request
+            .anneal_length
+            .unwrap_or(DEFAULT_ANNEAL_LENGTH)
+            .clamp(1, dna.len()),
+        _ => dna.len(),
+    };
+    if matches!(
+        request.kind,
+        CollectionSearchKind::Sequence | CollectionSearchKind::Primer
+    ) && 2 * request.max_mismatches >= annealed
+    {
+        return Err(format!(
+            "{} mismatches are too many for {} bases.",
+            request.max_mismatches, annealed
+        ));
+    }
+    with_collection(None, |collection, index| {
+        let mut hits = match request.kind {
+            CollectionSearchKind::Sequence | CollectionSearchKind::Primer => {
+                sequence_hits(collection, &dna, annealed, request.max_mismatches)
+            }
+            CollectionSearchKind::Feature => feature_hits(collection, &request.query),
+            CollectionSearchKind::Plasmid => plasmid_hits(
+                collection,
+                &dna,
+                request.topology.unwrap_or(Topology::Circular),
+            ),
+        };
+        let max_hits = collection.index.settings.max_hits.max(1);
+        let truncated = hits.len() > max_hits;
+        hits.truncate(max_hits);
+        Ok(CollectionSearchResult {
+            hits,
+            truncated,
+            index,
+        })
+    })
+}
+
+#[tauri::command]
+/// Get the collection folders and search settings.
+pub fn get_collection_settings() -> Result<CollectionSettings, String> {
+    let collection = COLLECTION.lock().map_err(|e| e.to_string())?;
+    match collection.as_ref() {
+        Some(loaded) => Ok(loaded.index.settings.clone()),
+        None => Ok(INDEX.load()?.settings),
+    }
+}
+
+#[tauri::command]
+/// Change the collection folders. Files of removed folders leave the index
+/// and new folders are indexed on the next search or indexing job.
+pub fn set_collection_settings(settings: CollectionSettings) -> Result<(), String> {
+    let mut collection = COLLECTION.lock().map_err(|e| e.to_string())?;
+    let loaded = loaded_collection(&mut collection)?;
+    loaded.index.settings = settings;
+    INDEX.save(&loaded.index)
+}
+
+#[tauri::command]
+/// Start indexing new and changed files of the collection in the background.
+/// Returns the job id, results arrive through the `job-finished` event.
+pub fn start_collection_index_job(app: tauri::AppHandle) -> u64 {
+    spawn_job(app, "collection-index", move |reporter| {
+        with_collection(Some(reporter), |_, summary| Ok(summary))
+    })
+}
+
+#[tauri::command]
+/// Search the plasmid collection by sequence, feature name, primer or whole
+/// plasmid.
+pub fn search_collection(
+    request: CollectionSearchRequest,
+) -> Result<CollectionSearchResult, String> {
+    search_collection_index(&request)
+}
+
+#[tauri::command]
+/// Open plasmids found in the collection, like files opened from the system.
+pub fn open_collection_files(app: tauri::AppHandle, paths: Vec<PathBuf>) {
+    crate::handle_file_associations(app, paths);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binding_sites::oligo_binding_sites;
+    use crate::sequence::tests::RANDOM_PLASMID;
+
+    fn indexed(name: &str, sequence: String) -> IndexedPlasmid {
+        IndexedPlasmid {
+            path: PathBuf::from(format!("{}.gb", name)),
+            modified: 0,
+            size: 0,
+            name: name.to_string(),
+            topology: Topology::Circular,
+            fingerprint: fingerprint(&sequence, Topology::Circular, &DuplicateSettings::default()),
+            sequence,
+            features: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn default_searches_are_seeded() {
+        let bases = RANDOM_PLASMID[0].as_bytes();
+        let settings = BindingSettings::default();
+        assert!(seeds(&bases[..settings.min_anneal], settings.max_mismatches).is_some());
+        assert!(seeds(&bases[..DEFAULT_ANNEAL_LENGTH], 1).is_some());
+        assert!(seeds(&bases[..12], 1).is_none());
+        assert!(seeds(b"ACGTNNNACGTACGT", 1).is_none());
+    }
+
+    #[test]
+    fn anneal_candidates_hold_every_binding_site() {
+        let sequence = RANDOM_PLASMID.concat();
+        let mutated = format!("{}{}{}", &sequence[..90], "A".repeat(40), &sequence[130..]);
+        let plasmids = vec![indexed("a", sequence.clone()), indexed("b", mutated)];
+        let collection = LoadedCollection {
+            kmers: KmerIndex::build(&plasmids),
+            index: CollectionIndex {
+                plasmids,
+                ..CollectionIndex::default()
+            },
+        };
+
+        // 5' tail and a mismatch on the top strand, then across the origin on
+        // the bottom strand
+        let mut annealed = sequence[100..122].to_string();
+        annealed.replace_range(4..5, if &annealed[4..5] == "A" { "C" } else { "A" });
+        let origin = format!("{}{}", &sequence[388..], &sequence[..12]);
+        let oligos = [
+            format!("GGATCCGAATTC{}", annealed),
+            reverse_complementary(&origin),
+        ];
+        let settings = BindingSettings::default();
+        let expected: [&[usize]; 2] = [&[0], &[0, 1]];
+        for (oligo, expected) in oligos.iter().zip(expected) {
+            assert_eq!(collection.anneal_candidates(oligo, &settings), expected);
+            let bound: Vec<usize> = (0..2)
+                .filter(|&n| {
+                    let plasmid = &collection.index.plasmids[n];
+                    let oligo = Oligo {
+                        name: "Oligo".to_string(),
+                        sequence: oligo.clone(),
+                    };
+                    !oligo_binding_sites(&plasmid.sequence, plasmid.topology, &oligo, &settings)
+                        .is_empty()
+                })
+                .collect();
+            assert_eq!(bound, expected);
+        }
+    }
+
+    #[test]
+    fn update_index_reads_only_changed_files() {
+        let folder = std::env::temp_dir().join(format!("iva-collection-{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let write = |name: &str, sequence: &str| {
+            fs::write(folder.join(name), format!(">{}\n{}\n", name, sequence)).unwrap();
+        };
+        write("a.fasta", RANDOM_PLASMID[0]);
+        write("b.fasta", RANDOM_PLASMID[1]);
+        let mut index = CollectionIndex::default();
+        index.settings.folders = vec![folder.clone()];
+        let update = |index: &mut CollectionIndex| {
+            let summary = update_index(index, None).unwrap();
+            (
+                summary.plasmids,
+                summary.added,
+                summary.updated,
+                summary.removed,
+            )
+        };
+
+        assert_eq!(update(&mut index), (2, 2, 0, 0));
+        assert_eq!(update(&mut index), (2, 0, 0, 0));
+
+        write("b.fasta", &RANDOM_PLASMID[1..3].concat());
+        assert_eq!(update(&mut index), (2, 0, 1, 0));
+        assert_eq!(index.plasmids[1].sequence, RANDOM_PLASMID[1..3].concat());
+
+        fs::remove_file(folder.join("a.fasta")).unwrap();
+        assert_eq!(update(&mut index), (1, 0, 0, 1));
+        assert_eq!(index.plasmids[0].path, folder.join("b.fasta"));
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -0,0 +1,554 @@
+use std::collections::{HashSet, VecDeque};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::feature_library::annotation_library;
+use crate::plasmid_file::Feature;
+use crate::sequence::{reverse_complementary, sanitize_sequence, Direction, Topology};
+use crate::translation::{genetic_code, GeneticCode};
+use crate::verification::span_contains;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SequenceType {
+    #[serde(rename = "DNA")]
+    Dna,
+    #[serde(rename = "AA")]
+    Aa,
+}
+
+impl SequenceType {
+    /// Symbols library entries are matched on, anything else never matches.
+    pub fn alphabet(self) -> &'static [u8] {
+        match self {
+            SequenceType::Dna => b"ACGT",
+            SequenceType::Aa => b"ACDEFGHIKLMNPQRSTVWY",
+        }
+    }
+}
+
+/// Entry of a feature library, same shape as `commonFeatures.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommonFeature {
+    pub label: String,
+    #[serde(rename = "type")]
+    pub feature_type: String,
+    #[serde(rename = "sequence type")]
+    pub sequence_type: SequenceType,
+    pub sequence: String,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+pub static COMMON_FEATURES: Lazy<Vec<CommonFeature>> = Lazy::new(|| {
+    serde_json::from_str(include_str!(
+        "../../../../docker/app/ivaprime/static/data/commonFeatures.json"
+    ))
+    .expect("Bundled commonFeatures.json is malformed")
+});
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AnnotationSettings {
+    /// Lowest fraction of matching bases (or residues) of a hit.
+    pub min_identity: f64,
+    /// Shortest part of a library feature reported, as a fraction of its
+    /// length. Below 1 truncated features are found as well.
+    pub min_coverage: f64,
+    /// Translation table for protein entries.
+    pub table: u8,
+}
+
+impl Default for AnnotationSettings {
+    fn default() -> Self {
+        Self {
+            min_identity: 0.95,
+            min_coverage: 1.0,
+            table: 11,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotationRequest {
+    pub sequence: String,
+    pub topology: Topology,
+    /// Only annotate within this span (1-based, inclusive) if given.
+    #[serde(default)]
+    pub span: Option<[usize; 2]>,
+    /// Features already on the plasmid, hits identical to one are left out.
+    #[serde(default)]
+    pub features: Vec<Feature>,
+    #[serde(default)]
+    pub settings: AnnotationSettings,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotationHit {
+    pub feature: Feature,
+    pub identity: f64,
+    /// Fraction of the library feature present.
+    pub coverage: f64,
+}
+
+/// Shortest seed used for inexact matching, shorter seeds hit too often.
+const MIN_SEED_LENGTH: [usize; 2] = [12, 5];
+
+/// Aho-Corasick automaton over a small alphabet. Bytes outside of the
+/// alphabet reset the search, patterns containing them are never reported.
+struct Automaton {
+    symbols: [u8; 256],
+    alphabet_size: usize,
+    transitions: Vec<u32>,
+    /// Patterns ending at every state, including those of its suffixes.
+    outputs: Vec<Vec<u32>>,
+}
+
+impl Automaton {
+    fn new(alphabet: &[u8], patterns: &[&[u8]]) -> Self {
+        let mut symbols = [0u8; 256];
+        for (i, b) in alphabet.iter().enumerate() {
+            symbols[*b as usize] = i as u8 + 1;
+        }
+        let size = alphabet.len();
+        // Trie with 0 for missing edges, the root is state 0
+        let mut transitions: Vec<u32> = vec![0; size];
+        let mut outputs: Vec<Vec<u32>> = vec![Vec::new()];
+        'patterns: for (id, pattern) in patterns.iter().enumerate() {
+            let mut state = 0;
+            for b in pattern.iter() {
+                let symbol = symbols[*b as usize] as usize;
+                if symbol == 0 {
+                    continue 'patterns;
+                }
+                let edge = state * size + symbol - 1;
+                if transitions[edge] == 0 {
+                    transitions[edge] = outputs.len() as u32;
+                    transitions.extend(std::iter::repeat_n(0, size));
+                    outputs.push(Vec::new());
+                }
+                state = transitions[edge] as usize;
+            }
+            outputs[state].push(id as u32);
+        }
+
+        // Breadth first, turning missing edges into failure transitions
+        let mut fail = vec![0u32; outputs.len()];
+        let mut queue: VecDeque<usize> = (0..size)
+            .map(|s| transitions[s] as usize)
+            .filter(|&child| child != 0)
+            .collect();
+        while let Some(state) = queue.pop_front() {
+            let inherited = outputs[fail[state] as usize].clone();
+            outputs[state].extend(inherited);
+            for s in 0..size {
+                let child = transitions[state * size + s];
+                let fallback = transitions[fail[state] as usize * size + s];
+                if child == 0 {
+                    transitions[state * size + s] = fallback;
+                } else {
+                    fail[child as usize] = fallback;
+                    queue.push_back(child as usize);
+                }
+            }
+        }
+
+        Self {
+            symbols,
+            alphabet_size: size,
+            transitions,
+            outputs,
+        }
+    }
+
+    /// End positions (exclusive) and ids of all pattern occurrences.
+    fn find(&self, text: &[u8]) -> Vec<(usize, u32)> {
+        let mut hits = Vec::new();
+        let mut state = 0;
+        for (i, b) in text.iter().enumerate() {
+            let symbol = self.symbols[*b as usize] as usize;
+            state = if symbol == 0 {
+                0
+            } else {
+                self.transitions[state * self.alphabet_size + symbol - 1] as usize
+            };
+            hits.extend(self.outputs[state].iter().map(|id| (i + 1, *id)));
+        }
+        hits
+    }
+}
+
+/// Exact piece of a library entry searched for first.
+struct Seed {
+    entry: usize,
+    offset: usize,
+}
+
+/// Split every pattern into seeds so that any match within the identity
+/// threshold contains at least one of them unchanged. Bytes outside of the
+/// alphabet (`N`, `X`, ...) never match, so seeds are cut down to the longest
+/// run without them.
+fn make_seeds<'a>(
+    entries: &[(usize, &'a [u8])],
+    settings: &AnnotationSettings,
+    alphabet: &[u8],
+    min_seed_length: usize,
+) -> (Vec<Seed>, Vec<&'a [u8]>) {
+    let mut seeds = Vec::new();
+    let mut pieces = Vec::new();
+    for &(entry, pattern) in entries {
+        let length = pattern.len();
+        let mismatches = ((1.0 - settings.min_identity.clamp(0.0, 1.0)) * length as f64) as usize;
+        // Partial hits must hold a whole seed, so seeds are at most half
+        // of the shortest covered part
+        let covered = ((settings.min_coverage.clamp(0.0, 1.0) * length as f64).ceil() as usize)
+            .clamp(1, length);
+        let partial_count = if covered < length {
+            (2 * length).div_ceil(covered)
+        } else {
+            1
+        };
+        let count = (mismatches + 1)
+            .max(partial_count)
+            .min(length / min_seed_length)
+            .max(1);
+        let seed_length = length / count;
+        for i in 0..count {
+            let offset = i * seed_length;
+            let end = if i + 1 == count {
+                length
+            } else {
+                offset + seed_length
+            };
+            let mut run = [offset, offset];
+            let mut run_start = offset;
+            for (j, b) in pattern.iter().enumerate().take(end).skip(offset) {
+                if !alphabet.contains(b) {
+                    run_start = j + 1;
+                } else if j + 1 - run_start > run[1] - run[0] {
+                    run = [run_start, j + 1];
+                }
+            }
+            let run_length = run[1] - run[0];
+            if run_length == 0 || (run_length < end - offset && run_length < min_seed_length) {
+                continue;
+            }
+            seeds.push(Seed {
+                entry,
+                offset: run[0],
+            });
+            pieces.push(&pattern[run[0]..run[1]]);
+        }
+    }
+    (seeds, pieces)
+}
+
+/// Strand of the plasmid prepared for searching, extended across the origin
+/// of circular plasmids.
+struct SearchText {
+    /// Per frame for proteins, a single text for DNA.
+    texts: Vec<Vec<u8>>,
+    /// Units (bases or codons) to strand offsets.
+    frames: Vec<usize>,
+    unit: usize,
+}
+
+/// Part of a library entry found on a strand, in strand units.
+struct Match {
+    entry: usize,
+    /// Strand offset of the first matched base.
+    start: usize,
+    /// Bases covered.
+    length: usize,
+    identity: f64,
+    coverage: f64,
+}
+
+/// Compare a candidate placement of a pattern and return the covered part
+/// (pattern offsets) and its identity, if it passes the settings.
+fn score_candidate(
+    pattern: &[u8],
+    text: &[u8],
+    start: isize,
+    seed: [usize; 2],
+    settings: &AnnotationSettings,
+) -> Option<([usize; 2], f64)> {
+    let length = pattern.len();
+    let matched: Vec<Option<bool>> = (0..length)
+        .map(|j| {
+            let position = start + j as isize;
+            (position >= 0 && (position as usize) < text.len())
+                .then(|| text[position as usize] == pattern[j])
+        })
+        .collect();
+    let matches = matched.iter().filter(|m| **m == Some(true)).count();
+    let identity = settings.min_identity;
+    if matches as f64 >= identity * length as f64 {
+        return Some(([0, length], matches as f64 / length as f64));
+    }
+    if settings.min_coverage >= 1.0 {
+        return None;
+    }
+
+    // Highest scoring stretch around the seed, which keeps the identity above
+    // the threshold
+    let score = |m: Option<bool>| match m {
+        Some(true) => Some(1.0 - identity),
+        Some(false) => Some(-identity),
+        None => None,
+    };
+    let extend = |range: &mut dyn Iterator<Item = usize>| {
+        let (mut sum, mut best, mut best_at) = (0.0, 0.0, None);
+        for j in range {
+            match score(matched[j]) {
+                Some(s) => sum += s,
+                None => break,
+            }
+            if sum > best {
+                best = sum;
+                best_at = Some(j);
+            }
+        }
+        best_at
+    };
+    let from = extend(&mut (0..seed[0]).rev()).unwrap_or(seed[0]);
+    let to = extend(&mut (seed[1]..length)).map_or(seed[1], |j| j + 1);
+    let covered = to - from;
+    let matches = matched[from..to]
+        .iter()
+        .filter(|m| **m == Some(true))
+        .count();
+    let part_identity = matches as f64 / covered as f64;
+    (covered as f64 >= settings.min_coverage * length as f64 && part_identity >= identity)
+        .then_some(([from, to], part_identity))
+}
+
+fn search_strand(
+    strand: &[u8],
+    circular: bool,
+    library: &[CommonFeature],
+    settings: &AnnotationSettings,
+    code: &GeneticCode,
+    sequence_type: SequenceType,
+) -> Vec<Match> {
+    let length = strand.len();
+    let entries: Vec<(usize, &[u8])> = library
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| e.sequence_type == sequence_type && !e.sequence.is_empty())
+        .map(|(i, e)| (i, e.sequence.as_bytes()))
+        .collect();
+    let Some(longest) = entries.iter().map(|(_, p)| p.len()).max() else {
+        return Vec::new();
+    };
+
+    let search = match sequence_type {
+        SequenceType::Dna => {
+            let mut text = strand.to_vec();
+            if circular {
+                text.extend((0..longest - 1).map(|i| strand[i % length]));
+            }
+            SearchText {
+                texts: vec![text],
+                frames: vec![0],
+                unit: 1,
+            }
+        }
+        SequenceType::Aa => {
+            let extension = if circular { 3 * longest + 2 } else { 0 };
+            let extended: String = (0..length + extension)
+                .map(|i| strand[i % length] as char)
+                .collect();
+            SearchText {
+                texts: (0..3)
+                    .map(|frame| {
+                        code.translate(extended.get(frame..).unwrap_or_default(), false)
+                            .into_bytes()
+                    })
+                    .collect(),
+                frames: vec![0, 1, 2],
+                unit: 3,
+            }
+        }
+    };
+
+    let alphabet = sequence_type.alphabet();
+    let min_seed = match sequence_type {
+        SequenceType::Dna => MIN_SEED_LENGTH[0],
+        SequenceType::Aa => MIN_SEED_LENGTH[1],
+    };
+    let (seeds, pieces) = make_seeds(&entries, settings, alphabet, min_seed);
+    let automaton = Automaton::new(alphabet, &pieces);
+
+    let mut matches = Vec::new();
+    let mut seen = HashSet::new();
+    for (text, frame) in search.texts.iter().zip(&search.frames) {
+        for (end, id) in automaton.find(text) {
+            let seed = &seeds[id as usize];
+            let pattern = library[seed.entry].sequence.as_bytes();
+            let seed_range = [seed.offset, seed.offset + pieces[id as usize].len()];
+            let start = (end - pieces[id as usize].len()) as isize - seed.offset as isize;
+            if !seen.insert((seed.entry, *frame, start)) {
+                continue;
+            }
+            let Some((covered, identity)) =
+                score_candidate(pattern, text, start, seed_range, settings)
+            else {
+                continue;
+            };
+            let first = start + covered[0] as isize;
+            let strand_start = frame + first as usize * search.unit;
+            // The extension repeats the start of circular strands
+            if first < 0 || strand_start >= length {
+                continue;
+            }
+            matches.push(Match {
+                entry: seed.entry,
+                start: strand_start,
+                length: (covered[1] - covered[0]) * search.unit,
+                identity,
+                coverage: (covered[1] - covered[0]) as f64 / pattern.len() as f64,
+            });
+        }
+    }
+    matches
+}
+
+/// Find library features on both strands of a plasmid.
+pub fn find_library_features(
+    request: &AnnotationRequest,
+    library: &[CommonFeature],
+) -> Result<Vec<AnnotationHit>, String> {
+    let sequence = sanitize_sequence(&request.sequence);
+    if sequence.is_empty() {
+        return Err("The sequence is empty.".to_string());
+    }
+    let settings = &request.settings;
+    let code = genetic_code(settings.table)
+        .ok_or_else(|| format!("Unknown translation table {}.", settings.table))?;
+    let circular = request.topology == Topology::Circular;
+    let length = sequence.len();
+
+    let mut hits = Vec::new();
+    for strand in [Direction::Fwd, Direction::Rev] {
+        let strand_sequence = match strand {
+            Direction::Fwd => sequence.clone(),
+            Direction::Rev => reverse_complementary(&sequence),
+        };
+        for sequence_type in [SequenceType::Dna, SequenceType::Aa] {
+            let found = search_strand(
+                strand_sequence.as_bytes(),
+                circular,
+                library,
+                settings,
+                code,
+                sequence_type,
+            );
+            for found in found {
+                if found.length > length {
+                    continue;
+                }
+                let last = (found.start + found.length - 1) % length;
+                let span = match strand {
+                    Direction::Fwd => [found.start + 1, last + 1],
+                    Direction::Rev => [length - last, length - found.start],
+                };
+                let entry = &library[found.entry];
+                let mut qualifiers = Vec::new();
+                if let Some(note) = entry.note.as_ref().filter(|n| !n.is_empty()) {
+                    qualifiers.push(("note".to_string(), note.clone()));
+                }
+                if entry.sequence_type == SequenceType::Aa {
+                    let strand_bases: String = (found.start..found.start + found.length)
+                        .map(|i| strand_sequence.as_bytes()[i % length] as char)
+                        .collect();
+                    qualifiers.push((
+                        "translation".to_string(),
+                        code.translate(&strand_bases, false),
+                    ));
+                }
+                hits.push(AnnotationHit {
+                    feature: Feature {
+                        label: entry.label.clone(),
+                        feature_type: entry.feature_type.clone(),
+                        span,
+                        directionality: Some(strand),
+                        segments: Vec::new(),
+                        qualifiers,
+                    },
+                    identity: found.identity,
+                    coverage: found.coverage,
+                });
+            }
+        }
+    }
+
+    // Best and longest hits first, then drop hits lying within a kept hit of
+    // the same name
+    let span_length = |[start, end]: [usize; 2]| (end + length - start) % length + 1;
+    hits.sort_by(|a, b| {
+        (b.coverage, b.identity)
+            .partial_cmp(&(a.coverage, a.identity))
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(span_length(b.feature.span).cmp(&span_length(a.feature.span)))
+            .then(a.feature.span.cmp(&b.feature.span))
+    });
+    let within = |inner: [usize; 2], outer: [usize; 2]| {
+        span_contains(outer, inner[0]) && span_contains(outer, inner[1])
+    };
+    let mut kept: Vec<AnnotationHit> = Vec::new();
+    for hit in hits {
+        let duplicate = kept
+            .iter()
+            .map(|k| &k.feature)
+            .chain(&request.features)
+            .any(|f| f.label == hit.feature.label && within(hit.feature.span, f.span));
+        let outside = request
+            .span
+            .is_some_and(|selection| !within(hit.feature.span, selection));
+        if !duplicate && !outside {
+            kept.push(hit);
+        }
+    }
+    kept.sort_by_key(|h| h.feature.span[0]);
+    Ok(kept)
+}
+
+#[tauri::command]
+/// Annotate a plasmid with the bundled common features and the user feature
+/// library.
+pub fn detect_common_features(request: AnnotationRequest) -> Result<Vec<AnnotationHit>, String> {
+    find_library_features(&request, &annotation_library())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequence::tests::RANDOM_PLASMID;
+
+    #[test]
+    fn library_entry_with_unknown_base() {
+        let library = [CommonFeature {
+            label: "with N".to_string(),
+            feature_type: "misc_feature".to_string(),
+            sequence_type: SequenceType::Dna,
+            sequence: "CCATTNTACGGAGGATACCAAATTCCTCCTTATTCAGGAC".to_string(),
+            note: None,
+        }];
+        let request = AnnotationRequest {
+            sequence: RANDOM_PLASMID.concat()[50..370].to_string(),
+            topology: Topology::Linear,
+            span: None,
+            features: Vec::new(),
+            settings: AnnotationSettings::default(),
+        };
+        let hits = find_library_features(&request, &library).unwrap();
+        let [hit] = hits.as_slice() else {
+            panic!("expected one hit, got {}", hits.len());
+        };
+        assert_eq!(hit.feature.span, [1, 40]);
+        assert_eq!(hit.identity, 39.0 / 40.0);
+    }
+}
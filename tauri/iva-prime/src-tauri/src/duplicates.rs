@@ -0,0 +1,628 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::alignment::encode_kmer;
+use crate::collection::with_indexed_plasmids;
+use crate::jobs::{spawn_job, JobReporter};
+use crate::plasmid_file::{is_plasmid_file, read_plasmid_file};
+use crate::sequence::{reverse_complementary, sanitize_sequence, Topology};
+
+/// Smallest sketch hashes under which plasmids are bucketed before they are
+/// compared. Two plasmids sharing 90% of their k-mers share the smallest hash
+/// of both sketches 9 times out of 10, so similar pairs almost never miss all
+/// of these buckets.
+const SKETCH_BANDS: usize = 16;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DuplicateSettings {
+    pub kmer_length: usize,
+    /// Number of k-mer hashes kept per plasmid.
+    pub sketch_size: usize,
+    /// Estimated share of k-mers two plasmids need in common to be grouped.
+    pub min_similarity: f64,
+    /// Also scan subfolders.
+    pub recursive: bool,
+}
+
+impl Default for DuplicateSettings {
+    fn default() -> Self {
+        Self {
+            kmer_length: 21,
+            sketch_size: 1000,
+            min_similarity: 0.9,
+            recursive: true,
+        }
+    }
+}
+
+/// Rotation and orientation independent summary of a plasmid sequence.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Fingerprint {
+    pub length: usize,
+    /// Hash of the canonical sequence, equal for identical plasmids no matter
+    /// where their origin lies or which strand was saved.
+    pub hash: String,
+    /// Smallest hashes of the plasmid's canonical k-mers (MinHash), sorted.
+    pub sketch: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedFile {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateMember {
+    pub path: PathBuf,
+    pub name: String,
+    pub length: usize,
+    pub hash: String,
+    /// Estimated k-mer similarity to the first member of the group, or to the
+    /// plasmid checked against the collection.
+    pub similarity: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    /// All members have the same sequence.
+    pub exact: bool,
+    pub members: Vec<DuplicateMember>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateReport {
+    pub directory: PathBuf,
+    pub scanned: usize,
+    pub groups: Vec<DuplicateGroup>,
+    pub skipped: Vec<SkippedFile>,
+    /// Plain text version of the result.
+    pub report: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateScanRequest {
+    pub directory: PathBuf,
+    #[serde(default)]
+    pub settings: DuplicateSettings,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionCheckRequest {
+    pub sequence: String,
+    pub topology: Topology,
+    pub directory: PathBuf,
+    #[serde(default)]
+    pub settings: DuplicateSettings,
+}
+
+/// Start of the lexicographically least rotation (Booth's algorithm).
+fn least_rotation(sequence: &[u8]) -> usize {
+    let n = sequence.len();
+    let at = |i: usize| sequence[i % n];
+    let mut failure: Vec<i64> = vec![-1; 2 * n];
+    let mut k = 0;
+    for j in 1..2 * n {
+        let current = at(j);
+        let mut i = failure[j - k - 1];
+        while i != -1 && current != at(k + i as usize + 1) {
+            if current < at(k + i as usize + 1) {
+                k = j - i as usize - 1;
+            }
+            i = failure[i as usize];
+        }
+        if current != at((k as i64 + i + 1) as usize) {
+            // i is -1 here
+            if current < at(k) {
+                k = j;
+            }
+            failure[j - k] = -1;
+        } else {
+            failure[j - k] = i + 1;
+        }
+    }
+    k
+}
+
+/// The least of all rotations (circular) of both strands.
+fn canonical_sequence(sequence: &str, topology: Topology) -> String {
+    let reverse = reverse_complementary(sequence);
+    let canonical = |s: &str| match topology {
+        Topology::Linear => s.to_string(),
+        Topology::Circular => {
+            let start = least_rotation(s.as_bytes());
+            format!("{}{}", &s[start..], &s[..start])
+        }
+    };
+    canonical(sequence).min(canonical(&reverse))
+}
+
+/// 64-bit FNV-1a, stable across platforms and releases.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// SplitMix64 finalizer, spreads k-mer codes evenly for MinHash.
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+pub fn fingerprint(
+    sequence: &str,
+    topology: Topology,
+    settings: &DuplicateSettings,
+) -> Fingerprint {
+    let sequence = sanitize_sequence(sequence);
+    let length = sequence.len();
+    let k = settings.kmer_length.clamp(8, 32);
+    let bases = sequence.as_bytes();
+    let starts = match topology {
+        Topology::Circular if length >= k => length,
+        _ => (length + 1).saturating_sub(k),
+    };
+    let mut hashes: Vec<u64> = (0..starts)
+        .filter_map(|start| {
+            let kmer: Vec<u8> = (start..start + k).map(|i| bases[i % length]).collect();
+            let forward = encode_kmer(&kmer)?;
+            let reverse =
+                encode_kmer(reverse_complementary(&String::from_utf8_lossy(&kmer)).as_bytes())?;
+            Some(mix(forward.min(reverse)))
+        })
+        .collect();
+    hashes.sort_unstable();
+    hashes.dedup();
+    hashes.truncate(settings.sketch_size.max(1));
+    Fingerprint {
+        length,
+        hash: format!(
+            "{:016x}",
+            fnv1a(canonical_sequence(&sequence, topology).as_bytes())
+        ),
+        sketch: hashes,
+    }
+}
+
+/// Estimated Jaccard similarity of the k-mer sets behind two sketches.
+pub fn sketch_similarity(a: &[u64], b: &[u64], sketch_size: usize) -> f64 {
+    let (mut i, mut j) = (0, 0);
+    let mut shared = 0;
+    let mut union = 0;
+    while union < sketch_size && (i < a.len() || j < b.len()) {
+        match (a.get(i), b.get(j)) {
+            (Some(x), Some(y)) if x == y => {
+                shared += 1;
+                i += 1;
+                j += 1;
+            }
+            (Some(x), Some(y)) if x < y => i += 1,
+            (Some(_), None) => i += 1,
+            _ => j += 1,
+        }
+        union += 1;
+    }
+    if union == 0 {
+        0.0
+    } else {
+        shared as f64 / union as f64
+    }
+}
+
+/// Similarity of two plasmids, 1 for identical ones. Repeated parts inflate
+/// the shared k-mers of plasmids of different size, so the estimate is capped
+/// by their size ratio.
+pub fn similarity(a: &Fingerprint, b: &Fingerprint, settings: &DuplicateSettings) -> f64 {
+    if a.hash == b.hash {
+        return 1.0;
+    }
+    let size_ratio = a.length.min(b.length) as f64 / a.length.max(b.length).max(1) as f64;
+    if size_ratio < settings.min_similarity {
+        return size_ratio;
+    }
+    sketch_similarity(&a.sketch, &b.sketch, settings.sketch_size).min(size_ratio)
+}
+
+/// Plasmid files in a folder, sorted by path.
+pub fn plasmid_files(directory: &Path, recursive: bool) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    let mut folders = vec![directory.to_path_buf()];
+    while let Some(folder) = folders.pop() {
+        let entries = fs::read_dir(&folder)
+            .map_err(|e| format!("Could not read \"{}\": {}", folder.display(), e))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                if recursive {
+                    folders.push(path);
+                }
+            } else if is_plasmid_file(&path) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+struct ScannedFile {
+    path: PathBuf,
+    name: String,
+    fingerprint: Fingerprint,
+}
+
+fn scan_files(
+    directory: &Path,
+    settings: &DuplicateSettings,
+    reporter: Option<&JobReporter>,
+) -> Result<(Vec<ScannedFile>, Vec<SkippedFile>), String> {
+    let paths = plasmid_files(directory, settings.recursive)?;
+    let mut scanned = Vec::new();
+    let mut skipped = Vec::new();
+    for (i, path) in paths.iter().enumerate() {
+        if let Some(reporter) = reporter {
+            if reporter.is_cancelled() {
+                return Err("Duplicate search cancelled.".to_string());
+            }
+            reporter.progress(i, paths.len(), format!("Reading {}", path.display()));
+        }
+        match read_plasmid_file(path) {
+            Ok(plasmid) => scanned.push(ScannedFile {
+                path: path.clone(),
+                name: plasmid.name,
+                fingerprint: fingerprint(&plasmid.sequence, plasmid.topology, settings),
+            }),
+            Err(error) => skipped.push(SkippedFile {
+                path: path.clone(),
+                error,
+            }),
+        }
+    }
+    Ok((scanned, skipped))
+}
+
+fn find_root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+/// For every plasmid, the later ones worth comparing it to: those with the
+/// same sequence or sharing one of the smallest hashes of their sketches.
+fn candidate_pairs(fingerprints: &[&Fingerprint]) -> Vec<Vec<usize>> {
+    let mut buckets: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut bands: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, fingerprint) in fingerprints.iter().enumerate() {
+        buckets.entry(&fingerprint.hash).or_default().push(i);
+        for &hash in fingerprint.sketch.iter().take(SKETCH_BANDS) {
+            bands.entry(hash).or_default().push(i);
+        }
+    }
+    let mut candidates: Vec<Vec<usize>> = vec![Vec::new(); fingerprints.len()];
+    for members in buckets.values().chain(bands.values()) {
+        for (n, &i) in members.iter().enumerate() {
+            candidates[i].extend(&members[n + 1..]);
+        }
+    }
+    for partners in candidates.iter_mut() {
+        partners.sort_unstable();
+        partners.dedup();
+    }
+    candidates
+}
+
+/// Group the plasmids of a folder that are identical or nearly so.
+pub fn find_duplicates(
+    directory: &Path,
+    settings: &DuplicateSettings,
+    reporter: Option<&JobReporter>,
+) -> Result<DuplicateReport, String> {
+    let (files, skipped) = scan_files(directory, settings, reporter)?;
+    let fingerprints: Vec<&Fingerprint> = files.iter().map(|f| &f.fingerprint).collect();
+    let candidates = candidate_pairs(&fingerprints);
+    let mut parents: Vec<usize> = (0..files.len()).collect();
+    for (i, partners) in candidates.iter().enumerate() {
+        if let Some(reporter) = reporter {
+            if reporter.is_cancelled() {
+                return Err("Duplicate search cancelled.".to_string());
+            }
+            reporter.progress(i, files.len(), "Comparing plasmids");
+        }
+        for &j in partners {
+            if similarity(fingerprints[i], fingerprints[j], settings) >= settings.min_similarity {
+                let (a, b) = (find_root(&mut parents, i), find_root(&mut parents, j));
+                parents[b.max(a)] = b.min(a);
+            }
+        }
+    }
+
+    let mut grouped: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..files.len() {
+        let root = find_root(&mut parents, i);
+        grouped.entry(root).or_default().push(i);
+    }
+    let mut groups: Vec<DuplicateGroup> = grouped
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .map(|mut members| {
+            members.sort_unstable();
+            let first = &files[members[0]].fingerprint;
+            DuplicateGroup {
+                exact: members
+                    .iter()
+                    .all(|&i| files[i].fingerprint.hash == first.hash),
+                members: members
+                    .iter()
+                    .map(|&i| {
+                        let file = &files[i];
+                        DuplicateMember {
+                            path: file.path.clone(),
+                            name: file.name.clone(),
+                            length: file.fingerprint.length,
+                            hash: file.fingerprint.hash.clone(),
+                            similarity: similarity(first, &file.fingerprint, settings),
+                        }
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| a.members[0].path.cmp(&b.members[0].path));
+
+    let mut report = DuplicateReport {
+        directory: directory.to_path_buf(),
+        scanned: files.len(),
+        groups,
+        skipped,
+        report: String::new(),
+    };
+    report.report = duplicate_report_text(&report);
+    Ok(report)
+}
+
+/// Plasmids of a folder that are identical or nearly identical to a sequence,
+/// most similar first. Folders of the plasmid collection are looked up in its
+/// index, other folders are read.
+pub fn check_collection(
+    request: &CollectionCheckRequest,
+    reporter: Option<&JobReporter>,
+) -> Result<Vec<DuplicateMember>, String> {
+    let settings = &request.settings;
+    let query = fingerprint(&request.sequence, request.topology, settings);
+    if query.length == 0 {
+        return Err("The plasmid has no sequence.".to_string());
+    }
+    let matching = |path: &Path, name: &str, fingerprint: &Fingerprint| {
+        let similarity = similarity(&query, fingerprint, settings);
+        (similarity >= settings.min_similarity).then(|| DuplicateMember {
+            path: path.to_path_buf(),
+            name: name.to_string(),
+            length: fingerprint.length,
+            hash: fingerprint.hash.clone(),
+            similarity,
+        })
+    };
+
+    // The index holds fingerprints made with the default k-mers and sketches
+    let defaults = DuplicateSettings::default();
+    let indexed = if settings.kmer_length == defaults.kmer_length
+        && settings.sketch_size == defaults.sketch_size
+    {
+        with_indexed_plasmids(reporter, |collection, plasmids| {
+            let directory = &request.directory;
+            let covered = collection.folders.iter().any(|folder| {
+                directory.starts_with(folder)
+                    && (collection.recursive || (directory == folder && !settings.recursive))
+            });
+            Ok(covered.then(|| {
+                plasmids
+                    .iter()
+                    .filter(|p| {
+                        p.path.starts_with(directory)
+                            && (settings.recursive || p.path.parent() == Some(directory.as_path()))
+                    })
+                    .filter_map(|p| matching(&p.path, &p.name, &p.fingerprint))
+                    .collect::<Vec<_>>()
+            }))
+        })?
+    } else {
+        None
+    };
+    let mut matches = match indexed {
+        Some(matches) => matches,
+        None => {
+            let (files, _) = scan_files(&request.directory, settings, reporter)?;
+            files
+                .iter()
+                .filter_map(|file| matching(&file.path, &file.name, &file.fingerprint))
+                .collect()
+        }
+    };
+    matches.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    Ok(matches)
+}
+
+/// Plain text report of a duplicate search.
+pub fn duplicate_report_text(report: &DuplicateReport) -> String {
+    let mut lines = vec![
+        format!("Duplicates in \"{}\"", report.directory.display()),
+        format!(
+            "{} plasmid(s) scanned, {} group(s) found",
+            report.scanned,
+            report.groups.len()
+        ),
+    ];
+    for (n, group) in report.groups.iter().enumerate() {
+        lines.push(String::new());
+        lines.push(format!(
+            "Group {} ({}):",
+            n + 1,
+            if group.exact { "identical" } else { "similar" }
+        ));
+        for member in &group.members {
+            lines.push(format!(
+                "  {} ({} bp, {:.1}%)",
+                member.path.display(),
+                member.length,
+                100.0 * member.similarity
+            ));
+        }
+    }
+    if !report.skipped.is_empty() {
+        lines.push(String::new());
+        lines.push("Skipped:".to_string());
+        for file in &report.skipped {
+            lines.push(format!("  {}: {}", file.path.display(), file.error));
+        }
+    }
+    lines.join("\n")
+}
+
+#[tauri::command]
+/// Start a search for duplicate plasmids in a folder in the background.
+/// Returns the job id, results arrive through the `job-finished` event.
+pub fn start_duplicate_scan_job(app: tauri::AppHandle, request: DuplicateScanRequest) -> u64 {
+    spawn_job(app, "duplicates", move |reporter| {
+        find_duplicates(&request.directory, &request.settings, Some(reporter))
+    })
+}
+
+#[tauri::command]
+/// Start looking for copies of a plasmid in a folder in the background.
+/// Returns the job id, results arrive through the `job-finished` event.
+pub fn start_collection_check_job(app: tauri::AppHandle, request: CollectionCheckRequest) -> u64 {
+    spawn_job(app, "collection-check", move |reporter| {
+        check_collection(&request, Some(reporter))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequence::tests::RANDOM_PLASMID;
+
+    #[test]
+    fn canonical_sequence_of_both_strands() {
+        assert_eq!(least_rotation(b"GATTACA"), 4);
+        // ACAGATT is the least rotation of the top strand, AATCTGT of the bottom strand
+        assert_eq!(canonical_sequence("GATTACA", Topology::Circular), "AATCTGT");
+        assert_eq!(canonical_sequence("GATTACA", Topology::Linear), "GATTACA");
+
+        let sequence = RANDOM_PLASMID.concat();
+        let least = (0..sequence.len())
+            .min_by_key(|&i| format!("{}{}", &sequence[i..], &sequence[..i]))
+            .unwrap();
+        assert_eq!(least_rotation(sequence.as_bytes()), least);
+    }
+
+    #[test]
+    fn fingerprint_hash_ignores_origin_and_strand() {
+        // FNV-1a test vectors
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x8594_4171_f739_67e8);
+
+        let settings = DuplicateSettings::default();
+        let hash = |sequence: &str, topology| fingerprint(sequence, topology, &settings).hash;
+        assert_eq!(hash("GATTACA", Topology::Circular), "c728529969f925cd");
+
+        let sequence = RANDOM_PLASMID.concat();
+        let rotated = format!("{}{}", &sequence[123..], &sequence[..123]);
+        let expected = hash(&sequence, Topology::Circular);
+        assert_eq!(hash(&rotated, Topology::Circular), expected);
+        assert_eq!(
+            hash(&reverse_complementary(&rotated), Topology::Circular),
+            expected
+        );
+        assert_eq!(hash(&rotated.to_lowercase(), Topology::Circular), expected);
+
+        let mutated = format!("{}A{}", &sequence[..50], &sequence[51..]);
+        assert_ne!(hash(&mutated, Topology::Circular), expected);
+        assert_ne!(
+            hash(&rotated, Topology::Linear),
+            hash(&sequence, Topology::Linear)
+        );
+        assert_eq!(
+            hash(&reverse_complementary(&sequence), Topology::Linear),
+            hash(&sequence, Topology::Linear)
+        );
+
+        let a = fingerprint(&sequence, Topology::Circular, &settings);
+        let b = fingerprint(
+            &reverse_complementary(&rotated),
+            Topology::Circular,
+            &settings,
+        );
+        assert_eq!(a.sketch, b.sketch);
+        assert_eq!(similarity(&a, &b, &settings), 1.0);
+    }
+
+    /// Reproducible random bases.
+    fn random_bases(length: usize, mut state: u64) -> String {
+        (0..length)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                b"ACGT"[(state >> 62) as usize] as char
+            })
+            .collect()
+    }
+
+    #[test]
+    fn near_duplicates_are_grouped() {
+        let folder = std::env::temp_dir().join(format!("iva-duplicates-{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        let write = |name: &str, sequence: &str| {
+            fs::write(folder.join(name), format!(">{}\n{}\n", name, sequence)).unwrap();
+        };
+        let sequence = random_bases(2000, 1);
+        let base = if &sequence[1000..1001] == "A" {
+            "C"
+        } else {
+            "A"
+        };
+        let mutated = format!("{}{}{}", &sequence[..1000], base, &sequence[1001..]);
+        write("a.fasta", &sequence);
+        write("b.fasta", &reverse_complementary(&sequence));
+        write("c.fasta", &mutated);
+        write("d.fasta", &random_bases(2000, 2));
+
+        let settings = DuplicateSettings::default();
+        let fingerprints: Vec<Fingerprint> = [&sequence, &mutated, &random_bases(2000, 2)]
+            .iter()
+            .map(|s| fingerprint(s, Topology::Linear, &settings))
+            .collect();
+        let candidates = candidate_pairs(&fingerprints.iter().collect::<Vec<_>>());
+        assert_eq!(candidates, [vec![1], vec![], vec![]]);
+
+        let report = find_duplicates(&folder, &settings, None).unwrap();
+        let _ = fs::remove_dir_all(&folder);
+        assert_eq!(report.scanned, 4);
+        assert_eq!(report.groups.len(), 1);
+        let group = &report.groups[0];
+        assert!(!group.exact);
+        let members: Vec<PathBuf> = group.members.iter().map(|m| m.path.clone()).collect();
+        assert_eq!(
+            members,
+            ["a.fasta", "b.fasta", "c.fasta"].map(|name| folder.join(name))
+        );
+        assert_eq!(group.members[1].similarity, 1.0);
+        assert!(group.members[2].similarity >= settings.min_similarity);
+    }
+}
@@ -0,0 +1,227 @@
+use serde::{Deserialize, Serialize};
+
+use crate::primers::PrimerSet;
+use crate::sequence::{degeneracy, iupac_mask};
+
+/// How mixed (degenerate) bases are written in order sheets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MixedBaseFormat {
+    /// Standard IUPAC letters, accepted by most vendors for machine-mixed bases.
+    #[default]
+    #[serde(rename = "iupac")]
+    Iupac,
+    /// IDT hand-mixed notation with explicit A/C/G/T percentages, e.g. `(N1:25252525)`.
+    #[serde(rename = "idtHandMixed")]
+    IdtHandMixed,
+}
+
+/// Write a primer sequence with its mixed bases in the requested vendor notation.
+pub fn format_mixed_bases(seq: &str, format: MixedBaseFormat) -> String {
+    match format {
+        MixedBaseFormat::Iupac => seq.to_string(),
+        MixedBaseFormat::IdtHandMixed => {
+            let mut defined: Vec<u8> = Vec::new();
+            let mut out = String::with_capacity(seq.len());
+            for b in seq.bytes() {
+                let mask = iupac_mask(b);
+                if mask.count_ones() <= 1 {
+                    out.push(b as char);
+                } else if defined.contains(&b) {
+                    out.push_str(&format!("({}1)", b as char));
+                } else {
+                    defined.push(b);
+                    out.push_str(&format!("({}1:{})", b as char, mix_percentages(mask)));
+                }
+            }
+            out
+        }
+    }
+}
+
+/// A/C/G/T percentages of an equimolar mix, two digits each, summing to 100.
+fn mix_percentages(mask: u8) -> String {
+    let n = mask.count_ones();
+    let mut remaining = 100;
+    let mut seen = 0;
+    (0..4)
+        .map(|i| {
+            if mask & (1 << i) == 0 {
+                return "00".to_string();
+            }
+            seen += 1;
+            let share = if seen == n { remaining } else { 100 / n };
+            remaining -= share;
+            format!("{:02}", share)
+        })
+        .collect()
+}
+
+/// One oligo to order, possibly shared between several primer sets.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderEntry {
+    pub name: String,
+    pub sequence: String,
+    pub degeneracy: u64,
+    pub used_in: Vec<String>,
+}
+
+/// Collapse the primers of several sets into unique oligos. The first primer
+/// with a given sequence names the oligo, later identical primers only add
+/// their set to `used_in`.
+pub fn consolidate_order(primer_sets: &[PrimerSet]) -> Vec<OrderEntry> {
+    let mut entries: Vec<OrderEntry> = Vec::new();
+    for set in primer_sets {
+        for primer in &set.primers {
+            let sequence = primer.sequence();
+            if sequence.is_empty() {
+                continue;
+            }
+            match entries.iter_mut().find(|e| e.sequence == sequence) {
+                Some(entry) => entry.used_in.push(set.title.clone()),
+                None => entries.push(OrderEntry {
+                    name: primer.label.clone(),
+                    degeneracy: degeneracy(&sequence),
+                    sequence,
+                    used_in: vec![set.title.clone()],
+                }),
+            }
+        }
+    }
+    entries
+}
+
+/// Order sheet table for consolidated oligos.
+pub fn order_sheet_table(entries: &[OrderEntry], format: MixedBaseFormat) -> Vec<Vec<String>> {
+    let mut table = vec![vec![
+        "Primer name".to_string(),
+        "Sequence (5'->3')".to_string(),
+        "Length [nt]".to_string(),
+        "Degeneracy".to_string(),
+        "Used in".to_string(),
+    ]];
+    for entry in entries {
+        table.push(vec![
+            entry.name.clone(),
+            format_mixed_bases(&entry.sequence, format),
+            entry.sequence.len().to_string(),
+            entry.degeneracy.to_string(),
+            entry.used_in.join("; "),
+        ]);
+    }
+    table
+}
+
+/// Render table rows as CSV, quoting cells where needed.
+pub fn table_to_csv(table: &[Vec<String>]) -> String {
+    table
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| {
+                    if cell.contains([',', '"', '\n']) {
+                        format!("\"{}\"", cell.replace('"', "\"\""))
+                    } else {
+                        cell.clone()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primers::{OperationType, PerEnd, Primer, PrimerRegion, RegionType, Symmetry};
+    use crate::sequence::Direction;
+
+    fn primer_set(title: &str, sequences: [&str; 2]) -> PrimerSet {
+        let primer = |label: &str, sequence: &str| Primer {
+            name: label.to_string(),
+            label: label.to_string(),
+            regions: vec![PrimerRegion {
+                sequence: sequence.to_string(),
+                region_type: RegionType::Tbr,
+                start: None,
+                max_length: None,
+                direction: Direction::Fwd,
+            }],
+        };
+        PrimerSet {
+            title: title.to_string(),
+            operation_type: OperationType::Mutation,
+            hr_length: PerEnd::Single(0),
+            hr_tm: PerEnd::Single(0.0),
+            symmetry: Symmetry::Symmetric,
+            current_plasmid_sequence: String::new(),
+            primers: vec![
+                primer(&format!("{}_F", title), sequences[0]),
+                primer(&format!("{}_R", title), sequences[1]),
+            ],
+        }
+    }
+
+    #[test]
+    fn idt_hand_mixed_bases() {
+        assert_eq!(
+            format_mixed_bases("ACNNKT", MixedBaseFormat::Iupac),
+            "ACNNKT"
+        );
+        assert_eq!(
+            format_mixed_bases("ACNNKT", MixedBaseFormat::IdtHandMixed),
+            "AC(N1:25252525)(N1)(K1:00005050)T"
+        );
+        assert_eq!(
+            format_mixed_bases("B", MixedBaseFormat::IdtHandMixed),
+            "(B1:00333334)"
+        );
+    }
+
+    #[test]
+    fn shared_primers_are_ordered_once() {
+        let sets = [
+            primer_set("K5A", ["GCAACCGTT", "ACGGTTNNK"]),
+            primer_set("K6A", ["GCAACCGTT", "TTGCAACCG"]),
+        ];
+        let order = consolidate_order(&sets);
+        let entries: Vec<(&str, &str, u64, Vec<String>)> = order
+            .iter()
+            .map(|e| {
+                (
+                    e.name.as_str(),
+                    e.sequence.as_str(),
+                    e.degeneracy,
+                    e.used_in.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                (
+                    "K5A_F",
+                    "GCAACCGTT",
+                    1,
+                    vec!["K5A".to_string(), "K6A".to_string()]
+                ),
+                ("K5A_R", "ACGGTTNNK", 32, vec!["K5A".to_string()]),
+                ("K6A_R", "TTGCAACCG", 1, vec!["K6A".to_string()]),
+            ]
+        );
+
+        let csv = table_to_csv(&order_sheet_table(&order, MixedBaseFormat::Iupac));
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "Primer name,Sequence (5'->3'),Length [nt],Degeneracy,Used in"
+        );
+        assert_eq!(lines[1], "K5A_F,GCAACCGTT,9,1,K5A; K6A");
+        assert_eq!(
+            table_to_csv(&[vec!["a,b".to_string(), "say \"hi\"".to_string()]]),
+            "\"a,b\",\"say \"\"hi\"\"\""
+        );
+    }
+}
@@ -0,0 +1,464 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common_features::{CommonFeature, SequenceType, COMMON_FEATURES};
+use crate::plasmid_file::read_plasmid_file;
+use crate::sequence::{reverse_complementary, sanitize_sequence, span_sequence, Direction};
+use crate::store::{new_id, read_file, timestamp, Store, Versioned};
+use crate::translation::{feature_sequence, genetic_code, translate_feature};
+
+/// The user library, in the app data directory.
+static LIBRARY: Store<FeatureLibrary> = Store::new("feature_library.json", true);
+
+/// Feature of the user library.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryFeature {
+    pub id: String,
+    #[serde(flatten)]
+    pub feature: CommonFeature,
+    /// Raised on every change, the higher version wins when libraries are
+    /// merged.
+    pub version: u32,
+    pub modified: String,
+    /// File or plasmid the feature was taken from.
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+/// User feature library, stored as a single file that can also be shared.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureLibrary {
+    pub format: u32,
+    /// Raised on every change to the library.
+    pub revision: u32,
+    pub features: Vec<LibraryFeature>,
+}
+
+impl Default for FeatureLibrary {
+    fn default() -> Self {
+        Self {
+            format: Self::FORMAT,
+            revision: 0,
+            features: Vec::new(),
+        }
+    }
+}
+
+impl Versioned for FeatureLibrary {
+    const FORMAT: u32 = 1;
+    const DESCRIPTION: &'static str = "feature library";
+
+    fn format(&self) -> u32 {
+        self.format
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryImportSummary {
+    pub added: usize,
+    pub updated: usize,
+    /// Entries already in the library or older than the library's copy.
+    pub skipped: usize,
+    /// Entries refused as empty, ambiguous or untranslatable.
+    pub rejected: usize,
+    /// Why entries were refused, one line each.
+    pub errors: Vec<String>,
+}
+
+impl LibraryImportSummary {
+    fn reject(&mut self, error: String) {
+        self.rejected += 1;
+        self.errors.push(error);
+    }
+}
+
+/// Apply a change to the user library and raise its revision.
+fn modify_library<T>(
+    change: impl FnOnce(&mut FeatureLibrary) -> Result<T, String>,
+) -> Result<T, String> {
+    LIBRARY.modify(|library| {
+        let result = change(library)?;
+        library.revision += 1;
+        Ok(result)
+    })
+}
+
+/// Check and normalize a library entry. Gaps are dropped; ambiguous bases,
+/// unknown residues and stops are refused since they never match a plasmid.
+fn clean_entry(mut feature: CommonFeature) -> Result<CommonFeature, String> {
+    feature.label = feature.label.trim().to_string();
+    if feature.label.is_empty() {
+        return Err("Library features need a label.".to_string());
+    }
+    let sequence: String = match feature.sequence_type {
+        SequenceType::Dna => sanitize_sequence(&feature.sequence).replace('U', "T"),
+        SequenceType::Aa => feature
+            .sequence
+            .chars()
+            .filter(|c| c.is_ascii_alphabetic() || *c == '*')
+            .map(|c| c.to_ascii_uppercase())
+            .collect::<String>()
+            .trim_end_matches('*')
+            .to_string(),
+    };
+    feature.sequence = sequence.replace(['.', '-'], "");
+    if feature.sequence.is_empty() {
+        return Err(format!("\"{}\" has no sequence.", feature.label));
+    }
+    let alphabet = feature.sequence_type.alphabet();
+    if let Some(c) = feature.sequence.bytes().find(|b| !alphabet.contains(b)) {
+        return Err(format!(
+            "\"{}\" contains \"{}\", library sequences may only use {}.",
+            feature.label,
+            c as char,
+            String::from_utf8_lossy(alphabet)
+        ));
+    }
+    Ok(feature)
+}
+
+/// Add an entry unless the library already holds the same label and sequence.
+/// Returns the new entry.
+fn insert_entry(
+    library: &mut FeatureLibrary,
+    feature: CommonFeature,
+    source: Option<String>,
+) -> Result<Option<LibraryFeature>, String> {
+    let feature = clean_entry(feature)?;
+    let exists = library
+        .features
+        .iter()
+        .any(|f| f.feature.label == feature.label && f.feature.sequence == feature.sequence);
+    if exists {
+        return Ok(None);
+    }
+    let entry = LibraryFeature {
+        id: new_id(|id| library.features.iter().any(|f| f.id == id)),
+        feature,
+        version: 1,
+        modified: timestamp(),
+        source,
+    };
+    library.features.push(entry.clone());
+    Ok(Some(entry))
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryRegionRequest {
+    pub sequence: String,
+    /// Selected region (1-based, inclusive). Spans where the end lies before
+    /// the start run across the origin.
+    pub span: [usize; 2],
+    pub directionality: Direction,
+    pub label: String,
+    pub feature_type: String,
+    #[serde(default)]
+    pub note: Option<String>,
+    /// Store the translation of the region instead of its bases.
+    #[serde(default)]
+    pub as_protein: bool,
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+/// Library entry for a region of a plasmid, read on its coding strand.
+pub fn region_entry(request: &LibraryRegionRequest) -> Result<CommonFeature, String> {
+    let sequence = sanitize_sequence(&request.sequence);
+    let in_bounds = |i: usize| i >= 1 && i <= sequence.len();
+    if !in_bounds(request.span[0]) || !in_bounds(request.span[1]) {
+        return Err(format!(
+            "Span [{}, {}] is out of bounds.",
+            request.span[0], request.span[1]
+        ));
+    }
+    let region = span_sequence(&sequence, request.span);
+    let region = match request.directionality {
+        Direction::Fwd => region,
+        Direction::Rev => reverse_complementary(&region),
+    };
+    let (sequence_type, sequence) = if request.as_protein {
+        // Bacterial table, the same default as for CDS features without a
+        // /transl_table qualifier
+        let code = genetic_code(11).ok_or("Unknown translation table 11.")?;
+        let protein = code.translate(&region, true);
+        (SequenceType::Aa, protein.trim_end_matches('*').to_string())
+    } else {
+        (SequenceType::Dna, region)
+    };
+    Ok(CommonFeature {
+        label: request.label.clone(),
+        feature_type: request.feature_type.clone(),
+        sequence_type,
+        sequence,
+        note: request.note.clone(),
+    })
+}
+
+/// Take the features of plasmid files into the library. CDS features are
+/// stored as protein, everything else as DNA.
+pub fn import_files(
+    library: &mut FeatureLibrary,
+    paths: &[PathBuf],
+    feature_types: &[String],
+) -> Result<LibraryImportSummary, String> {
+    let mut summary = LibraryImportSummary::default();
+    for path in paths {
+        let plasmid = read_plasmid_file(path)?;
+        let source = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string());
+        for feature in &plasmid.features {
+            let wanted = feature_types.is_empty()
+                || feature_types
+                    .iter()
+                    .any(|t| t.eq_ignore_ascii_case(&feature.feature_type));
+            if !wanted {
+                continue;
+            }
+            let (sequence_type, sequence) = if feature.feature_type.eq_ignore_ascii_case("CDS") {
+                match translate_feature(&plasmid.sequence, feature, None) {
+                    Ok(translation) => (
+                        SequenceType::Aa,
+                        translation.protein.trim_end_matches('*').to_string(),
+                    ),
+                    Err(error) => {
+                        summary.reject(format!(
+                            "\"{}\" was not translated: {}",
+                            feature.label, error
+                        ));
+                        continue;
+                    }
+                }
+            } else {
+                (
+                    SequenceType::Dna,
+                    feature_sequence(&plasmid.sequence, feature),
+                )
+            };
+            let note = feature
+                .qualifiers
+                .iter()
+                .find(|(key, _)| key == "note")
+                .map(|(_, value)| value.clone());
+            let entry = CommonFeature {
+                label: feature.label.clone(),
+                feature_type: feature.feature_type.clone(),
+                sequence_type,
+                sequence,
+                note,
+            };
+            // Empty, ambiguous or duplicate features are not worth failing the import
+            match insert_entry(library, entry, source.clone()) {
+                Ok(Some(_)) => summary.added += 1,
+                Ok(None) => summary.skipped += 1,
+                Err(error) => summary.reject(error),
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// Merge a shared library into another, keeping the newer version of entries
+/// both hold. Entries are checked like those added by hand.
+pub fn merge_library(library: &mut FeatureLibrary, other: FeatureLibrary) -> LibraryImportSummary {
+    let mut summary = LibraryImportSummary::default();
+    for mut entry in other.features {
+        entry.feature = match clean_entry(entry.feature) {
+            Ok(feature) => feature,
+            Err(error) => {
+                summary.reject(error);
+                continue;
+            }
+        };
+        match library.features.iter_mut().find(|f| f.id == entry.id) {
+            Some(existing) if existing.version < entry.version => {
+                *existing = entry;
+                summary.updated += 1;
+            }
+            Some(_) => summary.skipped += 1,
+            None => {
+                let duplicate = library.features.iter().any(|f| {
+                    f.feature.label == entry.feature.label
+                        && f.feature.sequence == entry.feature.sequence
+                });
+                if duplicate {
+                    summary.skipped += 1;
+                } else {
+                    library.features.push(entry);
+                    summary.added += 1;
+                }
+            }
+        }
+    }
+    summary
+}
+
+/// Bundled common features followed by those of the user library. A missing
+/// or broken library leaves just the bundled set.
+pub fn annotation_library() -> Vec<CommonFeature> {
+    let mut features = COMMON_FEATURES.clone();
+    match LIBRARY.load() {
+        Ok(library) => features.extend(library.features.into_iter().map(|f| f.feature)),
+        Err(e) => log::warn!("Feature library not used: {}", e),
+    }
+    features
+}
+
+#[tauri::command]
+/// The user feature library.
+pub fn get_feature_library() -> Result<FeatureLibrary, String> {
+    LIBRARY.load()
+}
+
+#[tauri::command]
+/// Add a selected region of a plasmid to the feature library.
+pub fn add_library_region(request: LibraryRegionRequest) -> Result<Option<LibraryFeature>, String> {
+    let entry = region_entry(&request)?;
+    modify_library(|library| insert_entry(library, entry, request.source.clone()))
+}
+
+#[tauri::command]
+/// Replace the label, type, sequence or note of a library feature.
+pub fn update_library_feature(
+    id: String,
+    feature: CommonFeature,
+) -> Result<LibraryFeature, String> {
+    let feature = clean_entry(feature)?;
+    modify_library(|library| {
+        let entry = library
+            .features
+            .iter_mut()
+            .find(|f| f.id == id)
+            .ok_or_else(|| format!("No library feature with id \"{}\".", id))?;
+        entry.feature = feature;
+        entry.version += 1;
+        entry.modified = timestamp();
+        Ok(entry.clone())
+    })
+}
+
+#[tauri::command]
+/// Remove features from the library.
+pub fn remove_library_features(ids: Vec<String>) -> Result<usize, String> {
+    modify_library(|library| {
+        let before = library.features.len();
+        library.features.retain(|f| !ids.contains(&f.id));
+        Ok(before - library.features.len())
+    })
+}
+
+#[tauri::command]
+/// Add the features of GenBank or SnapGene files to the library, optionally
+/// only those of the given types.
+pub fn import_library_from_files(
+    paths: Vec<PathBuf>,
+    feature_types: Vec<String>,
+) -> Result<LibraryImportSummary, String> {
+    modify_library(|library| import_files(library, &paths, &feature_types))
+}
+
+#[tauri::command]
+/// Write the library to a file for sharing.
+pub fn export_feature_library(path: PathBuf) -> Result<(), String> {
+    LIBRARY.export(&path)
+}
+
+#[tauri::command]
+/// Merge a shared library file into the library.
+pub fn import_feature_library(path: PathBuf) -> Result<LibraryImportSummary, String> {
+    let other: FeatureLibrary = read_file(&path)?;
+    modify_library(|library| Ok(merge_library(library, other)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(sequence_type: SequenceType, sequence: &str) -> CommonFeature {
+        CommonFeature {
+            label: " test ".to_string(),
+            feature_type: "misc_feature".to_string(),
+            sequence_type,
+            sequence: sequence.to_string(),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn clean_entry_drops_gaps_and_refuses_ambiguity() {
+        let cleaned = clean_entry(entry(SequenceType::Dna, "acg-t..ACGU\n")).unwrap();
+        assert_eq!(cleaned.label, "test");
+        assert_eq!(cleaned.sequence, "ACGTACGT");
+        assert!(clean_entry(entry(SequenceType::Dna, "ACGTNACGT")).is_err());
+        assert!(clean_entry(entry(SequenceType::Dna, "--")).is_err());
+
+        let cleaned = clean_entry(entry(SequenceType::Aa, "mkv-LT*")).unwrap();
+        assert_eq!(cleaned.sequence, "MKVLT");
+        assert!(clean_entry(entry(SequenceType::Aa, "MKXLT")).is_err());
+        assert!(clean_entry(entry(SequenceType::Aa, "MK*LT")).is_err());
+    }
+
+    #[test]
+    fn import_translates_from_codon_start_and_reports_bad_features() {
+        let genbank = "\
+LOCUS       test                      30 bp    DNA     circular
+FEATURES             Location/Qualifiers
+     CDS             1..13
+                     /label=shifted
+                     /codon_start=2
+     CDS             4..12
+                     /label=bad table
+                     /transl_table=99
+     misc_feature    14..25
+                     /label=site
+ORIGIN
+        1 gatgaaaccc taagggatcc aaaaattttt
+//
+";
+        let paths = [std::env::temp_dir().join(format!("iva-library-{}.gb", std::process::id()))];
+        std::fs::write(&paths[0], genbank).unwrap();
+        let mut library = FeatureLibrary::default();
+        let summary = import_files(&mut library, &paths, &[]).unwrap();
+        std::fs::remove_file(&paths[0]).unwrap();
+
+        assert_eq!(
+            (summary.added, summary.skipped, summary.rejected),
+            (2, 0, 1)
+        );
+        assert_eq!(
+            summary.errors,
+            ["\"bad table\" was not translated: Unknown translation table \"99\"."]
+        );
+        let entries: Vec<(&str, &str)> = library
+            .features
+            .iter()
+            .map(|f| (f.feature.label.as_str(), f.feature.sequence.as_str()))
+            .collect();
+        assert_eq!(entries, [("shifted", "MKP"), ("site", "GGGATCCAAAAA")]);
+    }
+
+    #[test]
+    fn merge_cleans_entries() {
+        let shared = |sequence: &str, id: &str| LibraryFeature {
+            id: id.to_string(),
+            feature: entry(SequenceType::Dna, sequence),
+            version: 1,
+            modified: timestamp(),
+            source: None,
+        };
+        let other = FeatureLibrary {
+            features: vec![shared("ACGT-ACGT", "a"), shared("ACGTNACGT", "b")],
+            ..FeatureLibrary::default()
+        };
+        let mut library = FeatureLibrary::default();
+        let summary = merge_library(&mut library, other);
+        assert_eq!((summary.added, summary.rejected), (1, 1));
+        assert_eq!(library.features[0].feature.sequence, "ACGTACGT");
+        assert_eq!(library.features[0].feature.label, "test");
+    }
+}
@@ -0,0 +1,404 @@
+use serde::{Deserialize, Serialize};
+
+use crate::gibson::{
+    OverlapReport, OverlapSettings, CROSS_SIMILARITY_WARNING, HAIRPIN_WARNING_STEM,
+};
+use crate::melting::melting_temperature;
+use crate::primers::{insert_from_linear_fragment, DesignSettings, PrimerSet};
+use crate::sequence::{
+    count_substring_occurences, fraction_gc, reverse_complementary, sanitize_sequence, Topology,
+};
+use crate::structure::{longest_common_substring, longest_hairpin, longest_homopolymer};
+
+/// Boundary positions tried on each side of the ideal split point.
+const BOUNDARY_WINDOW: usize = 60;
+/// Extra fragments tried when no unique overlaps are found at the minimum count.
+const MAX_EXTRA_FRAGMENTS: usize = 4;
+/// Homopolymer runs from this length on are penalised in overlaps.
+const OVERLAP_HOMOPOLYMER: usize = 5;
+/// Homopolymer runs from this length on are reported for fragments.
+const FRAGMENT_HOMOPOLYMER: usize = 10;
+/// GC content range accepted by synthesis vendors.
+const FRAGMENT_GC_RANGE: [f64; 2] = [0.25, 0.65];
+
+/// Synthetic gene fragment product.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FragmentVendor {
+    #[serde(rename = "gBlock")]
+    GBlock,
+    #[serde(rename = "eBlock")]
+    EBlock,
+}
+
+impl FragmentVendor {
+    /// Orderable length range in bp.
+    fn length_range(self) -> [usize; 2] {
+        match self {
+            FragmentVendor::GBlock => [125, 3000],
+            FragmentVendor::EBlock => [300, 1500],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeneFragmentRequest {
+    pub sequence: String,
+    pub topology: Topology,
+    pub operation_start: i64,
+    /// Missing for pure insertions.
+    #[serde(default)]
+    pub operation_end: Option<i64>,
+    pub insert: String,
+    #[serde(default = "default_name")]
+    pub name: String,
+    #[serde(default = "default_vendor")]
+    pub vendor: FragmentVendor,
+    /// Overrides the vendor's orderable length range.
+    #[serde(default)]
+    pub length_range: Option<[usize; 2]>,
+    #[serde(default)]
+    pub overlap: OverlapSettings,
+    #[serde(default)]
+    pub settings: DesignSettings,
+}
+
+fn default_name() -> String {
+    "Insert".to_string()
+}
+
+fn default_vendor() -> FragmentVendor {
+    FragmentVendor::GBlock
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeneFragment {
+    pub name: String,
+    pub sequence: String,
+    /// Span within the full linear fragment (1-based, inclusive).
+    pub span: [usize; 2],
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeneFragmentPlan {
+    /// Vector linearisation primers.
+    pub primer_set: PrimerSet,
+    pub fragments: Vec<GeneFragment>,
+    /// Overlaps between neighbouring fragments.
+    pub overlaps: Vec<OverlapReport>,
+    /// Insertion flanked by the vector homology, before splitting.
+    pub linear_sequence: String,
+}
+
+/// Overlap between two fragments, starting at `start` in the linear sequence.
+struct Candidate {
+    start: usize,
+    sequence: String,
+    tm: f64,
+    hairpin_stem: usize,
+    longest_shared: usize,
+    score: f64,
+}
+
+struct Planner<'a> {
+    linear: &'a str,
+    linear_rc: String,
+    request: &'a GeneFragmentRequest,
+    length_range: [usize; 2],
+}
+
+impl Planner<'_> {
+    /// Shortest overlap at `start` reaching the target Tm, scored by how well it
+    /// will behave in the assembly (lower is better). `None` if it is not unique.
+    fn candidate(&self, start: usize, ideal: usize, chosen: &[String]) -> Option<Candidate> {
+        let overlap = &self.request.overlap;
+        let mut length = overlap.overlap_min_length;
+        let (sequence, tm) = loop {
+            let sequence = self.linear.get(start..start + length)?;
+            let tm = melting_temperature(sequence, None, &self.request.settings.tm);
+            if tm >= overlap.overlap_tm || length >= overlap.overlap_max_length {
+                break (sequence, tm);
+            }
+            length += 1;
+        };
+
+        // Overlaps must only anneal at their own junction
+        if count_substring_occurences(sequence, self.linear)
+            + count_substring_occurences(sequence, &self.linear_rc)
+            > 1
+        {
+            return None;
+        }
+
+        let hairpin_stem = longest_hairpin(sequence).map_or(0, |h| h.stem_length);
+        let longest_shared = chosen
+            .iter()
+            .map(|other| {
+                longest_common_substring(sequence, other).max(longest_common_substring(
+                    sequence,
+                    &reverse_complementary(other),
+                ))
+            })
+            .max()
+            .unwrap_or(0);
+        let gc = fraction_gc(sequence);
+        let homopolymer = longest_homopolymer(sequence);
+
+        let mut score = (tm - overlap.overlap_tm).abs();
+        score += 20.0 * ((0.4 - gc).max(0.0) + (gc - 0.6).max(0.0));
+        if hairpin_stem >= HAIRPIN_WARNING_STEM {
+            score += 5.0 * hairpin_stem as f64;
+        }
+        if homopolymer >= OVERLAP_HOMOPOLYMER {
+            score += 2.0 * homopolymer as f64;
+        }
+        if longest_shared >= CROSS_SIMILARITY_WARNING {
+            score += 10.0 * longest_shared as f64;
+        }
+        // Prefer evenly sized fragments
+        score += 0.01 * start.abs_diff(ideal) as f64;
+
+        Some(Candidate {
+            start,
+            sequence: sequence.to_string(),
+            tm,
+            hairpin_stem,
+            longest_shared,
+            score,
+        })
+    }
+
+    /// Split the linear sequence into `count` fragments, picking the best overlap
+    /// around each evenly spaced split point.
+    fn split(&self, count: usize, vector_overhangs: &[String]) -> Option<Vec<Candidate>> {
+        let total = self.linear.len();
+        let [min_length, max_length] = self.length_range;
+        let max_overlap = self.request.overlap.overlap_max_length;
+        let mut chosen: Vec<String> = vector_overhangs.to_vec();
+        let mut junctions: Vec<Candidate> = Vec::new();
+        let mut fragment_start = 0;
+
+        for j in 1..count {
+            let ideal = j * total / count;
+            let remaining = count - j;
+            let best = (ideal.saturating_sub(BOUNDARY_WINDOW)
+                ..=(ideal + BOUNDARY_WINDOW).min(total))
+                .filter_map(|start| self.candidate(start, ideal, &chosen))
+                .filter(|c| c.start > fragment_start)
+                .filter(|c| {
+                    let Some(length) = (c.start + c.sequence.len()).checked_sub(fragment_start)
+                    else {
+                        return false;
+                    };
+                    let rest = total - c.start;
+                    (min_length..=max_length).contains(&length)
+                        && rest >= min_length
+                        && rest + (remaining - 1) * max_overlap <= remaining * max_length
+                })
+                .min_by(|a, b| a.score.total_cmp(&b.score))?;
+            fragment_start = best.start;
+            chosen.push(best.sequence.clone());
+            junctions.push(best);
+        }
+
+        (total - fragment_start <= max_length).then_some(junctions)
+    }
+}
+
+/// Split a long insertion into orderable gene fragments and design the primers
+/// linearising the vector.
+pub fn plan_fragments(request: &GeneFragmentRequest) -> Result<GeneFragmentPlan, String> {
+    let plasmid_sequence = sanitize_sequence(&request.sequence);
+    let insert = sanitize_sequence(&request.insert);
+    let last = request.operation_end.unwrap_or(request.operation_start - 1);
+    if request.operation_start < 1
+        || request.operation_start > plasmid_sequence.len() as i64 + 1
+        || last < 0
+        || last > plasmid_sequence.len() as i64
+    {
+        return Err("Operation range lies outside of the plasmid.".to_string());
+    }
+    if insert.is_empty() {
+        return Err("Nothing to insert.".to_string());
+    }
+
+    let insertion = insert_from_linear_fragment(
+        request.operation_start,
+        request.operation_end,
+        &plasmid_sequence,
+        &insert,
+        request.topology,
+        &request.settings,
+    )
+    .map_err(|e| e.to_string())?;
+    let linear = insertion.fragment.as_str();
+
+    let length_range = request
+        .length_range
+        .unwrap_or_else(|| request.vendor.length_range());
+    let [min_length, max_length] = length_range;
+    let max_overlap = request.overlap.overlap_max_length;
+    if max_length <= 2 * max_overlap {
+        return Err(format!(
+            "Maximum fragment length {} bp leaves no room for {} bp overlaps.",
+            max_length, max_overlap
+        ));
+    }
+
+    let planner = Planner {
+        linear,
+        linear_rc: reverse_complementary(linear),
+        request,
+        length_range,
+    };
+
+    // Vector homology at both ends, the overlaps must not resemble it either
+    let [insert_start, insert_end] = insertion.insert_span;
+    let vector_overhangs = [
+        linear[..insert_start - 1].to_string(),
+        linear[insert_end..].to_string(),
+    ];
+
+    let min_count = if linear.len() <= max_length {
+        1
+    } else {
+        (linear.len() - max_overlap).div_ceil(max_length - max_overlap)
+    };
+    let junctions = (min_count..=min_count + MAX_EXTRA_FRAGMENTS)
+        .find_map(|count| planner.split(count, &vector_overhangs))
+        .ok_or_else(|| {
+            "Could not find unique overlaps to split the insertion, try a longer fragment length."
+                .to_string()
+        })?;
+
+    let count = junctions.len() + 1;
+    let mut fragments = Vec::with_capacity(count);
+    for i in 0..count {
+        let start = if i == 0 { 0 } else { junctions[i - 1].start };
+        let end = junctions
+            .get(i)
+            .map_or(linear.len(), |c| c.start + c.sequence.len());
+        let sequence = linear[start..end].to_string();
+
+        let mut warnings = Vec::new();
+        if sequence.len() < min_length {
+            warnings.push(format!(
+                "Fragment is shorter than the minimum orderable length ({} bp).",
+                min_length
+            ));
+        }
+        let gc = fraction_gc(&sequence);
+        if !(FRAGMENT_GC_RANGE[0]..=FRAGMENT_GC_RANGE[1]).contains(&gc) {
+            warnings.push(format!(
+                "GC content of {:.0}% may be rejected for synthesis.",
+                gc * 100.0
+            ));
+        }
+        let homopolymer = longest_homopolymer(&sequence);
+        if homopolymer >= FRAGMENT_HOMOPOLYMER {
+            warnings.push(format!("Contains a {} nt homopolymer run.", homopolymer));
+        }
+
+        fragments.push(GeneFragment {
+            name: if count == 1 {
+                request.name.clone()
+            } else {
+                format!("{}_{}", request.name, i + 1)
+            },
+            sequence,
+            span: [start + 1, end],
+            warnings,
+        });
+    }
+
+    let overlaps = junctions
+        .into_iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let mut warnings = Vec::new();
+            if c.tm < request.overlap.overlap_tm {
+                warnings.push(format!(
+                    "Overlap only reaches {:.1} C (target {:.1} C).",
+                    c.tm, request.overlap.overlap_tm
+                ));
+            }
+            if c.hairpin_stem >= HAIRPIN_WARNING_STEM {
+                warnings.push(format!(
+                    "Overlap can fold into a {} bp hairpin.",
+                    c.hairpin_stem
+                ));
+            }
+            if c.longest_shared >= CROSS_SIMILARITY_WARNING {
+                warnings.push(format!(
+                    "Overlap shares {} nt with another junction.",
+                    c.longest_shared
+                ));
+            }
+            OverlapReport {
+                between: [i, i + 1],
+                sequence: c.sequence,
+                tm: c.tm,
+                hairpin_stem: c.hairpin_stem,
+                warnings,
+            }
+        })
+        .collect();
+
+    Ok(GeneFragmentPlan {
+        linear_sequence: linear.to_string(),
+        primer_set: insertion.primer_set,
+        fragments,
+        overlaps,
+    })
+}
+
+#[tauri::command]
+/// Split a long insertion into gBlock/eBlock fragments with matching vector primers.
+pub fn plan_gene_fragments(request: GeneFragmentRequest) -> Result<GeneFragmentPlan, String> {
+    plan_fragments(&request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequence::tests::RANDOM_PLASMID;
+
+    #[test]
+    fn short_fragments_split_without_overflow() {
+        let plasmid = RANDOM_PLASMID.concat();
+        // Pseudo-random insert without repeats
+        let mut state: u64 = 1;
+        let insert: String = (0..600)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                b"ACGT"[(state >> 62) as usize] as char
+            })
+            .collect();
+        let request = GeneFragmentRequest {
+            sequence: plasmid[..200].to_string(),
+            topology: Topology::Circular,
+            operation_start: 101,
+            operation_end: None,
+            insert,
+            name: default_name(),
+            vendor: default_vendor(),
+            length_range: Some([20, 50]),
+            overlap: OverlapSettings {
+                overlap_min_length: 15,
+                overlap_max_length: 20,
+                overlap_tm: 40.0,
+            },
+            settings: DesignSettings::default(),
+        };
+        let plan = plan_fragments(&request).unwrap();
+        for pair in plan.fragments.windows(2) {
+            assert!(pair[0].span[0] < pair[1].span[0]);
+            assert!(pair[1].span[0] <= pair[0].span[1]);
+        }
+    }
+}
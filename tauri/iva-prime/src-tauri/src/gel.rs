@@ -0,0 +1,585 @@
+use serde::{Deserialize, Serialize};
+
+use crate::restriction::{default_methylation, find_cut_sites, CutSiteRequest, Methylation};
+use crate::sequence::{sanitize_sequence, Topology};
+use crate::simulation::{simulate, SimulationRequest};
+
+/// NEB 1 kb DNA Ladder (N3232), sizes and ng per band in a 0.5 µg load.
+const NEB_1KB: [(usize, f64); 11] = [
+    (10002, 42.0),
+    (8001, 42.0),
+    (6001, 50.0),
+    (5001, 42.0),
+    (4001, 33.0),
+    (3001, 125.0),
+    (2000, 48.0),
+    (1500, 36.0),
+    (1000, 42.0),
+    (517, 42.0),
+    (500, 42.0),
+];
+
+/// NEB 100 bp DNA Ladder (N3231), sizes and ng per band in a 0.5 µg load.
+const NEB_100BP: [(usize, f64); 13] = [
+    (1517, 45.0),
+    (1200, 35.0),
+    (1000, 95.0),
+    (900, 27.0),
+    (800, 24.0),
+    (700, 21.0),
+    (600, 18.0),
+    (517, 97.0),
+    (500, 97.0),
+    (400, 38.0),
+    (300, 29.0),
+    (200, 25.0),
+    (100, 48.0),
+];
+
+/// Separation range (bp) of standard agarose gels, used to place the bands.
+const RESOLVING_RANGES: [(f64, f64, f64); 6] = [
+    (0.5, 1000.0, 30000.0),
+    (0.7, 800.0, 12000.0),
+    (1.0, 500.0, 10000.0),
+    (1.2, 400.0, 7000.0),
+    (1.5, 200.0, 3000.0),
+    (2.0, 50.0, 2000.0),
+];
+
+/// Apparent linear size of supercoiled and open circular (nicked) plasmid,
+/// relative to its length.
+const SUPERCOILED_FACTOR: f64 = 0.65;
+const OPEN_CIRCULAR_FACTOR: f64 = 1.6;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Ladder {
+    Neb1kb,
+    Neb100bp,
+    /// User-defined ladder, band sizes in bp with optional amounts in ng.
+    Custom {
+        name: String,
+        sizes: Vec<usize>,
+        #[serde(default)]
+        amounts: Vec<f64>,
+    },
+}
+
+impl Ladder {
+    pub fn name(&self) -> &str {
+        match self {
+            Ladder::Neb1kb => "1 kb ladder",
+            Ladder::Neb100bp => "100 bp ladder",
+            Ladder::Custom { name, .. } => name,
+        }
+    }
+
+    /// Band sizes with their amounts, equal amounts if a custom ladder has none.
+    pub fn bands(&self) -> Vec<(usize, f64)> {
+        match self {
+            Ladder::Neb1kb => NEB_1KB.to_vec(),
+            Ladder::Neb100bp => NEB_100BP.to_vec(),
+            Ladder::Custom { sizes, amounts, .. } => sizes
+                .iter()
+                .enumerate()
+                .map(|(i, size)| (*size, amounts.get(i).copied().unwrap_or(50.0)))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PlasmidForm {
+    /// Uncut plasmid prep, mostly supercoiled with some nicked plasmid.
+    #[default]
+    Supercoiled,
+    Linear,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LaneSample {
+    /// Restriction digest of a plasmid or linear sequence.
+    Digest {
+        sequence: String,
+        topology: Topology,
+        enzymes: Vec<String>,
+        /// Methylation blocking sites, Dam and Dcm if missing.
+        #[serde(default = "default_methylation")]
+        methylation: Vec<Methylation>,
+    },
+    /// PCR products of a primer set on its templates.
+    Pcr(SimulationRequest),
+    /// Undigested plasmid.
+    Plasmid {
+        length: usize,
+        #[serde(default)]
+        form: PlasmidForm,
+    },
+    /// Linear fragments of known size.
+    Fragments(Vec<usize>),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GelLaneRequest {
+    pub label: String,
+    pub sample: LaneSample,
+    /// DNA loaded, in ng.
+    #[serde(default = "default_load")]
+    pub load: f64,
+}
+
+fn default_load() -> f64 {
+    200.0
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GelRequest {
+    pub ladder: Ladder,
+    pub lanes: Vec<GelLaneRequest>,
+    /// Agarose percentage (w/v).
+    #[serde(default = "default_agarose")]
+    pub agarose: f64,
+    /// Dark bands on a white background, for printed reports.
+    #[serde(default)]
+    pub inverted: bool,
+}
+
+fn default_agarose() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GelBand {
+    /// Fragment sizes running in this band.
+    pub fragments: Vec<usize>,
+    /// Size of linear DNA running at the same height.
+    pub apparent_size: usize,
+    /// Distance from the well, as fraction of the gel length.
+    pub distance: f64,
+    /// DNA in the band, in ng.
+    pub amount: f64,
+    /// Description of non-linear forms, e.g. supercoiled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub form: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GelLane {
+    pub label: String,
+    pub bands: Vec<GelBand>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GelResult {
+    /// Ladder first, then the sample lanes.
+    pub lanes: Vec<GelLane>,
+    pub svg: String,
+    pub warnings: Vec<String>,
+}
+
+/// Separation range of a gel, interpolated on a log scale between the
+/// tabulated agarose percentages.
+fn resolving_range(agarose: f64) -> (f64, f64) {
+    let agarose = agarose.clamp(RESOLVING_RANGES[0].0, RESOLVING_RANGES[5].0);
+    let upper = RESOLVING_RANGES
+        .iter()
+        .position(|(percent, _, _)| *percent >= agarose)
+        .unwrap_or(RESOLVING_RANGES.len() - 1)
+        .max(1);
+    let (p0, min0, max0) = RESOLVING_RANGES[upper - 1];
+    let (p1, min1, max1) = RESOLVING_RANGES[upper];
+    let t = (agarose - p0) / (p1 - p0);
+    let interpolate = |a: f64, b: f64| (a.ln() + t * (b.ln() - a.ln())).exp();
+    (interpolate(min0, min1), interpolate(max0, max1))
+}
+
+/// Migration distance of linear DNA as fraction of the gel length.
+///
+/// Mobility is sigmoidal in log size: fragments within the resolving range
+/// spread over most of the gel, larger ones pile up under the well and
+/// smaller ones run towards the front.
+fn migration_distance(size: f64, agarose: f64) -> f64 {
+    let (min, max) = resolving_range(agarose);
+    let (low, high) = (min.log10(), max.log10());
+    // Range ends land at 90% and 10% of the gel
+    let steepness = 2.0 * 9f64.ln() / (high - low);
+    let center = (low + high) / 2.0;
+    1.0 / (1.0 + (steepness * (size.max(1.0).log10() - center)).exp())
+}
+
+/// Fragment sizes of a digest, an uncut circular sequence gives none. Without
+/// enzymes the molecule stays uncut.
+fn digest_fragments(
+    sequence: &str,
+    topology: Topology,
+    enzymes: &[String],
+    methylation: &[Methylation],
+    warnings: &mut Vec<String>,
+) -> Result<Vec<usize>, String> {
+    let sequence = sanitize_sequence(sequence);
+    if sequence.is_empty() {
+        return Err("The sequence is empty.".to_string());
+    }
+    // An empty list means all enzymes to find_cut_sites, here it means undigested
+    let cuts = if enzymes.is_empty() {
+        Vec::new()
+    } else {
+        find_cut_sites(&CutSiteRequest {
+            sequence: sequence.clone(),
+            topology,
+            enzymes: enzymes.to_vec(),
+            filter: Default::default(),
+            commercial_only: false,
+            methylation: methylation.to_vec(),
+        })?
+    };
+
+    let mut positions: Vec<usize> = Vec::new();
+    for enzyme in &cuts {
+        let blocked = enzyme
+            .sites
+            .iter()
+            .filter(|s| !s.blocked_by.is_empty())
+            .count();
+        if blocked > 0 {
+            warnings.push(format!(
+                "{} site(s) of {} blocked by methylation.",
+                blocked, enzyme.enzyme
+            ));
+        }
+        positions.extend(
+            enzyme
+                .sites
+                .iter()
+                .filter(|s| s.blocked_by.is_empty())
+                .map(|s| s.cut[0] % sequence.len()),
+        );
+    }
+    positions.sort_unstable();
+    positions.dedup();
+
+    let length = sequence.len();
+    Ok(match (topology, positions.len()) {
+        (Topology::Circular, 0) => Vec::new(),
+        (Topology::Circular, n) => (0..n)
+            .map(|i| (positions[(i + 1) % n] + length - positions[i] - 1) % length + 1)
+            .collect(),
+        (Topology::Linear, _) => {
+            let mut bounds = vec![0];
+            bounds.extend(positions.iter().copied());
+            bounds.push(length);
+            bounds.windows(2).map(|w| w[1] - w[0]).collect()
+        }
+    })
+}
+
+/// Bands of linear fragments, loaded at equal molarity.
+fn linear_bands(fragments: &[usize], load: f64, agarose: f64) -> Vec<GelBand> {
+    let total: usize = fragments.iter().sum();
+    fragments
+        .iter()
+        .map(|size| GelBand {
+            fragments: vec![*size],
+            apparent_size: *size,
+            distance: migration_distance(*size as f64, agarose),
+            amount: load * *size as f64 / total.max(1) as f64,
+            form: None,
+        })
+        .collect()
+}
+
+/// Supercoiled plasmid prep with a minor open circular band.
+fn uncut_plasmid_bands(length: usize, load: f64, agarose: f64) -> Vec<GelBand> {
+    [
+        (SUPERCOILED_FACTOR, 0.8, "supercoiled"),
+        (OPEN_CIRCULAR_FACTOR, 0.2, "open circular"),
+    ]
+    .into_iter()
+    .map(|(factor, share, form)| {
+        let apparent_size = (length as f64 * factor).round() as usize;
+        GelBand {
+            fragments: vec![length],
+            apparent_size,
+            distance: migration_distance(apparent_size as f64, agarose),
+            amount: load * share,
+            form: Some(form.to_string()),
+        }
+    })
+    .collect()
+}
+
+/// Merge bands too close to be told apart into one band.
+fn merge_bands(mut bands: Vec<GelBand>) -> Vec<GelBand> {
+    const RESOLUTION: f64 = 0.006;
+    bands.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    let mut merged: Vec<GelBand> = Vec::new();
+    for band in bands {
+        match merged.last_mut() {
+            Some(last) if band.distance - last.distance < RESOLUTION && band.form == last.form => {
+                last.fragments.extend(band.fragments);
+                last.amount += band.amount;
+            }
+            _ => merged.push(band),
+        }
+    }
+    merged
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn format_size(size: usize) -> String {
+    if size >= 1000 {
+        format!("{:.1} kb", size as f64 / 1000.0)
+    } else {
+        format!("{} bp", size)
+    }
+}
+
+/// Render the gel as SVG, ladder sizes on the left and lane labels on top.
+fn render_svg(lanes: &[GelLane], inverted: bool) -> String {
+    const LANE_WIDTH: f64 = 60.0;
+    const BAND_WIDTH: f64 = 44.0;
+    const LEFT: f64 = 64.0;
+    const TOP: f64 = 56.0;
+    const RUN_LENGTH: f64 = 420.0;
+
+    let width = LEFT + LANE_WIDTH * lanes.len() as f64 + 16.0;
+    let height = TOP + RUN_LENGTH + 24.0;
+    let (background, band_colour, text_colour) = if inverted {
+        ("#ffffff", "#000000", "#000000")
+    } else {
+        ("#111111", "#ffffff", "#dddddd")
+    };
+    let brightest = lanes
+        .iter()
+        .flat_map(|l| &l.bands)
+        .map(|b| b.amount)
+        .fold(0.0, f64::max)
+        .max(1e-9);
+
+    let mut svg = vec![
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\" font-family=\"sans-serif\" font-size=\"11\">",
+            width, height
+        ),
+        "<defs><filter id=\"band-blur\"><feGaussianBlur stdDeviation=\"0.8\"/></filter></defs>"
+            .to_string(),
+        format!(
+            "<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+            width, height, background
+        ),
+    ];
+
+    for (i, lane) in lanes.iter().enumerate() {
+        let x = LEFT + LANE_WIDTH * i as f64 + (LANE_WIDTH - BAND_WIDTH) / 2.0;
+        let center = x + BAND_WIDTH / 2.0;
+        svg.push(format!(
+            "<text x=\"{}\" y=\"{}\" fill=\"{}\" text-anchor=\"middle\">{}</text>",
+            center,
+            TOP - 24.0,
+            text_colour,
+            escape_xml(&lane.label)
+        ));
+        // Well
+        svg.push(format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"6\" fill=\"none\" stroke=\"{}\" stroke-opacity=\"0.5\"/>",
+            x,
+            TOP - 10.0,
+            BAND_WIDTH,
+            text_colour
+        ));
+        for band in &lane.bands {
+            let y = TOP + band.distance * RUN_LENGTH;
+            let opacity = (band.amount / brightest).sqrt().clamp(0.15, 1.0);
+            svg.push(format!(
+                "<rect x=\"{}\" y=\"{:.1}\" width=\"{}\" height=\"4\" rx=\"1.5\" fill=\"{}\" fill-opacity=\"{:.2}\" filter=\"url(#band-blur)\"><title>{}</title></rect>",
+                x,
+                y - 2.0,
+                BAND_WIDTH,
+                band_colour,
+                opacity,
+                band.fragments
+                    .iter()
+                    .map(|f| format_size(*f))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+            if i == 0 {
+                svg.push(format!(
+                    "<text x=\"{}\" y=\"{:.1}\" fill=\"{}\" text-anchor=\"end\">{}</text>",
+                    LEFT - 6.0,
+                    y + 4.0,
+                    text_colour,
+                    format_size(band.apparent_size)
+                ));
+            }
+        }
+    }
+    svg.push("</svg>".to_string());
+    svg.join("\n")
+}
+
+/// Simulate a gel with the ladder in the first lane.
+pub fn run_gel(request: &GelRequest) -> Result<GelResult, String> {
+    let agarose = request.agarose;
+    let mut warnings = Vec::new();
+    if !(RESOLVING_RANGES[0].0..=RESOLVING_RANGES[5].0).contains(&agarose) {
+        warnings.push(format!(
+            "{}% agarose is outside the modelled range of 0.5% to 2%.",
+            agarose
+        ));
+    }
+
+    let ladder_bands = request
+        .ladder
+        .bands()
+        .into_iter()
+        .map(|(size, amount)| GelBand {
+            fragments: vec![size],
+            apparent_size: size,
+            distance: migration_distance(size as f64, agarose),
+            amount,
+            form: None,
+        })
+        .collect();
+    let mut lanes = vec![GelLane {
+        label: request.ladder.name().to_string(),
+        bands: merge_bands(ladder_bands),
+    }];
+
+    for lane in &request.lanes {
+        let bands = match &lane.sample {
+            LaneSample::Digest {
+                sequence,
+                topology,
+                enzymes,
+                methylation,
+            } => {
+                let fragments =
+                    digest_fragments(sequence, *topology, enzymes, methylation, &mut warnings)?;
+                if fragments.is_empty() {
+                    if !enzymes.is_empty() {
+                        warnings.push(format!("Nothing cuts in lane \"{}\".", lane.label));
+                    }
+                    uncut_plasmid_bands(sanitize_sequence(sequence).len(), lane.load, agarose)
+                } else {
+                    linear_bands(&fragments, lane.load, agarose)
+                }
+            }
+            LaneSample::Pcr(simulation) => {
+                let result = simulate(simulation)?;
+                warnings.extend(
+                    result
+                        .warnings
+                        .into_iter()
+                        .map(|w| format!("{}: {}", lane.label, w)),
+                );
+                let sizes: Vec<usize> = result.amplicons.iter().map(|a| a.sequence.len()).collect();
+                linear_bands(&sizes, lane.load, agarose)
+            }
+            LaneSample::Plasmid { length, form } => match form {
+                PlasmidForm::Supercoiled => uncut_plasmid_bands(*length, lane.load, agarose),
+                PlasmidForm::Linear => linear_bands(&[*length], lane.load, agarose),
+            },
+            LaneSample::Fragments(sizes) => linear_bands(sizes, lane.load, agarose),
+        };
+        lanes.push(GelLane {
+            label: lane.label.clone(),
+            bands: merge_bands(bands),
+        });
+    }
+
+    let svg = render_svg(&lanes, request.inverted);
+    Ok(GelResult {
+        lanes,
+        svg,
+        warnings,
+    })
+}
+
+#[tauri::command]
+/// Simulate an agarose gel of digests, PCR products and plasmids.
+pub fn simulate_gel(request: GelRequest) -> Result<GelResult, String> {
+    run_gel(&request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(
+        sequence: &str,
+        topology: Topology,
+        enzymes: &[&str],
+        methylation: &[Methylation],
+    ) -> (Vec<usize>, Vec<String>) {
+        let enzymes: Vec<String> = enzymes.iter().map(|e| e.to_string()).collect();
+        let mut warnings = Vec::new();
+        let fragments =
+            digest_fragments(sequence, topology, &enzymes, methylation, &mut warnings).unwrap();
+        (fragments, warnings)
+    }
+
+    #[test]
+    fn digest_fragment_sizes() {
+        // EcoRI cuts after base 101, KpnI after base 311
+        let sequence = format!(
+            "{}GAATTC{}GGTACC{}",
+            "T".repeat(100),
+            "T".repeat(200),
+            "T".repeat(94)
+        );
+        let none = &[];
+        assert_eq!(
+            digest(&sequence, Topology::Circular, &["EcoRI", "KpnI"], none).0,
+            [210, 196]
+        );
+        assert_eq!(
+            digest(&sequence, Topology::Linear, &["EcoRI", "KpnI"], none).0,
+            [101, 210, 95]
+        );
+        assert_eq!(
+            digest(&sequence, Topology::Circular, &["EcoRI"], none).0,
+            [406]
+        );
+        assert!(digest(&sequence, Topology::Circular, &["BamHI"], none)
+            .0
+            .is_empty());
+    }
+
+    #[test]
+    fn no_enzymes_leave_the_molecule_uncut() {
+        let sequence = format!("{}GAATTCGGATCC{}", "T".repeat(100), "T".repeat(100));
+        assert!(digest(&sequence, Topology::Circular, &[], &[]).0.is_empty());
+        assert_eq!(digest(&sequence, Topology::Linear, &[], &[]).0, [212]);
+    }
+
+    #[test]
+    fn methylated_sites_are_not_cut() {
+        let sequence = format!("{}TCTAGATC{}", "A".repeat(50), "A".repeat(50));
+        let (fragments, warnings) = digest(
+            &sequence,
+            Topology::Linear,
+            &["XbaI"],
+            &default_methylation(),
+        );
+        assert_eq!(fragments, [108]);
+        assert_eq!(warnings, ["1 site(s) of XbaI blocked by methylation."]);
+        assert_eq!(
+            digest(&sequence, Topology::Linear, &["XbaI"], &[]).0,
+            [51, 57]
+        );
+    }
+}
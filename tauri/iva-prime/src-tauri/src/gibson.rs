@@ -0,0 +1,411 @@
+use serde::{Deserialize, Serialize};
+
+use crate::assembly::{AssemblyPart, PartPrimers};
+use crate::melting::melting_temperature;
+use crate::primers::{extend_sequence, DesignSettings, Primer, PrimerRegion, RegionType, Strand};
+use crate::sequence::{reverse_complementary, Direction, Topology};
+use crate::structure::{longest_common_substring, longest_hairpin};
+
+/// Hairpin stems from this length on are reported for overlaps.
+pub const HAIRPIN_WARNING_STEM: usize = 5;
+/// Stretches shared between two overlaps from this length on can misprime the assembly.
+pub const CROSS_SIMILARITY_WARNING: usize = 10;
+
+/// Overlap settings for Gibson/NEBuilder HiFi assemblies.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct OverlapSettings {
+    pub overlap_min_length: usize,
+    pub overlap_max_length: usize,
+    pub overlap_tm: f64,
+}
+
+impl Default for OverlapSettings {
+    fn default() -> Self {
+        Self {
+            overlap_min_length: 20,
+            overlap_max_length: 40,
+            overlap_tm: 48.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GibsonRequest {
+    /// Parts in assembly order.
+    pub parts: Vec<AssemblyPart>,
+    /// Close the last part back onto the first.
+    #[serde(default = "default_circular")]
+    pub circular: bool,
+    #[serde(default)]
+    pub overlap: OverlapSettings,
+    #[serde(default)]
+    pub settings: DesignSettings,
+}
+
+fn default_circular() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverlapReport {
+    /// Indices of the two parts joined by this overlap.
+    pub between: [usize; 2],
+    pub sequence: String,
+    pub tm: f64,
+    pub hairpin_stem: usize,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrossSimilarity {
+    /// Indices of the two overlaps.
+    pub overlaps: [usize; 2],
+    pub longest_shared: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GibsonResult {
+    pub parts: Vec<PartPrimers>,
+    pub overlaps: Vec<OverlapReport>,
+    pub cross_similarities: Vec<CrossSimilarity>,
+    pub assembled_sequence: String,
+    pub topology: Topology,
+}
+
+/// Overlap split across a junction, `left_length` bases from the end of the
+/// upstream part and `right_length` bases from the start of the downstream part.
+struct Junction {
+    left_length: usize,
+    right_length: usize,
+    sequence: String,
+    tm: f64,
+}
+
+/// Grow the overlap alternately into both parts until the target Tm and minimum
+/// length are reached, or the maximum length is hit.
+fn design_junction(left: &str, right: &str, request: &GibsonRequest) -> Junction {
+    let overlap = &request.overlap;
+    let mut left_length = (overlap.overlap_min_length / 2).min(left.len());
+    let mut right_length = (overlap.overlap_min_length - left_length).min(right.len());
+    let mut grow_left = true;
+
+    loop {
+        let sequence = format!(
+            "{}{}",
+            &left[left.len() - left_length..],
+            &right[..right_length]
+        );
+        let tm = melting_temperature(&sequence, None, &request.settings.tm);
+
+        let long_enough = sequence.len() >= overlap.overlap_min_length;
+        let done = (tm >= overlap.overlap_tm && long_enough)
+            || sequence.len() >= overlap.overlap_max_length;
+        let can_grow_left = left_length < left.len();
+        let can_grow_right = right_length < right.len();
+
+        if done || (!can_grow_left && !can_grow_right) {
+            return Junction {
+                left_length,
+                right_length,
+                sequence,
+                tm,
+            };
+        }
+
+        if (grow_left && can_grow_left) || !can_grow_right {
+            left_length += 1;
+        } else {
+            right_length += 1;
+        }
+        grow_left = !grow_left;
+    }
+}
+
+fn region(
+    sequence: String,
+    region_type: RegionType,
+    start: usize,
+    direction: Direction,
+) -> PrimerRegion {
+    PrimerRegion {
+        sequence,
+        region_type,
+        start: Some(start as i64),
+        max_length: None,
+        direction,
+    }
+}
+
+/// Design Gibson/NEBuilder HiFi primers for an ordered list of parts.
+pub fn design_gibson(request: &GibsonRequest) -> Result<GibsonResult, String> {
+    if request.parts.is_empty() {
+        return Err("Assembly has no parts.".to_string());
+    }
+
+    let fragments: Vec<String> = request
+        .parts
+        .iter()
+        .map(|p| p.oriented_sequence())
+        .collect::<Result<_, _>>()?;
+    let n = fragments.len();
+
+    // Junction i joins part i to part i + 1 (and the last part to the first if circular)
+    let junction_count = if request.circular { n } else { n - 1 };
+    let junctions: Vec<Junction> = (0..junction_count)
+        .map(|i| design_junction(&fragments[i], &fragments[(i + 1) % n], request))
+        .collect();
+
+    let assembled_sequence: String = fragments.concat();
+    let assembled_len = assembled_sequence.len();
+    let mut offsets = Vec::with_capacity(n);
+    let mut offset = 0;
+    for fragment in &fragments {
+        offsets.push(offset);
+        offset += fragment.len();
+    }
+    // 1-based position in the assembled construct, wrapping across the origin
+    let position = |p: i64| (p - 1).rem_euclid(assembled_len as i64) as usize + 1;
+
+    let mut parts = Vec::with_capacity(n);
+    for (i, fragment) in fragments.iter().enumerate() {
+        let upstream = if i > 0 || request.circular {
+            junctions.get((i + n - 1) % n)
+        } else {
+            None
+        };
+        let downstream = junctions.get(i);
+
+        let anneal = |strand: Strand, start: i64| {
+            extend_sequence(
+                fragment,
+                start,
+                strand,
+                Direction::Fwd,
+                request.settings.tbr_tm,
+                request.settings.tm.algorithm,
+                7,
+                "",
+                Topology::Linear,
+                &request.settings,
+            )
+            .map_err(|e| format!("Part \"{}\": {}", request.parts[i].name, e))
+        };
+        let anneal_fwd = anneal(Strand::Top, 0)?;
+        let anneal_rev = anneal(Strand::Bottom, fragment.len() as i64 + 1)?;
+
+        let part_start = offsets[i] as i64 + 1;
+        let part_end = (offsets[i] + fragment.len()) as i64;
+
+        // Forward primer carries the end of the upstream part as its 5' tail
+        let fwd_tail = upstream
+            .map(|j| {
+                let upstream_fragment = &fragments[(i + n - 1) % n];
+                upstream_fragment[upstream_fragment.len() - j.left_length..].to_string()
+            })
+            .unwrap_or_default();
+        // Reverse primer carries the start of the downstream part as its 5' tail
+        let rev_tail = downstream
+            .map(|j| reverse_complementary(&fragments[(i + 1) % n][..j.right_length]))
+            .unwrap_or_default();
+
+        let amplicon = format!(
+            "{}{}{}",
+            fwd_tail,
+            fragment,
+            reverse_complementary(&rev_tail)
+        );
+
+        let name = &request.parts[i].name;
+        let primers = vec![
+            Primer {
+                name: format!("{} forward primer", name),
+                label: format!("{} forward primer", name),
+                regions: vec![
+                    region(
+                        fwd_tail.clone(),
+                        RegionType::Hr,
+                        position(part_start - fwd_tail.len() as i64),
+                        Direction::Fwd,
+                    ),
+                    region(
+                        anneal_fwd,
+                        RegionType::Tbr,
+                        position(part_start),
+                        Direction::Fwd,
+                    ),
+                ],
+            },
+            Primer {
+                name: format!("{} reverse primer", name),
+                label: format!("{} reverse primer", name),
+                regions: vec![
+                    region(
+                        rev_tail.clone(),
+                        RegionType::Hr,
+                        position(part_end + rev_tail.len() as i64),
+                        Direction::Rev,
+                    ),
+                    region(
+                        anneal_rev,
+                        RegionType::Tbr,
+                        position(part_end),
+                        Direction::Rev,
+                    ),
+                ],
+            },
+        ];
+
+        parts.push(PartPrimers {
+            name: name.clone(),
+            primers,
+            amplicon,
+        });
+    }
+
+    let overlaps: Vec<OverlapReport> = junctions
+        .iter()
+        .enumerate()
+        .map(|(i, j)| {
+            let mut warnings = Vec::new();
+            if j.tm < request.overlap.overlap_tm {
+                warnings.push(format!(
+                    "Overlap only reaches {:.1} C (target {:.1} C).",
+                    j.tm, request.overlap.overlap_tm
+                ));
+            }
+            if j.sequence.len() < request.overlap.overlap_min_length {
+                warnings.push(format!(
+                    "Overlap is only {} nt long, parts are too short.",
+                    j.sequence.len()
+                ));
+            }
+            let hairpin_stem = longest_hairpin(&j.sequence).map_or(0, |h| h.stem_length);
+            if hairpin_stem >= HAIRPIN_WARNING_STEM {
+                warnings.push(format!(
+                    "Overlap can fold into a {} bp hairpin.",
+                    hairpin_stem
+                ));
+            }
+            OverlapReport {
+                between: [i, (i + 1) % n],
+                sequence: j.sequence.clone(),
+                tm: j.tm,
+                hairpin_stem,
+                warnings,
+            }
+        })
+        .collect();
+
+    let mut cross_similarities = Vec::new();
+    for a in 0..overlaps.len() {
+        for b in a + 1..overlaps.len() {
+            let longest_shared =
+                longest_common_substring(&overlaps[a].sequence, &overlaps[b].sequence).max(
+                    longest_common_substring(
+                        &overlaps[a].sequence,
+                        &reverse_complementary(&overlaps[b].sequence),
+                    ),
+                );
+            if longest_shared >= CROSS_SIMILARITY_WARNING {
+                cross_similarities.push(CrossSimilarity {
+                    overlaps: [a, b],
+                    longest_shared,
+                });
+            }
+        }
+    }
+
+    Ok(GibsonResult {
+        parts,
+        overlaps,
+        cross_similarities,
+        assembled_sequence,
+        topology: if request.circular {
+            Topology::Circular
+        } else {
+            Topology::Linear
+        },
+    })
+}
+
+#[tauri::command]
+/// Design Gibson/NEBuilder HiFi primers for parts from open plasmids and linear fragments.
+pub fn design_gibson_assembly(request: GibsonRequest) -> Result<GibsonResult, String> {
+    design_gibson(&request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequence::tests::RANDOM_PLASMID;
+
+    fn request(circular: bool) -> GibsonRequest {
+        let plasmid = RANDOM_PLASMID.concat();
+        let part = |name: &str, span: [usize; 2]| AssemblyPart {
+            name: name.to_string(),
+            sequence: plasmid.clone(),
+            topology: Topology::Circular,
+            span: Some(span),
+            direction: Direction::Fwd,
+        };
+        GibsonRequest {
+            parts: vec![part("A", [1, 200]), part("B", [201, 400])],
+            circular,
+            overlap: OverlapSettings::default(),
+            settings: DesignSettings::default(),
+        }
+    }
+
+    #[test]
+    fn two_fragments_assemble_through_their_overlaps() {
+        let plasmid = RANDOM_PLASMID.concat();
+        let result = design_gibson(&request(true)).unwrap();
+        assert_eq!(result.assembled_sequence, plasmid);
+        assert_eq!(result.overlaps.len(), 2);
+        for overlap in &result.overlaps {
+            let length = overlap.sequence.len();
+            assert!((20..=40).contains(&length));
+            assert!(overlap.tm >= 48.0 || length == 40);
+            assert!(format!("{}{}", plasmid, plasmid).contains(&overlap.sequence));
+        }
+
+        for part in &result.parts {
+            let [forward, reverse] = [&part.primers[0], &part.primers[1]].map(Primer::sequence);
+            assert!(part.amplicon.starts_with(&forward));
+            assert!(reverse_complementary(&part.amplicon).starts_with(&reverse));
+        }
+
+        // The amplicons share the overlaps at their ends, joining them closes
+        // the plasmid
+        let [a, b] = [&result.parts[0].amplicon, &result.parts[1].amplicon];
+        let [joint, closure] = [&result.overlaps[0].sequence, &result.overlaps[1].sequence];
+        assert!(a.ends_with(joint.as_str()) && b.starts_with(joint.as_str()));
+        assert!(b.ends_with(closure.as_str()) && a.starts_with(closure.as_str()));
+        let joined = format!("{}{}", a, &b[joint.len()..]);
+        let product = &joined[..joined.len() - closure.len()];
+        assert_eq!(product.len(), plasmid.len());
+        assert!(format!("{}{}", plasmid, plasmid).contains(product));
+    }
+
+    #[test]
+    fn linear_assembly_has_no_outer_tails() {
+        let result = design_gibson(&request(false)).unwrap();
+        assert_eq!(result.topology, Topology::Linear);
+        assert_eq!(result.overlaps.len(), 1);
+        assert!(result.parts[0].primers[0].regions[0].sequence.is_empty());
+        assert!(result.parts[1].primers[1].regions[0].sequence.is_empty());
+        assert_eq!(
+            format!(
+                "{}{}",
+                result.parts[0].amplicon,
+                &result.parts[1].amplicon[result.overlaps[0].sequence.len()..]
+            ),
+            RANDOM_PLASMID.concat()
+        );
+    }
+}
@@ -0,0 +1,362 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::optimization::{optimize_protein, OptimizationRequest, OptimizationSettings};
+use crate::sequence::sanitize_sequence;
+use crate::store::{new_id, read_file, timestamp, Store, Versioned};
+
+/// The user presets, in the app data directory.
+static PRESETS: Store<InsertionPresets> = Store::new("insertion_presets.json", true);
+
+/// Entry of the bundled insertion list, groups nest into categories.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum InsertionEntry {
+    Group {
+        name: String,
+        entries: Vec<InsertionEntry>,
+    },
+    Item {
+        name: String,
+        label: String,
+        #[serde(default)]
+        dna: Option<String>,
+        #[serde(default)]
+        aa: Option<String>,
+    },
+}
+
+/// Tags and linkers bundled with the frontend, as presets.
+pub static COMMON_INSERTIONS: Lazy<Vec<InsertionPreset>> = Lazy::new(|| {
+    let entries: Vec<InsertionEntry> = serde_json::from_str(include_str!(
+        "../../../../docker/app/ivaprime/static/data/commonInsertions.json"
+    ))
+    .expect("Bundled commonInsertions.json is malformed");
+    flatten_entries(&entries, &[])
+});
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InsertionPreset {
+    #[serde(default)]
+    pub id: String,
+    /// Name shown in the insertion dialog and used on the command line.
+    pub name: String,
+    /// Label of the feature added for the insertion.
+    pub label: String,
+    /// Categories from the outermost to the innermost, e.g.
+    /// `["Affinity tags", "FLAG tags"]`.
+    #[serde(default)]
+    pub category: Vec<String>,
+    #[serde(default)]
+    pub dna: Option<String>,
+    #[serde(default)]
+    pub aa: Option<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+    /// Raised on every change, the higher version wins on import.
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub modified: String,
+}
+
+/// User insertion presets, stored as a single file that can also be shared.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InsertionPresets {
+    pub format: u32,
+    pub presets: Vec<InsertionPreset>,
+}
+
+impl Default for InsertionPresets {
+    fn default() -> Self {
+        Self {
+            format: Self::FORMAT,
+            presets: Vec::new(),
+        }
+    }
+}
+
+impl Versioned for InsertionPresets {
+    const FORMAT: u32 = 1;
+    const DESCRIPTION: &'static str = "insertion presets";
+
+    fn format(&self) -> u32 {
+        self.format
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresetImportSummary {
+    pub added: usize,
+    pub updated: usize,
+    /// Presets already present or older than the existing copy.
+    pub skipped: usize,
+}
+
+fn flatten_entries(entries: &[InsertionEntry], category: &[String]) -> Vec<InsertionPreset> {
+    let mut presets = Vec::new();
+    for entry in entries {
+        match entry {
+            InsertionEntry::Group { name, entries } => {
+                let mut inner = category.to_vec();
+                inner.push(name.clone());
+                presets.extend(flatten_entries(entries, &inner));
+            }
+            InsertionEntry::Item {
+                name,
+                label,
+                dna,
+                aa,
+            } => presets.push(InsertionPreset {
+                id: String::new(),
+                name: name.clone(),
+                label: label.clone(),
+                category: category.to_vec(),
+                dna: dna.clone(),
+                aa: aa.clone(),
+                note: None,
+                version: 1,
+                modified: String::new(),
+            }),
+        }
+    }
+    presets
+}
+
+/// Check and normalize a preset. Names must be unique as the command line
+/// refers to presets by name.
+fn clean_preset(
+    mut preset: InsertionPreset,
+    presets: &InsertionPresets,
+) -> Result<InsertionPreset, String> {
+    preset.name = preset.name.trim().to_string();
+    if preset.name.is_empty() {
+        return Err("Insertion presets need a name.".to_string());
+    }
+    if preset.label.trim().is_empty() {
+        preset.label = preset.name.clone();
+    }
+    preset.category = preset
+        .category
+        .iter()
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty())
+        .collect();
+    preset.dna = preset
+        .dna
+        .map(|dna| sanitize_sequence(&dna))
+        .filter(|dna| !dna.is_empty());
+    preset.aa = preset
+        .aa
+        .map(|aa| {
+            aa.chars()
+                .filter(|c| c.is_ascii_alphabetic() || *c == '*')
+                .map(|c| c.to_ascii_uppercase())
+                .collect::<String>()
+        })
+        .filter(|aa| !aa.is_empty());
+    if preset.dna.is_none() && preset.aa.is_none() {
+        return Err(format!("\"{}\" has no DNA or AA sequence.", preset.name));
+    }
+    let taken = presets
+        .presets
+        .iter()
+        .any(|p| p.id != preset.id && p.name.eq_ignore_ascii_case(&preset.name));
+    if taken {
+        return Err(format!(
+            "An insertion preset named \"{}\" already exists.",
+            preset.name
+        ));
+    }
+    Ok(preset)
+}
+
+/// Merge presets into the user presets. Presets with a known id keep the
+/// higher version, new ones are added unless their name is taken.
+pub fn merge_presets(
+    presets: &mut InsertionPresets,
+    other: Vec<InsertionPreset>,
+) -> PresetImportSummary {
+    let mut summary = PresetImportSummary::default();
+    for preset in other {
+        let known = (!preset.id.is_empty())
+            .then(|| presets.presets.iter().position(|p| p.id == preset.id))
+            .flatten();
+        match known {
+            Some(i) if presets.presets[i].version < preset.version => {
+                match clean_preset(preset, presets) {
+                    Ok(preset) => {
+                        presets.presets[i] = preset;
+                        summary.updated += 1;
+                    }
+                    Err(_) => summary.skipped += 1,
+                }
+            }
+            Some(_) => summary.skipped += 1,
+            None => match clean_preset(preset, presets) {
+                Ok(mut preset) => {
+                    if preset.id.is_empty() {
+                        preset.id = new_id(|id| presets.presets.iter().any(|p| p.id == id));
+                    }
+                    preset.version = preset.version.max(1);
+                    if preset.modified.is_empty() {
+                        preset.modified = timestamp();
+                    }
+                    presets.presets.push(preset);
+                    summary.added += 1;
+                }
+                Err(_) => summary.skipped += 1,
+            },
+        }
+    }
+    summary
+}
+
+/// Presets from a shared presets file or from a list in the format of the
+/// bundled commonInsertions.json.
+pub fn read_preset_file(path: &Path) -> Result<Vec<InsertionPreset>, String> {
+    let json = fs::read_to_string(path)
+        .map_err(|e| format!("Could not read \"{}\": {}", path.display(), e))?;
+    if let Ok(entries) = serde_json::from_str::<Vec<InsertionEntry>>(&json) {
+        return Ok(flatten_entries(&entries, &[]));
+    }
+    Ok(read_file::<InsertionPresets>(path)?.presets)
+}
+
+/// Look up a preset by name or label, the user presets first and then the
+/// bundled ones.
+pub fn find_preset(name: &str) -> Result<InsertionPreset, String> {
+    let user = PRESETS.load()?;
+    let matches = |p: &&InsertionPreset| {
+        p.name.eq_ignore_ascii_case(name) || p.label.eq_ignore_ascii_case(name)
+    };
+    user.presets
+        .iter()
+        .find(matches)
+        .or_else(|| COMMON_INSERTIONS.iter().find(matches))
+        .cloned()
+        .ok_or_else(|| format!("Unknown insertion preset \"{}\".", name))
+}
+
+/// Bases of a preset, AA presets are codon optimized for the organism.
+pub fn preset_dna(preset: &InsertionPreset, organism: &str) -> Result<String, String> {
+    if let Some(dna) = &preset.dna {
+        return Ok(sanitize_sequence(dna));
+    }
+    let protein = preset
+        .aa
+        .as_ref()
+        .ok_or_else(|| format!("\"{}\" has no DNA or AA sequence.", preset.name))?;
+    let request = OptimizationRequest {
+        protein: protein.clone(),
+        organism: organism.to_string(),
+        settings: OptimizationSettings::default(),
+    };
+    Ok(optimize_protein(&request)?.sequence)
+}
+
+#[tauri::command]
+/// User insertion presets followed by the bundled ones.
+pub fn list_insertion_presets() -> Result<Vec<InsertionPreset>, String> {
+    let mut presets = PRESETS.load()?.presets;
+    presets.extend(COMMON_INSERTIONS.iter().cloned());
+    Ok(presets)
+}
+
+#[tauri::command]
+/// Add a preset to the user presets.
+pub fn create_insertion_preset(preset: InsertionPreset) -> Result<InsertionPreset, String> {
+    PRESETS.modify(|presets| {
+        let mut preset = clean_preset(preset, presets)?;
+        preset.id = new_id(|id| presets.presets.iter().any(|p| p.id == id));
+        preset.version = 1;
+        preset.modified = timestamp();
+        presets.presets.push(preset.clone());
+        Ok(preset)
+    })
+}
+
+#[tauri::command]
+/// Replace a user preset, found by its id.
+pub fn update_insertion_preset(preset: InsertionPreset) -> Result<InsertionPreset, String> {
+    PRESETS.modify(|presets| {
+        let i = presets
+            .presets
+            .iter()
+            .position(|p| p.id == preset.id)
+            .ok_or_else(|| format!("No insertion preset with id \"{}\".", preset.id))?;
+        let mut preset = clean_preset(preset, presets)?;
+        preset.version = presets.presets[i].version + 1;
+        preset.modified = timestamp();
+        presets.presets[i] = preset.clone();
+        Ok(preset)
+    })
+}
+
+#[tauri::command]
+/// Remove user presets.
+pub fn delete_insertion_presets(ids: Vec<String>) -> Result<usize, String> {
+    PRESETS.modify(|presets| {
+        let before = presets.presets.len();
+        presets.presets.retain(|p| !ids.contains(&p.id));
+        Ok(before - presets.presets.len())
+    })
+}
+
+#[tauri::command]
+/// Add the presets of a shared presets file or an insertion list.
+pub fn import_insertion_presets(path: PathBuf) -> Result<PresetImportSummary, String> {
+    let other = read_preset_file(&path)?;
+    PRESETS.modify(|presets| Ok(merge_presets(presets, other)))
+}
+
+#[tauri::command]
+/// Write the user presets to a file for sharing.
+pub fn export_insertion_presets(path: PathBuf) -> Result<(), String> {
+    PRESETS.export(&path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preset_files_from_newer_versions_are_refused() {
+        let path = std::env::temp_dir().join(format!("iva-presets-{}.json", std::process::id()));
+        let presets = |format: u32| {
+            format!(
+                r#"{{"format": {}, "presets": [{{"name": "FLAG", "label": "FLAG tag", "aa": "DYKDDDDK"}}]}}"#,
+                format
+            )
+        };
+
+        fs::write(&path, presets(InsertionPresets::FORMAT)).unwrap();
+        let read = read_preset_file(&path).unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].aa.as_deref(), Some("DYKDDDDK"));
+
+        fs::write(&path, presets(InsertionPresets::FORMAT + 1)).unwrap();
+        assert_eq!(
+            read_preset_file(&path).unwrap_err(),
+            format!(
+                "\"{}\" was written by a newer version of IVA Prime.",
+                path.display()
+            )
+        );
+
+        fs::write(
+            &path,
+            r#"[{"type": "group", "name": "Tags", "entries": [{"type": "item", "name": "His6", "label": "6xHis", "aa": "HHHHHH"}]}]"#,
+        )
+        .unwrap();
+        let read = read_preset_file(&path).unwrap();
+        assert_eq!(read[0].category, ["Tags"]);
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,309 @@
+use serde::{Deserialize, Serialize};
+
+use crate::assembly::{AssemblyPart, PartPrimers};
+use crate::primers::{generate_set, DesignSettings, OperationType, Primer, PrimerSet, RegionType};
+use crate::sequence::{reverse_complementary, sanitize_sequence, Direction, Topology};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IvaAssemblyRequest {
+    /// Sequence of the target vector.
+    pub sequence: String,
+    pub operation_start: i64,
+    /// Missing for pure insertions.
+    #[serde(default)]
+    pub operation_end: Option<i64>,
+    /// Parts in the order they are inserted.
+    pub parts: Vec<AssemblyPart>,
+    #[serde(default)]
+    pub settings: DesignSettings,
+}
+
+/// Homologous region recombining two neighbouring pieces.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssemblyJunction {
+    pub between: [String; 2],
+    pub hr_length: usize,
+    pub hr_tm: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IvaAssemblyResult {
+    /// Primers linearising the vector.
+    pub vector: PartPrimers,
+    pub parts: Vec<PartPrimers>,
+    pub junctions: Vec<AssemblyJunction>,
+    pub assembled_sequence: String,
+    /// Span of each part in the assembled sequence (1-based, inclusive).
+    pub part_spans: Vec<[usize; 2]>,
+}
+
+/// Subcloning primer from one of the pseudo plasmid sets. Sets designed on the
+/// flipped pseudo plasmid get their positions and directions mapped back.
+fn subcloning_primer(primer: &Primer, name: &str, flipped_length: Option<usize>) -> Primer {
+    let regions = primer
+        .regions
+        .iter()
+        .cloned()
+        .map(|mut region| {
+            region.region_type = match region.region_type {
+                RegionType::Hr => RegionType::SubHr,
+                RegionType::Tbr => RegionType::SubTbr,
+                other => other,
+            };
+            if let Some(length) = flipped_length {
+                region.start = region.start.map(|start| length as i64 - start + 1);
+                region.direction = match region.direction {
+                    Direction::Fwd => Direction::Rev,
+                    Direction::Rev => Direction::Fwd,
+                };
+            }
+            region
+        })
+        .collect();
+    Primer {
+        name: name.to_string(),
+        label: name.to_string(),
+        regions,
+    }
+}
+
+/// 5' tail of a primer, everything in front of the template binding region.
+fn tail(primer: &Primer) -> String {
+    primer
+        .regions
+        .iter()
+        .take_while(|r| r.region_type != RegionType::SubTbr)
+        .map(|r| r.sequence.as_str())
+        .collect()
+}
+
+fn amplified(name: &str, template: &str, forward: Primer, reverse: Primer) -> PartPrimers {
+    let amplicon = format!(
+        "{}{}{}",
+        tail(&forward),
+        template,
+        reverse_complementary(&tail(&reverse))
+    );
+    PartPrimers {
+        name: name.to_string(),
+        primers: vec![forward, reverse],
+        amplicon,
+    }
+}
+
+/// Design the primers inserting several parts into one vector site in a single
+/// IVA reaction.
+///
+/// Generalises `Primers.generateSubcloningSet`: we pretend all parts are already
+/// in the vector and design a plain insertion (with the insertion HR Tm) at
+/// every junction. The last junction is designed on the flipped sequence, so its
+/// homology sits on the last part rather than on the vector.
+pub fn design_assembly(request: &IvaAssemblyRequest) -> Result<IvaAssemblyResult, String> {
+    if request.parts.is_empty() {
+        return Err("Assembly has no parts.".to_string());
+    }
+    let vector = sanitize_sequence(&request.sequence);
+    let [start, end] = match request.operation_end {
+        None => [request.operation_start, request.operation_start - 1],
+        Some(end) => [
+            request.operation_start.min(end),
+            request.operation_start.max(end),
+        ],
+    };
+    if start < 1 || start > vector.len() as i64 + 1 || end < 0 || end > vector.len() as i64 {
+        return Err("Operation range lies outside of the plasmid.".to_string());
+    }
+
+    let fragments: Vec<String> = request
+        .parts
+        .iter()
+        .map(|p| p.oriented_sequence())
+        .collect::<Result<_, _>>()?;
+    let before = &vector[..start as usize - 1];
+    let after = &vector[end as usize..];
+    let pseudo_plasmid_sequence = format!("{}{}{}", before, fragments.concat(), after);
+    let length = pseudo_plasmid_sequence.len();
+
+    // 1-based position of the first base of each part, and of the vector after the last one
+    let mut junction_positions = vec![start as usize];
+    for fragment in &fragments {
+        junction_positions.push(junction_positions.last().unwrap() + fragment.len());
+    }
+
+    let design = |position: usize, sequence: &str| -> Result<PrimerSet, String> {
+        generate_set(
+            OperationType::Insertion,
+            position as i64,
+            None,
+            sequence,
+            "",
+            Topology::Circular,
+            &request.settings,
+        )
+        .map_err(|e| e.to_string())
+    };
+    let mut sets = Vec::with_capacity(fragments.len());
+    for &position in &junction_positions[..fragments.len()] {
+        sets.push(design(position, &pseudo_plasmid_sequence)?);
+    }
+    let last_position = *junction_positions.last().unwrap();
+    let last_set = design(
+        length - last_position + 2,
+        &reverse_complementary(&pseudo_plasmid_sequence),
+    )?;
+
+    let mut parts = Vec::with_capacity(fragments.len());
+    for (i, fragment) in fragments.iter().enumerate() {
+        let name = &request.parts[i].name;
+        let forward = subcloning_primer(
+            &sets[i].primers[0],
+            &format!("{} forward primer", name),
+            None,
+        );
+        let reverse_name = format!("{} reverse primer", name);
+        let reverse = match sets.get(i + 1) {
+            Some(set) => subcloning_primer(&set.primers[1], &reverse_name, None),
+            None => subcloning_primer(&last_set.primers[0], &reverse_name, Some(length)),
+        };
+        parts.push(amplified(name, fragment, forward, reverse));
+    }
+
+    let vector_forward =
+        subcloning_primer(&last_set.primers[1], "Vector forward primer", Some(length));
+    let vector_reverse = subcloning_primer(&sets[0].primers[1], "Vector reverse primer", None);
+    let linearised_vector = format!("{}{}", after, before);
+    let vector = amplified("Vector", &linearised_vector, vector_forward, vector_reverse);
+
+    let names: Vec<&str> = std::iter::once("Vector")
+        .chain(request.parts.iter().map(|p| p.name.as_str()))
+        .chain(std::iter::once("Vector"))
+        .collect();
+    let junctions = sets
+        .iter()
+        .chain(std::iter::once(&last_set))
+        .enumerate()
+        .map(|(i, set)| AssemblyJunction {
+            between: [names[i].to_string(), names[i + 1].to_string()],
+            hr_length: set.hr_length.first(),
+            hr_tm: set.hr_tm.first(),
+        })
+        .collect();
+
+    let part_spans = junction_positions
+        .windows(2)
+        .map(|w| [w[0], w[1] - 1])
+        .collect();
+
+    Ok(IvaAssemblyResult {
+        vector,
+        parts,
+        junctions,
+        assembled_sequence: pseudo_plasmid_sequence,
+        part_spans,
+    })
+}
+
+#[tauri::command]
+/// Design primers inserting an ordered list of parts into one vector site.
+pub fn design_iva_assembly(request: IvaAssemblyRequest) -> Result<IvaAssemblyResult, String> {
+    design_assembly(&request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primers::{generate_subcloning_set, PerEnd};
+    use crate::sequence::tests::RANDOM_PLASMID;
+
+    const PART: &str = "GTATTCCTAACGGAGCATAAATCCCACCCGAACTAAGTTTGTCGAACCTTGGTCCAAGATCGGGACTCGG";
+
+    #[test]
+    fn single_part_junctions_use_the_insertion_hr_tm() {
+        let vector = RANDOM_PLASMID.concat();
+        let settings = DesignSettings::default();
+        for (start, end, hr_lengths) in [(101, Some(150), [20, 18]), (201, None, [25, 22])] {
+            let request = IvaAssemblyRequest {
+                sequence: vector.clone(),
+                operation_start: start,
+                operation_end: end,
+                parts: vec![AssemblyPart {
+                    name: "Insert".to_string(),
+                    sequence: PART.to_string(),
+                    topology: Topology::Linear,
+                    span: None,
+                    direction: Direction::Fwd,
+                }],
+                settings: settings.clone(),
+            };
+            let assembly = design_assembly(&request).unwrap();
+            let after = end.unwrap_or(start - 1) as usize;
+            assert_eq!(
+                assembly.assembled_sequence,
+                format!(
+                    "{}{}{}",
+                    &vector[..start as usize - 1],
+                    PART,
+                    &vector[after..]
+                )
+            );
+            assert!(assembly.parts[0].amplicon.contains(PART));
+            let junctions: Vec<usize> = assembly.junctions.iter().map(|j| j.hr_length).collect();
+            assert_eq!(junctions, hr_lengths);
+            assert!(assembly
+                .junctions
+                .iter()
+                .all(|j| j.hr_tm < settings.hr_subcloning_tm));
+        }
+    }
+
+    #[test]
+    fn single_part_matches_subcloning() {
+        let vector = RANDOM_PLASMID.concat();
+        for (start, end) in [(101, Some(150)), (201, None)] {
+            let request = IvaAssemblyRequest {
+                sequence: vector.clone(),
+                operation_start: start,
+                operation_end: end,
+                parts: vec![AssemblyPart {
+                    name: "Insert".to_string(),
+                    sequence: PART.to_string(),
+                    topology: Topology::Linear,
+                    span: None,
+                    direction: Direction::Fwd,
+                }],
+                settings: DesignSettings::default(),
+            };
+            let assembly = design_assembly(&request).unwrap();
+            let subcloning = generate_subcloning_set(
+                start,
+                end,
+                &vector,
+                PART,
+                "",
+                "",
+                Topology::Circular,
+                &DesignSettings::default(),
+            )
+            .unwrap();
+
+            let assembly_primers: Vec<String> = assembly.parts[0]
+                .primers
+                .iter()
+                .chain(&assembly.vector.primers)
+                .map(Primer::sequence)
+                .collect();
+            let subcloning_primers: Vec<String> =
+                subcloning.primers.iter().map(Primer::sequence).collect();
+            assert_eq!(assembly_primers, subcloning_primers);
+
+            let hr_lengths: Vec<usize> = assembly.junctions.iter().map(|j| j.hr_length).collect();
+            let PerEnd::Pair(subcloning_hr_lengths) = subcloning.hr_length else {
+                panic!("subcloning set without per-end HR lengths");
+            };
+            assert_eq!(hr_lengths, subcloning_hr_lengths);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tauri::Emitter;
+
+static NEXT_JOB_ID: AtomicU64 = AtomicU64::new(1);
+static CANCELLED_JOBS: Lazy<Mutex<HashSet<u64>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct JobProgress {
+    job_id: u64,
+    kind: &'static str,
+    done: usize,
+    total: usize,
+    message: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct JobFinished<T: Serialize + Clone> {
+    job_id: u64,
+    kind: &'static str,
+    result: Option<T>,
+    error: Option<String>,
+}
+
+/// Handle passed to a running job to report progress to the frontend
+/// and check whether the job was cancelled.
+pub struct JobReporter {
+    app: tauri::AppHandle,
+    job_id: u64,
+    kind: &'static str,
+}
+
+impl JobReporter {
+    /// Emit a `job-progress` event.
+    pub fn progress(&self, done: usize, total: usize, message: impl Into<String>) {
+        let payload = JobProgress {
+            job_id: self.job_id,
+            kind: self.kind,
+            done,
+            total,
+            message: message.into(),
+        };
+        if let Err(e) = self.app.emit("job-progress", payload) {
+            log::error!("Failed to emit job progress: {}", e);
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        CANCELLED_JOBS.lock().unwrap().contains(&self.job_id)
+    }
+}
+
+/// Run `work` on a blocking worker thread so the UI stays responsive.
+///
+/// Progress is reported through `job-progress` events and the outcome through a
+/// single `job-finished` event carrying either `result` or `error`.
+/// Returns the job id, which is included in every event.
+pub fn spawn_job<T, F>(app: tauri::AppHandle, kind: &'static str, work: F) -> u64
+where
+    T: Serialize + Clone + Send + 'static,
+    F: FnOnce(&JobReporter) -> Result<T, String> + Send + 'static,
+{
+    let job_id = NEXT_JOB_ID.fetch_add(1, Ordering::Relaxed);
+    log::info!("Starting job -> kind={} job_id={}", kind, job_id);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let reporter = JobReporter {
+            app: app.clone(),
+            job_id,
+            kind,
+        };
+
+        let outcome = work(&reporter);
+        if let Err(e) = &outcome {
+            log::error!("Job failed -> kind={} job_id={}: {}", kind, job_id, e);
+        } else {
+            log::info!("Job finished -> kind={} job_id={}", kind, job_id);
+        }
+
+        let (result, error) = match outcome {
+            Ok(result) => (Some(result), None),
+            Err(e) => (None, Some(e)),
+        };
+        let payload = JobFinished {
+            job_id,
+            kind,
+            result,
+            error,
+        };
+        if let Err(e) = app.emit("job-finished", payload) {
+            log::error!("Failed to emit job result: {}", e);
+        }
+
+        CANCELLED_JOBS.lock().unwrap().remove(&job_id);
+    });
+
+    job_id
+}
+
+#[tauri::command]
+/// Request cancellation of a running job. Jobs check for this between steps.
+pub fn cancel_job(job_id: u64) {
+    log::info!("Cancelling job -> job_id={}", job_id);
+    CANCELLED_JOBS.lock().unwrap().insert(job_id);
+}
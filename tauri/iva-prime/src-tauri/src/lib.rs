@@ -0,0 +1,649 @@
+mod alignment;
+mod annotation_transfer;
+mod assembly;
+mod binding_sites;
+mod cli;
+mod codon_usage;
+mod codons;
+mod collection;
+mod common_features;
+mod duplicates;
+mod export;
+mod feature_library;
+mod fragments;
+mod gel;
+mod gibson;
+mod insertion_presets;
+mod iva_assembly;
+mod jobs;
+mod markers;
+mod melting;
+mod nanopore;
+mod optimization;
+mod orfs;
+mod plasmid_diff;
+mod plasmid_file;
+mod primers;
+mod reads;
+mod restriction;
+mod scanning;
+mod search;
+mod sequence;
+mod sequencing;
+mod simulation;
+mod store;
+mod structure;
+mod subcloning;
+mod translation;
+mod verification;
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use base64::{engine::general_purpose, Engine};
+use chrono::Local;
+use fern::colors::{Color, ColoredLevelConfig};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::json;
+use url::Url;
+
+use tauri::menu::{CheckMenuItemBuilder, MenuBuilder, SubmenuBuilder};
+use tauri::{Listener, Manager, WebviewWindow, WebviewWindowBuilder};
+use tauri_plugin_deep_link::DeepLinkExt;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons};
+use tauri_plugin_store::StoreExt;
+use tauri_plugin_updater::UpdaterExt;
+
+pub use cli::run_cli;
+
+/// App data directory holding settings, logs and the user libraries.
+#[cfg(target_os = "linux")]
+pub fn get_appdata_dir() -> PathBuf {
+    // Get the XDG_CONFIG_HOME or fall back to ~/.config if not set
+    let config_dir = std::env::var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| {
+            dirs::home_dir()
+                .unwrap_or_else(|| PathBuf::from("~"))
+                .join(".config")
+        });
+
+    config_dir.join("IVA Prime")
+}
+
+/// App data directory holding settings, logs and the user libraries.
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+pub fn get_appdata_dir() -> PathBuf {
+    // Use OS app data directories if possible
+    let base = dirs::data_local_dir()
+        .or_else(|| dirs::home_dir()) // fallback: home dir
+        .unwrap_or_else(|| PathBuf::from("."));
+    base.join("IVA Prime")
+}
+
+pub fn setup_logging(logs_dir: &std::path::Path) -> Result<(), fern::InitError> {
+    let log_file_path = logs_dir.join("output.log");
+
+    let colors = ColoredLevelConfig::new()
+        .debug(Color::Magenta)
+        .info(Color::Green)
+        .warn(Color::Yellow)
+        .error(Color::Red)
+        .trace(Color::Blue);
+
+    fern::Dispatch::new()
+        // Global filter
+        .level(log::LevelFilter::Debug)
+        // Module filter
+        .level_for("tauri_plugin_updater::updater", log::LevelFilter::Info)
+        // Terminal output (colored)
+        .chain(
+            fern::Dispatch::new()
+                .format(move |out, message, record| {
+                    out.finish(format_args!(
+                        "[{}][{}][{}] {}",
+                        Local::now().format("%Y-%m-%d %H:%M:%S"),
+                        colors.color(record.level()),
+                        record.target(),
+                        message
+                    ))
+                })
+                .chain(std::io::stdout()),
+        )
+        // File output (not colored)
+        .chain(
+            fern::Dispatch::new()
+                .format(|out, message, record| {
+                    out.finish(format_args!(
+                        "[{}][{}][{}] {}",
+                        Local::now().format("%Y-%m-%d %H:%M:%S"),
+                        record.level(),
+                        record.target(),
+                        message
+                    ))
+                })
+                .chain(fern::log_file(log_file_path)?),
+        )
+        .apply()?;
+    Ok(())
+}
+
+fn check_for_update(app_handle: tauri::AppHandle, release_channel: String) {
+    tauri::async_runtime::spawn(async move {
+        match get_update(app_handle.clone(), &release_channel).await {
+            Ok(_) => log::info!("Update check completed."),
+            Err(e) => {
+                log::error!("Update check failed: {}", e);
+                app_handle
+                    .dialog()
+                    .message(format!("Update check failed:\n{}", e))
+                    .title("Update Error")
+                    .blocking_show();
+            }
+        }
+    });
+}
+
+#[cfg(target_os = "linux")]
+fn detect_linux_install_type() -> String {
+    if std::env::var("APPIMAGE").is_ok() {
+        return "appimage".to_string();
+    }
+
+    let install_type: String =
+        std::env::var("IVA_PRIME_INSTALL_TYPE").unwrap_or_else(|_| "unknown".to_string());
+    log::debug!("Linux install type -> {:?}", install_type.clone());
+
+    install_type
+}
+
+async fn get_update(
+    app_handle: tauri::AppHandle,
+    release_channel: &str,
+) -> tauri_plugin_updater::Result<()> {
+    let update_url = if release_channel == "nightly" {
+        "https://github.com/RaduLeonte/IVA-Prime/releases/download/nightly/latest.json"
+    } else {
+        "https://github.com/RaduLeonte/IVA-Prime/releases/latest/download/latest.json"
+    };
+
+    let updater = {
+        #[cfg(target_os = "linux")]
+        {
+            let target = match detect_linux_install_type().as_str() {
+                "appimage" => "linux-x86_64",
+                "deb" => "linux-x86_64-deb",
+                "rpm" => "linux-x86_64-rpm",
+                _ => "linux-x86_64", // default fallback
+            };
+
+            app_handle
+                .updater_builder()
+                .endpoints(vec![Url::parse(update_url)?])?
+                .target(target)
+                .build()?
+        }
+
+        #[cfg(any(target_os = "windows", target_os = "macos"))]
+        {
+            app_handle
+                .updater_builder()
+                .endpoints(vec![Url::parse(update_url)?])?
+                .build()?
+        }
+    };
+
+    let update = updater.check().await?;
+
+    if let Some(ref update) = update {
+        log::info!(
+            "Update found!\n  Body: {:?}\n  Current version: {}\n  New version: {}\n  Download URL: {}",
+            update.body,
+            update.current_version,
+            update.version,
+            update.download_url
+        );
+
+        let current_version = update.current_version.to_string();
+        let new_version = update.version.to_string();
+
+        let dialog_message = format!(
+            "A new update is available!\n\nCurrent version: {}\nNew version: {}\n\nDo you want to download and install this update?",
+            current_version, new_version
+        );
+
+        let answer = app_handle
+            .dialog()
+            .message(dialog_message)
+            .title("Update Available")
+            .buttons(MessageDialogButtons::OkCancelCustom(
+                "Update Now".to_string(),
+                "Later".to_string(),
+            ))
+            .blocking_show();
+
+        if !answer {
+            log::info!("User declined update.");
+            return Ok(());
+        };
+
+        // alternatively we could also call update.download() and update.install() separately
+        log::info!("Starting download...");
+        update
+            .download_and_install(
+                |_chunk_length, _content_length| {},
+                || {
+                    log::info!("Download finished.");
+                },
+            )
+            .await?;
+
+        log::info!("Update installed.");
+        app_handle.restart();
+    } else {
+        log::info!("No update required.");
+        app_handle
+            .dialog()
+            .message("You are up to date!")
+            .title("No Update Available")
+            .blocking_show();
+    };
+
+    Ok(())
+}
+
+pub fn print_to_js_console(window: WebviewWindow, s: String) {
+    let js_call = format!("console.log('{}');", s);
+    if let Err(err) = window.eval(&js_call) {
+        eprintln!("Failed to execute JavaScript: {}", err);
+    }
+}
+
+static MAIN_WINDOW_READY_FLAG: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
+static PENDING_FILES: Lazy<Mutex<Vec<PathBuf>>> = Lazy::new(|| Mutex::new(vec![]));
+
+fn parse_files_from_args(args: Vec<String>) -> Vec<PathBuf> {
+    args.into_iter()
+        .skip(1) // Skip binary path
+        .filter_map(|maybe_file| {
+            if maybe_file.starts_with('-') {
+                return None; // Skip CLI flags
+            }
+
+            // Handle URL-style file paths (e.g. file://C:/...)
+            if let Ok(url) = Url::parse(&maybe_file) {
+                if let Ok(path) = url.to_file_path() {
+                    return Some(path);
+                }
+            }
+
+            // Fallback to raw path
+            Some(PathBuf::from(maybe_file))
+        })
+        .collect()
+}
+
+#[derive(Serialize)]
+struct JsFile {
+    name: String,
+    content_base64: String,
+}
+
+/// Converts a list of file paths into a vector of `JsFile` objects,
+/// where each file is read and base64-encoded for JavaScript consumption.
+fn prepare_js_files(paths: Vec<PathBuf>) -> Vec<JsFile> {
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            // Get file name from path
+            let name = path.file_name()?.to_string_lossy().to_string();
+
+            // Read file
+            let bytes = fs::read(&path).ok()?;
+
+            // Encode to base64
+            let encoded = general_purpose::STANDARD.encode(&bytes);
+
+            // Construct a JsFile with the filename and base64 string
+            Some(JsFile {
+                name,
+                content_base64: encoded,
+            })
+        })
+        // Collect all successful conversions into a Vec<JsFile>
+        .collect()
+}
+
+fn send_js_files(window: &tauri::WebviewWindow, files: Vec<PathBuf>) {
+    let js_files = prepare_js_files(files.clone());
+    let js_call = match serde_json::to_string(&js_files) {
+        Ok(json) => format!(
+            r#"
+            FileIO.importBase64Files({});
+            "#,
+            json
+        ),
+        Err(e) => {
+            log::error!("Failed to serialize JS file list: {}", e);
+            return;
+        }
+    };
+
+    if let Err(err) = window.eval(&js_call) {
+        log::error!("Failed to execute JavaScript: {}", err);
+    } else {
+        log::debug!(
+            "Successfully sent file paths to frontend -> files={:?}",
+            files
+        );
+    }
+}
+
+fn queue_js_files(files: Vec<PathBuf>) {
+    let mut pending = PENDING_FILES.lock().unwrap();
+    pending.extend(files);
+    log::debug!(
+        "Main window is not ready, queueing files -> pending={:?}",
+        pending
+    );
+}
+
+/// Sends file data (base64-encoded) to the front-end's `FileIO.importBase64Files`
+/// JavaScript function after the main window is ready.
+pub fn handle_file_associations(app: tauri::AppHandle, files: Vec<PathBuf>) {
+    // Try to get the main application window
+    if let Some(main_window) = app.get_webview_window("main") {
+        let main_window_ready_flag = MAIN_WINDOW_READY_FLAG.lock().unwrap();
+
+        if *main_window_ready_flag {
+            // If the result is Ok, it means the window is ready
+            send_js_files(&main_window, files);
+        } else {
+            // If the window is not ready, queue the files
+            queue_js_files(files);
+        }
+    } else {
+        // Queue files for later
+        queue_js_files(files);
+    }
+}
+
+#[tauri::command]
+#[allow(unused_variables)]
+/// Opens the "About" window in the Tauri application.
+async fn open_about_window(app: tauri::AppHandle) {
+    // Create the window
+    let about_window =
+        WebviewWindowBuilder::from_config(&app, &app.config().app.windows.get(1).unwrap().clone())
+            .unwrap()
+            .build()
+            .unwrap();
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    // Determine appdata directory based on the OS
+    let appdata_dir = get_appdata_dir();
+
+    let logs_dir = appdata_dir.join("logs");
+    // Make sure it exists
+    if let Err(e) = fs::create_dir_all(&logs_dir) {
+        log::error!("Could not create logs directory: {e}");
+    };
+
+    setup_logging(&logs_dir).expect("Failed to initialize logging");
+
+    let settings_path = appdata_dir.join("settings.json");
+
+    tauri::Builder::default()
+        .plugin(tauri_plugin_shell::init())
+        // Plugins
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_store::Builder::default().build())
+        .plugin(tauri_plugin_single_instance::init(|app, args, _cwd| {
+            // Ensure only one instance of the app is allowed.
+            // If a second instance is opened (e.g. by double-clicking a file),
+            // its arguments are captured here.
+
+            // Convert incoming args into filesystem paths (excluding flags)
+            let files: Vec<PathBuf> = parse_files_from_args(args);
+
+            if !files.is_empty() {
+                log::debug!("Single instance plugin -> files={:?}", files);
+
+                // Pass the list of paths to the frontend
+                handle_file_associations(app.clone(), files);
+            }
+        }))
+        // Register custom commands for frontend
+        .invoke_handler(tauri::generate_handler![
+            open_about_window,
+            annotation_transfer::transfer_reference_annotations,
+            binding_sites::find_primer_binding_sites,
+            binding_sites::parse_oligo_list,
+            binding_sites::start_collection_binding_job,
+            codon_usage::analyze_cds_codon_usage,
+            collection::get_collection_settings,
+            collection::open_collection_files,
+            collection::search_collection,
+            collection::set_collection_settings,
+            collection::start_collection_index_job,
+            common_features::detect_common_features,
+            duplicates::start_collection_check_job,
+            duplicates::start_duplicate_scan_job,
+            feature_library::add_library_region,
+            feature_library::export_feature_library,
+            feature_library::get_feature_library,
+            feature_library::import_feature_library,
+            feature_library::import_library_from_files,
+            feature_library::remove_library_features,
+            feature_library::update_library_feature,
+            fragments::plan_gene_fragments,
+            gel::simulate_gel,
+            gibson::design_gibson_assembly,
+            insertion_presets::create_insertion_preset,
+            insertion_presets::delete_insertion_presets,
+            insertion_presets::export_insertion_presets,
+            insertion_presets::import_insertion_presets,
+            insertion_presets::list_insertion_presets,
+            insertion_presets::update_insertion_preset,
+            iva_assembly::design_iva_assembly,
+            jobs::cancel_job,
+            markers::find_silent_marker_sites,
+            melting::get_degenerate_melting_temperature,
+            nanopore::start_nanopore_job,
+            optimization::optimize_codons,
+            orfs::find_open_reading_frames,
+            plasmid_diff::compare_plasmids,
+            restriction::list_enzymes,
+            restriction::map_cut_sites,
+            scanning::start_scan_job,
+            search::search_sequence,
+            sequencing::plan_sequencing_primers,
+            simulation::simulate_design,
+            subcloning::subclone_from_file,
+            translation::list_genetic_codes,
+            translation::translate_cds_feature,
+            translation::translate_six_frames,
+            verification::verify_sanger_reads
+        ])
+        // App setup
+        .setup(move |app| {
+            log::info!("Logs path -> \"{}\"", logs_dir.display());
+            log::info!("Settings path -> \"{}\"", settings_path.display());
+            log::info!("App is starting...!");
+
+            // Load setting from store
+            let store = app.store(settings_path)?;
+
+            let check_for_updates_on_startup: bool = store
+                .get("check_for_updates_on_startup")
+                .and_then(|obj| obj.get("value").and_then(|v| v.as_bool()))
+                .unwrap_or(false);
+
+            let release_channel = store
+                .get("release_channel")
+                .and_then(|obj| {
+                    obj.get("value")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_owned())
+                })
+                .unwrap_or_else(|| "full".to_string());
+
+            // Window menu
+            let check_updates_on_startup_item =
+                CheckMenuItemBuilder::new("Check for Updates on Startup")
+                    .id("check_for_updates_on_startup")
+                    .checked(check_for_updates_on_startup)
+                    .build(app)?;
+
+            let check_for_updates_now_item =
+                tauri::menu::MenuItemBuilder::new("Check for Updates Now")
+                    .id("check_for_updates_now")
+                    .build(app)?;
+
+            let full_release_item = CheckMenuItemBuilder::new("Full-release")
+                .id("release_channel_full")
+                .checked(release_channel == "full")
+                .build(app)?;
+
+            let nightly_release_item = CheckMenuItemBuilder::new("Nightly pre-release")
+                .id("release_channel_nightly")
+                .checked(release_channel == "nightly")
+                .build(app)?;
+
+            let release_channel_menu = SubmenuBuilder::new(app, "Release channel")
+                .item(&full_release_item)
+                .item(&nightly_release_item)
+                .build()?;
+
+            let settings_menu = SubmenuBuilder::new(app, "Settings")
+                .item(&check_updates_on_startup_item)
+                .item(&check_for_updates_now_item)
+                .separator()
+                .item(&release_channel_menu)
+                .build()?;
+
+            let menu = MenuBuilder::new(app).items(&[&settings_menu]).build()?;
+
+            app.set_menu(menu)?;
+            app.on_menu_event(move |app_handle: &tauri::AppHandle, event| {
+                match event.id().0.as_str() {
+                    "check_for_updates_on_startup" => {
+                        let checked = check_updates_on_startup_item.is_checked().unwrap_or(false);
+                        store.set("check_for_updates_on_startup", json!({ "value": checked }));
+                    }
+                    "release_channel_full" => {
+                        let _ = full_release_item.set_checked(true);
+                        let _ = nightly_release_item.set_checked(false);
+                        store.set("release_channel", json!({ "value": "full" }));
+                    }
+                    "release_channel_nightly" => {
+                        let _ = full_release_item.set_checked(false);
+                        let _ = nightly_release_item.set_checked(true);
+                        store.set("release_channel", json!({ "value": "nightly" }));
+                    }
+                    "check_for_updates_now" => {
+                        let app_handle = app_handle.clone();
+                        let release_channel = store
+                            .get("release_channel")
+                            .and_then(|obj| {
+                                obj.get("value")
+                                    .and_then(|v| v.as_str())
+                                    .map(|s| s.to_owned())
+                            })
+                            .unwrap_or_else(|| "full".to_string());
+                        check_for_update(app_handle, release_channel);
+                    }
+                    _ => {
+                        log::debug!("Unexpected menu event.");
+                    }
+                }
+            });
+
+            // Check for updates
+            if check_for_updates_on_startup {
+                check_for_update(app.app_handle().clone(), release_channel);
+            };
+
+            // On main window ready
+            let app_handle = app.app_handle().clone();
+            app.listen("window-ready", move |_event| {
+                log::info!("Window is ready!");
+
+                let mut flag = MAIN_WINDOW_READY_FLAG.lock().unwrap();
+                *flag = true;
+
+                if let Some(main_window) = app_handle.get_webview_window("main") {
+                    print_to_js_console(
+                        main_window.clone(),
+                        format!(
+                            "Logs path -> {:?}",
+                            std::env::current_exe().unwrap().parent().unwrap()
+                        ),
+                    );
+
+                    let _ = main_window.set_title(&format!(
+                        "IVA Prime v{}",
+                        app_handle.package_info().version.to_string()
+                    ));
+
+                    let files = {
+                        let mut pending = PENDING_FILES.lock().unwrap();
+                        std::mem::take(&mut *pending)
+                    };
+                    if !files.is_empty() {
+                        log::debug!("Sending queued files to window -> files={:?}", files);
+                        send_js_files(&main_window, files);
+                    }
+                }
+            });
+
+            // Register deep links
+            app.deep_link().register_all()?;
+
+            // Process file args passed at startup
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            {
+                let args: Vec<String> = std::env::args().collect();
+
+                let files: Vec<PathBuf> = parse_files_from_args(args);
+
+                if !files.is_empty() {
+                    log::debug!("Setup -> files={:?}", files);
+                    let app_handle = app.app_handle();
+                    handle_file_associations(app_handle.clone(), files.clone());
+                }
+            }
+
+            Ok(())
+        })
+        // Final app build step
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        // Event loop for runtime events
+        .run(
+            #[allow(unused_variables)]
+            |app, event| {
+                // On macOS, handle files opened via finder after startup
+                #[cfg(any(target_os = "macos"))]
+                if let tauri::RunEvent::Opened { urls } = event {
+                    let files = urls
+                        .into_iter()
+                        .filter_map(|url| url.to_file_path().ok())
+                        .collect::<Vec<_>>();
+
+                    if !files.is_empty() {
+                        log::debug!("MacOS RunEvent::Opened -> files={:?}", files);
+                        handle_file_associations(app.app_handle().clone(), files.clone())
+                    }
+                }
+            },
+        );
+}
@@ -0,0 +1,27 @@
+// Prevents additional console window on Windows in release, DO NOT REMOVE!!
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
+/// Reconnect stdout/stderr to the console the app was started from, so
+/// command line subcommands can print in release builds on Windows.
+#[cfg(windows)]
+fn attach_parent_console() {
+    use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+    // Fails harmlessly when there is no parent console (e.g. started from Explorer)
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    #[cfg(windows)]
+    if args.len() > 1 {
+        attach_parent_console();
+    }
+    if let Some(code) = iva_prime_lib::run_cli(&args) {
+        std::process::exit(code);
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "linux"))]
+    let _ = fix_path_env::fix(); // fix PATH on macos aarch64
+
+    iva_prime_lib::run()
+}
@@ -0,0 +1,460 @@
+use serde::{Deserialize, Serialize};
+
+use crate::codons::{codon_distance, relative_adaptiveness, synonymous_codons, translate_codon};
+use crate::primers::{generate_set, DesignSettings, OperationType, PrimerSet};
+use crate::restriction::{
+    default_methylation, enzyme_cut_sites, find_enzyme, methylated_positions, site_starts, Enzyme,
+    Methylation, ENZYMES,
+};
+use crate::scanning::{codon_span, codon_start, feature_length, ScanFeature};
+use crate::sequence::{
+    reverse_complementary, sanitize_sequence, span_sequence, Direction, Topology,
+};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkerSiteRequest {
+    pub sequence: String,
+    pub topology: Topology,
+    pub feature: ScanFeature,
+    /// Residue of the planned edit (1-based).
+    pub residue: usize,
+    /// Codon replacing the residue's codon, on the coding strand.
+    pub new_codon: String,
+    /// Residues searched on either side of the edit.
+    #[serde(default = "default_window")]
+    pub window: usize,
+    /// Most bases changed in addition to the edit.
+    #[serde(default = "default_max_base_changes")]
+    pub max_base_changes: usize,
+    /// Enzymes to consider, all commercial enzymes if empty.
+    #[serde(default)]
+    pub enzymes: Vec<String>,
+    #[serde(default = "default_methylation")]
+    pub methylation: Vec<Methylation>,
+    #[serde(default = "default_organism")]
+    pub organism: String,
+    /// Design mutation primers for the best hits.
+    #[serde(default)]
+    pub design_primers: bool,
+    #[serde(default = "default_max_hits")]
+    pub max_hits: usize,
+    #[serde(default)]
+    pub settings: DesignSettings,
+}
+
+fn default_window() -> usize {
+    5
+}
+
+fn default_max_base_changes() -> usize {
+    3
+}
+
+fn default_organism() -> String {
+    "Escherichia coli".to_string()
+}
+
+fn default_max_hits() -> usize {
+    10
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarkerEffect {
+    /// Mutant plasmids cut once, the template does not cut.
+    Created,
+    /// The template cuts once, mutant plasmids do not cut.
+    Destroyed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodonChange {
+    pub residue: usize,
+    pub amino_acid: char,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkerSiteHit {
+    pub enzyme: String,
+    pub site: String,
+    pub effect: MarkerEffect,
+    /// Silent changes, empty if the edit itself changes the site.
+    pub codon_changes: Vec<CodonChange>,
+    /// Bases changed in addition to the edit.
+    pub base_changes: usize,
+    /// Lowest codon usage of the new codons relative to the best codon for
+    /// their amino acid, 1 if there are no silent changes.
+    pub codon_usage: f64,
+    /// Recognition site in the plasmid (1-based, inclusive).
+    pub site_span: [usize; 2],
+    /// Plasmid span covering the edit and the silent changes, with the top
+    /// strand bases replacing it, ready for a mutation primer design.
+    pub mutation_span: [usize; 2],
+    pub replacement: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub primer_set: Option<PrimerSet>,
+}
+
+/// Codon at a residue of the coding strand.
+fn codon_at(sequence: &str, feature: &ScanFeature, residue: usize) -> String {
+    let codon = span_sequence(sequence, codon_span(feature, residue, sequence.len()));
+    match feature.directionality {
+        Direction::Fwd => codon,
+        Direction::Rev => reverse_complementary(&codon),
+    }
+}
+
+/// Plasmid with codons replaced, codons given on the coding strand.
+fn apply_codons(sequence: &str, feature: &ScanFeature, codons: &[(usize, &str)]) -> Vec<u8> {
+    let mut plasmid = sequence.as_bytes().to_vec();
+    let length = plasmid.len() as i64;
+    for (residue, codon) in codons {
+        let start = codon_start(feature, *residue) - 1;
+        let top = match feature.directionality {
+            Direction::Fwd => codon.to_string(),
+            Direction::Rev => reverse_complementary(codon),
+        };
+        // Codons of features across the origin may wrap
+        for (i, base) in top.bytes().enumerate() {
+            plasmid[(start + i as i64).rem_euclid(length) as usize] = base;
+        }
+    }
+    plasmid
+}
+
+/// Recognition sites of an enzyme starting in `[from, to]` (0-based, may run
+/// past either end of a circular sequence), on both strands.
+fn local_site_count(plasmid: &[u8], topology: Topology, site: &str, from: i64, to: i64) -> usize {
+    let length = plasmid.len() as i64;
+    let end = to + site.len() as i64;
+    let window: Vec<u8> = match topology {
+        Topology::Circular => (from..end)
+            .map(|i| plasmid[i.rem_euclid(length) as usize])
+            .collect(),
+        Topology::Linear => plasmid[from.max(0) as usize..end.min(length) as usize].to_vec(),
+    };
+    count_in_window(&window, site)
+}
+
+fn count_in_window(window: &[u8], site: &str) -> usize {
+    let forward = site_starts(window, site.as_bytes(), Topology::Linear).len();
+    let reverse_site = reverse_complementary(site);
+    if reverse_site == site {
+        forward
+    } else {
+        forward + site_starts(window, reverse_site.as_bytes(), Topology::Linear).len()
+    }
+}
+
+/// Unblocked cut sites of an enzyme on a whole plasmid.
+fn unblocked_sites(
+    plasmid: &str,
+    topology: Topology,
+    enzyme: &Enzyme,
+    methylation: &[Methylation],
+) -> Vec<[usize; 2]> {
+    let methylated = methylated_positions(plasmid.as_bytes(), topology, methylation);
+    enzyme_cut_sites(plasmid, topology, enzyme, &methylated)
+        .into_iter()
+        .filter(|s| s.blocked_by.is_empty())
+        .map(|s| s.site_span)
+        .collect()
+}
+
+/// Silent codon changes near an edit that create or destroy a unique
+/// restriction site, fewest base changes and best codon usage first.
+pub fn find_marker_sites(request: &MarkerSiteRequest) -> Result<Vec<MarkerSiteHit>, String> {
+    let sequence = sanitize_sequence(&request.sequence);
+    let feature = &request.feature;
+    let residues = feature_length(feature.span, sequence.len(), request.topology).unwrap_or(0) / 3;
+    if residues == 0 {
+        return Err(format!(
+            "Feature \"{}\" lies outside of the sequence.",
+            feature.label
+        ));
+    }
+    if request.residue == 0 || request.residue > residues {
+        return Err(format!(
+            "Residue {} lies outside of feature \"{}\".",
+            request.residue, feature.label
+        ));
+    }
+    let new_codon = sanitize_sequence(&request.new_codon).replace('U', "T");
+    if new_codon.len() != 3 || !new_codon.bytes().all(|b| b"ACGT".contains(&b)) {
+        return Err(format!(
+            "\"{}\" is not a codon of A, C, G and T.",
+            request.new_codon
+        ));
+    }
+
+    let enzymes: Vec<&Enzyme> = if request.enzymes.is_empty() {
+        ENZYMES.iter().filter(|e| e.is_commercial()).collect()
+    } else {
+        request
+            .enzymes
+            .iter()
+            .map(|name| find_enzyme(name).ok_or_else(|| format!("Unknown enzyme \"{}\".", name)))
+            .collect::<Result<_, _>>()?
+    };
+    let topology = request.topology;
+    let template_counts: Vec<usize> = enzymes
+        .iter()
+        .map(|e| unblocked_sites(&sequence, topology, e, &request.methylation).len())
+        .collect();
+
+    // Silent alternatives per residue in the window, coding strand
+    let first = request.residue.saturating_sub(request.window).max(1);
+    let last = (request.residue + request.window).min(residues);
+    let mut alternatives: Vec<(usize, String, char, String)> = Vec::new();
+    for residue in first..=last {
+        if residue == request.residue {
+            continue;
+        }
+        let codon = codon_at(&sequence, feature, residue);
+        let aa = translate_codon(&codon);
+        if aa == '?' {
+            continue;
+        }
+        for synonym in synonymous_codons(aa) {
+            if synonym != codon {
+                alternatives.push((residue, codon.clone(), aa, synonym.to_string()));
+            }
+        }
+    }
+
+    // The edit alone, each alternative, and pairs of alternatives at neighbouring residues
+    let mut candidates: Vec<Vec<usize>> = vec![Vec::new()];
+    for (i, a) in alternatives.iter().enumerate() {
+        candidates.push(vec![i]);
+        for (j, b) in alternatives.iter().enumerate().skip(i + 1) {
+            if b.0 == a.0 + 1 {
+                candidates.push(vec![i, j]);
+            }
+        }
+    }
+
+    let length = sequence.len() as i64;
+    let edit_start = codon_start(feature, request.residue) - 1;
+    let mut hits = Vec::new();
+    for candidate in candidates {
+        let changes: Vec<&(usize, String, char, String)> =
+            candidate.iter().map(|i| &alternatives[*i]).collect();
+        let base_changes: usize = changes
+            .iter()
+            .map(|(_, from, _, to)| codon_distance(from, to))
+            .sum();
+        if base_changes > request.max_base_changes {
+            continue;
+        }
+
+        let mut codons: Vec<(usize, &str)> = vec![(request.residue, new_codon.as_str())];
+        codons.extend(changes.iter().map(|(r, _, _, to)| (*r, to.as_str())));
+        let plasmid = apply_codons(&sequence, feature, &codons);
+        let mutant = String::from_utf8_lossy(&plasmid).to_string();
+
+        // Plasmid bases touched by the edit and the silent changes (0-based)
+        let starts = codons.iter().map(|(r, _)| codon_start(feature, *r) - 1);
+        let region_start = starts.clone().min().unwrap_or(edit_start);
+        let region_end = starts.max().unwrap_or(edit_start) + 2;
+        // Same region with its start on the plasmid, the end may still wrap
+        let shift = region_start.div_euclid(length) * length;
+        let (mutation_start, mutation_end) = (region_start - shift, region_end - shift);
+
+        for (enzyme, template_count) in enzymes.iter().zip(&template_counts) {
+            let from = region_start - enzyme.site.len() as i64 + 1;
+            let before = local_site_count(
+                sequence.as_bytes(),
+                topology,
+                &enzyme.site,
+                from,
+                region_end,
+            );
+            let after = local_site_count(&plasmid, topology, &enzyme.site, from, region_end);
+            let effect = match (*template_count, after as i64 - before as i64) {
+                (0, 1) => MarkerEffect::Created,
+                (1, -1) => MarkerEffect::Destroyed,
+                _ => continue,
+            };
+
+            // Confirm on the whole plasmid, including methylation
+            let mutant_sites = unblocked_sites(&mutant, topology, enzyme, &request.methylation);
+            let site_span = match effect {
+                MarkerEffect::Created if mutant_sites.len() == 1 => mutant_sites[0],
+                MarkerEffect::Destroyed if mutant_sites.is_empty() => {
+                    unblocked_sites(&sequence, topology, enzyme, &request.methylation)[0]
+                }
+                _ => continue,
+            };
+
+            let codon_usage = changes
+                .iter()
+                .map(|(_, _, aa, to)| relative_adaptiveness(&request.organism, *aa, to))
+                .fold(1.0, f64::min);
+            hits.push(MarkerSiteHit {
+                enzyme: enzyme.name.clone(),
+                site: enzyme.site.clone(),
+                effect,
+                codon_changes: changes
+                    .iter()
+                    .map(|(residue, from, aa, to)| CodonChange {
+                        residue: *residue,
+                        amino_acid: *aa,
+                        from: from.clone(),
+                        to: to.clone(),
+                    })
+                    .collect(),
+                base_changes,
+                codon_usage,
+                site_span,
+                mutation_span: [
+                    mutation_start as usize + 1,
+                    (mutation_end % length) as usize + 1,
+                ],
+                replacement: (mutation_start..=mutation_end)
+                    .map(|i| plasmid[(i % length) as usize] as char)
+                    .collect(),
+                primer_set: None,
+            });
+        }
+    }
+
+    let distance = |hit: &MarkerSiteHit| {
+        hit.codon_changes
+            .iter()
+            .map(|c| c.residue.abs_diff(request.residue))
+            .max()
+            .unwrap_or(0)
+    };
+    hits.sort_by(|a, b| {
+        a.base_changes
+            .cmp(&b.base_changes)
+            .then(b.codon_usage.total_cmp(&a.codon_usage))
+            .then(distance(a).cmp(&distance(b)))
+            .then(a.enzyme.cmp(&b.enzyme))
+    });
+    // Best hit per enzyme, the others only differ in which codons change
+    let mut seen = Vec::new();
+    hits.retain(|hit| {
+        let key = (hit.enzyme.clone(), hit.effect);
+        let first = !seen.contains(&key);
+        seen.push(key);
+        first
+    });
+    hits.truncate(request.max_hits);
+
+    if request.design_primers {
+        // Mutations across the origin cannot be designed as one IVA operation
+        for hit in hits
+            .iter_mut()
+            .filter(|h| h.mutation_span[0] <= h.mutation_span[1])
+        {
+            let mut primer_set = generate_set(
+                OperationType::Mutation,
+                hit.mutation_span[0] as i64,
+                Some(hit.mutation_span[1] as i64),
+                &sequence,
+                &hit.replacement,
+                topology,
+                &request.settings,
+            )
+            .map_err(|e| e.to_string())?;
+            primer_set.title = format!("{} {} site", feature.label, hit.enzyme);
+            hit.primer_set = Some(primer_set);
+        }
+    }
+    Ok(hits)
+}
+
+#[tauri::command]
+/// Silent restriction sites near a codon edit for screening colonies by digest.
+pub fn find_silent_marker_sites(request: MarkerSiteRequest) -> Result<Vec<MarkerSiteHit>, String> {
+    find_marker_sites(&request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Request changing residue 5 (CTG, Leu) of an 8 codon CDS to alanine,
+    /// looking for EcoRI sites only.
+    fn request(cds: &str) -> MarkerSiteRequest {
+        MarkerSiteRequest {
+            sequence: format!("{}{}{}", "T".repeat(10), cds, "T".repeat(10)),
+            topology: Topology::Circular,
+            feature: ScanFeature {
+                label: "CDS".to_string(),
+                span: [11, 34],
+                directionality: Direction::Fwd,
+            },
+            residue: 5,
+            new_codon: "GCG".to_string(),
+            window: default_window(),
+            max_base_changes: default_max_base_changes(),
+            enzymes: vec!["EcoRI".to_string()],
+            methylation: default_methylation(),
+            organism: default_organism(),
+            design_primers: false,
+            max_hits: default_max_hits(),
+            settings: DesignSettings::default(),
+        }
+    }
+
+    fn changes(hit: &MarkerSiteHit) -> Vec<(usize, &str, &str)> {
+        hit.codon_changes
+            .iter()
+            .map(|c| (c.residue, c.from.as_str(), c.to.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn silent_change_creates_site() {
+        // GAG TTC reads GAATTC once Glu 2 uses GAA
+        let hits = find_marker_sites(&request("ATGGAGTTCAAACTGGGCAAATAA")).unwrap();
+        let [hit] = hits.as_slice() else {
+            panic!("expected one hit, got {}", hits.len());
+        };
+        assert_eq!(hit.effect, MarkerEffect::Created);
+        assert_eq!(changes(hit), [(2, "GAG", "GAA")]);
+        assert_eq!(hit.base_changes, 1);
+        assert_eq!(hit.site_span, [14, 19]);
+        assert_eq!(hit.mutation_span, [14, 25]);
+        assert_eq!(hit.replacement, "GAATTCAAAGCG");
+    }
+
+    #[test]
+    fn silent_change_destroys_site() {
+        // Either Glu 2 or Phe 3 can break GAATTC, TTT is the preferred Phe
+        // codon in E. coli
+        let hits = find_marker_sites(&request("ATGGAATTCAAACTGGGCAAATAA")).unwrap();
+        let [hit] = hits.as_slice() else {
+            panic!("expected one hit, got {}", hits.len());
+        };
+        assert_eq!(hit.effect, MarkerEffect::Destroyed);
+        assert_eq!(changes(hit), [(3, "TTC", "TTT")]);
+        assert_eq!(hit.codon_usage, 1.0);
+        assert_eq!(hit.site_span, [14, 19]);
+    }
+
+    #[test]
+    fn feature_across_origin() {
+        // Same CDS starting 9 bases before the origin
+        let cds = "ATGGAGTTCAAACTGGGCAAATAA";
+        let mut request = request(cds);
+        request.sequence = format!("{}{}{}", &cds[9..], "T".repeat(20), &cds[..9]);
+        request.feature.span = [36, 15];
+        let hits = find_marker_sites(&request).unwrap();
+        let [hit] = hits.as_slice() else {
+            panic!("expected one hit, got {}", hits.len());
+        };
+        assert_eq!(hit.effect, MarkerEffect::Created);
+        assert_eq!(changes(hit), [(2, "GAG", "GAA")]);
+        assert_eq!(hit.site_span, [39, 44]);
+        assert_eq!(hit.mutation_span, [39, 6]);
+        assert_eq!(hit.replacement, "GAATTCAAAGCG");
+    }
+}
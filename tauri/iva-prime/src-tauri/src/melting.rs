@@ -0,0 +1,323 @@
+use serde::{Deserialize, Serialize};
+
+use crate::sequence::{
+    complementary, count_substring_occurences, degeneracy, expand_degenerate, fraction_gc,
+    is_degenerate, iupac_bases, iupac_mask,
+};
+
+/// Melting temperature algorithm, same names as the `TmAlgorithm` user preference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TmAlgorithm {
+    #[serde(rename = "nnSantaLucia")]
+    NnSantaLucia,
+    #[serde(rename = "oligoCalc")]
+    OligoCalc,
+}
+
+/// Salt correction, same names as the `saltCorr` user preference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SaltCorrection {
+    SchildkrautLifson,
+    Owczarzy,
+}
+
+/// Melting temperature settings, deserialized straight from the frontend's
+/// user preferences.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TmSettings {
+    #[serde(rename = "TmAlgorithm")]
+    pub algorithm: TmAlgorithm,
+    /// Primer concentration in nM
+    #[serde(rename = "primerConc")]
+    pub primer_conc: f64,
+    /// Salt concentration in M
+    #[serde(rename = "saltConc")]
+    pub salt_conc: f64,
+    #[serde(rename = "saltCorr")]
+    pub salt_correction: SaltCorrection,
+    /// DMSO concentration in %
+    #[serde(rename = "dmsoConc")]
+    pub dmso_conc: f64,
+}
+
+impl Default for TmSettings {
+    fn default() -> Self {
+        Self {
+            algorithm: TmAlgorithm::OligoCalc,
+            primer_conc: 100.0,
+            salt_conc: 0.0,
+            salt_correction: SaltCorrection::SchildkrautLifson,
+            dmso_conc: 0.0,
+        }
+    }
+}
+
+/// Maximum number of variants scored when expanding degenerate sequences,
+/// more degenerate sequences are scored on a deterministic sample.
+const MAX_DEGENERATE_VARIANTS: usize = 4096;
+
+/// Melting temperature spread over all variants of a degenerate sequence.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DegenerateTm {
+    pub min: f64,
+    pub max: f64,
+    pub average: f64,
+    pub degeneracy: u64,
+    /// True if the values were estimated from a sample of the variants.
+    pub sampled: bool,
+}
+
+/// Calculate melting temperature of a DNA duplex with specified sequence.
+///
+/// `method` overrides the algorithm from the settings, like the optional
+/// `method` argument of `Nucleotides.getMeltingTemperature`.
+/// Degenerate sequences are scored by their least stable variant, so a
+/// primer reaching the target Tm does so for every variant.
+pub fn melting_temperature(seq: &str, method: Option<TmAlgorithm>, settings: &TmSettings) -> f64 {
+    if is_degenerate(seq) {
+        return degenerate_melting_temperature(seq, method, settings).min;
+    }
+    concrete_melting_temperature(seq, method, settings)
+}
+
+/// Minimum, maximum and average melting temperature over the expanded
+/// variants of a degenerate sequence.
+pub fn degenerate_melting_temperature(
+    seq: &str,
+    method: Option<TmAlgorithm>,
+    settings: &TmSettings,
+) -> DegenerateTm {
+    let degeneracy = degeneracy(seq);
+
+    // Oligo Calc only depends on the GC count, so the extremes are exact.
+    if method.unwrap_or(settings.algorithm) == TmAlgorithm::OligoCalc {
+        const GC: u8 = 0b0110;
+        let masks: Vec<u8> = seq.bytes().map(iupac_mask).collect();
+        let certain_gc = masks.iter().filter(|m| **m != 0 && *m & !GC == 0).count();
+        let possible_gc = masks.iter().filter(|m| *m & GC != 0).count();
+        let expected_gc: f64 = masks
+            .iter()
+            .filter(|m| m.count_ones() > 0)
+            .map(|m| (m & GC).count_ones() as f64 / m.count_ones() as f64)
+            .sum();
+        return DegenerateTm {
+            min: oligo_calc_from_gc(certain_gc as f64, seq.len()),
+            max: oligo_calc_from_gc(possible_gc as f64, seq.len()),
+            average: oligo_calc_from_gc(expected_gc, seq.len()),
+            degeneracy,
+            sampled: false,
+        };
+    }
+
+    let (variants, sampled) = match expand_degenerate(seq, MAX_DEGENERATE_VARIANTS) {
+        Some(variants) => (variants, false),
+        None => (sample_variants(seq, MAX_DEGENERATE_VARIANTS), true),
+    };
+
+    let tms: Vec<f64> = variants
+        .iter()
+        .map(|v| concrete_melting_temperature(v, method, settings))
+        .collect();
+
+    DegenerateTm {
+        min: tms.iter().copied().fold(f64::INFINITY, f64::min),
+        max: tms.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        average: tms.iter().sum::<f64>() / tms.len() as f64,
+        degeneracy,
+        sampled,
+    }
+}
+
+/// Deterministic sample of variants of a degenerate sequence.
+fn sample_variants(seq: &str, count: usize) -> Vec<String> {
+    // Small LCG so the same sequence always gets the same sample
+    let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+    let options: Vec<Vec<u8>> = seq
+        .bytes()
+        .map(|b| match iupac_bases(b) {
+            bases if bases.is_empty() => vec![b],
+            bases => bases,
+        })
+        .collect();
+    (0..count)
+        .map(|_| {
+            options
+                .iter()
+                .map(|bases| {
+                    state = state
+                        .wrapping_mul(6_364_136_223_846_793_005)
+                        .wrapping_add(1_442_695_040_888_963_407);
+                    bases[((state >> 33) as usize) % bases.len()] as char
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn concrete_melting_temperature(
+    seq: &str,
+    method: Option<TmAlgorithm>,
+    settings: &TmSettings,
+) -> f64 {
+    let method = method.unwrap_or(settings.algorithm);
+
+    // Convert primer concentration from nM to M
+    let primer_conc_m = settings.primer_conc * 1e-9;
+
+    let tm = match method {
+        TmAlgorithm::NnSantaLucia => nn_santa_lucia(seq, primer_conc_m),
+        TmAlgorithm::OligoCalc => oligo_calc(seq),
+    };
+
+    let tm_corrected_salt = if method != TmAlgorithm::OligoCalc && settings.salt_conc > 0.0 {
+        match settings.salt_correction {
+            SaltCorrection::SchildkrautLifson => tm + 16.6 * settings.salt_conc.ln(),
+            SaltCorrection::Owczarzy => owczarzy(tm, seq, settings.salt_conc),
+        }
+    } else {
+        tm
+    };
+
+    let tm_corrected_salt_dmso = if method != TmAlgorithm::OligoCalc && settings.dmso_conc > 0.0 {
+        tm_corrected_salt - 0.6 * settings.dmso_conc
+    } else {
+        tm_corrected_salt
+    };
+
+    // Clamp output to absolute zero
+    tm_corrected_salt_dmso.max(-273.15)
+}
+
+/// Nearest-neighbour algorithm as described by SantaLucia (1998).
+fn nn_santa_lucia(seq: &str, c: f64) -> f64 {
+    // (pair, enthalpy [cal mol-1], entropy [cal K-1 mol-1])
+    const NN_PARAMS: [(&str, f64, f64); 16] = [
+        ("AA", -7.9e3, -22.2),
+        ("TT", -7.9e3, -22.2),
+        ("AT", -7.2e3, -20.4),
+        ("TA", -7.2e3, -21.3),
+        ("CA", -8.5e3, -22.7),
+        ("TG", -8.5e3, -22.7),
+        ("GT", -8.4e3, -22.4),
+        ("AC", -8.4e3, -22.4),
+        ("CT", -7.8e3, -21.0),
+        ("AG", -7.8e3, -21.0),
+        ("GA", -8.2e3, -22.2),
+        ("TC", -8.2e3, -22.2),
+        ("CG", -10.6e3, -27.2),
+        ("GC", -9.8e3, -24.4),
+        ("GG", -8.0e3, -19.9),
+        ("CC", -8.0e3, -19.9),
+    ];
+
+    let mut delta_h = 0.0;
+    let mut delta_s = 0.0;
+
+    // Symmetry correction
+    let mut symm_fraction = 4.0;
+    if seq == complementary(seq) {
+        delta_s += -1.4;
+        symm_fraction = 1.0;
+    }
+
+    // Nucleation term
+    if seq.contains('G') || seq.contains('C') {
+        delta_h += 0.1e3;
+        delta_s += -2.8;
+    } else {
+        delta_h += 2.3e3;
+        delta_s += 4.1;
+    }
+
+    for (pair, h, s) in NN_PARAMS {
+        let pair_count = count_substring_occurences(pair, seq) as f64;
+        delta_h += pair_count * h;
+        delta_s += pair_count * s;
+    }
+
+    // Ideal gas constant
+    const R: f64 = 1.987; // cal mol-1 K-1
+
+    (delta_h / (delta_s + R * (c / symm_fraction).ln())) - 273.15
+}
+
+/// Algorithm from the Oligo Calc online calculator (http://biotools.nubic.northwestern.edu/oligocalc.html)
+fn oligo_calc(seq: &str) -> f64 {
+    let gc = seq.bytes().filter(|b| matches!(b, b'G' | b'C')).count() as f64;
+    oligo_calc_from_gc(gc, seq.len())
+}
+
+fn oligo_calc_from_gc(gc: f64, length: usize) -> f64 {
+    if length == 0 {
+        return -273.15;
+    }
+    64.9 + 41.0 * ((gc - 16.4) / length as f64)
+}
+
+/// Owczarzy salt correction, operating on absolute temperatures.
+fn owczarzy(t1: f64, seq: &str, c: f64) -> f64 {
+    let f_gc = fraction_gc(seq);
+    let reciproc_t2 =
+        (1.0 / (t1 + 273.15)) + ((4.29 * f_gc - 3.95) * 1e-5 * c.ln()) + 9.4e-6 * c.ln().powi(2);
+    1.0 / reciproc_t2 - 273.15
+}
+
+#[tauri::command]
+/// Melting temperature spread of a (possibly degenerate) sequence.
+pub fn get_degenerate_melting_temperature(sequence: String, settings: TmSettings) -> DegenerateTm {
+    degenerate_melting_temperature(&sequence, None, &settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// M13 forward (-20) sequencing primer.
+    const M13_FORWARD: &str = "GTAAAACGACGGCCAGT";
+
+    #[test]
+    fn melting_temperature_of_a_known_oligo() {
+        let settings = TmSettings::default();
+        let oligo_calc = melting_temperature(M13_FORWARD, None, &settings);
+        assert!((oligo_calc - 47.05).abs() < 0.01);
+        let nn = melting_temperature(M13_FORWARD, Some(TmAlgorithm::NnSantaLucia), &settings);
+        assert!((nn - 66.96).abs() < 0.01);
+
+        let dmso = TmSettings {
+            dmso_conc: 5.0,
+            ..TmSettings::default()
+        };
+        let corrected = melting_temperature(M13_FORWARD, Some(TmAlgorithm::NnSantaLucia), &dmso);
+        assert!((nn - corrected - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn degenerate_oligos_span_their_variants() {
+        let settings = TmSettings {
+            algorithm: TmAlgorithm::NnSantaLucia,
+            ..TmSettings::default()
+        };
+        let tm = degenerate_melting_temperature("GTAAAACGASGGCCAGT", None, &settings);
+        let variants = ["GTAAAACGACGGCCAGT", "GTAAAACGAGGGCCAGT"]
+            .map(|v| melting_temperature(v, None, &settings));
+        assert_eq!(tm.degeneracy, 2);
+        assert!(!tm.sampled);
+        assert_eq!(tm.min, variants[0].min(variants[1]));
+        assert_eq!(tm.max, variants[0].max(variants[1]));
+        assert_eq!(
+            melting_temperature("GTAAAACGASGGCCAGT", None, &settings),
+            tm.min
+        );
+
+        // Oligo Calc only counts GC, W never adds to it and S always does
+        let settings = TmSettings::default();
+        let tm = degenerate_melting_temperature("GTAAAACGAWGGCCAGT", None, &settings);
+        assert_eq!(tm.min, tm.max);
+        assert_eq!(
+            tm.min,
+            melting_temperature("GTAAAACGAAGGCCAGT", None, &settings)
+        );
+    }
+}
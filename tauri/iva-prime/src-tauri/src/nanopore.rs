@@ -0,0 +1,717 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::alignment::{
+    banded_smith_waterman, AlignOp, Alignment, AlignmentScoring, ReferenceIndex,
+};
+use crate::jobs::{spawn_job, JobReporter};
+use crate::plasmid_file::PlasmidFile;
+use crate::reads::{read_sequencing_reads, SequencingRead};
+use crate::sequence::{sanitize_sequence, Topology};
+use crate::verification::{
+    features_at, format_span, join_across_origin, runs, DifferenceKind, UnconfirmedRegion, Verdict,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct NanoporeSettings {
+    /// Reads within this fraction of the plasmid length count as full length.
+    pub length_tolerance: f64,
+    /// Reads with a lower mean Phred quality are discarded.
+    pub min_mean_quality: f64,
+    /// Highest quality full-length reads used for the consensus.
+    pub max_reads: usize,
+    pub kmer_length: usize,
+    /// Smallest allowed drift from the seeded diagonal, grows with the plasmid
+    /// length to absorb the indel drift of long reads.
+    pub band: usize,
+    /// Reads below this identity are not used.
+    pub min_identity: f64,
+    /// Positions read fewer times are reported as low coverage.
+    pub min_depth: usize,
+    /// Fraction of reads a second allele needs to call a mixed population.
+    pub mixed_fraction: f64,
+    pub scoring: AlignmentScoring,
+}
+
+impl Default for NanoporeSettings {
+    fn default() -> Self {
+        Self {
+            length_tolerance: 0.1,
+            min_mean_quality: 10.0,
+            max_reads: 300,
+            kmer_length: 11,
+            band: 50,
+            min_identity: 0.75,
+            min_depth: 10,
+            mixed_fraction: 0.3,
+            scoring: AlignmentScoring::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NanoporeRequest {
+    /// Expected construct.
+    pub plasmid: PlasmidFile,
+    /// Whole-plasmid reads (`.fastq`, `.fasta`).
+    pub reads_path: PathBuf,
+    #[serde(default)]
+    pub settings: NanoporeSettings,
+}
+
+/// What happened to the reads of a run.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadSummary {
+    pub total: usize,
+    pub full_length: usize,
+    pub low_quality: usize,
+    pub unmapped: usize,
+    /// Reads that went into the consensus.
+    pub used: usize,
+    pub mean_identity: f64,
+}
+
+/// Difference between the consensus and the expected construct.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Variant {
+    pub kind: DifferenceKind,
+    /// Affected plasmid bases. Insertions sit between the two bases of the span.
+    pub span: [usize; 2],
+    pub expected: String,
+    pub observed: String,
+    pub depth: usize,
+    /// Fraction of the reads supporting the consensus call.
+    pub frequency: f64,
+    pub features: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlleleFraction {
+    /// Base, `-` for a deletion or `+<bases>` for an insertion in front of the position.
+    pub allele: String,
+    pub fraction: f64,
+}
+
+/// Position where the reads disagree, pointing to a mixed population.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MixedSite {
+    pub position: usize,
+    pub depth: usize,
+    /// Alleles seen at the position, most common first.
+    pub alleles: Vec<AlleleFraction>,
+    pub features: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NanoporeResult {
+    pub verdict: Verdict,
+    pub reads: ReadSummary,
+    /// Consensus of the used reads, starting at the plasmid origin. Bases not
+    /// covered by any read are `N`.
+    pub consensus: String,
+    pub variants: Vec<Variant>,
+    pub mixed: Vec<MixedSite>,
+    /// Read depth per plasmid base.
+    pub depth: Vec<usize>,
+    pub mean_depth: f64,
+    pub low_coverage: Vec<UnconfirmedRegion>,
+    /// Plain text version of the result.
+    pub report: String,
+}
+
+const ALLELES: [u8; 5] = [b'A', b'C', b'G', b'T', b'-'];
+
+fn allele_index(base: u8) -> Option<usize> {
+    ALLELES.iter().position(|a| *a == base)
+}
+
+/// Mean Phred quality of a read, averaged over error probabilities.
+fn mean_quality(read: &SequencingRead) -> Option<f64> {
+    let qualities = read.qualities.as_ref().filter(|q| !q.is_empty())?;
+    let error = qualities
+        .iter()
+        .map(|q| 10f64.powf(-(*q as f64) / 10.0))
+        .sum::<f64>()
+        / qualities.len() as f64;
+    Some(-10.0 * error.log10())
+}
+
+/// Query position aligned to the plasmid origin, estimated from the
+/// alignment start if the origin lies outside the alignment. Reads ending
+/// before the origin are not rotated.
+fn origin_offset(alignment: &Alignment, query_length: usize, reference_length: usize) -> usize {
+    let mut query = alignment.query_span[0];
+    for column in alignment.columns(reference_length) {
+        if column.reference == 0 && column.op != AlignOp::Insertion {
+            return query;
+        }
+        if column.op != AlignOp::Deletion {
+            query += 1;
+        }
+    }
+    let estimate = alignment.query_span[0] + reference_length - alignment.reference_start;
+    if estimate < query_length {
+        estimate
+    } else {
+        0
+    }
+}
+
+/// Base and insertion counts of all aligned reads.
+struct Pileup {
+    /// Counts of A, C, G, T and deletions per plasmid base.
+    counts: Vec<[usize; 5]>,
+    /// Inserted bases in front of each plasmid base.
+    insertions: Vec<HashMap<Vec<u8>, usize>>,
+}
+
+impl Pileup {
+    fn new(length: usize) -> Self {
+        Self {
+            counts: vec![[0; 5]; length],
+            insertions: vec![HashMap::new(); length],
+        }
+    }
+
+    fn add(&mut self, query: &[u8], alignment: &Alignment) {
+        let mut inserted = Vec::new();
+        for column in alignment.columns(self.counts.len()) {
+            match column.op {
+                AlignOp::Insertion => {
+                    inserted.extend(column.query.map(|q| query[q]));
+                    continue;
+                }
+                AlignOp::Deletion => self.counts[column.reference][4] += 1,
+                _ => {
+                    let base = column.query.map(|q| query[q]).unwrap_or(b'N');
+                    if let Some(index) = allele_index(base) {
+                        self.counts[column.reference][index] += 1;
+                    }
+                }
+            }
+            if !inserted.is_empty() {
+                *self.insertions[column.reference]
+                    .entry(std::mem::take(&mut inserted))
+                    .or_default() += 1;
+            }
+        }
+    }
+
+    fn depth(&self, position: usize) -> usize {
+        self.counts[position].iter().sum()
+    }
+
+    /// Most common insertion in front of a position, with the number of reads
+    /// carrying any insertion there. Read errors split a real insertion over
+    /// several variants, so they all count as support.
+    fn top_insertion(&self, position: usize) -> Option<(&Vec<u8>, usize)> {
+        let bases = self.insertions[position]
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+            .map(|(bases, _)| bases)?;
+        Some((bases, self.insertions[position].values().sum()))
+    }
+
+    /// Most common allele index at a position.
+    fn top_allele(&self, position: usize) -> usize {
+        (0..5)
+            .max_by_key(|i| (self.counts[position][*i], std::cmp::Reverse(*i)))
+            .unwrap_or(0)
+    }
+}
+
+/// Pick, orient and rotate full-length reads and pile them up on the plasmid.
+fn build_pileup(
+    sequence: &str,
+    topology: Topology,
+    reads: &[SequencingRead],
+    settings: &NanoporeSettings,
+    reporter: Option<&JobReporter>,
+) -> Result<(Pileup, ReadSummary), String> {
+    let length = sequence.len();
+    let mut summary = ReadSummary {
+        total: reads.len(),
+        ..Default::default()
+    };
+
+    let min_length = (length as f64 * (1.0 - settings.length_tolerance)).floor() as usize;
+    let max_length = (length as f64 * (1.0 + settings.length_tolerance)).ceil() as usize;
+    let mut candidates: Vec<(&SequencingRead, f64)> = Vec::new();
+    for read in reads {
+        if !(min_length..=max_length).contains(&read.sequence.len()) {
+            continue;
+        }
+        summary.full_length += 1;
+        let quality = mean_quality(read).unwrap_or(f64::INFINITY);
+        if quality < settings.min_mean_quality {
+            summary.low_quality += 1;
+            continue;
+        }
+        candidates.push((read, quality));
+    }
+    candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+    candidates.truncate(settings.max_reads);
+
+    let index = ReferenceIndex::new(sequence, topology, settings.kmer_length);
+    let band = settings.band.max(length / 50);
+    let reference = sequence.as_bytes();
+    let mut pileup = Pileup::new(length);
+    let mut identity_sum = 0.0;
+
+    let total = candidates.len();
+    for (i, (read, _)) in candidates.into_iter().enumerate() {
+        if let Some(reporter) = reporter {
+            if reporter.is_cancelled() {
+                return Err("Nanopore verification cancelled.".to_string());
+            }
+            reporter.progress(i, total, format!("Aligning {}", read.name));
+        }
+
+        let Some(hit) = index.align(&read.sequence, band, &settings.scoring) else {
+            summary.unmapped += 1;
+            continue;
+        };
+        // Rotate the read so that it starts at the plasmid origin, then align
+        // it again end to end
+        let (query, alignment) = match topology {
+            Topology::Linear => (hit.query, Some(hit.alignment)),
+            Topology::Circular => {
+                let cut = origin_offset(&hit.alignment, hit.query.len(), length);
+                let rotated = [&hit.query[cut..], &hit.query[..cut]].concat();
+                let alignment =
+                    banded_smith_waterman(&rotated, reference, 0, band, &settings.scoring);
+                (rotated, alignment)
+            }
+        };
+        match alignment.filter(|a| a.identity() >= settings.min_identity) {
+            Some(alignment) => {
+                pileup.add(&query, &alignment);
+                identity_sum += alignment.identity();
+                summary.used += 1;
+            }
+            None => summary.unmapped += 1,
+        }
+    }
+    if summary.used > 0 {
+        summary.mean_identity = identity_sum / summary.used as f64;
+    }
+    Ok((pileup, summary))
+}
+
+/// Consensus sequence and its differences to the plasmid.
+fn call_consensus(
+    sequence: &str,
+    pileup: &Pileup,
+    settings: &NanoporeSettings,
+) -> (String, Vec<Variant>) {
+    let reference = sequence.as_bytes();
+    let length = reference.len();
+    let mut consensus = Vec::with_capacity(length);
+    let mut variants: Vec<Variant> = Vec::new();
+
+    for (position, &expected) in reference.iter().enumerate() {
+        let depth = pileup.depth(position);
+        let called = depth >= settings.min_depth;
+
+        if let Some((bases, count)) = pileup.top_insertion(position) {
+            if 2 * count > depth {
+                consensus.extend_from_slice(bases);
+                if called {
+                    variants.push(Variant {
+                        kind: DifferenceKind::Insertion,
+                        span: [(position + length - 1) % length + 1, position + 1],
+                        expected: String::new(),
+                        observed: String::from_utf8_lossy(bases).to_string(),
+                        depth,
+                        frequency: count as f64 / depth as f64,
+                        features: Vec::new(),
+                    });
+                }
+            }
+        }
+
+        if depth == 0 {
+            consensus.push(b'N');
+            continue;
+        }
+        let top = pileup.top_allele(position);
+        let frequency = pileup.counts[position][top] as f64 / depth as f64;
+        let base = ALLELES[top];
+        if base != b'-' {
+            consensus.push(base);
+        }
+        if !called || base == expected {
+            continue;
+        }
+
+        let kind = if base == b'-' {
+            DifferenceKind::Deletion
+        } else {
+            DifferenceKind::Mismatch
+        };
+        // Extend the previous variant if it is of the same kind and adjacent
+        if let Some(previous) = variants
+            .last_mut()
+            .filter(|v| v.kind == kind && v.span[1] == position)
+        {
+            previous.span[1] = position + 1;
+            previous.expected.push(expected as char);
+            if kind == DifferenceKind::Mismatch {
+                previous.observed.push(base as char);
+            }
+            previous.depth = previous.depth.min(depth);
+            previous.frequency = previous.frequency.min(frequency);
+            continue;
+        }
+        variants.push(Variant {
+            kind,
+            span: [position + 1, position + 1],
+            expected: (expected as char).to_string(),
+            observed: match kind {
+                DifferenceKind::Deletion => String::new(),
+                _ => (base as char).to_string(),
+            },
+            depth,
+            frequency,
+            features: Vec::new(),
+        });
+    }
+
+    (String::from_utf8_lossy(&consensus).to_string(), variants)
+}
+
+/// Positions where a second allele reaches the mixed population fraction.
+fn find_mixed_sites(pileup: &Pileup, settings: &NanoporeSettings) -> Vec<MixedSite> {
+    let mut sites = Vec::new();
+    for position in 0..pileup.counts.len() {
+        let depth = pileup.depth(position);
+        if depth < settings.min_depth {
+            continue;
+        }
+        let fraction = |count: usize| count as f64 / depth as f64;
+        let is_mixed = |count: usize| {
+            (settings.mixed_fraction..=1.0 - settings.mixed_fraction).contains(&fraction(count))
+        };
+
+        let mut alleles: Vec<(String, usize)> = ALLELES
+            .iter()
+            .zip(pileup.counts[position])
+            .map(|(allele, count)| ((*allele as char).to_string(), count))
+            .collect();
+        alleles.sort_by_key(|a| std::cmp::Reverse(a.1));
+        let mut mixed = fraction(alleles[1].1) >= settings.mixed_fraction;
+        // Insertions are weighed against the reads without one
+        if let Some((bases, count)) = pileup.top_insertion(position) {
+            if is_mixed(count) {
+                mixed = true;
+                alleles.push((format!("+{}", String::from_utf8_lossy(bases)), count));
+            }
+        }
+        if !mixed {
+            continue;
+        }
+        sites.push(MixedSite {
+            position: position + 1,
+            depth,
+            alleles: alleles
+                .into_iter()
+                .filter(|(_, count)| fraction(*count) >= settings.mixed_fraction)
+                .map(|(allele, count)| AlleleFraction {
+                    allele,
+                    fraction: fraction(count),
+                })
+                .collect(),
+            features: Vec::new(),
+        });
+    }
+    sites
+}
+
+/// Plain text report of a nanopore verification result.
+pub fn report_text(plasmid_name: &str, result: &NanoporeResult) -> String {
+    let reads = &result.reads;
+    let mut lines = vec![
+        format!("Whole-plasmid verification of \"{}\"", plasmid_name),
+        format!("Verdict: {:?}", result.verdict),
+        String::new(),
+        format!(
+            "Reads: {} total, {} full length, {} low quality, {} unmapped, {} used",
+            reads.total, reads.full_length, reads.low_quality, reads.unmapped, reads.used
+        ),
+        format!(
+            "Mean identity {:.1}%, mean depth {:.1}",
+            100.0 * reads.mean_identity,
+            result.mean_depth
+        ),
+    ];
+    let in_features = |features: &[String]| {
+        if features.is_empty() {
+            String::new()
+        } else {
+            format!(", in {}", features.join(", "))
+        }
+    };
+    if !result.variants.is_empty() {
+        lines.push(String::new());
+        lines.push("Variants:".to_string());
+        for v in &result.variants {
+            lines.push(format!(
+                "  {:?} at {}: {} -> {} ({:.0}% of {} reads{})",
+                v.kind,
+                format_span(v.span),
+                if v.expected.is_empty() {
+                    "-"
+                } else {
+                    &v.expected
+                },
+                if v.observed.is_empty() {
+                    "-"
+                } else {
+                    &v.observed
+                },
+                100.0 * v.frequency,
+                v.depth,
+                in_features(&v.features)
+            ));
+        }
+    }
+    if !result.mixed.is_empty() {
+        lines.push(String::new());
+        lines.push("Mixed positions:".to_string());
+        for site in &result.mixed {
+            let alleles: Vec<String> = site
+                .alleles
+                .iter()
+                .map(|a| format!("{} {:.0}%", a.allele, 100.0 * a.fraction))
+                .collect();
+            lines.push(format!(
+                "  {}: {} ({} reads{})",
+                site.position,
+                alleles.join(", "),
+                site.depth,
+                in_features(&site.features)
+            ));
+        }
+    }
+    if !result.low_coverage.is_empty() {
+        lines.push(String::new());
+        lines.push("Low coverage regions:".to_string());
+        for region in &result.low_coverage {
+            lines.push(format!(
+                "  {}{}",
+                format_span(region.span),
+                if region.features.is_empty() {
+                    String::new()
+                } else {
+                    format!(" ({})", region.features.join(", "))
+                }
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Build a consensus from whole-plasmid reads and compare it to the expected
+/// construct.
+pub fn verify_whole_plasmid(
+    plasmid: &PlasmidFile,
+    reads: &[SequencingRead],
+    settings: &NanoporeSettings,
+    reporter: Option<&JobReporter>,
+) -> Result<NanoporeResult, String> {
+    let sequence = sanitize_sequence(&plasmid.sequence);
+    if sequence.is_empty() {
+        return Err("The plasmid has no sequence.".to_string());
+    }
+    let length = sequence.len();
+
+    let (pileup, read_summary) =
+        build_pileup(&sequence, plasmid.topology, reads, settings, reporter)?;
+    if let Some(reporter) = reporter {
+        reporter.progress(read_summary.used, read_summary.used, "Building consensus");
+    }
+
+    let (consensus, mut variants) = call_consensus(&sequence, &pileup, settings);
+    for variant in &mut variants {
+        variant.features = features_at(&plasmid.features, variant.span);
+    }
+    let mut mixed = find_mixed_sites(&pileup, settings);
+    for site in &mut mixed {
+        site.features = features_at(&plasmid.features, [site.position, site.position]);
+    }
+
+    let depth: Vec<usize> = (0..length).map(|p| pileup.depth(p)).collect();
+    let mean_depth = depth.iter().sum::<usize>() as f64 / length as f64;
+    let low_coverage_runs = runs(length, |i| depth[i] < settings.min_depth);
+    let low_coverage: Vec<UnconfirmedRegion> =
+        join_across_origin(low_coverage_runs, length, plasmid.topology)
+            .into_iter()
+            .map(|[start, end]| [start + 1, end + 1])
+            .map(|span| UnconfirmedRegion {
+                span,
+                features: features_at(&plasmid.features, span),
+            })
+            .collect();
+
+    let verdict = if read_summary.used == 0 {
+        Verdict::Failed
+    } else if !variants.is_empty() || !mixed.is_empty() {
+        Verdict::Mismatch
+    } else if !low_coverage.is_empty() {
+        Verdict::Incomplete
+    } else {
+        Verdict::Confirmed
+    };
+
+    let mut result = NanoporeResult {
+        verdict,
+        reads: read_summary,
+        consensus,
+        variants,
+        mixed,
+        depth,
+        mean_depth,
+        low_coverage,
+        report: String::new(),
+    };
+    result.report = report_text(&plasmid.name, &result);
+    Ok(result)
+}
+
+/// Load the reads of a request and verify the plasmid against them.
+pub fn run_nanopore_verification(
+    request: &NanoporeRequest,
+    reporter: Option<&JobReporter>,
+) -> Result<NanoporeResult, String> {
+    let reads = read_sequencing_reads(&request.reads_path)?;
+    verify_whole_plasmid(&request.plasmid, &reads, &request.settings, reporter)
+}
+
+#[tauri::command]
+/// Start a whole-plasmid nanopore verification in the background.
+/// Returns the job id, results arrive through the `job-finished` event.
+pub fn start_nanopore_job(app: tauri::AppHandle, request: NanoporeRequest) -> u64 {
+    spawn_job(app, "nanopore", move |reporter| {
+        run_nanopore_verification(&request, Some(reporter))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plasmid_file::Feature;
+    use crate::sequence::tests::RANDOM_PLASMID;
+    use crate::sequence::{reverse_complementary, Direction};
+
+    fn plasmid() -> PlasmidFile {
+        PlasmidFile {
+            name: "test".to_string(),
+            sequence: RANDOM_PLASMID.concat(),
+            topology: Topology::Circular,
+            features: vec![Feature {
+                label: "site".to_string(),
+                feature_type: "misc_feature".to_string(),
+                span: [201, 220],
+                directionality: Some(Direction::Fwd),
+                segments: Vec::new(),
+                qualifiers: Vec::new(),
+            }],
+        }
+    }
+
+    /// Reads of a sequence starting at different places, every other one from
+    /// the bottom strand.
+    fn reads(sequence: &str, count: usize) -> Vec<SequencingRead> {
+        (0..count)
+            .map(|i| {
+                let start = 33 * i % sequence.len();
+                let rotated = format!("{}{}", &sequence[start..], &sequence[..start]);
+                SequencingRead {
+                    name: format!("read {}", i),
+                    sequence: if i % 2 == 0 {
+                        rotated
+                    } else {
+                        reverse_complementary(&rotated)
+                    },
+                    qualities: Some(vec![30; sequence.len()]),
+                }
+            })
+            .collect()
+    }
+
+    fn mutant() -> String {
+        let sequence = RANDOM_PLASMID.concat();
+        let base = if &sequence[209..210] == "A" { "C" } else { "A" };
+        format!("{}{}{}", &sequence[..209], base, &sequence[210..])
+    }
+
+    #[test]
+    fn consensus_of_reads_on_both_strands() {
+        let plasmid = plasmid();
+        let settings = NanoporeSettings::default();
+        let result =
+            verify_whole_plasmid(&plasmid, &reads(&plasmid.sequence, 12), &settings, None).unwrap();
+        assert_eq!(result.reads.used, 12);
+        assert_eq!(result.consensus, plasmid.sequence);
+        assert!(result.variants.is_empty());
+        assert_eq!(result.mean_depth, 12.0);
+        assert_eq!(result.verdict, Verdict::Confirmed);
+    }
+
+    #[test]
+    fn point_mutations_and_mixed_populations() {
+        let plasmid = plasmid();
+        let settings = NanoporeSettings::default();
+        let mutant = mutant();
+        let result = verify_whole_plasmid(&plasmid, &reads(&mutant, 12), &settings, None).unwrap();
+        assert_eq!(result.consensus, mutant);
+        let variants: Vec<(DifferenceKind, [usize; 2], &str, Vec<String>)> = result
+            .variants
+            .iter()
+            .map(|v| (v.kind, v.span, v.observed.as_str(), v.features.clone()))
+            .collect();
+        assert_eq!(
+            variants,
+            [(
+                DifferenceKind::Mismatch,
+                [210, 210],
+                &mutant[209..210],
+                vec!["site".to_string()]
+            )]
+        );
+        assert!(result.mixed.is_empty());
+        assert_eq!(result.verdict, Verdict::Mismatch);
+
+        let mut mixed_reads = reads(&mutant, 6);
+        mixed_reads.extend(reads(&plasmid.sequence, 6));
+        let result = verify_whole_plasmid(&plasmid, &mixed_reads, &settings, None).unwrap();
+        assert_eq!(result.mixed.len(), 1);
+        assert_eq!(result.mixed[0].position, 210);
+        let fractions: Vec<f64> = result.mixed[0].alleles.iter().map(|a| a.fraction).collect();
+        assert_eq!(fractions, [0.5, 0.5]);
+        assert_eq!(result.verdict, Verdict::Mismatch);
+    }
+
+    #[test]
+    fn low_coverage_across_the_origin() {
+        let plasmid = plasmid();
+        let settings = NanoporeSettings::default();
+        let partial = &plasmid.sequence[10..390];
+        let reads: Vec<SequencingRead> = reads(partial, 1).into_iter().cycle().take(12).collect();
+        let result = verify_whole_plasmid(&plasmid, &reads, &settings, None).unwrap();
+        assert_eq!(result.reads.used, 12);
+        let spans: Vec<[usize; 2]> = result.low_coverage.iter().map(|r| r.span).collect();
+        assert_eq!(spans, [[391, 10]]);
+        assert!(result.variants.is_empty());
+        assert_eq!(result.verdict, Verdict::Incomplete);
+    }
+}
@@ -0,0 +1,474 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::codons::{
+    codon_adaptation_index, codon_weight, relative_adaptiveness, synonymous_codons, CODON_WEIGHTS,
+};
+use crate::restriction::{find_enzyme, site_starts};
+use crate::sequence::{fraction_gc, reverse_complementary, sanitize_sequence, Topology};
+
+/// Shine-Dalgarno cores, internal ones only matter with a start codon behind them.
+pub const SHINE_DALGARNO: [&str; 3] = ["AGGAGG", "GGAGG", "AGGAG"];
+/// Bases between a Shine-Dalgarno core and a start codon it can drive.
+pub const SHINE_DALGARNO_SPACING: [usize; 2] = [4, 12];
+/// Strong Kozak-like context around an internal ATG.
+const KOZAK: &str = "RCCATGG";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct OptimizationSettings {
+    /// Codons used less than this fraction of the best synonymous codon are
+    /// only picked if nothing else fits.
+    pub rare_codon_threshold: f64,
+    pub gc_window: usize,
+    pub gc_range: [f64; 2],
+    /// Enzymes whose sites must not occur.
+    pub avoid_enzymes: Vec<String>,
+    /// Further sequences that must not occur on either strand (IUPAC allowed).
+    pub avoid_sequences: Vec<String>,
+    pub max_homopolymer: usize,
+    /// Shortest direct repeat reported.
+    pub repeat_length: usize,
+    pub avoid_shine_dalgarno: bool,
+    pub avoid_kozak: bool,
+    pub max_iterations: usize,
+    pub seed: u64,
+}
+
+impl Default for OptimizationSettings {
+    fn default() -> Self {
+        Self {
+            rare_codon_threshold: 0.1,
+            gc_window: 50,
+            gc_range: [0.3, 0.7],
+            avoid_enzymes: Vec::new(),
+            avoid_sequences: Vec::new(),
+            max_homopolymer: 6,
+            repeat_length: 14,
+            avoid_shine_dalgarno: true,
+            avoid_kozak: true,
+            max_iterations: 5000,
+            seed: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OptimizationRequest {
+    pub protein: String,
+    pub organism: String,
+    #[serde(default)]
+    pub settings: OptimizationSettings,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConstraintKind {
+    GcContent,
+    RestrictionSite,
+    AvoidedSequence,
+    Homopolymer,
+    Repeat,
+    ShineDalgarno,
+    Kozak,
+}
+
+/// Constraint the optimised sequence still breaks.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConstraintViolation {
+    pub kind: ConstraintKind,
+    /// Affected bases of the DNA sequence (1-based, inclusive).
+    pub span: [usize; 2],
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OptimizationResult {
+    pub sequence: String,
+    pub protein: String,
+    pub organism: String,
+    pub cai: f64,
+    /// Relative adaptiveness of every codon.
+    pub codon_scores: Vec<f64>,
+    pub gc: f64,
+    pub violations: Vec<ConstraintViolation>,
+    pub iterations: usize,
+}
+
+/// SplitMix64, small and good enough to make runs reproducible from a seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n.max(1) as u64) as usize
+    }
+
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Index picked with probability proportional to its weight.
+    fn weighted(&mut self, weights: &[f64]) -> usize {
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return self.below(weights.len());
+        }
+        let mut target = self.unit() * total;
+        for (i, w) in weights.iter().enumerate() {
+            target -= w;
+            if target <= 0.0 {
+                return i;
+            }
+        }
+        weights.len() - 1
+    }
+}
+
+/// Codons allowed for an amino acid with their usage weights.
+fn codon_choices(organism: &str, aa: char, rare_threshold: f64) -> Vec<(&'static str, f64)> {
+    let all: Vec<(&'static str, f64)> = synonymous_codons(aa)
+        .into_iter()
+        .map(|c| (c, codon_weight(organism, aa, c)))
+        .collect();
+    let common: Vec<(&'static str, f64)> = all
+        .iter()
+        .filter(|(c, _)| relative_adaptiveness(organism, aa, c) >= rare_threshold)
+        .copied()
+        .collect();
+    if common.is_empty() {
+        all
+    } else {
+        common
+    }
+}
+
+/// Compiled sequence constraints.
+struct Constraints<'a> {
+    settings: &'a OptimizationSettings,
+    /// Name and both orientations of every forbidden site.
+    sites: Vec<(ConstraintKind, String, Vec<String>)>,
+}
+
+impl<'a> Constraints<'a> {
+    fn new(settings: &'a OptimizationSettings) -> Result<Self, String> {
+        let mut sites = Vec::new();
+        for name in &settings.avoid_enzymes {
+            let enzyme =
+                find_enzyme(name).ok_or_else(|| format!("Unknown enzyme \"{}\".", name))?;
+            sites.push((
+                ConstraintKind::RestrictionSite,
+                enzyme.name.clone(),
+                vec![enzyme.site.clone(), reverse_complementary(&enzyme.site)],
+            ));
+        }
+        for sequence in &settings.avoid_sequences {
+            let sequence = sanitize_sequence(sequence);
+            if !sequence.is_empty() {
+                sites.push((
+                    ConstraintKind::AvoidedSequence,
+                    sequence.clone(),
+                    vec![sequence.clone(), reverse_complementary(&sequence)],
+                ));
+            }
+        }
+        Ok(Self { settings, sites })
+    }
+
+    /// Broken constraints and a penalty to minimise. Sites, homopolymers and
+    /// repeats count their length, GC windows the number of bases they are off
+    /// by, so that partial progress towards the range is rewarded.
+    fn violations(&self, dna: &[u8]) -> (Vec<ConstraintViolation>, f64) {
+        let settings = self.settings;
+        let mut violations = Vec::new();
+        let mut gc_penalty = 0.0;
+
+        // GC content, merging consecutive failing windows
+        let window = settings.gc_window.min(dna.len());
+        if window > 0 {
+            let is_gc = |b: u8| matches!(b, b'G' | b'C');
+            let mut gc_count = dna[..window].iter().filter(|b| is_gc(**b)).count();
+            let mut run: Option<[usize; 2]> = None;
+            for start in 0..=dna.len() - window {
+                if start > 0 {
+                    gc_count += is_gc(dna[start + window - 1]) as usize;
+                    gc_count -= is_gc(dna[start - 1]) as usize;
+                }
+                let gc = gc_count as f64 / window as f64;
+                let off = (settings.gc_range[0] - gc).max(gc - settings.gc_range[1]);
+                let failing = off > 0.0;
+                if failing {
+                    gc_penalty += off * window as f64;
+                }
+                match (failing, &mut run) {
+                    (true, Some(span)) => span[1] = start + window,
+                    (true, None) => run = Some([start + 1, start + window]),
+                    (false, Some(span)) => {
+                        violations.push(ConstraintViolation {
+                            kind: ConstraintKind::GcContent,
+                            span: *span,
+                            detail: "GC content outside of the allowed range.".to_string(),
+                        });
+                        run = None;
+                    }
+                    (false, None) => {}
+                }
+            }
+            if let Some(span) = run {
+                violations.push(ConstraintViolation {
+                    kind: ConstraintKind::GcContent,
+                    span,
+                    detail: "GC content outside of the allowed range.".to_string(),
+                });
+            }
+        }
+
+        for (kind, name, patterns) in &self.sites {
+            for pattern in patterns {
+                for start in site_starts(dna, pattern.as_bytes(), Topology::Linear) {
+                    violations.push(ConstraintViolation {
+                        kind: *kind,
+                        span: [start + 1, start + pattern.len()],
+                        detail: name.clone(),
+                    });
+                }
+            }
+        }
+
+        let mut run_start = 0;
+        for i in 1..=dna.len() {
+            if i == dna.len() || dna[i] != dna[run_start] {
+                if i - run_start > settings.max_homopolymer {
+                    violations.push(ConstraintViolation {
+                        kind: ConstraintKind::Homopolymer,
+                        span: [run_start + 1, i],
+                        detail: format!("{} x {}", i - run_start, dna[run_start] as char),
+                    });
+                }
+                run_start = i;
+            }
+        }
+
+        // Direct repeats, reported at their second copy
+        let k = settings.repeat_length;
+        if k > 0 && dna.len() > k {
+            let mut first_seen: HashMap<&[u8], usize> = HashMap::new();
+            let mut reported_until = 0;
+            for start in 0..=dna.len() - k {
+                let kmer = &dna[start..start + k];
+                match first_seen.get(kmer) {
+                    Some(&first) if start >= first + k && start >= reported_until => {
+                        violations.push(ConstraintViolation {
+                            kind: ConstraintKind::Repeat,
+                            span: [start + 1, start + k],
+                            detail: format!("Repeats bases {}..{}", first + 1, first + k),
+                        });
+                        reported_until = start + k;
+                    }
+                    Some(_) => {}
+                    None => {
+                        first_seen.insert(kmer, start);
+                    }
+                }
+            }
+        }
+
+        if settings.avoid_shine_dalgarno {
+            for core in SHINE_DALGARNO {
+                for start in site_starts(dna, core.as_bytes(), Topology::Linear) {
+                    let end = start + core.len();
+                    let [min, max] = SHINE_DALGARNO_SPACING;
+                    let drives_start = (end + min..=end + max).any(|s| {
+                        dna.get(s..s + 3)
+                            .is_some_and(|c| c == b"ATG" || c == b"GTG")
+                    });
+                    if drives_start {
+                        violations.push(ConstraintViolation {
+                            kind: ConstraintKind::ShineDalgarno,
+                            span: [start + 1, end],
+                            detail: core.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+
+        if settings.avoid_kozak {
+            // The ATG sits inside the motif, so the start codon itself never matches
+            for start in site_starts(dna, KOZAK.as_bytes(), Topology::Linear) {
+                violations.push(ConstraintViolation {
+                    kind: ConstraintKind::Kozak,
+                    span: [start + 1, start + KOZAK.len()],
+                    detail: KOZAK.to_string(),
+                });
+            }
+        }
+
+        let penalty = violations
+            .iter()
+            .filter(|v| v.kind != ConstraintKind::GcContent)
+            .map(|v| (v.span[1] + 1 - v.span[0]) as f64)
+            .sum::<f64>()
+            + gc_penalty;
+        (violations, penalty)
+    }
+}
+
+/// Reverse-translate a protein, sampling codons by usage and then changing
+/// codons inside violated constraints until none are left or the iteration
+/// limit is hit. The same seed gives the same sequence.
+pub fn optimize_protein(request: &OptimizationRequest) -> Result<OptimizationResult, String> {
+    let settings = &request.settings;
+    let organism = request.organism.as_str();
+    if !CODON_WEIGHTS.contains_key(organism) {
+        return Err(format!("No codon table for \"{}\".", organism));
+    }
+    let protein: String = request
+        .protein
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if protein.is_empty() {
+        return Err("The protein sequence is empty.".to_string());
+    }
+
+    let mut choices = Vec::with_capacity(protein.len());
+    for (i, aa) in protein.chars().enumerate() {
+        let options = codon_choices(organism, aa, settings.rare_codon_threshold);
+        if options.is_empty() {
+            return Err(format!("Unknown amino acid \"{}\" at {}.", aa, i + 1));
+        }
+        choices.push(options);
+    }
+
+    let constraints = Constraints::new(settings)?;
+    let mut rng = Rng(settings.seed);
+    let mut codons: Vec<&str> = choices
+        .iter()
+        .map(|options| {
+            let weights: Vec<f64> = options.iter().map(|(_, w)| *w).collect();
+            options[rng.weighted(&weights)].0
+        })
+        .collect();
+
+    let mut dna = codons.concat().into_bytes();
+    let (mut violations, mut penalty) = constraints.violations(&dna);
+    let mut iterations = 0;
+    while !violations.is_empty() && iterations < settings.max_iterations {
+        iterations += 1;
+
+        // Change a random codon within or next to a random violation
+        let violation = &violations[rng.below(violations.len())];
+        let first = (violation.span[0] - 1) / 3;
+        let last = ((violation.span[1] - 1) / 3).min(codons.len() - 1);
+        let first = first.saturating_sub(1);
+        let last = (last + 1).min(codons.len() - 1);
+        let index = first + rng.below(last - first + 1);
+
+        let alternatives: Vec<&(&str, f64)> = choices[index]
+            .iter()
+            .filter(|(c, _)| *c != codons[index])
+            .collect();
+        if alternatives.is_empty() {
+            continue;
+        }
+        let weights: Vec<f64> = alternatives.iter().map(|(_, w)| *w).collect();
+        let candidate = alternatives[rng.weighted(&weights)].0;
+
+        let previous = codons[index];
+        dna[3 * index..3 * index + 3].copy_from_slice(candidate.as_bytes());
+        let (candidate_violations, candidate_penalty) = constraints.violations(&dna);
+        // Equal penalties are accepted to move along plateaus
+        if candidate_penalty <= penalty {
+            codons[index] = candidate;
+            violations = candidate_violations;
+            penalty = candidate_penalty;
+        } else {
+            dna[3 * index..3 * index + 3].copy_from_slice(previous.as_bytes());
+        }
+    }
+
+    let sequence = String::from_utf8_lossy(&dna).to_string();
+    let codon_scores = protein
+        .chars()
+        .zip(&codons)
+        .map(|(aa, codon)| relative_adaptiveness(organism, aa, codon))
+        .collect();
+    Ok(OptimizationResult {
+        cai: codon_adaptation_index(organism, &sequence),
+        gc: fraction_gc(&sequence),
+        sequence,
+        protein,
+        organism: organism.to_string(),
+        codon_scores,
+        violations,
+        iterations,
+    })
+}
+
+#[tauri::command]
+/// Reverse-translate a protein for an organism under sequence constraints.
+pub fn optimize_codons(request: OptimizationRequest) -> Result<OptimizationResult, String> {
+    optimize_protein(&request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codons::translate_codon;
+
+    fn request(protein: &str) -> OptimizationRequest {
+        OptimizationRequest {
+            protein: protein.to_string(),
+            organism: "Escherichia coli".to_string(),
+            settings: OptimizationSettings {
+                avoid_enzymes: vec!["EcoRI".to_string()],
+                ..OptimizationSettings::default()
+            },
+        }
+    }
+
+    fn translate(dna: &str) -> String {
+        dna.as_bytes()
+            .chunks_exact(3)
+            .map(|codon| translate_codon(&String::from_utf8_lossy(codon)))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_sequence() {
+        let protein = "MEFEFEFKLVNGSTQWRHHHHHH*";
+        let first = optimize_protein(&request(protein)).unwrap();
+        let second = optimize_protein(&request(protein)).unwrap();
+        assert_eq!(first.sequence, second.sequence);
+        assert_eq!(first.iterations, second.iterations);
+
+        assert_eq!(translate(&first.sequence), protein);
+        assert_eq!(first.codon_scores.len(), protein.len());
+        assert!(!first.sequence.contains("GAATTC"));
+        assert!(first
+            .violations
+            .iter()
+            .all(|v| v.kind != ConstraintKind::RestrictionSite));
+    }
+
+    #[test]
+    fn unknown_residues_are_refused() {
+        assert_eq!(
+            optimize_protein(&request("MKB")).unwrap_err(),
+            "Unknown amino acid \"B\" at 3."
+        );
+    }
+}
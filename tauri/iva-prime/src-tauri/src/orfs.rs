@@ -0,0 +1,369 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::plasmid_file::Feature;
+use crate::sequence::{reverse_complementary, sanitize_sequence, Direction, Topology};
+use crate::translation::{genetic_code, GeneticCode};
+use crate::verification::span_contains;
+
+/// Codons an ORF may begin with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StartCodons {
+    /// ATG only.
+    #[default]
+    Atg,
+    /// All start codons of the translation table, e.g. GTG and TTG.
+    Alternative,
+    /// Any codon, ORFs run from stop to stop.
+    Any,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrfRequest {
+    pub sequence: String,
+    pub topology: Topology,
+    /// Existing features, ORFs are compared to the CDS features among them.
+    #[serde(default)]
+    pub features: Vec<Feature>,
+    /// Shortest ORF reported, in amino acids without the stop codon.
+    #[serde(default = "default_min_length")]
+    pub min_length: usize,
+    #[serde(default)]
+    pub start_codons: StartCodons,
+    #[serde(default = "default_table")]
+    pub table: u8,
+}
+
+fn default_min_length() -> usize {
+    75
+}
+
+fn default_table() -> u8 {
+    11
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OrfStatus {
+    /// A CDS feature on the same strand ends at the ORF's stop codon.
+    Annotated,
+    /// Overlaps a CDS feature without sharing its stop codon.
+    Overlapping,
+    Unannotated,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Orf {
+    pub strand: Direction,
+    /// 1 to 3 on the top strand, -1 to -3 on the bottom strand, counted from
+    /// the first base of the strand.
+    pub frame: i8,
+    /// Bases from the start codon to the stop codon (1-based, inclusive).
+    /// Spans where the end lies before the start run across the origin.
+    pub span: [usize; 2],
+    /// Amino acids without the stop codon.
+    pub length: usize,
+    pub start_codon: String,
+    pub protein: String,
+    pub status: OrfStatus,
+    /// Labels of the CDS features the ORF matches or overlaps.
+    pub features: Vec<String>,
+    /// CDS feature to add for unannotated ORFs.
+    pub suggestion: Option<Feature>,
+}
+
+/// For every position of a strand, the distance in bases to the first stop
+/// codon in its frame, `None` if the frame has no stop (or, on linear
+/// sequences, runs off the end first).
+fn distances_to_stop(strand: &[u8], circular: bool, code: &GeneticCode) -> Vec<Option<usize>> {
+    let length = strand.len();
+    let codon_at = |i: usize| -> Option<String> {
+        if i + 3 <= length {
+            Some(String::from_utf8_lossy(&strand[i..i + 3]).to_string())
+        } else if circular {
+            Some((i..i + 3).map(|j| strand[j % length] as char).collect())
+        } else {
+            None
+        }
+    };
+    let is_stop: Vec<Option<bool>> = (0..length)
+        .map(|i| codon_at(i).map(|codon| code.is_stop(&codon)))
+        .collect();
+
+    let mut distances: Vec<Option<usize>> = vec![None; length];
+    // A frame can wrap around the origin up to three times before it closes
+    let passes = if circular { 4 } else { 1 };
+    for _ in 0..passes {
+        for i in (0..length).rev() {
+            distances[i] = match is_stop[i] {
+                Some(true) => Some(0),
+                Some(false) if circular || i + 3 < length => distances[(i + 3) % length]
+                    .map(|d| d + 3)
+                    .filter(|d| *d < length),
+                _ => None,
+            };
+        }
+    }
+    distances
+}
+
+/// ORFs of one strand as start (in strand coordinates) and distance to the
+/// stop codon, keeping the longest ORF for every stop codon.
+fn strand_orfs(
+    strand: &[u8],
+    circular: bool,
+    code: &GeneticCode,
+    start_codons: StartCodons,
+    min_length: usize,
+) -> Vec<(usize, usize)> {
+    let length = strand.len();
+    let distances = distances_to_stop(strand, circular, code);
+    let codon_at =
+        |i: usize| -> String { (i..i + 3).map(|j| strand[j % length] as char).collect() };
+
+    let mut longest: HashMap<usize, (usize, usize)> = HashMap::new();
+    for (start, distance) in distances.iter().enumerate() {
+        let Some(distance) = *distance else {
+            continue;
+        };
+        if distance == 0 || distance / 3 < min_length {
+            continue;
+        }
+        let is_start = match start_codons {
+            StartCodons::Atg => codon_at(start) == "ATG",
+            StartCodons::Alternative => code.is_start(&codon_at(start)),
+            // Right behind a stop, or at the beginning of a linear sequence
+            StartCodons::Any => {
+                (!circular && start < 3) || distances[(start + length - 3) % length] == Some(0)
+            }
+        };
+        if is_start {
+            let entry = longest.entry((start + distance) % length).or_default();
+            if distance > entry.1 {
+                *entry = (start, distance);
+            }
+        }
+    }
+    let mut orfs: Vec<(usize, usize)> = longest.into_values().collect();
+    orfs.sort();
+    orfs
+}
+
+/// Find ORFs on both strands and compare them to the CDS features.
+pub fn find_orfs(request: &OrfRequest) -> Result<Vec<Orf>, String> {
+    let sequence = sanitize_sequence(&request.sequence);
+    if sequence.len() < 3 {
+        return Err("The sequence is too short.".to_string());
+    }
+    let code = genetic_code(request.table)
+        .ok_or_else(|| format!("Unknown translation table {}.", request.table))?;
+    let circular = request.topology == Topology::Circular;
+    let length = sequence.len();
+    let cds_features: Vec<&Feature> = request
+        .features
+        .iter()
+        .filter(|f| f.feature_type.eq_ignore_ascii_case("CDS"))
+        .collect();
+
+    let mut orfs = Vec::new();
+    for strand in [Direction::Fwd, Direction::Rev] {
+        let strand_sequence = match strand {
+            Direction::Fwd => sequence.clone(),
+            Direction::Rev => reverse_complementary(&sequence),
+        };
+        let bytes = strand_sequence.as_bytes();
+        let found = strand_orfs(
+            bytes,
+            circular,
+            code,
+            request.start_codons,
+            request.min_length.max(1),
+        );
+        for (start, distance) in found {
+            let stop_end = (start + distance + 2) % length;
+            let coding: String = (start..start + distance)
+                .map(|i| bytes[i % length] as char)
+                .collect();
+            // Strand coordinates to 1-based top strand positions
+            let (span, frame) = match strand {
+                Direction::Fwd => ([start + 1, stop_end + 1], (start % 3) as i8 + 1),
+                Direction::Rev => (
+                    [length - stop_end, length - start],
+                    -((start % 3) as i8 + 1),
+                ),
+            };
+
+            let stop_position = match strand {
+                Direction::Fwd => span[1],
+                Direction::Rev => span[0],
+            };
+            let annotated: Vec<String> = cds_features
+                .iter()
+                .filter(|f| f.directionality == Some(strand))
+                .filter(|f| match strand {
+                    Direction::Fwd => f.span[1] == stop_position,
+                    Direction::Rev => f.span[0] == stop_position,
+                })
+                .map(|f| f.label.clone())
+                .collect();
+            let overlapping: Vec<String> = cds_features
+                .iter()
+                .filter(|f| {
+                    span_contains(f.span, span[0])
+                        || span_contains(f.span, span[1])
+                        || span_contains(span, f.span[0])
+                })
+                .map(|f| f.label.clone())
+                .collect();
+            let (status, features) = if !annotated.is_empty() {
+                (OrfStatus::Annotated, annotated)
+            } else if !overlapping.is_empty() {
+                (OrfStatus::Overlapping, overlapping)
+            } else {
+                (OrfStatus::Unannotated, Vec::new())
+            };
+
+            let protein = code.translate(&coding, true);
+            let suggestion = (status == OrfStatus::Unannotated).then(|| {
+                let mut qualifiers = vec![("translation".to_string(), protein.clone())];
+                if code.id != 11 {
+                    qualifiers.push(("transl_table".to_string(), code.id.to_string()));
+                }
+                Feature {
+                    label: format!("ORF {}..{}", span[0], span[1]),
+                    feature_type: "CDS".to_string(),
+                    span,
+                    directionality: Some(strand),
+                    segments: Vec::new(),
+                    qualifiers,
+                }
+            });
+            orfs.push(Orf {
+                strand,
+                frame,
+                span,
+                length: protein.len(),
+                start_codon: coding.get(..3).unwrap_or_default().to_string(),
+                protein,
+                status,
+                features,
+                suggestion,
+            });
+        }
+    }
+    orfs.sort_by(|a, b| b.length.cmp(&a.length).then(a.span.cmp(&b.span)));
+    Ok(orfs)
+}
+
+#[tauri::command]
+/// Open reading frames on both strands, with CDS features to add for the
+/// unannotated ones.
+pub fn find_open_reading_frames(request: OrfRequest) -> Result<Vec<Orf>, String> {
+    find_orfs(&request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stop codons in every frame of both strands, without start codons.
+    const FILLER: &str = "CTAGCTAGCTAGCTAGCTAGCTAGCTAGCTAGCTAGCTAG";
+
+    fn orf() -> String {
+        format!("ATG{}TAA", "GCT".repeat(9))
+    }
+
+    fn request(sequence: String, topology: Topology, start_codons: StartCodons) -> OrfRequest {
+        OrfRequest {
+            sequence,
+            topology,
+            features: Vec::new(),
+            min_length: 5,
+            start_codons,
+            table: 11,
+        }
+    }
+
+    fn summary(orfs: &[Orf]) -> Vec<(Direction, i8, [usize; 2], &str, &str)> {
+        orfs.iter()
+            .map(|o| {
+                (
+                    o.strand,
+                    o.frame,
+                    o.span,
+                    o.start_codon.as_str(),
+                    o.protein.as_str(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn orf_across_the_origin() {
+        let orf = orf();
+        let sequence = format!("{}{}{}", &orf[10..], FILLER, &orf[..10]);
+        let circular = request(sequence.clone(), Topology::Circular, StartCodons::Atg);
+        let circular = find_orfs(&circular).unwrap();
+        assert_eq!(
+            summary(&circular),
+            [(Direction::Fwd, 1, [64, 23], "ATG", "MAAAAAAAAA")]
+        );
+        assert_eq!(circular[0].status, OrfStatus::Unannotated);
+        assert_eq!(
+            circular[0].suggestion.as_ref().map(|f| f.span),
+            Some([64, 23])
+        );
+
+        let linear = find_orfs(&request(sequence, Topology::Linear, StartCodons::Atg));
+        assert!(linear.unwrap().is_empty());
+    }
+
+    #[test]
+    fn reverse_strand_orf() {
+        let sequence = format!("{}{}{}", FILLER, reverse_complementary(&orf()), FILLER);
+        let mut request = request(sequence, Topology::Linear, StartCodons::Atg);
+        request.features = vec![Feature {
+            label: "gene".to_string(),
+            feature_type: "CDS".to_string(),
+            span: [41, 73],
+            directionality: Some(Direction::Rev),
+            segments: Vec::new(),
+            qualifiers: Vec::new(),
+        }];
+        let orfs = find_orfs(&request).unwrap();
+        assert_eq!(
+            summary(&orfs),
+            [(Direction::Rev, -2, [41, 73], "ATG", "MAAAAAAAAA")]
+        );
+        assert_eq!(orfs[0].status, OrfStatus::Annotated);
+        assert_eq!(orfs[0].features, ["gene"]);
+        assert!(orfs[0].suggestion.is_none());
+    }
+
+    #[test]
+    fn alternative_start_codons() {
+        let orf = format!("TTG{}GTG{}TAA", "GCC".repeat(3), "GCC".repeat(6));
+        let sequence = format!("{}{}{}", FILLER, orf, FILLER);
+        let atg = find_orfs(&request(
+            sequence.clone(),
+            Topology::Linear,
+            StartCodons::Atg,
+        ));
+        assert!(atg.unwrap().is_empty());
+
+        let alternative = find_orfs(&request(
+            sequence,
+            Topology::Linear,
+            StartCodons::Alternative,
+        ))
+        .unwrap();
+        assert_eq!(
+            summary(&alternative),
+            [(Direction::Fwd, 2, [41, 76], "TTG", "MAAAVAAAAAA")]
+        );
+    }
+}
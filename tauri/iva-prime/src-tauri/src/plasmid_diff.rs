@@ -0,0 +1,713 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::alignment::{encode_kmer, global_alignment, AlignOp, AlignmentScoring};
+use crate::plasmid_file::{Feature, PlasmidFile};
+use crate::sequence::{reverse_complementary, sanitize_sequence, Direction, Topology};
+use crate::verification::{features_at, format_span};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DiffSettings {
+    /// Length of the unique k-mers anchoring the alignment.
+    pub kmer_length: usize,
+    /// Largest stretch between anchors (query times reference bases) that is
+    /// aligned base by base. Larger ones are reported as one replacement.
+    pub max_gap_cells: usize,
+    pub scoring: AlignmentScoring,
+}
+
+impl Default for DiffSettings {
+    fn default() -> Self {
+        Self {
+            kmer_length: 16,
+            max_gap_cells: 4_000_000,
+            scoring: AlignmentScoring::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffRequest {
+    /// Plasmid compared from, e.g. the parent vector.
+    pub from: PlasmidFile,
+    /// Plasmid compared to, e.g. the designed product.
+    pub to: PlasmidFile,
+    #[serde(default)]
+    pub settings: DiffSettings,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditKind {
+    Substitution,
+    Insertion,
+    Deletion,
+    /// Bases replaced by a different number of other bases.
+    Replacement,
+}
+
+/// Sequence difference between the two plasmids.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequenceEdit {
+    pub kind: EditKind,
+    /// Affected bases of the first plasmid (1-based, inclusive). Insertions
+    /// sit between the two bases of the span.
+    pub from_span: [usize; 2],
+    /// Affected bases of the second plasmid, deletions sit between the two
+    /// bases of the span.
+    pub to_span: [usize; 2],
+    pub from_bases: String,
+    /// Bases of the second plasmid, in the orientation of the first.
+    pub to_bases: String,
+    /// Labels of the features of the first plasmid the edit lies in.
+    pub features: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FeatureChange {
+    Added,
+    Removed,
+    /// The feature no longer covers the bases it covered before.
+    Moved,
+    /// Same place, different qualifiers.
+    Modified,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QualifierChange {
+    pub key: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureDiff {
+    pub change: FeatureChange,
+    pub label: String,
+    pub feature_type: String,
+    pub from_span: Option<[usize; 2]>,
+    pub to_span: Option<[usize; 2]>,
+    /// Where the feature of the first plasmid lands in the second.
+    pub expected_span: Option<[usize; 2]>,
+    pub qualifiers: Vec<QualifierChange>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlasmidDiff {
+    pub identical: bool,
+    /// The second plasmid is the reverse complement of the first.
+    pub reversed: bool,
+    /// Position in the second plasmid of the first plasmid's first base, if
+    /// it aligns.
+    pub origin_offset: Option<usize>,
+    /// Fraction of alignment columns that are matches.
+    pub identity: f64,
+    pub edits: Vec<SequenceEdit>,
+    pub features: Vec<FeatureDiff>,
+    /// Plain text version of the result.
+    pub report: String,
+}
+
+/// K-mers occurring exactly once, with their start.
+fn unique_kmers(sequence: &[u8], k: usize, circular: bool) -> HashMap<u64, usize> {
+    let length = sequence.len();
+    if length < k {
+        return HashMap::new();
+    }
+    let starts = if circular { length } else { length - k + 1 };
+    let mut counts: HashMap<u64, (usize, usize)> = HashMap::new();
+    for start in 0..starts {
+        let kmer: Vec<u8> = (start..start + k).map(|i| sequence[i % length]).collect();
+        if let Some(code) = encode_kmer(&kmer) {
+            counts.entry(code).or_insert((0, start)).0 += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .filter(|(_, (count, _))| *count == 1)
+        .map(|(code, (_, start))| (code, start))
+        .collect()
+}
+
+/// Shared unique k-mers as (first, second) starts.
+fn shared_kmers(first: &HashMap<u64, usize>, second: &HashMap<u64, usize>) -> Vec<(usize, usize)> {
+    let mut anchors: Vec<(usize, usize)> = first
+        .iter()
+        .filter_map(|(code, &i)| second.get(code).map(|&j| (i, j)))
+        .collect();
+    anchors.sort_unstable();
+    anchors
+}
+
+/// Longest chain of anchors increasing in both sequences.
+fn chain_anchors(anchors: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    // Patience sorting on the second coordinate, anchors come sorted by the
+    // first
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous: Vec<Option<usize>> = vec![None; anchors.len()];
+    for (n, &(_, j)) in anchors.iter().enumerate() {
+        let slot = tails.partition_point(|&t| anchors[t].1 < j);
+        if slot > 0 {
+            previous[n] = Some(tails[slot - 1]);
+        }
+        if slot == tails.len() {
+            tails.push(n);
+        } else {
+            tails[slot] = n;
+        }
+    }
+    let mut chain = Vec::new();
+    let mut current = tails.last().copied();
+    while let Some(n) = current {
+        chain.push(anchors[n]);
+        current = previous[n];
+    }
+    chain.reverse();
+    chain
+}
+
+/// Align the stretch between two anchors, `second` as query.
+fn fill_gap(first: &[u8], second: &[u8], settings: &DiffSettings, ops: &mut Vec<AlignOp>) {
+    if first.is_empty() || second.is_empty() || first.len() * second.len() > settings.max_gap_cells
+    {
+        ops.extend(std::iter::repeat_n(AlignOp::Deletion, first.len()));
+        ops.extend(std::iter::repeat_n(AlignOp::Insertion, second.len()));
+    } else {
+        ops.extend(global_alignment(second, first, &settings.scoring));
+    }
+}
+
+/// Alignment of the two sequences, as oriented and rotated by the caller,
+/// from anchor to anchor.
+fn anchored_alignment(first: &[u8], second: &[u8], settings: &DiffSettings) -> Vec<AlignOp> {
+    let k = settings.kmer_length.clamp(8, 32);
+    let anchors = shared_kmers(
+        &unique_kmers(first, k, false),
+        &unique_kmers(second, k, false),
+    );
+    let chain = chain_anchors(&anchors);
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for (a, b) in chain {
+        if a < i || b < j {
+            continue;
+        }
+        fill_gap(&first[i..a], &second[j..b], settings, &mut ops);
+        ops.push(AlignOp::Match);
+        i = a + 1;
+        j = b + 1;
+    }
+    fill_gap(&first[i..], &second[j..], settings, &mut ops);
+    ops
+}
+
+fn qualifier_changes(from: &Feature, to: &Feature) -> Vec<QualifierChange> {
+    let collect = |feature: &Feature| {
+        let mut values: Vec<(String, String)> = Vec::new();
+        for (key, value) in &feature.qualifiers {
+            match values.iter_mut().find(|(k, _)| k == key) {
+                Some((_, existing)) => {
+                    existing.push_str("; ");
+                    existing.push_str(value);
+                }
+                None => values.push((key.clone(), value.clone())),
+            }
+        }
+        values
+    };
+    let from_values = collect(from);
+    let to_values = collect(to);
+    let lookup = |values: &[(String, String)], key: &str| {
+        values
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.clone())
+    };
+    let mut keys: Vec<&String> = from_values.iter().map(|(k, _)| k).collect();
+    for (key, _) in &to_values {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    keys.into_iter()
+        .filter_map(|key| {
+            let from = lookup(&from_values, key);
+            let to = lookup(&to_values, key);
+            (from != to).then(|| QualifierChange {
+                key: key.clone(),
+                from,
+                to,
+            })
+        })
+        .collect()
+}
+
+/// Compare the features of both plasmids, with the first plasmid's features
+/// carried over to the second through the alignment.
+fn feature_diffs(
+    from: &PlasmidFile,
+    to: &PlasmidFile,
+    mapped: &[Option<usize>],
+    reversed: bool,
+) -> Vec<FeatureDiff> {
+    let from_length = from.sequence.len();
+    let to_length = to.sequence.len();
+    let mut matched = vec![false; to.features.len()];
+    let mut diffs = Vec::new();
+    for feature in &from.features {
+        let [start, end] = feature.span;
+        let start = start.max(1);
+        let covered: Vec<usize> = if start <= end {
+            (start - 1..end.min(from_length)).collect()
+        } else {
+            (start - 1..from_length).chain(0..end).collect()
+        };
+        let first = covered
+            .iter()
+            .find_map(|&i| mapped.get(i).copied().flatten());
+        let last = covered
+            .iter()
+            .rev()
+            .find_map(|&i| mapped.get(i).copied().flatten());
+        let expected = match (first, last) {
+            (Some(first), Some(last)) if reversed => Some([last + 1, first + 1]),
+            (Some(first), Some(last)) => Some([first + 1, last + 1]),
+            _ => None,
+        };
+        let directionality = match (feature.directionality, reversed) {
+            (Some(Direction::Fwd), true) => Some(Direction::Rev),
+            (Some(Direction::Rev), true) => Some(Direction::Fwd),
+            (directionality, _) => directionality,
+        };
+
+        // Features of the second plasmid with the same label and type, the
+        // one at the expected place or else the closest
+        let distance = |span: [usize; 2]| match expected {
+            Some(expected) => {
+                let d = span[0].abs_diff(expected[0]);
+                d.min(to_length - d.min(to_length))
+            }
+            None => usize::MAX,
+        };
+        let candidate = to
+            .features
+            .iter()
+            .enumerate()
+            .filter(|(n, f)| {
+                !matched[*n] && f.label == feature.label && f.feature_type == feature.feature_type
+            })
+            .min_by_key(|(_, f)| {
+                let same_place = Some(f.span) == expected && f.directionality == directionality;
+                (!same_place, distance(f.span))
+            });
+
+        let Some((n, counterpart)) = candidate else {
+            diffs.push(FeatureDiff {
+                change: FeatureChange::Removed,
+                label: feature.label.clone(),
+                feature_type: feature.feature_type.clone(),
+                from_span: Some(feature.span),
+                to_span: None,
+                expected_span: expected,
+                qualifiers: Vec::new(),
+            });
+            continue;
+        };
+        matched[n] = true;
+        let qualifiers = qualifier_changes(feature, counterpart);
+        let moved =
+            Some(counterpart.span) != expected || counterpart.directionality != directionality;
+        let change = if moved {
+            FeatureChange::Moved
+        } else if !qualifiers.is_empty() {
+            FeatureChange::Modified
+        } else {
+            continue;
+        };
+        diffs.push(FeatureDiff {
+            change,
+            label: feature.label.clone(),
+            feature_type: feature.feature_type.clone(),
+            from_span: Some(feature.span),
+            to_span: Some(counterpart.span),
+            expected_span: expected,
+            qualifiers,
+        });
+    }
+    for (n, feature) in to.features.iter().enumerate() {
+        if !matched[n] {
+            diffs.push(FeatureDiff {
+                change: FeatureChange::Added,
+                label: feature.label.clone(),
+                feature_type: feature.feature_type.clone(),
+                from_span: None,
+                to_span: Some(feature.span),
+                expected_span: None,
+                qualifiers: Vec::new(),
+            });
+        }
+    }
+    diffs
+}
+
+/// Compare two plasmids. Circular plasmids are compared independently of
+/// where their origin lies, and either plasmid may be the reverse complement
+/// of the other.
+pub fn diff_plasmids(request: &DiffRequest) -> Result<PlasmidDiff, String> {
+    let mut from = request.from.clone();
+    let mut to = request.to.clone();
+    from.sequence = sanitize_sequence(&from.sequence);
+    to.sequence = sanitize_sequence(&to.sequence);
+    if from.sequence.is_empty() || to.sequence.is_empty() {
+        return Err("Both plasmids need a sequence.".to_string());
+    }
+    let settings = &request.settings;
+    let k = settings.kmer_length.clamp(8, 32);
+    let circular = from.topology == Topology::Circular && to.topology == Topology::Circular;
+    let from_length = from.sequence.len();
+    let to_length = to.sequence.len();
+
+    // Orientation with the most shared unique k-mers
+    let from_kmers = unique_kmers(from.sequence.as_bytes(), k, circular);
+    let forward = shared_kmers(
+        &from_kmers,
+        &unique_kmers(to.sequence.as_bytes(), k, circular),
+    );
+    let to_reversed = reverse_complementary(&to.sequence);
+    let reverse = shared_kmers(
+        &from_kmers,
+        &unique_kmers(to_reversed.as_bytes(), k, circular),
+    );
+    let reversed = reverse.len() > forward.len();
+    let (oriented, anchors) = if reversed {
+        (to_reversed, reverse)
+    } else {
+        (to.sequence.clone(), forward)
+    };
+
+    // Start both sequences at an anchor on the most common diagonal, so that
+    // edits across the origin stay in one piece
+    let (from_start, to_start) = if circular {
+        let mut diagonals: HashMap<usize, Vec<(usize, usize)>> = HashMap::new();
+        for &(i, j) in &anchors {
+            let diagonal = (j as i64 - i as i64).rem_euclid(to_length as i64) as usize;
+            diagonals.entry(diagonal).or_default().push((i, j));
+        }
+        diagonals
+            .into_values()
+            .max_by_key(|a| (a.len(), std::cmp::Reverse(a[0])))
+            .map(|a| a[0])
+            .unwrap_or((0, 0))
+    } else {
+        (0, 0)
+    };
+    let rotate =
+        |sequence: &str, start: usize| format!("{}{}", &sequence[start..], &sequence[..start]);
+    let first = rotate(&from.sequence, from_start);
+    let second = rotate(&oriented, to_start);
+    let ops = anchored_alignment(first.as_bytes(), second.as_bytes(), settings);
+
+    // Rotated positions back to 0-based positions of the plasmids
+    let from_position = |i: usize| (i + from_start) % from_length;
+    let to_position = |j: usize| {
+        let oriented = (j + to_start) % to_length;
+        if reversed {
+            to_length - 1 - oriented
+        } else {
+            oriented
+        }
+    };
+    let to_span = |j_first: usize, j_last: usize| {
+        let (a, b) = (to_position(j_first) + 1, to_position(j_last) + 1);
+        if reversed {
+            [b, a]
+        } else {
+            [a, b]
+        }
+    };
+    // Bases on both sides of a gap, clamped at the ends of linear sequences
+    let between = |position: usize, length: usize| -> [usize; 2] {
+        let before = (position + length - 1) % length;
+        if !circular && position == 0 {
+            [0, 0]
+        } else if !circular && position >= length {
+            [length - 1, length - 1]
+        } else {
+            [before, position % length]
+        }
+    };
+
+    let mut mapped: Vec<Option<usize>> = vec![None; from_length];
+    let mut edits = Vec::new();
+    let mut matches = 0;
+    let (mut i, mut j) = (0, 0);
+    let mut n = 0;
+    while n < ops.len() {
+        if ops[n] == AlignOp::Match {
+            mapped[from_position(i)] = Some(to_position(j));
+            matches += 1;
+            i += 1;
+            j += 1;
+            n += 1;
+            continue;
+        }
+        let (i_start, j_start) = (i, j);
+        let mut kinds = Vec::new();
+        while n < ops.len() && ops[n] != AlignOp::Match {
+            match ops[n] {
+                AlignOp::Mismatch => {
+                    mapped[from_position(i)] = Some(to_position(j));
+                    i += 1;
+                    j += 1;
+                }
+                AlignOp::Deletion => i += 1,
+                _ => j += 1,
+            }
+            kinds.push(ops[n]);
+            n += 1;
+        }
+        let kind = if kinds.iter().all(|op| *op == AlignOp::Mismatch) {
+            EditKind::Substitution
+        } else if kinds.iter().all(|op| *op == AlignOp::Insertion) {
+            EditKind::Insertion
+        } else if kinds.iter().all(|op| *op == AlignOp::Deletion) {
+            EditKind::Deletion
+        } else {
+            EditKind::Replacement
+        };
+        let from_span = if i > i_start {
+            [from_position(i_start) + 1, from_position(i - 1) + 1]
+        } else {
+            let [before, after] = between(i_start, from_length);
+            [from_position(before) + 1, from_position(after) + 1]
+        };
+        let to_span = if j > j_start {
+            to_span(j_start, j - 1)
+        } else {
+            let [before, after] = between(j_start, to_length);
+            to_span(before, after)
+        };
+        edits.push(SequenceEdit {
+            kind,
+            from_span,
+            to_span,
+            from_bases: first[i_start..i].to_string(),
+            to_bases: second[j_start..j].to_string(),
+            features: features_at(&from.features, from_span),
+        });
+    }
+    edits.sort_by_key(|e| e.from_span[0]);
+
+    let features = feature_diffs(&from, &to, &mapped, reversed);
+    let mut diff = PlasmidDiff {
+        identical: edits.is_empty() && features.is_empty(),
+        reversed,
+        origin_offset: mapped[0].map(|p| p + 1),
+        identity: if ops.is_empty() {
+            0.0
+        } else {
+            matches as f64 / ops.len() as f64
+        },
+        edits,
+        features,
+        report: String::new(),
+    };
+    diff.report = diff_report_text(&from.name, &to.name, &diff);
+    Ok(diff)
+}
+
+fn or_dash(bases: &str) -> &str {
+    if bases.is_empty() {
+        "-"
+    } else {
+        bases
+    }
+}
+
+/// Bases shown in the text report, long edits are shortened.
+fn shorten(bases: &str) -> String {
+    if bases.len() > 30 {
+        format!(
+            "{}...{} ({} bp)",
+            &bases[..12],
+            &bases[bases.len() - 12..],
+            bases.len()
+        )
+    } else {
+        or_dash(bases).to_string()
+    }
+}
+
+/// Plain text report of a plasmid comparison.
+pub fn diff_report_text(from_name: &str, to_name: &str, diff: &PlasmidDiff) -> String {
+    let mut lines = vec![
+        format!("Comparison of \"{}\" to \"{}\"", from_name, to_name),
+        format!(
+            "Identity: {:.2}%{}",
+            100.0 * diff.identity,
+            if diff.reversed {
+                ", reverse complemented"
+            } else {
+                ""
+            }
+        ),
+    ];
+    if let Some(offset) = diff.origin_offset {
+        lines.push(format!("Origin of \"{}\" at {}", from_name, offset));
+    }
+    if diff.identical {
+        lines.push(String::new());
+        lines.push("No differences.".to_string());
+        return lines.join("\n");
+    }
+    if !diff.edits.is_empty() {
+        lines.push(String::new());
+        lines.push("Sequence edits:".to_string());
+        for e in &diff.edits {
+            lines.push(format!(
+                "  {:?} at {} / {}: {} -> {}{}",
+                e.kind,
+                format_span(e.from_span),
+                format_span(e.to_span),
+                shorten(&e.from_bases),
+                shorten(&e.to_bases),
+                if e.features.is_empty() {
+                    String::new()
+                } else {
+                    format!(", in {}", e.features.join(", "))
+                }
+            ));
+        }
+    }
+    if !diff.features.is_empty() {
+        lines.push(String::new());
+        lines.push("Features:".to_string());
+        let span = |span: Option<[usize; 2]>| span.map(format_span).unwrap_or("-".to_string());
+        for f in &diff.features {
+            lines.push(format!(
+                "  {:?} {} ({}): {} -> {}",
+                f.change,
+                f.label,
+                f.feature_type,
+                span(f.from_span),
+                span(f.to_span)
+            ));
+            for q in &f.qualifiers {
+                lines.push(format!(
+                    "    /{}: {} -> {}",
+                    q.key,
+                    q.from.as_deref().unwrap_or("-"),
+                    q.to.as_deref().unwrap_or("-")
+                ));
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+#[tauri::command]
+/// Sequence and feature differences between two plasmids.
+pub fn compare_plasmids(request: DiffRequest) -> Result<PlasmidDiff, String> {
+    diff_plasmids(&request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequence::tests::RANDOM_PLASMID;
+
+    fn plasmid(sequence: String, features: Vec<Feature>) -> PlasmidFile {
+        PlasmidFile {
+            name: "Plasmid".to_string(),
+            sequence,
+            topology: Topology::Circular,
+            features,
+        }
+    }
+
+    fn feature(span: [usize; 2], directionality: Direction) -> Feature {
+        Feature {
+            label: "ori".to_string(),
+            feature_type: "rep_origin".to_string(),
+            span,
+            directionality: Some(directionality),
+            segments: Vec::new(),
+            qualifiers: Vec::new(),
+        }
+    }
+
+    fn diff(from: PlasmidFile, to: PlasmidFile) -> PlasmidDiff {
+        diff_plasmids(&DiffRequest {
+            from,
+            to,
+            settings: DiffSettings::default(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn rotated_and_reversed_copies_are_identical() {
+        let sequence = RANDOM_PLASMID.concat();
+        let rotated = format!("{}{}", &sequence[100..], &sequence[..100]);
+        let from = plasmid(sequence, vec![feature([11, 40], Direction::Fwd)]);
+
+        let result = diff(from.clone(), plasmid(rotated.clone(), Vec::new()));
+        assert!(result.edits.is_empty());
+        assert!(!result.reversed);
+        assert_eq!(result.origin_offset, Some(301));
+        assert_eq!(result.identity, 1.0);
+
+        // The feature sits on the bottom strand of the reverse complement
+        let to = plasmid(
+            reverse_complementary(&rotated),
+            vec![feature([61, 90], Direction::Rev)],
+        );
+        let result = diff(from, to);
+        assert!(result.identical);
+        assert!(result.reversed);
+        assert_eq!(result.origin_offset, Some(100));
+    }
+
+    #[test]
+    fn edits_of_a_rotated_copy() {
+        let sequence = RANDOM_PLASMID.concat();
+        // C51G and CC inserted between bases 200 and 201
+        let mutant = format!(
+            "{}G{}CC{}",
+            &sequence[..50],
+            &sequence[51..200],
+            &sequence[200..]
+        );
+        let rotated = format!("{}{}", &mutant[100..], &mutant[..100]);
+        let result = diff(plasmid(sequence, Vec::new()), plasmid(rotated, Vec::new()));
+        assert_eq!(result.origin_offset, Some(303));
+        let edits: Vec<_> = result
+            .edits
+            .iter()
+            .map(|e| {
+                (
+                    e.kind,
+                    e.from_span,
+                    e.to_span,
+                    e.from_bases.as_str(),
+                    e.to_bases.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            edits,
+            [
+                (EditKind::Substitution, [51, 51], [353, 353], "C", "G"),
+                (EditKind::Insertion, [200, 201], [101, 102], "", "CC"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,511 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::sequence::{sanitize_sequence, Direction, Topology};
+
+/// Annotated feature, same shape as the frontend's feature dictionaries.
+/// Spans where the end lies before the start run across the origin.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Feature {
+    pub label: String,
+    #[serde(rename = "type")]
+    pub feature_type: String,
+    pub span: [usize; 2],
+    /// `None` for features without an orientation.
+    #[serde(default)]
+    pub directionality: Option<Direction>,
+    /// Parts of `join()` locations in top strand order, empty for features
+    /// made of a single span.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub segments: Vec<[usize; 2]>,
+    /// Remaining qualifiers in file order, without the label.
+    #[serde(default)]
+    pub qualifiers: Vec<(String, String)>,
+}
+
+/// Plasmid read from or written to disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlasmidFile {
+    pub name: String,
+    pub sequence: String,
+    pub topology: Topology,
+    pub features: Vec<Feature>,
+}
+
+/// Region of a plasmid, picked by span or by feature.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RegionSelection {
+    /// 1-based, inclusive. Spans where the end lies before the start run across
+    /// the origin of circular plasmids.
+    Span([usize; 2]),
+    /// Label of a single feature, matched ignoring case.
+    Feature(String),
+}
+
+/// Qualifiers used as the feature label, in order of preference.
+const LABEL_QUALIFIERS: [&str; 5] = ["label", "gene", "product", "note", "locus_tag"];
+
+impl PlasmidFile {
+    /// Features whose label matches, ignoring case.
+    pub fn features_labelled<'a>(&'a self, label: &'a str) -> impl Iterator<Item = &'a Feature> {
+        self.features
+            .iter()
+            .filter(move |f| f.label.eq_ignore_ascii_case(label))
+    }
+
+    /// Span of a selected region.
+    pub fn resolve_region(&self, region: &RegionSelection) -> Result<[usize; 2], String> {
+        match region {
+            RegionSelection::Span([start, end]) => {
+                let in_bounds = |i: usize| i >= 1 && i <= self.sequence.len();
+                if !in_bounds(*start) || !in_bounds(*end) {
+                    return Err(format!(
+                        "Span [{}, {}] is out of bounds for \"{}\".",
+                        start, end, self.name
+                    ));
+                }
+                if end < start && self.topology == Topology::Linear {
+                    return Err(format!(
+                        "Span [{}, {}] crosses the end of linear plasmid \"{}\".",
+                        start, end, self.name
+                    ));
+                }
+                Ok([*start, *end])
+            }
+            RegionSelection::Feature(label) => {
+                let matches: Vec<&Feature> = self.features_labelled(label).collect();
+                match matches.as_slice() {
+                    [feature] => Ok(feature.span),
+                    [] => Err(format!(
+                        "\"{}\" has no feature labelled \"{}\".",
+                        self.name, label
+                    )),
+                    _ => Err(format!(
+                        "\"{}\" has {} features labelled \"{}\", use a span instead.",
+                        self.name,
+                        matches.len(),
+                        label
+                    )),
+                }
+            }
+        }
+    }
+}
+
+/// Whether a path has the extension of a plasmid file `read_plasmid_file`
+/// can read.
+pub fn is_plasmid_file(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    matches!(
+        extension.as_str(),
+        "gb" | "gbk" | "genbank" | "dna" | "fasta" | "fa" | "fas" | "fna"
+    )
+}
+
+/// Read a plasmid file, picking the parser from the file extension
+/// (`.gb`, `.gbk`, `.dna`, `.fasta`, `.fa`).
+pub fn read_plasmid_file(path: &Path) -> Result<PlasmidFile, String> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let name = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Untitled".to_string());
+    let bytes =
+        fs::read(path).map_err(|e| format!("Could not read \"{}\": {}", path.display(), e))?;
+
+    let plasmid = match extension.as_str() {
+        "gb" | "gbk" | "genbank" => parse_genbank(&String::from_utf8_lossy(&bytes))?,
+        "dna" => parse_snapgene(&bytes, &name)?,
+        "fasta" | "fa" | "fas" | "fna" => parse_fasta(&String::from_utf8_lossy(&bytes), &name)?,
+        other => return Err(format!("Unsupported file extension \".{}\".", other)),
+    };
+    if plasmid.sequence.is_empty() {
+        return Err(format!("\"{}\" contains no sequence.", path.display()));
+    }
+    Ok(plasmid)
+}
+
+/// Span, orientation and `join()` parts of a GenBank location.
+type Location = ([usize; 2], Option<Direction>, Vec<[usize; 2]>);
+
+/// Parse a GenBank location into a span, orientation and parts. The span of
+/// joined locations runs over their outermost bases; fuzzy ends (`<`, `>`) are
+/// taken as is.
+fn parse_location(location: &str) -> Option<Location> {
+    let directionality = if location.contains("complement(") {
+        Direction::Rev
+    } else {
+        Direction::Fwd
+    };
+    let mut parts: Vec<[usize; 2]> = location
+        .split(',')
+        .filter_map(|part| {
+            let numbers: Vec<usize> = part
+                .split(|c: char| !c.is_ascii_digit())
+                .filter(|s| !s.is_empty())
+                .filter_map(|s| s.parse().ok())
+                .collect();
+            Some([*numbers.first()?, *numbers.last()?])
+        })
+        .collect();
+    // join(complement(..),complement(..)) lists the parts in reading order
+    if directionality == Direction::Rev && !location.trim_start().starts_with("complement(") {
+        parts.reverse();
+    }
+    let span = [parts.first()?[0], parts.last()?[1]];
+    let segments = if parts.len() > 1 { parts } else { Vec::new() };
+    Some((span, Some(directionality), segments))
+}
+
+fn format_location(feature: &Feature, sequence_length: usize) -> String {
+    let [start, end] = feature.span;
+    let range = |a: usize, b: usize| {
+        if a == b {
+            a.to_string()
+        } else {
+            format!("{}..{}", a, b)
+        }
+    };
+    let location = if !feature.segments.is_empty() {
+        let parts: Vec<String> = feature
+            .segments
+            .iter()
+            .map(|[a, b]| range(*a, *b))
+            .collect();
+        format!("join({})", parts.join(","))
+    } else if start <= end {
+        range(start, end)
+    } else {
+        format!("join({},{})", range(start, sequence_length), range(1, end))
+    };
+    match feature.directionality {
+        Some(Direction::Rev) => format!("complement({})", location),
+        _ => location,
+    }
+}
+
+/// Parse the first record of a GenBank file.
+pub fn parse_genbank(text: &str) -> Result<PlasmidFile, String> {
+    let mut lines = text.lines();
+    let locus = lines
+        .by_ref()
+        .find(|l| l.starts_with("LOCUS"))
+        .ok_or("GenBank file has no LOCUS line.")?;
+    let name = locus
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or("Untitled")
+        .to_string();
+    let topology = if locus.to_ascii_lowercase().contains("circular") {
+        Topology::Circular
+    } else {
+        Topology::Linear
+    };
+
+    let mut features: Vec<Feature> = Vec::new();
+    let mut sequence = String::new();
+    let mut in_features = false;
+    let mut in_origin = false;
+    // Qualifier currently being read, continued over several lines
+    let mut qualifier: Option<(String, String)> = None;
+    let mut location = String::new();
+
+    let finish_qualifier = |features: &mut Vec<Feature>,
+                            qualifier: &mut Option<(String, String)>| {
+        if let (Some(feature), Some((key, value))) = (features.last_mut(), qualifier.take()) {
+            let value = value.trim_matches('"').replace("\"\"", "\"");
+            if feature.label.is_empty() && key == "label" {
+                feature.label = value;
+            } else {
+                feature.qualifiers.push((key, value));
+            }
+        }
+    };
+
+    for line in lines {
+        if line.starts_with("//") {
+            break;
+        }
+        if in_origin {
+            sequence.push_str(&sanitize_sequence(line));
+            continue;
+        }
+        if line.starts_with("ORIGIN") {
+            finish_qualifier(&mut features, &mut qualifier);
+            in_features = false;
+            in_origin = true;
+            continue;
+        }
+        if line.starts_with("FEATURES") {
+            in_features = true;
+            continue;
+        }
+        if !in_features {
+            continue;
+        }
+        if !line.starts_with(' ') {
+            finish_qualifier(&mut features, &mut qualifier);
+            in_features = false;
+            continue;
+        }
+
+        let key = line.get(5..21).unwrap_or_default().trim();
+        let content = line.get(21..).unwrap_or_default().trim_end();
+        if !key.is_empty() {
+            // New feature
+            finish_qualifier(&mut features, &mut qualifier);
+            location = content.to_string();
+            features.push(Feature {
+                label: String::new(),
+                feature_type: key.to_string(),
+                span: [0, 0],
+                directionality: None,
+                segments: Vec::new(),
+                qualifiers: Vec::new(),
+            });
+        } else if let Some(stripped) = content.strip_prefix('/') {
+            finish_qualifier(&mut features, &mut qualifier);
+            let (key, value) = stripped.split_once('=').unwrap_or((stripped, ""));
+            qualifier = Some((key.to_string(), value.to_string()));
+        } else if let Some((key, value)) = qualifier.as_mut() {
+            // Continued qualifier, translations are wrapped without spaces
+            if key != "translation" {
+                value.push(' ');
+            }
+            value.push_str(content.trim_start());
+            continue;
+        } else {
+            // Continued location
+            location.push_str(content.trim_start());
+        }
+
+        if let Some(feature) = features.last_mut() {
+            if let Some((span, directionality, segments)) = parse_location(&location) {
+                feature.span = span;
+                feature.directionality = directionality;
+                feature.segments = segments;
+            }
+        }
+    }
+
+    for feature in &mut features {
+        if feature.label.is_empty() {
+            feature.label = LABEL_QUALIFIERS
+                .iter()
+                .find_map(|key| {
+                    feature
+                        .qualifiers
+                        .iter()
+                        .find(|(k, _)| k == key)
+                        .map(|(_, v)| v.clone())
+                })
+                .unwrap_or_else(|| feature.feature_type.clone());
+        }
+    }
+    features.retain(|f| f.feature_type != "source" && f.span[0] > 0);
+
+    Ok(PlasmidFile {
+        name,
+        sequence,
+        topology,
+        features,
+    })
+}
+
+/// Parse the first record of a FASTA file. FASTA carries no topology, so
+/// the plasmid is assumed to be circular.
+pub fn parse_fasta(text: &str, fallback_name: &str) -> Result<PlasmidFile, String> {
+    let mut lines = text.lines().skip_while(|l| l.trim().is_empty()).peekable();
+    let name = match lines.peek() {
+        Some(header) if header.starts_with('>') => {
+            let name = header[1..]
+                .split_whitespace()
+                .next()
+                .unwrap_or(fallback_name);
+            let name = name.to_string();
+            lines.next();
+            name
+        }
+        _ => fallback_name.to_string(),
+    };
+    let sequence = lines
+        .take_while(|l| !l.starts_with('>'))
+        .map(sanitize_sequence)
+        .collect();
+    Ok(PlasmidFile {
+        name,
+        sequence,
+        topology: Topology::Circular,
+        features: Vec::new(),
+    })
+}
+
+/// Value of an XML attribute inside a single tag.
+fn xml_attribute(tag: &str, name: &str) -> Option<String> {
+    let needle = format!(" {}=\"", name);
+    let start = tag.find(&needle)? + needle.len();
+    let end = tag[start..].find('"')? + start;
+    Some(
+        tag[start..end]
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&"),
+    )
+}
+
+/// Features from the XML features packet of a SnapGene file.
+fn snapgene_features(xml: &str) -> Vec<Feature> {
+    // Split on the tag name only, so the leading space stays with the attributes
+    xml.split("<Feature")
+        .skip(1)
+        .filter(|block| block.starts_with(' '))
+        .filter_map(|block| {
+            let block = block.split("</Feature>").next().unwrap_or(block);
+            let tag = &block[..block.find('>')?];
+            let directionality = match xml_attribute(tag, "directionality").as_deref() {
+                Some("1") => Some(Direction::Fwd),
+                Some("2") => Some(Direction::Rev),
+                _ => None,
+            };
+            let ranges: Vec<[usize; 2]> = block
+                .split("<Segment")
+                .skip(1)
+                .filter_map(|segment| {
+                    let range = xml_attribute(segment, "range")?;
+                    let (start, end) = range.split_once('-')?;
+                    Some([start.trim().parse().ok()?, end.trim().parse().ok()?])
+                })
+                .collect();
+            let span = [ranges.first()?[0], ranges.last()?[1]];
+            Some(Feature {
+                label: xml_attribute(tag, "name").unwrap_or_default(),
+                feature_type: xml_attribute(tag, "type").unwrap_or_else(|| "misc_feature".into()),
+                span,
+                directionality,
+                segments: if ranges.len() > 1 { ranges } else { Vec::new() },
+                qualifiers: Vec::new(),
+            })
+        })
+        .collect()
+}
+
+/// Parse a SnapGene `.dna` file. Only the DNA and features packets are read.
+pub fn parse_snapgene(bytes: &[u8], name: &str) -> Result<PlasmidFile, String> {
+    let mut sequence = None;
+    let mut topology = Topology::Linear;
+    let mut features = Vec::new();
+
+    let mut i = 0;
+    while i + 5 <= bytes.len() {
+        let packet_type = bytes[i];
+        let length = u32::from_be_bytes([bytes[i + 1], bytes[i + 2], bytes[i + 3], bytes[i + 4]]);
+        let data = bytes
+            .get(i + 5..i + 5 + length as usize)
+            .ok_or("SnapGene file is truncated.")?;
+        match packet_type {
+            0x09 if i == 0 && !data.starts_with(b"SnapGene") => {
+                return Err("Not a SnapGene file.".to_string());
+            }
+            0x00 if !data.is_empty() => {
+                if data[0] & 0x01 != 0 {
+                    topology = Topology::Circular;
+                }
+                sequence = Some(sanitize_sequence(&String::from_utf8_lossy(&data[1..])));
+            }
+            0x0A => features = snapgene_features(&String::from_utf8_lossy(data)),
+            _ => {}
+        }
+        i += 5 + length as usize;
+    }
+
+    Ok(PlasmidFile {
+        name: name.to_string(),
+        sequence: sequence.ok_or("SnapGene file has no DNA packet.")?,
+        topology,
+        features,
+    })
+}
+
+/// Wrap a qualifier into GenBank feature table lines.
+fn push_qualifier(out: &mut String, key: &str, value: &str) {
+    const INDENT: &str = "                     ";
+    const WIDTH: usize = 58;
+    let text = format!("/{}=\"{}\"", key, value.replace('"', "\"\""));
+    let chars: Vec<char> = text.chars().collect();
+    for chunk in chars.chunks(WIDTH) {
+        out.push_str(INDENT);
+        out.extend(chunk);
+        out.push('\n');
+    }
+}
+
+/// Write a plasmid as a GenBank record.
+pub fn to_genbank(plasmid: &PlasmidFile) -> String {
+    let mut out = String::new();
+    let locus_name: String = plasmid
+        .name
+        .chars()
+        .map(|c| if c.is_whitespace() { '_' } else { c })
+        .take(16)
+        .collect();
+    let topology = match plasmid.topology {
+        Topology::Circular => "circular",
+        Topology::Linear => "linear",
+    };
+    out.push_str(&format!(
+        "LOCUS       {:<16} {:>11} bp    DNA     {:<8} SYN {}\n",
+        locus_name,
+        plasmid.sequence.len(),
+        topology,
+        Local::now().format("%d-%b-%Y").to_string().to_uppercase()
+    ));
+    out.push_str(&format!("DEFINITION  {}.\n", plasmid.name));
+    out.push_str("FEATURES             Location/Qualifiers\n");
+    out.push_str(&format!(
+        "     source          1..{}\n",
+        plasmid.sequence.len()
+    ));
+    push_qualifier(&mut out, "mol_type", "other DNA");
+    for feature in &plasmid.features {
+        out.push_str(&format!(
+            "     {:<16}{}\n",
+            feature.feature_type,
+            format_location(feature, plasmid.sequence.len())
+        ));
+        push_qualifier(&mut out, "label", &feature.label);
+        for (key, value) in &feature.qualifiers {
+            push_qualifier(&mut out, key, value);
+        }
+    }
+
+    out.push_str("ORIGIN\n");
+    let sequence = plasmid.sequence.to_ascii_lowercase();
+    for (line, chunk) in sequence.as_bytes().chunks(60).enumerate() {
+        out.push_str(&format!("{:>9}", line * 60 + 1));
+        for block in chunk.chunks(10) {
+            out.push(' ');
+            out.push_str(&String::from_utf8_lossy(block));
+        }
+        out.push('\n');
+    }
+    out.push_str("//\n");
+    out
+}
+
+/// Write a plasmid to disk as GenBank.
+pub fn write_genbank(path: &Path, plasmid: &PlasmidFile) -> Result<(), String> {
+    fs::write(path, to_genbank(plasmid))
+        .map_err(|e| format!("Could not write \"{}\": {}", path.display(), e))
+}
@@ -0,0 +1,1123 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::melting::{melting_temperature, TmAlgorithm, TmSettings};
+use crate::sequence::{iupac_mask, repeating_slice, reverse_complementary, Direction, Topology};
+
+/// Primer design settings, deserialized straight from the frontend's
+/// user preferences (same keys and defaults as `UserPreferences.defaultSettings`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DesignSettings {
+    #[serde(rename = "symmetricPrimers")]
+    pub symmetric_primers: bool,
+    #[serde(rename = "HRMinLength")]
+    pub hr_min_length: usize,
+    #[serde(rename = "HRTm")]
+    pub hr_tm: f64,
+    #[serde(rename = "HRSubcloningTm")]
+    pub hr_subcloning_tm: f64,
+    #[serde(rename = "TBRTm")]
+    pub tbr_tm: f64,
+    #[serde(rename = "maxTmSi")]
+    pub max_tm_si: f64,
+    #[serde(rename = "useGCClamp")]
+    pub use_gc_clamp: bool,
+    #[serde(flatten)]
+    pub tm: TmSettings,
+}
+
+impl Default for DesignSettings {
+    fn default() -> Self {
+        Self {
+            symmetric_primers: false,
+            hr_min_length: 18,
+            hr_tm: 50.0,
+            hr_subcloning_tm: 55.0,
+            tbr_tm: 60.0,
+            max_tm_si: 49.5,
+            use_gc_clamp: false,
+            tm: TmSettings::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PrimerError {
+    /// Primer contains characters that are not IUPAC nucleotide codes.
+    InvalidBase(String),
+    /// Primer ran past the end of a linear sequence.
+    OutOfBases,
+    /// Target melting temperature could not be reached.
+    TargetTmNotReached,
+}
+
+impl fmt::Display for PrimerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrimerError::InvalidBase(seq) => {
+                write!(f, "Primer sequence contains invalid bases: {}", seq)
+            }
+            PrimerError::OutOfBases => write!(
+                f,
+                "Primer could not be extended, ran out of bases on linear sequence."
+            ),
+            PrimerError::TargetTmNotReached => write!(
+                f,
+                "Primer could not be extended to the target melting temperature."
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PrimerError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OperationType {
+    Insertion,
+    Deletion,
+    Mutation,
+    Subcloning,
+}
+
+impl fmt::Display for OperationType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OperationType::Insertion => "Insertion",
+            OperationType::Deletion => "Deletion",
+            OperationType::Mutation => "Mutation",
+            OperationType::Subcloning => "Subcloning",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RegionType {
+    #[serde(rename = "HR")]
+    Hr,
+    #[serde(rename = "INS")]
+    Ins,
+    #[serde(rename = "TBR")]
+    Tbr,
+    #[serde(rename = "subHR")]
+    SubHr,
+    #[serde(rename = "subTBR")]
+    SubTbr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Symmetry {
+    Symmetric,
+    Asymmetric,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrimerRegion {
+    pub sequence: String,
+    #[serde(rename = "type")]
+    pub region_type: RegionType,
+    pub start: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+    pub direction: Direction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Primer {
+    pub name: String,
+    pub label: String,
+    pub regions: Vec<PrimerRegion>,
+}
+
+impl Primer {
+    /// Full primer sequence (5'->3').
+    pub fn sequence(&self) -> String {
+        self.regions.iter().map(|r| r.sequence.as_str()).collect()
+    }
+}
+
+/// HR length or Tm of a primer set. Subcloning sets carry one value per end.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PerEnd<T> {
+    Single(T),
+    Pair([T; 2]),
+}
+
+impl<T: Copy> PerEnd<T> {
+    /// The value, or the value of the first end for subcloning sets.
+    pub fn first(&self) -> T {
+        match self {
+            PerEnd::Single(value) | PerEnd::Pair([value, _]) => *value,
+        }
+    }
+}
+
+/// Primer set in the same shape as the frontend's primer set dictionaries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrimerSet {
+    pub title: String,
+    #[serde(rename = "type")]
+    pub operation_type: OperationType,
+    pub hr_length: PerEnd<usize>,
+    pub hr_tm: PerEnd<f64>,
+    pub symmetry: Symmetry,
+    pub current_plasmid_sequence: String,
+    pub primers: Vec<Primer>,
+}
+
+/// Strand onto which a primer is extended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strand {
+    Top,
+    Bottom,
+}
+
+/// Check if sequence contains only IUPAC nucleotide codes. Degenerate
+/// bases are allowed and scored by their least stable variant.
+pub fn validate_primer_sequence(seq: &str) -> Result<(), PrimerError> {
+    if seq.bytes().all(|b| iupac_mask(b) != 0) {
+        Ok(())
+    } else {
+        Err(PrimerError::InvalidBase(seq.to_string()))
+    }
+}
+
+/// Extends the primer from the starting position in the specified direction
+/// on the specified strand until the target melting temperature is reached.
+///
+/// Example:
+///                              startingPos
+///                                  |
+///                   <--- backward  ▼  forward  --->
+/// fwdStrand  -> 5'-GGGGAAAAAAAATTTATATATGGGGAAAAAAAATTTATATAT-3'
+/// compStrand -> 3'-CCCCTTTTTTTTAAATATATACCCCTTTTTTTTAAATATATA-5'
+///                   <--- forward   ▲  backward --->
+#[allow(clippy::too_many_arguments)]
+pub fn extend_sequence(
+    plasmid_sequence: &str,
+    starting_index: i64,
+    target_strand: Strand,
+    direction: Direction,
+    target_tm: f64,
+    tm_method: TmAlgorithm,
+    minimum_length: usize,
+    initial_sequence: &str,
+    topology: Topology,
+    settings: &DesignSettings,
+) -> Result<String, PrimerError> {
+    // Select correct strand
+    let strand_sequence = match target_strand {
+        Strand::Top => plasmid_sequence.to_string(),
+        Strand::Bottom => reverse_complementary(plasmid_sequence),
+    };
+    let seq_len = strand_sequence.len() as i64;
+
+    // Adjust starting index
+    let starting_index = match target_strand {
+        Strand::Top => starting_index,
+        Strand::Bottom => seq_len - starting_index + 1,
+    };
+
+    let tm = |s: &str| melting_temperature(s, Some(tm_method), &settings.tm);
+    let build = |extension_length: i64| match direction {
+        Direction::Fwd => format!(
+            "{}{}",
+            initial_sequence,
+            repeating_slice(
+                &strand_sequence,
+                starting_index,
+                starting_index + extension_length - 1
+            )
+        ),
+        Direction::Rev => format!(
+            "{}{}",
+            repeating_slice(
+                &strand_sequence,
+                starting_index - extension_length + 1,
+                starting_index
+            ),
+            initial_sequence
+        ),
+    };
+
+    // Initial extension length minus the initial sequence
+    let mut extension_length = minimum_length as i64 - initial_sequence.len() as i64;
+
+    let mut prev_primer_sequence = build(extension_length);
+    let mut prev_tm = tm(&prev_primer_sequence);
+
+    let mut primer_sequence = prev_primer_sequence.clone();
+    let mut curr_tm = prev_tm;
+    const MAX_ITER: usize = 100;
+
+    for _ in 0..MAX_ITER {
+        validate_primer_sequence(&primer_sequence)?;
+
+        let primer_stable_enough = curr_tm >= target_tm && primer_sequence.len() >= minimum_length;
+
+        let newest_base = match direction {
+            Direction::Fwd => primer_sequence.bytes().last(),
+            Direction::Rev => primer_sequence.bytes().next(),
+        };
+        let is_gc_clamp_condition_met =
+            !settings.use_gc_clamp || matches!(newest_base, Some(b'G' | b'C'));
+
+        let current_primer_closer_to_target_tm =
+            (curr_tm - target_tm).abs() <= (prev_tm - target_tm).abs();
+        let prev_primer_too_short = prev_primer_sequence.len() < minimum_length;
+
+        if primer_stable_enough && is_gc_clamp_condition_met {
+            if (current_primer_closer_to_target_tm && prev_primer_too_short)
+                || settings.use_gc_clamp
+            {
+                return Ok(primer_sequence);
+            } else {
+                return Ok(prev_primer_sequence);
+            }
+        }
+
+        // Save current sequence and tm, then recalculate current seq and tm
+        prev_primer_sequence = primer_sequence;
+        prev_tm = curr_tm;
+
+        extension_length += 1;
+
+        let next_end_index = match direction {
+            Direction::Fwd => starting_index + extension_length - 1,
+            Direction::Rev => starting_index,
+        };
+        if topology == Topology::Linear && seq_len - next_end_index < 0 {
+            return Err(PrimerError::OutOfBases);
+        }
+
+        primer_sequence = build(extension_length);
+        curr_tm = tm(&primer_sequence);
+    }
+
+    Err(PrimerError::TargetTmNotReached)
+}
+
+/// Generate the set of primers for a given IVA operation.
+///
+/// `operation_end` is `None` for pure insertions at `operation_start`.
+pub fn generate_set(
+    operation_type: OperationType,
+    operation_start: i64,
+    operation_end: Option<i64>,
+    plasmid_sequence: &str,
+    seq_to_insert: &str,
+    topology: Topology,
+    settings: &DesignSettings,
+) -> Result<PrimerSet, PrimerError> {
+    // Target Tm for homologous region
+    let target_tm_hr = if operation_type != OperationType::Subcloning {
+        settings.hr_tm
+    } else {
+        settings.hr_subcloning_tm
+    };
+
+    // Make sure indices are sorted
+    let operation_range = match operation_end {
+        None => [operation_start, operation_start - 1],
+        Some(end) => [operation_start.min(end), operation_start.max(end)],
+    };
+
+    // Forward template binding region, extend forward on the forward strand from the end position
+    let temp_fwd = extend_sequence(
+        plasmid_sequence,
+        operation_range[1],
+        Strand::Top,
+        Direction::Fwd,
+        settings.tbr_tm,
+        settings.tm.algorithm,
+        7,
+        "",
+        topology,
+        settings,
+    )?;
+    // Reverse template binding region, extend forward on the complementary strand from the start position
+    let temp_rev = extend_sequence(
+        plasmid_sequence,
+        operation_range[0],
+        Strand::Bottom,
+        Direction::Fwd,
+        settings.tbr_tm,
+        settings.tm.algorithm,
+        7,
+        "",
+        topology,
+        settings,
+    )?;
+
+    let is_short_insertion =
+        melting_temperature(seq_to_insert, Some(TmAlgorithm::OligoCalc), &settings.tm)
+            < settings.max_tm_si;
+
+    let ctx = SetContext {
+        plasmid_sequence,
+        operation_range,
+        temp_fwd,
+        temp_rev,
+        seq_to_insert,
+        target_tm_hr,
+        operation_type,
+        topology,
+        settings,
+    };
+
+    match (is_short_insertion, settings.symmetric_primers) {
+        (true, true) => ctx.sym_short_set(),
+        (true, false) => ctx.asym_short_set(),
+        (false, true) => Ok(ctx.sym_long_set()),
+        (false, false) => Ok(ctx.asym_long_set()),
+    }
+}
+
+/// Subcloning primer built from the HR, INS and TBR regions of a primer designed
+/// on a pseudo plasmid. `ins_max_length` overrides the INS region's maximum length.
+fn subcloning_primer(
+    name: &str,
+    source: &Primer,
+    starts: [i64; 3],
+    ins_max_length: Option<usize>,
+    direction: Direction,
+) -> Primer {
+    let types = [RegionType::SubHr, RegionType::Ins, RegionType::SubTbr];
+    let regions = source
+        .regions
+        .iter()
+        .zip(types.into_iter().zip(starts))
+        .map(|(region, (region_type, start))| PrimerRegion {
+            sequence: region.sequence.clone(),
+            region_type,
+            start: Some(start),
+            max_length: if region_type == RegionType::Ins {
+                ins_max_length.or(region.max_length)
+            } else {
+                None
+            },
+            direction,
+        })
+        .collect();
+    SetContext::primer(name, regions)
+}
+
+/// Generate subcloning primers, port of `Primers.generateSubcloningSet`.
+///
+/// `seq_5_prime` and `seq_3_prime` are added in front of and behind the
+/// subcloning target.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_subcloning_set(
+    operation_start: i64,
+    operation_end: Option<i64>,
+    plasmid_sequence: &str,
+    subcloning_target: &str,
+    seq_5_prime: &str,
+    seq_3_prime: &str,
+    topology: Topology,
+    settings: &DesignSettings,
+) -> Result<PrimerSet, PrimerError> {
+    // Adjust range if pure insertion
+    let [start, end] = match operation_end {
+        None => [operation_start, operation_start - 1],
+        Some(end) => [operation_start.min(end), operation_start.max(end)],
+    };
+
+    // We pretend the subcloning target is already in the target vector and
+    // that we're doing a simple insertion at the 5' end (designed with the
+    // insertion HR Tm, as the web app does)
+    let before = &plasmid_sequence[..(start - 1).max(0) as usize];
+    let after = &plasmid_sequence[(end.max(0) as usize).min(plasmid_sequence.len())..];
+    let pseudo_plasmid_sequence_5_prime = format!("{}{}{}", before, subcloning_target, after);
+    let primer_set_5_prime = generate_set(
+        OperationType::Insertion,
+        start,
+        None,
+        &pseudo_plasmid_sequence_5_prime,
+        seq_5_prime,
+        topology,
+        settings,
+    )?;
+
+    // Same at the 3' end, on the flipped pseudo plasmid sequence
+    let pseudo_plasmid_sequence_3_prime = reverse_complementary(&pseudo_plasmid_sequence_5_prime);
+    let operation_pos =
+        pseudo_plasmid_sequence_3_prime.len() as i64 - start - subcloning_target.len() as i64 + 2;
+    let primer_set_3_prime = generate_set(
+        OperationType::Insertion,
+        operation_pos,
+        None,
+        &pseudo_plasmid_sequence_3_prime,
+        &reverse_complementary(seq_3_prime),
+        topology,
+        settings,
+    )?;
+
+    let sets = [primer_set_5_prime, primer_set_3_prime];
+    let insertion_sequence_full = format!("{}{}{}", seq_5_prime, subcloning_target, seq_3_prime);
+    let full_len = insertion_sequence_full.len() as i64;
+    let len_5_prime = seq_5_prime.len() as i64;
+    let len_3_prime = seq_3_prime.len() as i64;
+
+    Ok(PrimerSet {
+        title: "Subcloning".to_string(),
+        operation_type: OperationType::Subcloning,
+        hr_length: PerEnd::Pair([sets[0].hr_length.first(), sets[1].hr_length.first()]),
+        hr_tm: PerEnd::Pair([sets[0].hr_tm.first(), sets[1].hr_tm.first()]),
+        symmetry: sets[0].symmetry,
+        current_plasmid_sequence: format!("{}{}{}", before, insertion_sequence_full, after),
+        primers: vec![
+            subcloning_primer(
+                "Forward primer",
+                &sets[0].primers[0],
+                [start - 1, start - 1 + len_5_prime, start + len_5_prime],
+                None,
+                Direction::Fwd,
+            ),
+            subcloning_primer(
+                "Reverse primer",
+                &sets[1].primers[0],
+                [
+                    start + full_len,
+                    start + full_len - len_3_prime,
+                    start + full_len - len_3_prime - 1,
+                ],
+                None,
+                Direction::Rev,
+            ),
+            subcloning_primer(
+                "Vector forward primer",
+                &sets[1].primers[1],
+                [
+                    start + full_len - len_3_prime - 1,
+                    start + full_len - 1,
+                    start + full_len,
+                ],
+                None,
+                Direction::Fwd,
+            ),
+            subcloning_primer(
+                "Vector reverse primer",
+                &sets[0].primers[1],
+                [start + len_5_prime, start, start - 1],
+                None,
+                Direction::Rev,
+            ),
+        ],
+    })
+}
+
+/// Vector primers and linear fragment for an insertion that is too long for primer tails.
+#[derive(Debug, Clone)]
+pub struct LinearFragmentInsertion {
+    pub primer_set: PrimerSet,
+    /// Insertion flanked by the overhangs homologous to the linearised vector.
+    pub fragment: String,
+    /// Span of the insertion within the fragment (1-based, inclusive).
+    pub insert_span: [usize; 2],
+}
+
+/// Insert a linear fragment into the plasmid, port of `Primers.insertFromLinearFragment`.
+///
+/// We pretend the insertion is already in the target vector and design a plain
+/// insertion at its 5' end, then at its 3' end on the flipped sequence.
+pub fn insert_from_linear_fragment(
+    operation_start: i64,
+    operation_end: Option<i64>,
+    plasmid_sequence: &str,
+    seq_to_insert: &str,
+    topology: Topology,
+    settings: &DesignSettings,
+) -> Result<LinearFragmentInsertion, PrimerError> {
+    // Adjust range if pure insertion
+    let [start, end] = match operation_end {
+        None => [operation_start, operation_start - 1],
+        Some(end) => [operation_start.min(end), operation_start.max(end)],
+    };
+    let ins_len = seq_to_insert.len() as i64;
+
+    let before = &plasmid_sequence[..(start - 1).max(0) as usize];
+    let after = &plasmid_sequence[(end.max(0) as usize).min(plasmid_sequence.len())..];
+    let pseudo_plasmid_sequence_5_prime = format!("{}{}{}", before, seq_to_insert, after);
+    let primer_set_5_prime = generate_set(
+        OperationType::Insertion,
+        start,
+        None,
+        &pseudo_plasmid_sequence_5_prime,
+        "",
+        topology,
+        settings,
+    )?;
+
+    // Adjust operation position because the 3' pseudo plasmid sequence is flipped
+    let pseudo_plasmid_sequence_3_prime = reverse_complementary(&pseudo_plasmid_sequence_5_prime);
+    let operation_pos = pseudo_plasmid_sequence_3_prime.len() as i64 - start - ins_len + 2;
+    let primer_set_3_prime = generate_set(
+        OperationType::Insertion,
+        operation_pos,
+        None,
+        &pseudo_plasmid_sequence_3_prime,
+        "",
+        topology,
+        settings,
+    )?;
+
+    let sets = [primer_set_5_prime, primer_set_3_prime];
+    let overhang_5_prime = sets[0].primers[0].regions[0].sequence.clone();
+    let overhang_3_prime = reverse_complementary(&sets[1].primers[0].regions[0].sequence);
+    let fragment = format!("{}{}{}", overhang_5_prime, seq_to_insert, overhang_3_prime);
+    let insert_span = [
+        overhang_5_prime.len() + 1,
+        overhang_5_prime.len() + seq_to_insert.len(),
+    ];
+
+    let primer_set = PrimerSet {
+        title: "Insertion from linear fragment".to_string(),
+        operation_type: OperationType::Subcloning,
+        hr_length: PerEnd::Pair([sets[0].hr_length.first(), sets[1].hr_length.first()]),
+        hr_tm: PerEnd::Pair([sets[0].hr_tm.first(), sets[1].hr_tm.first()]),
+        symmetry: sets[0].symmetry,
+        primers: vec![
+            subcloning_primer(
+                "Vector forward primer",
+                &sets[1].primers[1],
+                [start + ins_len - 1, start + ins_len - 1, start + ins_len],
+                Some(0),
+                Direction::Fwd,
+            ),
+            subcloning_primer(
+                "Vector reverse primer",
+                &sets[0].primers[1],
+                [start, start, start],
+                Some(0),
+                Direction::Rev,
+            ),
+        ],
+        current_plasmid_sequence: pseudo_plasmid_sequence_5_prime,
+    };
+
+    Ok(LinearFragmentInsertion {
+        primer_set,
+        fragment,
+        insert_span,
+    })
+}
+
+/// Shared inputs of the four primer set generators.
+struct SetContext<'a> {
+    plasmid_sequence: &'a str,
+    operation_range: [i64; 2],
+    temp_fwd: String,
+    temp_rev: String,
+    seq_to_insert: &'a str,
+    target_tm_hr: f64,
+    operation_type: OperationType,
+    topology: Topology,
+    settings: &'a DesignSettings,
+}
+
+impl SetContext<'_> {
+    fn hr_tm(&self, seq: &str) -> f64 {
+        melting_temperature(seq, Some(TmAlgorithm::OligoCalc), &self.settings.tm)
+    }
+
+    fn title(&self, length_class: &str) -> String {
+        if self.operation_type != OperationType::Deletion {
+            format!("{} {}", length_class, self.operation_type)
+        } else {
+            self.operation_type.to_string()
+        }
+    }
+
+    fn current_plasmid_sequence(&self) -> String {
+        let [start, end] = self.operation_range;
+        let before = &self.plasmid_sequence[..(start - 1).max(0) as usize];
+        let after =
+            &self.plasmid_sequence[(end.max(0) as usize).min(self.plasmid_sequence.len())..];
+        format!("{}{}{}", before, self.seq_to_insert, after)
+    }
+
+    /// Take turns deleting bases from each end of the overlapping sequence until
+    /// the target melting temperature is reached. Returns the trimmed sequence and
+    /// the number of bases deleted from the 5' and 3' ends.
+    fn trim_overlap(&self, mut overlapping_seq: String) -> (String, usize, usize) {
+        let mut trimmed_5_prime = 0;
+        let mut trimmed_3_prime = 0;
+        let mut turn_5_prime = true;
+        loop {
+            let sliced = if turn_5_prime {
+                overlapping_seq.get(1..).unwrap_or_default().to_string()
+            } else {
+                overlapping_seq
+                    .get(..overlapping_seq.len().saturating_sub(1))
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            let sliced_tm = self.hr_tm(&sliced);
+            let still_above_target_tm = sliced_tm > self.target_tm_hr;
+            let slicing_gets_us_closer = (self.target_tm_hr - sliced_tm).abs()
+                <= (self.target_tm_hr - self.hr_tm(&overlapping_seq)).abs();
+            let minimum_length_not_reached = overlapping_seq.len() > self.settings.hr_min_length;
+
+            if (still_above_target_tm || slicing_gets_us_closer) && minimum_length_not_reached {
+                overlapping_seq = sliced;
+                if turn_5_prime {
+                    trimmed_5_prime += 1;
+                } else {
+                    trimmed_3_prime += 1;
+                }
+                turn_5_prime = !turn_5_prime;
+            } else {
+                break;
+            }
+        }
+        (overlapping_seq, trimmed_5_prime, trimmed_3_prime)
+    }
+
+    fn region(
+        sequence: String,
+        region_type: RegionType,
+        start: Option<i64>,
+        max_length: Option<usize>,
+        direction: Direction,
+    ) -> PrimerRegion {
+        PrimerRegion {
+            sequence,
+            region_type,
+            start,
+            max_length,
+            direction,
+        }
+    }
+
+    fn primer(name: &str, regions: Vec<PrimerRegion>) -> Primer {
+        Primer {
+            name: name.to_string(),
+            label: name.to_string(),
+            regions,
+        }
+    }
+
+    /// Symmetric primers for short insertions/mutations, add bases to the 5' and 3'
+    /// end of the sequence to add, or to nothing in case of deletions.
+    fn sym_short_set(&self) -> Result<PrimerSet, PrimerError> {
+        let [start, end] = self.operation_range;
+        let ins = self.seq_to_insert;
+        let ins_len = ins.len() as i64;
+
+        // Extend more than we need
+        let homo_fwd_1 = extend_sequence(
+            self.plasmid_sequence,
+            start - 1,
+            Strand::Top,
+            Direction::Rev,
+            self.target_tm_hr,
+            TmAlgorithm::OligoCalc,
+            self.settings.hr_min_length,
+            "",
+            self.topology,
+            self.settings,
+        )?;
+        let homo_fwd_2 = extend_sequence(
+            self.plasmid_sequence,
+            end,
+            Strand::Top,
+            Direction::Fwd,
+            self.target_tm_hr,
+            TmAlgorithm::OligoCalc,
+            self.settings.hr_min_length,
+            "",
+            self.topology,
+            self.settings,
+        )?;
+
+        let (_, trimmed_1, trimmed_2) =
+            self.trim_overlap(format!("{}{}{}", homo_fwd_1, ins, homo_fwd_2));
+
+        // Save the fragments that were added on each side
+        let homo_fwd_1 = homo_fwd_1.get(trimmed_1..).unwrap_or_default().to_string();
+        let homo_fwd_2_end = (homo_fwd_2.len() + 1)
+            .saturating_sub(trimmed_2)
+            .min(homo_fwd_2.len());
+        let homo_fwd_2 = homo_fwd_2[..homo_fwd_2_end].to_string();
+        let homo_rev_1 = reverse_complementary(&homo_fwd_2);
+
+        let hr_length = homo_fwd_1.len() + ins.len() + homo_rev_1.len();
+        let hr_tm = self.hr_tm(&format!("{}{}{}", homo_fwd_1, ins, homo_fwd_2));
+
+        Ok(PrimerSet {
+            title: self.title("Short"),
+            operation_type: self.operation_type,
+            hr_length: PerEnd::Single(hr_length),
+            hr_tm: PerEnd::Single(hr_tm),
+            symmetry: Symmetry::Symmetric,
+            current_plasmid_sequence: self.current_plasmid_sequence(),
+            primers: vec![
+                Self::primer(
+                    "Forward primer",
+                    vec![
+                        Self::region(
+                            homo_fwd_1,
+                            RegionType::Hr,
+                            Some(start - 1),
+                            None,
+                            Direction::Fwd,
+                        ),
+                        Self::region(
+                            ins.to_string(),
+                            RegionType::Ins,
+                            None,
+                            Some(ins.len()),
+                            Direction::Fwd,
+                        ),
+                        Self::region(
+                            self.temp_fwd.clone(),
+                            RegionType::Tbr,
+                            Some(start + ins_len),
+                            None,
+                            Direction::Fwd,
+                        ),
+                    ],
+                ),
+                Self::primer(
+                    "Reverse primer",
+                    vec![
+                        Self::region(
+                            homo_rev_1,
+                            RegionType::Hr,
+                            Some(start + ins_len),
+                            None,
+                            Direction::Rev,
+                        ),
+                        Self::region(
+                            reverse_complementary(ins),
+                            RegionType::Ins,
+                            None,
+                            Some(ins.len()),
+                            Direction::Rev,
+                        ),
+                        Self::region(
+                            self.temp_rev.clone(),
+                            RegionType::Tbr,
+                            Some(start - 1),
+                            None,
+                            Direction::Rev,
+                        ),
+                    ],
+                ),
+            ],
+        })
+    }
+
+    /// Asymmetric primers for short insertions/mutations, the whole overlap
+    /// sits on the forward primer.
+    fn asym_short_set(&self) -> Result<PrimerSet, PrimerError> {
+        let [start, _] = self.operation_range;
+        let ins = self.seq_to_insert;
+        let ins_len = ins.len() as i64;
+
+        let homo_fwd = extend_sequence(
+            self.plasmid_sequence,
+            start - 1,
+            Strand::Top,
+            Direction::Rev,
+            self.target_tm_hr,
+            TmAlgorithm::OligoCalc,
+            self.settings.hr_min_length,
+            "",
+            self.topology,
+            self.settings,
+        )?;
+
+        Ok(PrimerSet {
+            title: self.title("Short"),
+            operation_type: self.operation_type,
+            hr_length: PerEnd::Single(homo_fwd.len()),
+            hr_tm: PerEnd::Single(self.hr_tm(&homo_fwd)),
+            symmetry: Symmetry::Asymmetric,
+            current_plasmid_sequence: self.current_plasmid_sequence(),
+            primers: vec![
+                Self::primer(
+                    "Forward primer",
+                    vec![
+                        Self::region(
+                            homo_fwd,
+                            RegionType::Hr,
+                            Some(start - 1),
+                            None,
+                            Direction::Fwd,
+                        ),
+                        Self::region(
+                            ins.to_string(),
+                            RegionType::Ins,
+                            None,
+                            Some(ins.len()),
+                            Direction::Fwd,
+                        ),
+                        Self::region(
+                            self.temp_fwd.clone(),
+                            RegionType::Tbr,
+                            Some(start + ins_len),
+                            None,
+                            Direction::Fwd,
+                        ),
+                    ],
+                ),
+                Self::primer(
+                    "Reverse primer",
+                    vec![
+                        Self::region(
+                            String::new(),
+                            RegionType::Hr,
+                            Some(start - ins_len),
+                            None,
+                            Direction::Rev,
+                        ),
+                        Self::region(
+                            String::new(),
+                            RegionType::Ins,
+                            Some(start),
+                            Some(ins.len()),
+                            Direction::Rev,
+                        ),
+                        Self::region(
+                            self.temp_rev.clone(),
+                            RegionType::Tbr,
+                            Some(start - 1),
+                            None,
+                            Direction::Rev,
+                        ),
+                    ],
+                ),
+            ],
+        })
+    }
+
+    /// Symmetric primers for long insertions, take turns deleting bases from
+    /// each end of the insertion until the target melting temperature is reached.
+    fn sym_long_set(&self) -> PrimerSet {
+        let [start, _] = self.operation_range;
+        let ins = self.seq_to_insert;
+        let ins_len = ins.len() as i64;
+
+        let (overlapping_seq, trimmed_1, trimmed_2) = self.trim_overlap(ins.to_string());
+
+        let homo_fwd = ins[trimmed_1..].to_string();
+        let ins_rev_comp = reverse_complementary(ins);
+        let homo_rev = ins_rev_comp[trimmed_2..].to_string();
+
+        PrimerSet {
+            title: self.title("Long"),
+            operation_type: self.operation_type,
+            hr_length: PerEnd::Single(overlapping_seq.len()),
+            hr_tm: PerEnd::Single(self.hr_tm(&overlapping_seq)),
+            symmetry: Symmetry::Symmetric,
+            current_plasmid_sequence: self.current_plasmid_sequence(),
+            primers: vec![
+                Self::primer(
+                    "Forward primer",
+                    vec![
+                        Self::region(
+                            String::new(),
+                            RegionType::Hr,
+                            Some(start - 1),
+                            None,
+                            Direction::Fwd,
+                        ),
+                        Self::region(
+                            homo_fwd,
+                            RegionType::Ins,
+                            Some(start + ins_len - 1),
+                            Some(ins.len()),
+                            Direction::Fwd,
+                        ),
+                        Self::region(
+                            self.temp_fwd.clone(),
+                            RegionType::Tbr,
+                            Some(start + ins_len),
+                            None,
+                            Direction::Fwd,
+                        ),
+                    ],
+                ),
+                Self::primer(
+                    "Reverse primer",
+                    vec![
+                        Self::region(
+                            String::new(),
+                            RegionType::Hr,
+                            Some(start + ins_len),
+                            None,
+                            Direction::Rev,
+                        ),
+                        Self::region(
+                            homo_rev,
+                            RegionType::Ins,
+                            Some(start),
+                            Some(ins.len()),
+                            Direction::Rev,
+                        ),
+                        Self::region(
+                            self.temp_rev.clone(),
+                            RegionType::Tbr,
+                            Some(start - 1),
+                            None,
+                            Direction::Rev,
+                        ),
+                    ],
+                ),
+            ],
+        }
+    }
+
+    /// Asymmetric primers for long insertions, remove bases from the
+    /// complementary strand until the target melting temperature is reached.
+    fn asym_long_set(&self) -> PrimerSet {
+        let [start, _] = self.operation_range;
+        let ins = self.seq_to_insert;
+        let ins_len = ins.len() as i64;
+
+        let mut overlapping_seq = reverse_complementary(ins);
+        loop {
+            let sliced = overlapping_seq.get(1..).unwrap_or_default().to_string();
+            let sliced_tm = self.hr_tm(&sliced);
+            let still_above_target_tm = sliced_tm > self.target_tm_hr;
+            let slicing_gets_us_closer = (self.target_tm_hr - sliced_tm).abs()
+                <= (self.target_tm_hr - self.hr_tm(&overlapping_seq)).abs();
+            let minimum_length_not_reached = overlapping_seq.len() > self.settings.hr_min_length;
+            if (still_above_target_tm || slicing_gets_us_closer) && minimum_length_not_reached {
+                overlapping_seq = sliced;
+            } else {
+                break;
+            }
+        }
+
+        PrimerSet {
+            title: self.title("Long"),
+            operation_type: self.operation_type,
+            hr_length: PerEnd::Single(overlapping_seq.len()),
+            hr_tm: PerEnd::Single(self.hr_tm(&overlapping_seq)),
+            symmetry: Symmetry::Asymmetric,
+            current_plasmid_sequence: self.current_plasmid_sequence(),
+            primers: vec![
+                Self::primer(
+                    "Forward primer",
+                    vec![
+                        Self::region(
+                            String::new(),
+                            RegionType::Hr,
+                            Some(start - 1),
+                            None,
+                            Direction::Fwd,
+                        ),
+                        Self::region(
+                            ins.to_string(),
+                            RegionType::Ins,
+                            None,
+                            Some(ins.len()),
+                            Direction::Fwd,
+                        ),
+                        Self::region(
+                            self.temp_fwd.clone(),
+                            RegionType::Tbr,
+                            Some(start + ins_len),
+                            None,
+                            Direction::Fwd,
+                        ),
+                    ],
+                ),
+                Self::primer(
+                    "Reverse primer",
+                    vec![
+                        Self::region(
+                            String::new(),
+                            RegionType::Hr,
+                            Some(start + ins_len),
+                            None,
+                            Direction::Rev,
+                        ),
+                        Self::region(
+                            overlapping_seq,
+                            RegionType::Ins,
+                            Some(start),
+                            Some(ins.len()),
+                            Direction::Rev,
+                        ),
+                        Self::region(
+                            self.temp_rev.clone(),
+                            RegionType::Tbr,
+                            Some(start - 1),
+                            None,
+                            Direction::Rev,
+                        ),
+                    ],
+                ),
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequence::tests::RANDOM_PLASMID;
+
+    const DONOR: [&str; 2] = [
+        "TTCCCCCCGCGGCCCACCCAGTATTCCTAACGGAGCATAAATCCCACCCGAACTAAGTTTGTCGAACCTTGGTCCAAGAT",
+        "CGGGACTCGGTCTCCAGGTAAGACGGGCTCATTCATAAACGTTACTAAGGGGTATAATCTTCTATTTGTGGGTGGGAACA",
+    ];
+
+    fn subclone(start: i64, end: Option<i64>, seq_5_prime: &str, seq_3_prime: &str) -> PrimerSet {
+        let target = &DONOR.concat()[20..140];
+        generate_subcloning_set(
+            start,
+            end,
+            &RANDOM_PLASMID.concat(),
+            target,
+            seq_5_prime,
+            seq_3_prime,
+            Topology::Circular,
+            &DesignSettings::default(),
+        )
+        .unwrap()
+    }
+
+    fn sequences(set: &PrimerSet) -> Vec<String> {
+        set.primers.iter().map(Primer::sequence).collect()
+    }
+
+    // Expected primers were generated with `Primers.generateSubcloningSet`
+    // from the web app on the same sequences and default preferences.
+    #[test]
+    fn subcloning_matches_web_app() {
+        let set = subclone(101, Some(150), "GGATCC", "CTCGAG");
+        assert!(matches!(set.hr_length, PerEnd::Pair([20, 18])));
+        assert_eq!(
+            sequences(&set),
+            [
+                "TATTCAGGACCTAACCTGAGGGATCCGTATTCCTAACGGAGCATAAATCCCACC",
+                "ATTGCAGCTGCCGTTGAACTCGAGAGATTATACCCCTTAGTAACGTTTATGAATGAGC",
+                "TTCAACGGCAGCTGCAATGGAAATAGG",
+                "CTCAGGTTAGGTCCTGAATAAGGAGGAA",
+            ]
+        );
+
+        let set = subclone(201, None, "", "");
+        assert!(matches!(set.hr_length, PerEnd::Pair([25, 22])));
+        assert_eq!(
+            sequences(&set),
+            [
+                "GGCAATGACGGATATATATTAAAAAGTATTCCTAACGGAGCATAAATCCCACC",
+                "GCCTCAATGTATCTTAAAACACAGATTATACCCCTTAGTAACGTTTATGAATGAGC",
+                "GTGTTTTAAGATACATTGAGGCCCGTTC",
+                "TTTTTAATATATATCCGTCATTGCCTATTTCCATTGCA",
+            ]
+        );
+    }
+}
@@ -0,0 +1,312 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::sequence::{iupac_mask, sanitize_sequence};
+
+/// Sequencing read with optional per-base Phred quality values.
+#[derive(Debug, Clone, Serialize)]
+pub struct SequencingRead {
+    pub name: String,
+    pub sequence: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub qualities: Option<Vec<u8>>,
+}
+
+/// Base calls as upper case IUPAC codes, anything else becomes `N` so that
+/// calls stay paired with their quality values.
+fn base_calls(calls: &[u8]) -> String {
+    calls
+        .iter()
+        .map(|&b| match iupac_mask(b) {
+            0 => 'N',
+            _ => b.to_ascii_uppercase() as char,
+        })
+        .collect()
+}
+
+/// Read all reads from a file, picking the parser from the file extension
+/// (`.ab1`, `.abi`, `.fastq`, `.fq`, `.fasta`, `.fa`, `.seq`).
+pub fn read_sequencing_reads(path: &Path) -> Result<Vec<SequencingRead>, String> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let name = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Read".to_string());
+    let bytes =
+        fs::read(path).map_err(|e| format!("Could not read \"{}\": {}", path.display(), e))?;
+
+    let reads = match extension.as_str() {
+        "ab1" | "abi" | "abif" => vec![parse_abif(&bytes, &name)?],
+        "fastq" | "fq" => parse_fastq(&String::from_utf8_lossy(&bytes))?,
+        "fasta" | "fa" | "fas" | "seq" | "txt" => {
+            parse_fasta_reads(&String::from_utf8_lossy(&bytes), &name)
+        }
+        other => return Err(format!("Unsupported read file extension \".{}\".", other)),
+    };
+    if reads.is_empty() {
+        return Err(format!("\"{}\" contains no reads.", path.display()));
+    }
+    Ok(reads)
+}
+
+/// Parse every record of a FASTA file. A file without header is a single read.
+pub fn parse_fasta_reads(text: &str, fallback_name: &str) -> Vec<SequencingRead> {
+    let mut reads: Vec<SequencingRead> = Vec::new();
+    for line in text.lines() {
+        if let Some(header) = line.strip_prefix('>') {
+            reads.push(SequencingRead {
+                name: header
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or(fallback_name)
+                    .to_string(),
+                sequence: String::new(),
+                qualities: None,
+            });
+            continue;
+        }
+        if reads.is_empty() {
+            reads.push(SequencingRead {
+                name: fallback_name.to_string(),
+                sequence: String::new(),
+                qualities: None,
+            });
+        }
+        reads
+            .last_mut()
+            .unwrap()
+            .sequence
+            .push_str(&sanitize_sequence(line));
+    }
+    reads.retain(|r| !r.sequence.is_empty());
+    reads
+}
+
+/// Parse a FASTQ file with Phred+33 quality values.
+pub fn parse_fastq(text: &str) -> Result<Vec<SequencingRead>, String> {
+    let mut reads = Vec::new();
+    let mut lines = text.lines().filter(|l| !l.is_empty());
+    while let Some(header) = lines.next() {
+        let name = header
+            .strip_prefix('@')
+            .ok_or_else(|| format!("Expected a FASTQ header, found \"{}\".", header))?;
+        let (Some(sequence), Some(_), Some(qualities)) = (lines.next(), lines.next(), lines.next())
+        else {
+            return Err(format!("FASTQ record \"{}\" is truncated.", name));
+        };
+        if sequence.len() != qualities.len() {
+            return Err(format!(
+                "FASTQ record \"{}\" has {} bases but {} quality values.",
+                name,
+                sequence.len(),
+                qualities.len()
+            ));
+        }
+        reads.push(SequencingRead {
+            name: name
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            sequence: base_calls(sequence.as_bytes()),
+            qualities: Some(qualities.bytes().map(|q| q.saturating_sub(33)).collect()),
+        });
+    }
+    Ok(reads)
+}
+
+/// Directory entry of an ABIF file.
+struct AbifEntry<'a> {
+    name: &'a [u8],
+    number: u32,
+    data: &'a [u8],
+}
+
+fn be_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Parse an ABIF (`.ab1`) trace file, keeping the base calls and their
+/// quality values. Edited calls (`PBAS1`) are preferred over the basecaller's.
+pub fn parse_abif(bytes: &[u8], fallback_name: &str) -> Result<SequencingRead, String> {
+    if !bytes.starts_with(b"ABIF") {
+        return Err("Not an ABIF trace file.".to_string());
+    }
+    // Root directory entry starts at byte 6
+    let count = be_u32(bytes, 6 + 12).ok_or("ABIF header is truncated.")? as usize;
+    let offset = be_u32(bytes, 6 + 20).ok_or("ABIF header is truncated.")? as usize;
+
+    let mut entries = Vec::with_capacity(count);
+    for i in 0..count {
+        let entry = bytes
+            .get(offset + 28 * i..offset + 28 * (i + 1))
+            .ok_or("ABIF directory is truncated.")?;
+        let data_size = be_u32(entry, 16).unwrap_or_default() as usize;
+        // Data of up to 4 bytes is stored in the offset field itself
+        let data = if data_size <= 4 {
+            &entry[20..20 + data_size]
+        } else {
+            let data_offset = be_u32(entry, 20).unwrap_or_default() as usize;
+            bytes
+                .get(data_offset..data_offset + data_size)
+                .ok_or("ABIF data is truncated.")?
+        };
+        entries.push(AbifEntry {
+            name: &entry[..4],
+            number: be_u32(entry, 4).unwrap_or_default(),
+            data,
+        });
+    }
+    let find = |name: &[u8]| {
+        [1, 2].into_iter().find_map(|number| {
+            entries
+                .iter()
+                .find(|e| e.name == name && e.number == number)
+                .map(|e| e.data)
+        })
+    };
+
+    let calls = find(b"PBAS").ok_or("ABIF file has no base calls.")?;
+    let qualities = find(b"PCON").filter(|q| q.len() == calls.len());
+    // Sample name is a Pascal string
+    let name = find(b"SMPL")
+        .and_then(|s| s.split_first())
+        .map(|(length, text)| {
+            String::from_utf8_lossy(&text[..(*length as usize).min(text.len())]).to_string()
+        })
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or_else(|| fallback_name.to_string());
+
+    Ok(SequencingRead {
+        name,
+        sequence: base_calls(calls),
+        qualities: qualities.map(|q| q.to_vec()),
+    })
+}
+
+/// Span (0-based, end exclusive) kept after trimming low quality ends with
+/// the modified Mott algorithm: the stretch with the highest summed
+/// `error_limit - error probability`. Reads without qualities are not trimmed.
+pub fn quality_trim(read: &SequencingRead, error_limit: f64) -> [usize; 2] {
+    let Some(qualities) = &read.qualities else {
+        return [0, read.sequence.len()];
+    };
+    let mut best = [0, 0];
+    let mut best_score = 0.0;
+    let mut start = 0;
+    let mut score = 0.0;
+    for (i, q) in qualities.iter().enumerate() {
+        score += error_limit - 10f64.powf(-(*q as f64) / 10.0);
+        if score <= 0.0 {
+            score = 0.0;
+            start = i + 1;
+        } else if score > best_score {
+            best_score = score;
+            best = [start, i + 1];
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ABIF file with the given directory entries after the header.
+    fn abif(entries: &[(&[u8; 4], u32, &[u8])]) -> Vec<u8> {
+        let directory = 128;
+        let mut data_offset = directory + 28 * entries.len();
+        let mut bytes = b"ABIF".to_vec();
+        bytes.extend(101u16.to_be_bytes());
+        for field in [
+            b"tdir".as_slice(),
+            &1u32.to_be_bytes(),
+            &1023u16.to_be_bytes(),
+        ] {
+            bytes.extend(field);
+        }
+        bytes.extend(28u16.to_be_bytes());
+        bytes.extend((entries.len() as u32).to_be_bytes());
+        bytes.extend((28 * entries.len() as u32).to_be_bytes());
+        bytes.extend((directory as u32).to_be_bytes());
+        bytes.resize(directory, 0);
+
+        let mut data: Vec<u8> = Vec::new();
+        for (name, number, value) in entries {
+            bytes.extend(*name);
+            bytes.extend(number.to_be_bytes());
+            bytes.extend([0, 2, 0, 1]);
+            bytes.extend((value.len() as u32).to_be_bytes());
+            bytes.extend((value.len() as u32).to_be_bytes());
+            if value.len() <= 4 {
+                let mut inline = value.to_vec();
+                inline.resize(4, 0);
+                bytes.extend(inline);
+            } else {
+                bytes.extend((data_offset as u32).to_be_bytes());
+                data.extend(*value);
+                data_offset += value.len();
+            }
+            bytes.extend(0u32.to_be_bytes());
+        }
+        bytes.extend(data);
+        bytes
+    }
+
+    #[test]
+    fn fastq_reads_keep_bases_paired_with_qualities() {
+        let reads =
+            parse_fastq("@r1 first read\nacgTU\n+\nI5+!I\n\n@r2\nAC\u{e9}\n+\nIIII\n").unwrap();
+        assert_eq!(reads.len(), 2);
+        assert_eq!(reads[0].name, "r1");
+        assert_eq!(reads[0].sequence, "ACGTU");
+        assert_eq!(reads[0].qualities, Some(vec![40, 20, 10, 0, 40]));
+        // The two bytes of é become two unknown bases
+        assert_eq!(reads[1].sequence, "ACNN");
+
+        assert!(parse_fastq("@r1\nACGT\n+\nIII\n").is_err());
+        assert!(parse_fastq("@r1\nACGT\n+\n").is_err());
+        assert!(parse_fastq("r1\nACGT\n+\nIIII\n").is_err());
+    }
+
+    #[test]
+    fn abif_base_calls_and_qualities() {
+        let bytes = abif(&[
+            (b"SMPL", 1, b"\x05clone"),
+            (b"PBAS", 2, b"ACGTT\xc3"),
+            (b"PBAS", 1, b"acgtta\xc3"),
+            (b"PCON", 1, &[30, 35, 40, 40, 20, 10, 5]),
+        ]);
+        let read = parse_abif(&bytes, "fallback").unwrap();
+        assert_eq!(read.name, "clone");
+        assert_eq!(read.sequence, "ACGTTAN");
+        assert_eq!(read.qualities, Some(vec![30, 35, 40, 40, 20, 10, 5]));
+
+        // Qualities that do not match the calls are dropped
+        let read =
+            parse_abif(&abif(&[(b"PBAS", 2, b"ACGTT"), (b"PCON", 2, b"\x1e")]), "x").unwrap();
+        assert_eq!((read.name.as_str(), read.qualities), ("x", None));
+
+        assert!(parse_abif(b"ABIF", "x").is_err());
+        assert!(parse_abif(b"FASTA", "x").is_err());
+        assert!(parse_abif(&abif(&[(b"PCON", 1, b"\x1e")]), "x").is_err());
+    }
+
+    #[test]
+    fn quality_trim_drops_low_quality_ends() {
+        let mut qualities = vec![5; 10];
+        qualities.extend([40; 30]);
+        qualities.extend([2; 10]);
+        let read = SequencingRead {
+            name: "r".to_string(),
+            sequence: "A".repeat(50),
+            qualities: Some(qualities),
+        };
+        assert_eq!(quality_trim(&read, 0.05), [10, 40]);
+    }
+}
@@ -0,0 +1,425 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::sequence::{iupac_mask, reverse_complementary, sanitize_sequence, Direction, Topology};
+
+/// Methylation that can block or impair an enzyme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Methylation {
+    /// `GATC`, methylated A (E. coli Dam).
+    Dam,
+    /// `CCWGG`, methylated internal C (E. coli Dcm).
+    Dcm,
+    /// `CG`, methylated C (mammalian and plant DNA).
+    Cpg,
+}
+
+impl Methylation {
+    /// Motif and the offsets of its methylated bases on the top strand,
+    /// including the partner base of the methylated base on the bottom strand.
+    fn motif(self) -> (&'static str, &'static [usize]) {
+        match self {
+            Methylation::Dam => ("GATC", &[1, 2]),
+            Methylation::Dcm => ("CCWGG", &[1, 3]),
+            Methylation::Cpg => ("CG", &[0, 1]),
+        }
+    }
+}
+
+/// Restriction enzyme from the bundled REBASE-derived table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Enzyme {
+    pub name: String,
+    /// Recognition site, may contain IUPAC codes.
+    pub site: String,
+    /// Top and bottom strand cut, counted in top strand bases from the start of
+    /// the site. `GAATTC` cut `[1, 5]` is G^AATTC.
+    pub cut: [i64; 2],
+    /// Methylation that blocks or impairs cutting when it overlaps the site.
+    pub methylation: Vec<Methylation>,
+    /// REBASE supplier codes, see [`SUPPLIERS`].
+    pub suppliers: String,
+}
+
+impl Enzyme {
+    pub fn is_commercial(&self) -> bool {
+        !self.suppliers.is_empty()
+    }
+
+    pub fn is_palindromic(&self) -> bool {
+        reverse_complementary(&self.site) == self.site
+    }
+
+    /// Names of the companies selling the enzyme, unknown codes are skipped.
+    pub fn supplier_names(&self) -> Vec<&'static str> {
+        self.suppliers
+            .chars()
+            .filter_map(|code| SUPPLIERS.iter().find(|(c, _)| *c == code))
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
+/// REBASE supplier codes used in the enzyme table.
+pub const SUPPLIERS: [(char, &str); 9] = [
+    ('B', "Thermo Fisher Scientific (Invitrogen)"),
+    ('F', "Thermo Fisher Scientific"),
+    ('I', "SibEnzyme"),
+    ('K', "Takara"),
+    ('N', "New England Biolabs"),
+    ('R', "Promega"),
+    ('S', "Sigma-Aldrich"),
+    ('V', "Vivantis"),
+    ('X', "EURx"),
+];
+
+pub static ENZYMES: Lazy<Vec<Enzyme>> = Lazy::new(|| {
+    serde_json::from_str(include_str!("../data/rebase_enzymes.json"))
+        .expect("Bundled rebase_enzymes.json is malformed")
+});
+
+/// Look up an enzyme by name, ignoring case.
+pub fn find_enzyme(name: &str) -> Option<&'static Enzyme> {
+    ENZYMES.iter().find(|e| e.name.eq_ignore_ascii_case(name))
+}
+
+/// Which enzymes to keep, by their number of cuts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CutterFilter {
+    #[default]
+    All,
+    /// Enzymes that do not cut.
+    None,
+    Unique,
+    Double,
+}
+
+impl CutterFilter {
+    fn keeps(self, cuts: usize) -> bool {
+        match self {
+            CutterFilter::All => true,
+            CutterFilter::None => cuts == 0,
+            CutterFilter::Unique => cuts == 1,
+            CutterFilter::Double => cuts == 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverhangType {
+    Blunt,
+    #[serde(rename = "5'")]
+    FivePrime,
+    #[serde(rename = "3'")]
+    ThreePrime,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CutSite {
+    /// Orientation of the recognition site.
+    pub strand: Direction,
+    /// Recognition site (1-based, inclusive). Spans where the end lies before
+    /// the start run across the origin.
+    pub site_span: [usize; 2],
+    /// Top and bottom strand cuts, as the 1-based base after which the strand
+    /// is cut.
+    pub cut: [usize; 2],
+    pub overhang_type: OverhangType,
+    /// Top strand bases between the two cuts.
+    pub overhang: String,
+    /// Active methylation overlapping the site that the enzyme is sensitive to.
+    pub blocked_by: Vec<Methylation>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnzymeCuts {
+    pub enzyme: String,
+    pub site: String,
+    pub sites: Vec<CutSite>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CutSiteRequest {
+    pub sequence: String,
+    pub topology: Topology,
+    /// Enzymes to map, all enzymes of the table if empty.
+    #[serde(default)]
+    pub enzymes: Vec<String>,
+    #[serde(default)]
+    pub filter: CutterFilter,
+    #[serde(default)]
+    pub commercial_only: bool,
+    /// Methylation present on the DNA, e.g. Dam and Dcm for plasmids from E. coli.
+    #[serde(default = "default_methylation")]
+    pub methylation: Vec<Methylation>,
+}
+
+pub fn default_methylation() -> Vec<Methylation> {
+    vec![Methylation::Dam, Methylation::Dcm]
+}
+
+/// True if every base of the sequence window is one of the bases allowed by
+/// the site. Ambiguous plasmid bases never match.
+fn site_matches(sequence: &[u8], start: usize, site: &[u8]) -> bool {
+    site.iter().enumerate().all(|(i, s)| {
+        let base = iupac_mask(sequence[(start + i) % sequence.len()]);
+        base.count_ones() == 1 && base & iupac_mask(*s) != 0
+    })
+}
+
+/// Start positions (0-based) of a site on the top strand.
+pub fn site_starts(sequence: &[u8], site: &[u8], topology: Topology) -> Vec<usize> {
+    if site.is_empty() || site.len() > sequence.len() {
+        return Vec::new();
+    }
+    let last_start = match topology {
+        Topology::Linear => sequence.len() - site.len(),
+        Topology::Circular => sequence.len() - 1,
+    };
+    (0..=last_start)
+        .filter(|&start| site_matches(sequence, start, site))
+        .collect()
+}
+
+/// 0-based positions of methylated bases (and their bottom strand partners).
+pub fn methylated_positions(
+    sequence: &[u8],
+    topology: Topology,
+    methylation: &[Methylation],
+) -> Vec<(Methylation, usize)> {
+    let mut positions = Vec::new();
+    for &kind in methylation {
+        let (motif, offsets) = kind.motif();
+        for start in site_starts(sequence, motif.as_bytes(), topology) {
+            positions.extend(
+                offsets
+                    .iter()
+                    .map(|offset| (kind, (start + offset) % sequence.len())),
+            );
+        }
+    }
+    positions
+}
+
+/// Map every site of an enzyme, one entry per recognition site. Type IIS cuts
+/// that fall off the end of a linear sequence are left out.
+pub fn enzyme_cut_sites(
+    sequence: &str,
+    topology: Topology,
+    enzyme: &Enzyme,
+    methylated: &[(Methylation, usize)],
+) -> Vec<CutSite> {
+    let bytes = sequence.as_bytes();
+    let length = bytes.len() as i64;
+    let site_length = enzyme.site.len() as i64;
+
+    let mut orientations = vec![(Direction::Fwd, enzyme.site.clone())];
+    if !enzyme.is_palindromic() {
+        orientations.push((Direction::Rev, reverse_complementary(&enzyme.site)));
+    }
+
+    let mut sites = Vec::new();
+    for (strand, site) in orientations {
+        for start in site_starts(bytes, site.as_bytes(), topology) {
+            let start = start as i64;
+            // Cuts as boundaries between bases, 0 is in front of the first base
+            let [top, bottom] = match strand {
+                Direction::Fwd => [start + enzyme.cut[0], start + enzyme.cut[1]],
+                Direction::Rev => [
+                    start + site_length - enzyme.cut[1],
+                    start + site_length - enzyme.cut[0],
+                ],
+            };
+            if topology == Topology::Linear && [top, bottom].iter().any(|b| *b <= 0 || *b >= length)
+            {
+                continue;
+            }
+
+            let (overhang_type, from, to) = match bottom.cmp(&top) {
+                std::cmp::Ordering::Equal => (OverhangType::Blunt, top, top),
+                std::cmp::Ordering::Greater => (OverhangType::FivePrime, top, bottom),
+                std::cmp::Ordering::Less => (OverhangType::ThreePrime, bottom, top),
+            };
+            let overhang: String = (from..to)
+                .map(|i| bytes[i.rem_euclid(length) as usize] as char)
+                .collect();
+
+            let site_end = start + site_length - 1;
+            let in_site = |position: usize| {
+                let offset = (position as i64 - start).rem_euclid(length);
+                offset <= site_end - start
+            };
+            let mut blocked_by: Vec<Methylation> = methylated
+                .iter()
+                .filter(|(kind, position)| enzyme.methylation.contains(kind) && in_site(*position))
+                .map(|(kind, _)| *kind)
+                .collect();
+            blocked_by.dedup();
+
+            let to_position = |boundary: i64| ((boundary - 1).rem_euclid(length) + 1) as usize;
+            sites.push(CutSite {
+                strand,
+                site_span: [start as usize + 1, to_position(site_end + 1)],
+                cut: [to_position(top), to_position(bottom)],
+                overhang_type,
+                overhang,
+                blocked_by,
+            });
+        }
+    }
+    sites.sort_by_key(|s| s.cut[0]);
+    sites
+}
+
+/// Map the cut sites of the requested enzymes and keep those passing the filter.
+pub fn find_cut_sites(request: &CutSiteRequest) -> Result<Vec<EnzymeCuts>, String> {
+    let sequence = sanitize_sequence(&request.sequence);
+    if sequence.is_empty() {
+        return Err("The sequence is empty.".to_string());
+    }
+
+    let enzymes: Vec<&Enzyme> = if request.enzymes.is_empty() {
+        ENZYMES.iter().collect()
+    } else {
+        request
+            .enzymes
+            .iter()
+            .map(|name| find_enzyme(name).ok_or_else(|| format!("Unknown enzyme \"{}\".", name)))
+            .collect::<Result<_, _>>()?
+    };
+
+    let methylated =
+        methylated_positions(sequence.as_bytes(), request.topology, &request.methylation);
+    Ok(enzymes
+        .into_iter()
+        .filter(|e| !request.commercial_only || e.is_commercial())
+        .map(|enzyme| EnzymeCuts {
+            enzyme: enzyme.name.clone(),
+            site: enzyme.site.clone(),
+            sites: enzyme_cut_sites(&sequence, request.topology, enzyme, &methylated),
+        })
+        .filter(|cuts| request.filter.keeps(cuts.sites.len()))
+        .collect())
+}
+
+/// Enzyme as listed to the frontend, with its supplier codes spelled out.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnzymeListing {
+    #[serde(flatten)]
+    pub enzyme: Enzyme,
+    pub supplier_names: Vec<&'static str>,
+}
+
+#[tauri::command]
+/// Restriction enzymes of the bundled table.
+pub fn list_enzymes() -> Vec<EnzymeListing> {
+    ENZYMES
+        .iter()
+        .map(|enzyme| EnzymeListing {
+            enzyme: enzyme.clone(),
+            supplier_names: enzyme.supplier_names(),
+        })
+        .collect()
+}
+
+#[tauri::command]
+/// Cut sites of restriction enzymes on a sequence, filtered by number of cuts.
+pub fn map_cut_sites(request: CutSiteRequest) -> Result<Vec<EnzymeCuts>, String> {
+    find_cut_sites(&request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cuts, overhang type and overhang of every site of an enzyme.
+    fn cuts(
+        sequence: &str,
+        topology: Topology,
+        name: &str,
+    ) -> Vec<([usize; 2], OverhangType, String)> {
+        let enzyme = find_enzyme(name).unwrap();
+        enzyme_cut_sites(sequence, topology, enzyme, &[])
+            .into_iter()
+            .map(|site| (site.cut, site.overhang_type, site.overhang))
+            .collect()
+    }
+
+    #[test]
+    fn overhangs_of_common_enzymes() {
+        let sequence = "TTTTGAATTCTTTTGGTACCTTTTGGTCTCACGTATTTTACTGTGAGACCTTTT";
+        assert_eq!(
+            cuts(sequence, Topology::Linear, "EcoRI"),
+            [([5, 9], OverhangType::FivePrime, "AATT".to_string())]
+        );
+        assert_eq!(
+            cuts(sequence, Topology::Linear, "KpnI"),
+            [([19, 15], OverhangType::ThreePrime, "GTAC".to_string())]
+        );
+        // Type IIS sites cut outside of the site, on both orientations
+        assert_eq!(
+            cuts(sequence, Topology::Linear, "BsaI"),
+            [
+                ([31, 35], OverhangType::FivePrime, "CGTA".to_string()),
+                ([39, 43], OverhangType::FivePrime, "ACTG".to_string()),
+            ]
+        );
+        let enzyme = find_enzyme("BsaI").unwrap();
+        let spans: Vec<([usize; 2], Direction)> =
+            enzyme_cut_sites(sequence, Topology::Linear, enzyme, &[])
+                .into_iter()
+                .map(|site| (site.site_span, site.strand))
+                .collect();
+        assert_eq!(
+            spans,
+            [([25, 30], Direction::Fwd), ([45, 50], Direction::Rev)]
+        );
+    }
+
+    #[test]
+    fn sites_across_the_origin() {
+        let sequence = "ATTCTTTTTTTTGA";
+        assert!(cuts(sequence, Topology::Linear, "EcoRI").is_empty());
+        let enzyme = find_enzyme("EcoRI").unwrap();
+        let sites = enzyme_cut_sites(sequence, Topology::Circular, enzyme, &[]);
+        assert_eq!(sites.len(), 1);
+        assert_eq!(sites[0].site_span, [13, 4]);
+        assert_eq!(sites[0].cut, [13, 3]);
+        assert_eq!(sites[0].overhang, "AATT");
+
+        // BsaI cuts past the end of the site, off a linear sequence
+        let sequence = "TTGGTCTCAA";
+        assert!(cuts(sequence, Topology::Linear, "BsaI").is_empty());
+        assert_eq!(
+            cuts(sequence, Topology::Circular, "BsaI"),
+            [([9, 3], OverhangType::FivePrime, "ATTG".to_string())]
+        );
+    }
+
+    #[test]
+    fn dam_methylation_blocks_overlapping_site() {
+        let request = CutSiteRequest {
+            sequence: "AATCTAGATCAATCTAGAAA".to_string(),
+            topology: Topology::Linear,
+            enzymes: vec!["XbaI".to_string()],
+            filter: CutterFilter::All,
+            commercial_only: false,
+            methylation: default_methylation(),
+        };
+        let cuts = find_cut_sites(&request).unwrap();
+        let blocked: Vec<Vec<Methylation>> = cuts[0]
+            .sites
+            .iter()
+            .map(|site| site.blocked_by.clone())
+            .collect();
+        assert_eq!(blocked, [vec![Methylation::Dam], vec![]]);
+    }
+}
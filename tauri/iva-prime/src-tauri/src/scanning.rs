@@ -0,0 +1,309 @@
+use serde::{Deserialize, Serialize};
+
+use crate::codons::{codon_distance, codon_weight, synonymous_codons, translate_codon};
+use crate::export::{
+    consolidate_order, order_sheet_table, table_to_csv, MixedBaseFormat, OrderEntry,
+};
+use crate::jobs::{spawn_job, JobReporter};
+use crate::primers::{generate_set, DesignSettings, OperationType, PrimerSet};
+use crate::sequence::{reverse_complementary, span_sequence, Direction, Topology};
+
+/// Replacement codon used for every scanned residue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScanMode {
+    /// Alanine codon closest to the wild type codon.
+    #[serde(rename = "alanine")]
+    Alanine,
+    /// NNK degenerate codon (all 20 amino acids, one stop).
+    #[serde(rename = "NNK")]
+    Nnk,
+    /// NNS degenerate codon (all 20 amino acids, one stop).
+    #[serde(rename = "NNS")]
+    Nns,
+}
+
+/// CDS feature to scan, as found in the frontend's feature dictionaries.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScanFeature {
+    pub label: String,
+    pub span: [usize; 2],
+    pub directionality: Direction,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanRequest {
+    pub sequence: String,
+    pub topology: Topology,
+    pub feature: ScanFeature,
+    /// First and last residue to scan (1-based, inclusive).
+    pub residue_range: [usize; 2],
+    pub mode: ScanMode,
+    /// Organism used to break ties between alanine codons.
+    #[serde(default = "default_organism")]
+    pub organism: String,
+    /// Notation for NNK/NNS bases on the order sheet.
+    #[serde(default)]
+    pub mixed_base_format: MixedBaseFormat,
+    #[serde(default)]
+    pub settings: DesignSettings,
+}
+
+fn default_organism() -> String {
+    "Escherichia coli".to_string()
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedResidue {
+    pub residue: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanResult {
+    pub primer_sets: Vec<PrimerSet>,
+    pub skipped: Vec<SkippedResidue>,
+    /// Unique oligos across all sets.
+    pub order: Vec<OrderEntry>,
+    pub order_sheet_csv: String,
+}
+
+/// Number of bases of a feature span, `None` if it does not fit the sequence.
+/// Spans ending before they start run across the origin of circular plasmids.
+pub fn feature_length(span: [usize; 2], length: usize, topology: Topology) -> Option<usize> {
+    let [start, end] = span;
+    if start == 0 || end == 0 || start > length || end > length {
+        return None;
+    }
+    match (start <= end, topology) {
+        (true, _) => Some(end - start + 1),
+        (false, Topology::Circular) => Some(length - start + 1 + end),
+        (false, Topology::Linear) => None,
+    }
+}
+
+/// First plasmid base (1-based) of the codon for a residue of a CDS feature,
+/// not wrapped: it lies past either end when the feature crosses the origin.
+pub fn codon_start(feature: &ScanFeature, residue: usize) -> i64 {
+    let [start, end] = feature.span;
+    match feature.directionality {
+        Direction::Fwd => start as i64 + 3 * (residue as i64 - 1),
+        Direction::Rev => end as i64 + 1 - 3 * residue as i64,
+    }
+}
+
+/// Plasmid span (1-based, inclusive) of the codon for a residue of a CDS
+/// feature on a sequence of `length` bases, wrapped around the origin.
+pub fn codon_span(feature: &ScanFeature, residue: usize, length: usize) -> [usize; 2] {
+    let length = length as i64;
+    let wrap = |position: i64| ((position - 1).rem_euclid(length) + 1) as usize;
+    let start = codon_start(feature, residue);
+    [wrap(start), wrap(start + 2)]
+}
+
+/// Pick the replacement codon for a residue, reading 5'->3' on the coding strand.
+fn replacement_codon(mode: ScanMode, wt_codon: &str, organism: &str) -> String {
+    match mode {
+        ScanMode::Alanine => synonymous_codons('A')
+            .into_iter()
+            .min_by(|a, b| {
+                codon_distance(wt_codon, a)
+                    .cmp(&codon_distance(wt_codon, b))
+                    .then(codon_weight(organism, 'A', b).total_cmp(&codon_weight(organism, 'A', a)))
+            })
+            .unwrap_or("GCT")
+            .to_string(),
+        ScanMode::Nnk => "NNK".to_string(),
+        ScanMode::Nns => "NNS".to_string(),
+    }
+}
+
+/// Systematic mutation name, e.g. `K12A` or `K12NNK`.
+fn mutation_name(mode: ScanMode, wt_aa: char, residue: usize) -> String {
+    let target = match mode {
+        ScanMode::Alanine => "A",
+        ScanMode::Nnk => "NNK",
+        ScanMode::Nns => "NNS",
+    };
+    format!("{}{}{}", wt_aa, residue, target)
+}
+
+/// Design one mutation primer set per residue in the requested range.
+pub fn run_scan(
+    request: &ScanRequest,
+    reporter: Option<&JobReporter>,
+) -> Result<ScanResult, String> {
+    let [first, last] = request.residue_range;
+    if first == 0 || last < first {
+        return Err(format!("Invalid residue range [{}, {}].", first, last));
+    }
+
+    let length = request.sequence.len();
+    let cds_length = feature_length(request.feature.span, length, request.topology).unwrap_or(0);
+    if last * 3 > cds_length {
+        return Err(format!(
+            "Residue range [{}, {}] lies outside of feature \"{}\".",
+            first, last, request.feature.label
+        ));
+    }
+
+    let label = request
+        .feature
+        .label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-");
+    let total = last - first + 1;
+    let mut primer_sets = Vec::new();
+    let mut skipped = Vec::new();
+
+    for (i, residue) in (first..=last).enumerate() {
+        if let Some(reporter) = reporter {
+            if reporter.is_cancelled() {
+                return Err("Scan cancelled.".to_string());
+            }
+            reporter.progress(i, total, format!("Residue {}", residue));
+        }
+
+        let span = codon_span(&request.feature, residue, length);
+        if span[1] < span[0] {
+            skipped.push(SkippedResidue {
+                residue,
+                reason: "Codon crosses the origin.".to_string(),
+            });
+            continue;
+        }
+        let plasmid_codon = span_sequence(&request.sequence, span);
+        let wt_codon = match request.feature.directionality {
+            Direction::Fwd => plasmid_codon,
+            Direction::Rev => reverse_complementary(&plasmid_codon),
+        };
+        let wt_aa = translate_codon(&wt_codon);
+
+        if request.mode == ScanMode::Alanine && wt_aa == 'A' {
+            skipped.push(SkippedResidue {
+                residue,
+                reason: "Residue is already alanine.".to_string(),
+            });
+            continue;
+        }
+
+        let new_codon = replacement_codon(request.mode, &wt_codon, &request.organism);
+        let seq_to_insert = match request.feature.directionality {
+            Direction::Fwd => new_codon,
+            Direction::Rev => reverse_complementary(&new_codon),
+        };
+
+        let mut primer_set = match generate_set(
+            OperationType::Mutation,
+            span[0] as i64,
+            Some(span[1] as i64),
+            &request.sequence,
+            &seq_to_insert,
+            request.topology,
+            &request.settings,
+        ) {
+            Ok(set) => set,
+            Err(e) => {
+                skipped.push(SkippedResidue {
+                    residue,
+                    reason: e.to_string(),
+                });
+                continue;
+            }
+        };
+
+        let name = format!("{}_{}", label, mutation_name(request.mode, wt_aa, residue));
+        primer_set.title = name.clone();
+        for primer in primer_set.primers.iter_mut() {
+            let suffix = match primer.name.as_str() {
+                "Forward primer" => "F",
+                _ => "R",
+            };
+            primer.label = format!("{}_{}", name, suffix);
+        }
+        primer_sets.push(primer_set);
+    }
+
+    if let Some(reporter) = reporter {
+        reporter.progress(total, total, "Consolidating order sheet");
+    }
+
+    let order = consolidate_order(&primer_sets);
+    let order_sheet_csv = table_to_csv(&order_sheet_table(&order, request.mixed_base_format));
+
+    Ok(ScanResult {
+        primer_sets,
+        skipped,
+        order,
+        order_sheet_csv,
+    })
+}
+
+#[tauri::command]
+/// Start an alanine/saturation scan in the background.
+/// Returns the job id, results arrive through the `job-finished` event.
+pub fn start_scan_job(app: tauri::AppHandle, request: ScanRequest) -> u64 {
+    spawn_job(app, "scan", move |reporter| {
+        run_scan(&request, Some(reporter))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequence::tests::RANDOM_PLASMID;
+
+    fn feature(span: [usize; 2], directionality: Direction) -> ScanFeature {
+        ScanFeature {
+            label: "test CDS".to_string(),
+            span,
+            directionality,
+        }
+    }
+
+    #[test]
+    fn codon_spans_wrap_around_the_origin() {
+        assert_eq!(feature_length([391, 30], 400, Topology::Circular), Some(40));
+        assert_eq!(feature_length([391, 30], 400, Topology::Linear), None);
+        assert_eq!(feature_length([1, 401], 400, Topology::Circular), None);
+
+        let forward = feature([391, 30], Direction::Fwd);
+        assert_eq!(codon_span(&forward, 1, 400), [391, 393]);
+        assert_eq!(codon_span(&forward, 4, 400), [400, 2]);
+        assert_eq!(codon_span(&forward, 5, 400), [3, 5]);
+
+        let reverse = feature([391, 30], Direction::Rev);
+        assert_eq!(codon_span(&reverse, 1, 400), [28, 30]);
+        assert_eq!(codon_span(&reverse, 10, 400), [1, 3]);
+        assert_eq!(codon_span(&reverse, 11, 400), [398, 400]);
+    }
+
+    #[test]
+    fn scan_across_the_origin() {
+        let request = ScanRequest {
+            sequence: RANDOM_PLASMID.concat(),
+            topology: Topology::Circular,
+            feature: feature([391, 30], Direction::Fwd),
+            residue_range: [1, 13],
+            mode: ScanMode::Nnk,
+            organism: default_organism(),
+            mixed_base_format: MixedBaseFormat::default(),
+            settings: DesignSettings::default(),
+        };
+        let result = run_scan(&request, None).unwrap();
+        let skipped: Vec<(usize, &str)> = result
+            .skipped
+            .iter()
+            .map(|s| (s.residue, s.reason.as_str()))
+            .collect();
+        assert!(skipped.contains(&(4, "Codon crosses the origin.")));
+        assert_eq!(result.primer_sets.len() + result.skipped.len(), 13);
+
+        let mut linear = request.clone();
+        linear.topology = Topology::Linear;
+        assert!(run_scan(&linear, None).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+use serde::Serialize;
+
+use crate::sequence::{
+    find_iupac_matches, reverse_complementary, sanitize_sequence, Direction, Topology,
+};
+
+/// A search hit, same shape as the frontend's `Search.searchResults` entries.
+/// Spans where the end lies before the start run across the origin.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub strand: Direction,
+    pub span: [usize; 2],
+}
+
+/// Search for a DNA sequence on both strands, allowing IUPAC codes in the
+/// query and in the plasmid.
+pub fn search_dna(sequence: &str, query: &str, topology: Topology) -> Vec<SearchHit> {
+    let query = sanitize_sequence(query);
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let seq_len = sequence.len();
+    let to_span = |start: usize| [start + 1, (start + query.len() - 1) % seq_len + 1];
+
+    let mut hits: Vec<SearchHit> = find_iupac_matches(sequence, &query, topology)
+        .into_iter()
+        .map(|start| SearchHit {
+            strand: Direction::Fwd,
+            span: to_span(start),
+        })
+        .collect();
+
+    let query_rev_comp = reverse_complementary(&query);
+    hits.extend(
+        find_iupac_matches(sequence, &query_rev_comp, topology)
+            .into_iter()
+            .map(|start| SearchHit {
+                strand: Direction::Rev,
+                span: to_span(start),
+            }),
+    );
+
+    hits
+}
+
+#[tauri::command]
+/// Search the active plasmid for a (possibly degenerate) DNA sequence.
+pub fn search_sequence(sequence: String, query: String, topology: Topology) -> Vec<SearchHit> {
+    search_dna(&sequence, &query, topology)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hits(sequence: &str, query: &str, topology: Topology) -> Vec<(Direction, [usize; 2])> {
+        search_dna(sequence, query, topology)
+            .into_iter()
+            .map(|hit| (hit.strand, hit.span))
+            .collect()
+    }
+
+    #[test]
+    fn hits_on_both_strands_and_across_the_origin() {
+        let sequence = "TTCCCAAAGGATCCAAAAAAGAA";
+        assert_eq!(
+            hits(sequence, "GGATCC", Topology::Circular),
+            [(Direction::Fwd, [9, 14]), (Direction::Rev, [9, 14])]
+        );
+        assert_eq!(
+            hits(sequence, "CCCAAA", Topology::Linear),
+            [(Direction::Fwd, [3, 8])]
+        );
+        assert_eq!(
+            hits(sequence, "TTTGGG", Topology::Linear),
+            [(Direction::Rev, [3, 8])]
+        );
+        assert_eq!(
+            hits(sequence, "AGAATTC", Topology::Circular),
+            [(Direction::Fwd, [20, 3])]
+        );
+        assert!(hits(sequence, "AGAATTC", Topology::Linear).is_empty());
+    }
+
+    #[test]
+    fn degenerate_queries() {
+        assert_eq!(
+            hits("AAGGATCCAA", "GGNTCC", Topology::Linear),
+            [(Direction::Fwd, [3, 8]), (Direction::Rev, [3, 8])]
+        );
+        assert_eq!(
+            hits("AAGGRTCCAA", "GGATCC", Topology::Linear),
+            [(Direction::Fwd, [3, 8]), (Direction::Rev, [3, 8])]
+        );
+        assert!(hits("AAGGATCCAA", "", Topology::Linear).is_empty());
+    }
+}
@@ -0,0 +1,239 @@
+use serde::{Deserialize, Serialize};
+
+/// Strand/direction of a feature, primer or region, serialized the same way
+/// as the frontend ("fwd" | "rev").
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Fwd,
+    Rev,
+}
+
+/// Plasmid topology ("linear" | "circular").
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Topology {
+    Linear,
+    Circular,
+}
+
+/// Complementary single letter code, including IUPAC ambiguity codes and gaps.
+pub fn complement_base(base: u8) -> u8 {
+    match base {
+        b'A' => b'T',
+        b'C' => b'G',
+        b'G' => b'C',
+        b'T' => b'A',
+        b'U' => b'A',
+        b'R' => b'Y',        // A or G -> T or C
+        b'Y' => b'R',        // T or C -> A or G
+        b'S' => b'S',        // G or C -> G or C
+        b'W' => b'W',        // A or T -> A or T
+        b'K' => b'M',        // G or T -> A or C
+        b'M' => b'K',        // A or C -> G or T
+        b'B' => b'V',        // C or G or T -> G or C or A
+        b'D' => b'H',        // A or G or T -> T or C or A
+        b'H' => b'D',        // A or C or T -> T or G or A
+        b'V' => b'B',        // A or C or G -> T or G or C
+        b'N' => b'N',        // any
+        b'.' | b'-' => b'-', // gap
+        other => other,
+    }
+}
+
+/// Sanitize DNA/RNA sequence leaving only allowed IUPAC nucleotide
+/// one letter codes.
+pub fn sanitize_sequence(input: &str) -> String {
+    input
+        .chars()
+        .map(|c| c.to_ascii_uppercase())
+        .filter(|c| "ACGTURYSWKMBDHVN.-".contains(*c))
+        .collect()
+}
+
+/// Create the complementary sequence to a given DNA/RNA sequence.
+pub fn complementary(seq: &str) -> String {
+    seq.bytes().map(|b| complement_base(b) as char).collect()
+}
+
+/// Create the reverse complementary sequence to a given DNA/RNA sequence.
+pub fn reverse_complementary(seq: &str) -> String {
+    seq.bytes()
+        .rev()
+        .map(|b| complement_base(b) as char)
+        .collect()
+}
+
+/// Fraction of G and C bases in a sequence.
+pub fn fraction_gc(seq: &str) -> f64 {
+    if seq.is_empty() {
+        return 0.0;
+    }
+    let gc = seq.bytes().filter(|b| matches!(b, b'G' | b'C')).count();
+    gc as f64 / seq.len() as f64
+}
+
+/// Count (overlapping) occurences of a substring.
+pub fn count_substring_occurences(sub: &str, seq: &str) -> usize {
+    if sub.is_empty() || sub.len() > seq.len() {
+        return 0;
+    }
+    seq.as_bytes()
+        .windows(sub.len())
+        .filter(|w| *w == sub.as_bytes())
+        .count()
+}
+
+/// Slice that allows negative indices or indices past the end of the
+/// sequence by assuming the sequence loops, same as `Utilities.repeatingSlice`.
+///
+/// Example:
+///         start                 end
+///           ▼                    ▼
+///   -3 -2 -1 0 1 2 3 4 5 6 7 8 9
+///    _  _  _ A B C D E F G _ _ _
+///
+/// Result -> FGABCDEFGA
+pub fn repeating_slice(seq: &str, start: i64, end: i64) -> String {
+    let len = seq.len() as i64;
+    if len == 0 || end <= start {
+        return String::new();
+    }
+    let bytes = seq.as_bytes();
+    (start..end)
+        .map(|i| bytes[i.rem_euclid(len) as usize] as char)
+        .collect()
+}
+
+/// Extract a 1-based, inclusive span from a sequence. Spans where the end
+/// lies before the start are read across the origin.
+pub fn span_sequence(seq: &str, span: [usize; 2]) -> String {
+    let [start, end] = span;
+    if start <= end {
+        seq.get(start.saturating_sub(1)..end.min(seq.len()))
+            .unwrap_or_default()
+            .to_string()
+    } else {
+        match (seq.get(start - 1..), seq.get(..end)) {
+            (Some(head), Some(tail)) => format!("{}{}", head, tail),
+            _ => String::new(),
+        }
+    }
+}
+
+/// Bitmask of the bases (A=1, C=2, G=4, T=8) an IUPAC code stands for,
+/// 0 for anything that is not a nucleotide code.
+pub fn iupac_mask(base: u8) -> u8 {
+    match base.to_ascii_uppercase() {
+        b'A' => 0b0001,
+        b'C' => 0b0010,
+        b'G' => 0b0100,
+        b'T' | b'U' => 0b1000,
+        b'R' => 0b0101, // A or G
+        b'Y' => 0b1010, // C or T
+        b'S' => 0b0110, // G or C
+        b'W' => 0b1001, // A or T
+        b'K' => 0b1100, // G or T
+        b'M' => 0b0011, // A or C
+        b'B' => 0b1110, // C or G or T
+        b'D' => 0b1101, // A or G or T
+        b'H' => 0b1011, // A or C or T
+        b'V' => 0b0111, // A or C or G
+        b'N' => 0b1111, // any
+        _ => 0,
+    }
+}
+
+/// Concrete bases an IUPAC code stands for.
+pub fn iupac_bases(base: u8) -> Vec<u8> {
+    let mask = iupac_mask(base);
+    [b'A', b'C', b'G', b'T']
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| mask & (1 << i) != 0)
+        .map(|(_, b)| b)
+        .collect()
+}
+
+/// True if the sequence contains any ambiguity codes.
+pub fn is_degenerate(seq: &str) -> bool {
+    seq.bytes().any(|b| iupac_mask(b).count_ones() > 1)
+}
+
+/// Number of distinct sequences a degenerate sequence expands to.
+/// Saturates instead of overflowing for very degenerate sequences.
+pub fn degeneracy(seq: &str) -> u64 {
+    seq.bytes().fold(1u64, |acc, b| {
+        acc.saturating_mul(iupac_mask(b).count_ones().max(1) as u64)
+    })
+}
+
+/// Expand a degenerate sequence into concrete variants.
+/// Returns `None` if there would be more than `limit` variants.
+pub fn expand_degenerate(seq: &str, limit: usize) -> Option<Vec<String>> {
+    if degeneracy(seq) > limit as u64 {
+        return None;
+    }
+    let mut variants = vec![String::with_capacity(seq.len())];
+    for b in seq.bytes() {
+        let options = match iupac_bases(b) {
+            bases if bases.is_empty() => vec![b],
+            bases => bases,
+        };
+        if options.len() == 1 {
+            variants.iter_mut().for_each(|v| v.push(options[0] as char));
+            continue;
+        }
+        variants = variants
+            .iter()
+            .flat_map(|v| {
+                options.iter().map(move |o| {
+                    let mut variant = v.clone();
+                    variant.push(*o as char);
+                    variant
+                })
+            })
+            .collect();
+    }
+    Some(variants)
+}
+
+/// Check if two IUPAC codes share at least one base.
+pub fn iupac_compatible(a: u8, b: u8) -> bool {
+    iupac_mask(a) & iupac_mask(b) != 0
+}
+
+/// Find all start positions (0-based) where `query` matches `seq`, allowing
+/// IUPAC codes on either side. On circular sequences matches may run across
+/// the origin.
+pub fn find_iupac_matches(seq: &str, query: &str, topology: Topology) -> Vec<usize> {
+    let seq = seq.as_bytes();
+    let query = query.as_bytes();
+    if query.is_empty() || seq.is_empty() || query.len() > seq.len() {
+        return Vec::new();
+    }
+    let last_start = match topology {
+        Topology::Linear => seq.len() - query.len(),
+        Topology::Circular => seq.len() - 1,
+    };
+    (0..=last_start)
+        .filter(|&start| {
+            query
+                .iter()
+                .enumerate()
+                .all(|(i, q)| iupac_compatible(seq[(start + i) % seq.len()], *q))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    /// Random 400 bp sequence used as the plasmid in test fixtures.
+    pub(crate) const RANDOM_PLASMID: [&str; 5] = [
+        "TTTCCTCATGCAATTCAAAACCATGTCCGTAATGTAGGCGAAATAGTAAACCATTTTACGGAGGATACCAAATTCCTCCT",
+        "TATTCAGGACCTAACCTGAGGTAAACCAGGTCTCTCCGCCCCCTTATAAAAGCTGTTGCACCTAGCCAAGTTCAACGGCA",
+        "GCTGCAATGGAAATAGGCAATGACGGATATATATTAAAAAGTGTTTTAAGATACATTGAGGCCCGTTCGTGCTCCTCGCC",
+        "CTGAAGCATTGCTTTGTGAAGAGGGACTTCAGCCAATAGACCTGCATACCGGCTCATTCTTCATGTGCAACCTAGGGAGA",
+        "ATGTGTACATACGCTCTTACTGCGGTCGCGTCTAATAATATACATTTGCTTCGTTGACTAGCAACCCAGGGCTATAGCTA",
+    ];
+}
@@ -0,0 +1,461 @@
+use serde::{Deserialize, Serialize};
+
+use crate::melting::{melting_temperature, TmSettings};
+use crate::plasmid_file::{PlasmidFile, RegionSelection};
+use crate::search::search_dna;
+use crate::sequence::{
+    find_iupac_matches, fraction_gc, repeating_slice, reverse_complementary, sanitize_sequence,
+    Direction, Topology,
+};
+use crate::structure::longest_homopolymer;
+
+/// Read and primer constraints for sequencing primer design.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SequencingSettings {
+    /// Last readable base behind the primer's 3' end.
+    pub read_length: usize,
+    /// Bases right behind the primer that do not read well.
+    pub dead_zone: usize,
+    /// Bases shared by consecutive reads on the same strand.
+    pub min_overlap: usize,
+    pub primer_length: [usize; 2],
+    pub tm_range: [f64; 2],
+    pub gc_range: [f64; 2],
+    pub gc_clamp: bool,
+    pub max_homopolymer: usize,
+    /// 3' terminal bases that must bind nowhere else on the plasmid.
+    pub unique_3_prime_length: usize,
+    /// How far in front of the ideal position primers are looked for.
+    pub search_window: usize,
+    #[serde(flatten)]
+    pub tm: TmSettings,
+}
+
+impl Default for SequencingSettings {
+    fn default() -> Self {
+        Self {
+            read_length: 900,
+            dead_zone: 50,
+            min_overlap: 50,
+            primer_length: [18, 25],
+            tm_range: [52.0, 62.0],
+            gc_range: [0.4, 0.6],
+            gc_clamp: true,
+            max_homopolymer: 4,
+            unique_3_prime_length: 10,
+            search_window: 200,
+            tm: TmSettings::default(),
+        }
+    }
+}
+
+/// Primer from the plasmid's primer list.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExistingPrimer {
+    pub name: String,
+    pub sequence: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequencingRequest {
+    pub plasmid: PlasmidFile,
+    /// Region to verify.
+    pub target: RegionSelection,
+    #[serde(default)]
+    pub existing_primers: Vec<ExistingPrimer>,
+    #[serde(default)]
+    pub settings: SequencingSettings,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequencingPrimer {
+    pub name: String,
+    pub sequence: String,
+    /// Strand the read runs along.
+    pub strand: Direction,
+    /// Where the primer binds (1-based, inclusive, top strand coordinates).
+    pub binding_span: [usize; 2],
+    /// Readable part of the read, in the same coordinates.
+    pub read_span: [usize; 2],
+    pub tm: f64,
+    pub gc: f64,
+    /// True if taken from the existing primers.
+    pub reused: bool,
+}
+
+/// Stretch of the target read by the same number of reads on each strand.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoverageSegment {
+    pub span: [usize; 2],
+    pub forward: usize,
+    pub reverse: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequencingPlan {
+    pub target_span: [usize; 2],
+    pub primers: Vec<SequencingPrimer>,
+    /// Coverage map over the target, from its first to its last base.
+    pub coverage: Vec<CoverageSegment>,
+    /// Parts of the target no read covers.
+    pub uncovered: Vec<[usize; 2]>,
+    pub warnings: Vec<String>,
+}
+
+/// Primer picked on one strand, in that strand's unrolled target coordinates.
+struct PlacedPrimer {
+    name: Option<String>,
+    sequence: String,
+    /// Position of the 3' base relative to the first target base.
+    three_prime: i64,
+}
+
+/// Tiles reads along one strand. Positions are "unrolled": 0 is the first
+/// target base on this strand and they may run past the origin of circular
+/// plasmids.
+struct StrandTiler<'a> {
+    /// Sequence of the strand being read, 5'->3'.
+    strand_sequence: String,
+    /// Top strand, for the uniqueness check.
+    plasmid_sequence: &'a str,
+    topology: Topology,
+    /// 0-based index of the first target base on this strand.
+    offset: i64,
+    target_length: i64,
+    settings: &'a SequencingSettings,
+}
+
+impl StrandTiler<'_> {
+    fn length(&self) -> i64 {
+        self.strand_sequence.len() as i64
+    }
+
+    /// Primer of the given length ending at an unrolled position, `None` if it
+    /// would run off a linear sequence.
+    fn primer_at(&self, three_prime: i64, length: usize) -> Option<String> {
+        let end = self.offset + three_prime + 1;
+        let start = end - length as i64;
+        if self.topology == Topology::Linear && (start < 0 || end > self.length()) {
+            return None;
+        }
+        Some(repeating_slice(&self.strand_sequence, start, end))
+    }
+
+    fn passes(&self, primer: &str, tm: f64) -> bool {
+        let settings = self.settings;
+        let gc = fraction_gc(primer);
+        let tail_length = settings.unique_3_prime_length.min(primer.len());
+        tm >= settings.tm_range[0]
+            && tm <= settings.tm_range[1]
+            && gc >= settings.gc_range[0]
+            && gc <= settings.gc_range[1]
+            && (!settings.gc_clamp || matches!(primer.bytes().last(), Some(b'G' | b'C')))
+            && longest_homopolymer(primer) <= settings.max_homopolymer
+            && search_dna(
+                self.plasmid_sequence,
+                &primer[primer.len() - tail_length..],
+                self.topology,
+            )
+            .len()
+                == 1
+    }
+
+    /// Best new primer ending at an unrolled position, the one with the Tm
+    /// closest to the middle of the allowed range.
+    fn candidate(&self, three_prime: i64) -> Option<String> {
+        let [min_length, max_length] = self.settings.primer_length;
+        let target_tm = (self.settings.tm_range[0] + self.settings.tm_range[1]) / 2.0;
+        (min_length..=max_length)
+            .filter_map(|length| self.primer_at(three_prime, length))
+            .map(|primer| {
+                let tm = melting_temperature(&primer, None, &self.settings.tm);
+                (primer, tm)
+            })
+            .filter(|(primer, tm)| self.passes(primer, *tm))
+            .min_by(|(_, a), (_, b)| (a - target_tm).abs().total_cmp(&(b - target_tm).abs()))
+            .map(|(primer, _)| primer)
+    }
+
+    /// Unrolled 3' end of an existing primer binding uniquely to this strand.
+    fn existing_site(&self, primer: &str) -> Option<i64> {
+        let hits = find_iupac_matches(&self.strand_sequence, primer, self.topology);
+        let [hit] = hits.as_slice() else {
+            return None;
+        };
+        let other_strand = find_iupac_matches(
+            &reverse_complementary(&self.strand_sequence),
+            primer,
+            self.topology,
+        );
+        if !other_strand.is_empty() {
+            return None;
+        }
+        let three_prime = (*hit + primer.len() - 1) as i64 - self.offset;
+        Some(match self.topology {
+            Topology::Linear => three_prime,
+            Topology::Circular => {
+                let wrapped = three_prime.rem_euclid(self.length());
+                if wrapped < self.target_length {
+                    wrapped
+                } else {
+                    wrapped - self.length()
+                }
+            }
+        })
+    }
+
+    /// Place primers until the target is read on this strand. Existing primers
+    /// are preferred over new ones whenever they fall in the search window.
+    fn tile(&self, existing: &[ExistingPrimer]) -> Vec<PlacedPrimer> {
+        let settings = self.settings;
+        let read_length = settings.read_length as i64;
+        let dead_zone = settings.dead_zone as i64;
+        let existing: Vec<(&ExistingPrimer, String, i64)> = existing
+            .iter()
+            .filter_map(|p| {
+                let sequence = sanitize_sequence(&p.sequence);
+                let site = self.existing_site(&sequence)?;
+                Some((p, sequence, site))
+            })
+            .collect();
+
+        let mut placed: Vec<PlacedPrimer> = Vec::new();
+        let mut first_unread = 0;
+        while first_unread < self.target_length {
+            let ideal = first_unread - dead_zone - 1;
+            // Each primer must bind past the previous one, or a wide search
+            // window can pick the same site again and never get anywhere
+            let window_start = (ideal - settings.search_window as i64)
+                .max(placed.last().map_or(i64::MIN, |p| p.three_prime + 1));
+
+            let reused = existing
+                .iter()
+                .filter(|(_, _, site)| (window_start..=ideal).contains(site))
+                .max_by_key(|(_, _, site)| *site);
+            let next = match reused {
+                Some((primer, sequence, site)) => Some(PlacedPrimer {
+                    name: Some(primer.name.clone()),
+                    sequence: sequence.clone(),
+                    three_prime: *site,
+                }),
+                None => (window_start..=ideal)
+                    .rev()
+                    // Past the ideal position the reads leave a gap, but still cover more
+                    .chain(window_start.max(ideal + 1)..first_unread + read_length - dead_zone)
+                    .find_map(|three_prime| {
+                        self.candidate(three_prime).map(|sequence| PlacedPrimer {
+                            name: None,
+                            sequence,
+                            three_prime,
+                        })
+                    }),
+            };
+            let Some(next) = next else {
+                break;
+            };
+            first_unread = next.three_prime + read_length + 1 - settings.min_overlap as i64;
+            placed.push(next);
+        }
+        placed
+    }
+}
+
+/// Pick sequencing primers reading a region on both strands.
+pub fn plan_sequencing(request: &SequencingRequest) -> Result<SequencingPlan, String> {
+    let plasmid = &request.plasmid;
+    let sequence = sanitize_sequence(&plasmid.sequence);
+    let settings = &request.settings;
+    if settings.read_length <= settings.dead_zone + settings.min_overlap {
+        return Err("Read length must exceed the dead zone and the overlap.".to_string());
+    }
+    let [min_length, max_length] = settings.primer_length;
+    if min_length == 0 || max_length < min_length {
+        return Err(format!(
+            "Invalid primer length range [{}, {}].",
+            min_length, max_length
+        ));
+    }
+
+    let [start, end] = plasmid.resolve_region(&request.target)?;
+    let length = sequence.len();
+    let target_length = if start <= end {
+        end - start + 1
+    } else {
+        length - start + 1 + end
+    };
+    let label = match &request.target {
+        RegionSelection::Feature(label) => label.clone(),
+        RegionSelection::Span(_) => plasmid.name.clone(),
+    };
+
+    let tiler = |strand: Direction| StrandTiler {
+        strand_sequence: match strand {
+            Direction::Fwd => sequence.clone(),
+            Direction::Rev => reverse_complementary(&sequence),
+        },
+        plasmid_sequence: &sequence,
+        topology: plasmid.topology,
+        offset: match strand {
+            Direction::Fwd => start as i64 - 1,
+            Direction::Rev => (length - end) as i64,
+        },
+        target_length: target_length as i64,
+        settings,
+    };
+
+    // Unrolled position on a strand -> 1-based top strand position
+    let to_top = |strand: Direction, unrolled: i64| match strand {
+        Direction::Fwd => (start as i64 - 1 + unrolled).rem_euclid(length as i64) as usize + 1,
+        Direction::Rev => (end as i64 - 1 - unrolled).rem_euclid(length as i64) as usize + 1,
+    };
+    let ordered = |strand: Direction, a: usize, b: usize| match strand {
+        Direction::Fwd => [a, b],
+        Direction::Rev => [b, a],
+    };
+
+    let mut primers = Vec::new();
+    let mut depth = vec![[0usize; 2]; target_length];
+    for (i, strand) in [Direction::Fwd, Direction::Rev].into_iter().enumerate() {
+        let tiler = tiler(strand);
+        for (n, placed) in tiler
+            .tile(&request.existing_primers)
+            .into_iter()
+            .enumerate()
+        {
+            let primer_start = placed.three_prime - placed.sequence.len() as i64 + 1;
+            let read_start = placed.three_prime + settings.dead_zone as i64 + 1;
+            let mut read_end = placed.three_prime + settings.read_length as i64;
+            if plasmid.topology == Topology::Linear {
+                read_end = read_end.min(tiler.length() - 1 - tiler.offset);
+            }
+            // A read can't cover more than the whole plasmid
+            let read_end = read_end.min(read_start + length as i64 - 1);
+
+            // Reverse reads are counted in reverse strand coordinates and flipped below
+            for position in read_start.max(0)..=read_end.min(target_length as i64 - 1) {
+                depth[position as usize][i] += 1;
+            }
+
+            let reused = placed.name.is_some();
+            primers.push(SequencingPrimer {
+                name: placed.name.unwrap_or_else(|| {
+                    let suffix = if strand == Direction::Fwd { "F" } else { "R" };
+                    format!("{} seq {}{}", label, suffix, n + 1)
+                }),
+                tm: melting_temperature(&placed.sequence, None, &settings.tm),
+                gc: fraction_gc(&placed.sequence),
+                binding_span: ordered(
+                    strand,
+                    to_top(strand, primer_start),
+                    to_top(strand, placed.three_prime),
+                ),
+                read_span: ordered(strand, to_top(strand, read_start), to_top(strand, read_end)),
+                sequence: placed.sequence,
+                strand,
+                reused,
+            });
+        }
+    }
+    let depth: Vec<[usize; 2]> = (0..target_length)
+        .map(|u| [depth[u][0], depth[target_length - 1 - u][1]])
+        .collect();
+
+    // Runs of bases without reads on each strand
+    let mut warnings = Vec::new();
+    for (i, strand_name) in ["forward", "reverse"].into_iter().enumerate() {
+        let mut u = 0;
+        while u < target_length {
+            if depth[u][i] > 0 {
+                u += 1;
+                continue;
+            }
+            let gap_start = u;
+            while u < target_length && depth[u][i] == 0 {
+                u += 1;
+            }
+            warnings.push(format!(
+                "No {} read covers {}..{}.",
+                strand_name,
+                to_top(Direction::Fwd, gap_start as i64),
+                to_top(Direction::Fwd, u as i64 - 1)
+            ));
+        }
+    }
+
+    let mut coverage: Vec<CoverageSegment> = Vec::new();
+    let mut segment_start = 0;
+    for u in 1..=target_length {
+        if u < target_length && depth[u] == depth[segment_start] {
+            continue;
+        }
+        let [forward, reverse] = depth[segment_start];
+        coverage.push(CoverageSegment {
+            span: [
+                to_top(Direction::Fwd, segment_start as i64),
+                to_top(Direction::Fwd, u as i64 - 1),
+            ],
+            forward,
+            reverse,
+        });
+        segment_start = u;
+    }
+    let uncovered = coverage
+        .iter()
+        .filter(|s| s.forward + s.reverse == 0)
+        .map(|s| s.span)
+        .collect();
+
+    Ok(SequencingPlan {
+        target_span: [start, end],
+        primers,
+        coverage,
+        uncovered,
+        warnings,
+    })
+}
+
+#[tauri::command]
+/// Plan sequencing primers verifying a region or feature of a plasmid.
+pub fn plan_sequencing_primers(request: SequencingRequest) -> Result<SequencingPlan, String> {
+    plan_sequencing(&request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequence::tests::RANDOM_PLASMID;
+
+    #[test]
+    fn tiling_advances_with_wide_search_window() {
+        let sequence = RANDOM_PLASMID.concat();
+        let settings = SequencingSettings {
+            read_length: 120,
+            dead_zone: 10,
+            min_overlap: 100,
+            search_window: 500,
+            ..Default::default()
+        };
+        let tiler = StrandTiler {
+            strand_sequence: sequence.clone(),
+            plasmid_sequence: &sequence,
+            topology: Topology::Circular,
+            offset: 0,
+            target_length: sequence.len() as i64,
+            settings: &settings,
+        };
+        let existing = [ExistingPrimer {
+            name: "existing".to_string(),
+            sequence: sequence[100..120].to_string(),
+        }];
+        let placed = tiler.tile(&existing);
+        assert!(placed.iter().any(|p| p.name.is_some()));
+        assert!(placed
+            .windows(2)
+            .all(|w| w[0].three_prime < w[1].three_prime));
+    }
+}
@@ -0,0 +1,528 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::primers::PrimerSet;
+use crate::sequence::{
+    complement_base, find_iupac_matches, iupac_compatible, repeating_slice, reverse_complementary,
+    sanitize_sequence, Direction, Topology,
+};
+
+/// Length of the shared stretches used to split differences.
+const DIFF_ANCHOR_LENGTH: usize = 12;
+
+/// Settings for the in-silico PCR and recombination.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SimulationSettings {
+    /// Bases at the 3' end of a primer that must anneal for it to prime.
+    pub min_anneal_length: usize,
+    /// Shortest homologous end that recombines.
+    pub min_homology_length: usize,
+    /// Longest homologous end looked for when joining amplicons.
+    pub max_homology_length: usize,
+    pub max_amplicon_length: usize,
+}
+
+impl Default for SimulationSettings {
+    fn default() -> Self {
+        Self {
+            min_anneal_length: 12,
+            min_homology_length: 12,
+            max_homology_length: 100,
+            max_amplicon_length: 20_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SimulationTemplate {
+    pub name: String,
+    pub sequence: String,
+    #[serde(default = "default_topology")]
+    pub topology: Topology,
+}
+
+fn default_topology() -> Topology {
+    Topology::Circular
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationRequest {
+    pub templates: Vec<SimulationTemplate>,
+    /// Primers listed pairwise, the forward and reverse primer of each PCR.
+    pub primer_set: PrimerSet,
+    /// Linear parts joined as they are, e.g. synthetic gene fragments.
+    #[serde(default)]
+    pub linear_fragments: Vec<String>,
+    /// Plasmid the design should produce, the primer set's
+    /// `currentPlasmidSequence` if missing.
+    #[serde(default)]
+    pub expected_sequence: Option<String>,
+    #[serde(default)]
+    pub settings: SimulationSettings,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Amplicon {
+    pub template: String,
+    pub forward_primer: String,
+    pub reverse_primer: String,
+    /// Template region between the annealed primer ends (1-based, inclusive).
+    /// Spans where the end lies before the start run across the origin.
+    pub template_span: [usize; 2],
+    /// Product including the 5' tails of both primers.
+    pub sequence: String,
+}
+
+/// Region where the simulated product differs from the expected plasmid.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequenceDifference {
+    /// Span in the expected plasmid (1-based, inclusive), the end lies before
+    /// the start for bases missing from the expected plasmid.
+    pub expected_span: [usize; 2],
+    pub expected: String,
+    pub observed: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationResult {
+    pub amplicons: Vec<Amplicon>,
+    /// Recombined product, rotated and oriented like the expected plasmid.
+    pub product: Option<String>,
+    pub matches_expected: bool,
+    pub differences: Vec<SequenceDifference>,
+    pub warnings: Vec<String>,
+}
+
+/// Where a primer anneals on a template.
+struct BindingSite {
+    primer: usize,
+    strand: Direction,
+    /// Template position (0-based, top strand) paired with the primer's 3' base.
+    three_prime: usize,
+    anneal_length: usize,
+}
+
+struct SimulatedPcr<'a> {
+    template: &'a SimulationTemplate,
+    sequence: String,
+    settings: &'a SimulationSettings,
+}
+
+impl SimulatedPcr<'_> {
+    fn wrap(&self, i: i64) -> Option<usize> {
+        let len = self.sequence.len() as i64;
+        match self.template.topology {
+            Topology::Circular => Some(i.rem_euclid(len) as usize),
+            Topology::Linear => (0..len).contains(&i).then_some(i as usize),
+        }
+    }
+
+    /// Sites where the 3' end of the primer anneals, extended towards the 5'
+    /// end for as long as the primer keeps pairing.
+    fn binding_sites(&self, index: usize, primer: &str) -> Vec<BindingSite> {
+        let p = primer.as_bytes();
+        let t = self.sequence.as_bytes();
+        let anchor_length = self.settings.min_anneal_length.min(p.len());
+        if anchor_length == 0 {
+            return Vec::new();
+        }
+        let anchor = &primer[p.len() - anchor_length..];
+        let topology = self.template.topology;
+        let max_anneal = p.len().min(t.len());
+
+        let mut sites = Vec::new();
+        for start in find_iupac_matches(&self.sequence, anchor, topology) {
+            let three_prime = start as i64 + anchor_length as i64 - 1;
+            let anneal_length = (anchor_length..max_anneal)
+                .find(|&i| {
+                    self.wrap(three_prime - i as i64)
+                        .is_none_or(|pos| !iupac_compatible(p[p.len() - 1 - i], t[pos]))
+                })
+                .unwrap_or(max_anneal);
+            sites.push(BindingSite {
+                primer: index,
+                strand: Direction::Fwd,
+                three_prime: three_prime as usize % t.len(),
+                anneal_length,
+            });
+        }
+        for start in find_iupac_matches(&self.sequence, &reverse_complementary(anchor), topology) {
+            let three_prime = start as i64;
+            let anneal_length = (anchor_length..max_anneal)
+                .find(|&i| {
+                    self.wrap(three_prime + i as i64).is_none_or(|pos| {
+                        !iupac_compatible(complement_base(p[p.len() - 1 - i]), t[pos])
+                    })
+                })
+                .unwrap_or(max_anneal);
+            sites.push(BindingSite {
+                primer: index,
+                strand: Direction::Rev,
+                three_prime: start,
+                anneal_length,
+            });
+        }
+        sites
+    }
+
+    /// Product primed by a forward and a reverse site, if they face each other.
+    fn amplicon(
+        &self,
+        fwd: &BindingSite,
+        rev: &BindingSite,
+        primers: &[(String, String)],
+    ) -> Option<Amplicon> {
+        let len = self.sequence.len() as i64;
+        let f = fwd.three_prime as i64;
+        let r = rev.three_prime as i64;
+        let gap = match self.template.topology {
+            Topology::Circular => (r - f - 1).rem_euclid(len),
+            Topology::Linear if r > f => r - f - 1,
+            Topology::Linear => return None,
+        };
+        if gap as usize > self.settings.max_amplicon_length {
+            return None;
+        }
+
+        let (fwd_name, fwd_seq) = &primers[fwd.primer];
+        let (rev_name, rev_seq) = &primers[rev.primer];
+        let sequence = format!(
+            "{}{}{}",
+            fwd_seq,
+            repeating_slice(&self.sequence, f + 1, f + 1 + gap),
+            reverse_complementary(rev_seq)
+        );
+        let span_start = (f - fwd.anneal_length as i64 + 1).rem_euclid(len) as usize + 1;
+        let span_end = (r + rev.anneal_length as i64 - 1).rem_euclid(len) as usize + 1;
+
+        Some(Amplicon {
+            template: self.template.name.clone(),
+            forward_primer: fwd_name.clone(),
+            reverse_primer: rev_name.clone(),
+            template_span: [span_start, span_end],
+            sequence,
+        })
+    }
+}
+
+/// Longest `k` (at least `min`, at most `max`) for which `a` ends with the first
+/// `k` bases of `b`.
+fn end_homology(a: &[u8], b: &[u8], min: usize, max: usize) -> Option<usize> {
+    (min..=max.min(a.len()).min(b.len())).rev().find(|&k| {
+        a[a.len() - k..]
+            .iter()
+            .zip(&b[..k])
+            .all(|(x, y)| iupac_compatible(*x, *y))
+    })
+}
+
+/// Join amplicons at their homologous ends and close the circle, like IVA
+/// recombination in the host.
+fn recombine(amplicons: &[String], settings: &SimulationSettings) -> Result<String, String> {
+    let (min, max) = (settings.min_homology_length, settings.max_homology_length);
+    let mut product = amplicons[0].clone();
+    let mut unused: Vec<&String> = amplicons[1..].iter().collect();
+
+    while !unused.is_empty() {
+        // Either strand of the next product can anneal
+        let next = unused
+            .iter()
+            .enumerate()
+            .flat_map(|(i, a)| [(i, a.to_string()), (i, reverse_complementary(a))])
+            .filter_map(|(i, a)| {
+                end_homology(product.as_bytes(), a.as_bytes(), min, max).map(|k| (i, a, k))
+            })
+            .max_by_key(|(_, _, k)| *k);
+        let Some((i, amplicon, k)) = next else {
+            return Err(format!(
+                "{} amplicon(s) share no homologous end with the rest of the assembly.",
+                unused.len()
+            ));
+        };
+        product.push_str(&amplicon[k..]);
+        unused.remove(i);
+    }
+
+    let closing = end_homology(
+        product.as_bytes(),
+        product.as_bytes(),
+        min,
+        max.min(product.len().saturating_sub(1)),
+    )
+    .ok_or("The ends of the assembly are not homologous, the product cannot circularise.")?;
+    product.truncate(product.len() - closing);
+    Ok(product)
+}
+
+/// Number of leading positions where two sequences agree.
+fn common_prefix(a: impl Iterator<Item = u8>, b: impl Iterator<Item = u8>) -> usize {
+    a.zip(b)
+        .take_while(|(x, y)| iupac_compatible(*x, *y))
+        .count()
+}
+
+/// Rotate and orient a circular product like the expected plasmid, returning it
+/// with the regions where it still differs.
+fn compare_circular(product: &str, expected: &str) -> (String, Vec<SequenceDifference>) {
+    let e = expected.as_bytes();
+    let mut best = (0, product.to_string());
+
+    for candidate in [product.to_string(), reverse_complementary(product)] {
+        let c = candidate.as_bytes();
+        let max_shared = c.len().min(e.len());
+        for rotation in 0..c.len() {
+            let rotated = || c[rotation..].iter().chain(&c[..rotation]).copied();
+            let prefix = common_prefix(rotated(), e.iter().copied());
+            let suffix = common_prefix(rotated().rev(), e.iter().rev().copied());
+            let shared = (prefix + suffix).min(max_shared);
+            if shared > best.0 {
+                best = (shared, rotated().map(char::from).collect());
+            }
+        }
+    }
+
+    let observed = best.1;
+    let mut differences = Vec::new();
+    diff_regions(observed.as_bytes(), e, 0, &mut differences);
+    (observed, differences)
+}
+
+/// Collect the regions where `observed` differs from `expected`, splitting them
+/// at shared stretches so separate mutations are reported separately.
+fn diff_regions(
+    observed: &[u8],
+    expected: &[u8],
+    offset: usize,
+    differences: &mut Vec<SequenceDifference>,
+) {
+    let prefix = common_prefix(observed.iter().copied(), expected.iter().copied());
+    let max_suffix = observed.len().min(expected.len()) - prefix;
+    let suffix = common_prefix(
+        observed.iter().rev().copied(),
+        expected.iter().rev().copied(),
+    )
+    .min(max_suffix);
+    let o = &observed[prefix..observed.len() - suffix];
+    let e = &expected[prefix..expected.len() - suffix];
+    let offset = offset + prefix;
+    if o.is_empty() && e.is_empty() {
+        return;
+    }
+
+    // Split at the first stretch the two sides still share
+    let kmers: HashMap<&[u8], usize> = o
+        .windows(DIFF_ANCHOR_LENGTH)
+        .enumerate()
+        .rev()
+        .map(|(i, kmer)| (kmer, i))
+        .collect();
+    let anchor = e
+        .windows(DIFF_ANCHOR_LENGTH)
+        .enumerate()
+        .find_map(|(ei, kmer)| kmers.get(kmer).map(|&oi| (oi, ei)));
+
+    match anchor {
+        Some((oi, ei)) => {
+            let shared = common_prefix(o[oi..].iter().copied(), e[ei..].iter().copied());
+            diff_regions(&o[..oi], &e[..ei], offset, differences);
+            diff_regions(
+                &o[oi + shared..],
+                &e[ei + shared..],
+                offset + ei + shared,
+                differences,
+            );
+        }
+        None => differences.push(SequenceDifference {
+            expected_span: [offset + 1, offset + e.len()],
+            expected: String::from_utf8_lossy(e).into_owned(),
+            observed: String::from_utf8_lossy(o).into_owned(),
+        }),
+    }
+}
+
+/// Predict the PCR products of a primer set and the plasmid they recombine into,
+/// and compare it with the plasmid the design is supposed to produce.
+pub fn simulate(request: &SimulationRequest) -> Result<SimulationResult, String> {
+    if request.templates.is_empty() {
+        return Err("No template to amplify.".to_string());
+    }
+    let primers: Vec<(String, String)> = request
+        .primer_set
+        .primers
+        .iter()
+        .map(|p| (p.label.clone(), sanitize_sequence(&p.sequence())))
+        .collect();
+
+    // Primers are listed pairwise, the forward and reverse primer of each reaction
+    if !primers.len().is_multiple_of(2) {
+        return Err("Primers must come in forward and reverse pairs.".to_string());
+    }
+    let pcrs: Vec<(SimulatedPcr, Vec<BindingSite>)> = request
+        .templates
+        .iter()
+        .map(|template| {
+            let pcr = SimulatedPcr {
+                template,
+                sequence: sanitize_sequence(&template.sequence),
+                settings: &request.settings,
+            };
+            let sites = primers
+                .iter()
+                .enumerate()
+                .flat_map(|(i, (_, seq))| pcr.binding_sites(i, seq))
+                .collect();
+            (pcr, sites)
+        })
+        .collect();
+
+    let mut warnings = Vec::new();
+    let mut amplicons = Vec::new();
+    let mut assembly_parts = Vec::new();
+    for reaction in (0..primers.len()).collect::<Vec<_>>().chunks(2) {
+        let in_reaction = |site: &&BindingSite| reaction.contains(&site.primer);
+        let mut products: Vec<(usize, Amplicon)> = Vec::new();
+        for (pcr, sites) in &pcrs {
+            for fwd in sites.iter().filter(in_reaction) {
+                for rev in sites.iter().filter(in_reaction) {
+                    if fwd.strand != Direction::Fwd || rev.strand != Direction::Rev {
+                        continue;
+                    }
+                    if let Some(amplicon) = pcr.amplicon(fwd, rev, &primers) {
+                        products.push((fwd.anneal_length + rev.anneal_length, amplicon));
+                    }
+                }
+            }
+        }
+        // Strongest and shortest product first
+        products.sort_by_key(|(strength, amplicon)| {
+            (std::cmp::Reverse(*strength), amplicon.sequence.len())
+        });
+
+        let names = format!(
+            "\"{}\" and \"{}\"",
+            primers[reaction[0]].0, primers[reaction[1]].0
+        );
+        match products.len() {
+            0 => {
+                warnings.push(format!("{} give no PCR product.", names));
+                continue;
+            }
+            1 => {}
+            n => warnings.push(format!(
+                "{} give {} PCR products, assuming the one with the longest annealing primers.",
+                names, n
+            )),
+        }
+        assembly_parts.push(products[0].1.sequence.clone());
+        amplicons.extend(products.into_iter().map(|(_, amplicon)| amplicon));
+    }
+
+    assembly_parts.extend(
+        request
+            .linear_fragments
+            .iter()
+            .map(|f| sanitize_sequence(f)),
+    );
+
+    let expected = sanitize_sequence(
+        request
+            .expected_sequence
+            .as_deref()
+            .unwrap_or(&request.primer_set.current_plasmid_sequence),
+    );
+
+    let (product, differences) = if assembly_parts.is_empty() {
+        (None, Vec::new())
+    } else {
+        match recombine(&assembly_parts, &request.settings) {
+            Ok(product) => {
+                let (product, differences) = compare_circular(&product, &expected);
+                (Some(product), differences)
+            }
+            Err(e) => {
+                warnings.push(e);
+                (None, Vec::new())
+            }
+        }
+    };
+
+    Ok(SimulationResult {
+        amplicons,
+        matches_expected: product.is_some() && differences.is_empty(),
+        product,
+        differences,
+        warnings,
+    })
+}
+
+#[tauri::command]
+/// Verify a primer set by simulating PCR and recombination on its template(s).
+pub fn simulate_design(request: SimulationRequest) -> Result<SimulationResult, String> {
+    simulate(&request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primers::{generate_set, DesignSettings, OperationType};
+    use crate::sequence::tests::RANDOM_PLASMID;
+
+    const INSERT: &str = "GATTACAGATTACAGATTACA";
+
+    fn insertion_request(expected: String) -> SimulationRequest {
+        let plasmid = RANDOM_PLASMID.concat();
+        let primer_set = generate_set(
+            OperationType::Insertion,
+            101,
+            None,
+            &plasmid,
+            INSERT,
+            Topology::Circular,
+            &DesignSettings::default(),
+        )
+        .unwrap();
+        SimulationRequest {
+            templates: vec![SimulationTemplate {
+                name: "template".to_string(),
+                sequence: plasmid,
+                topology: Topology::Circular,
+            }],
+            primer_set,
+            linear_fragments: Vec::new(),
+            expected_sequence: Some(expected),
+            settings: SimulationSettings::default(),
+        }
+    }
+
+    #[test]
+    fn insertion_primers_give_the_expected_plasmid() {
+        let plasmid = RANDOM_PLASMID.concat();
+        let expected = format!("{}{}{}", &plasmid[..100], INSERT, &plasmid[100..]);
+        let result = simulate(&insertion_request(expected.clone())).unwrap();
+        assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+        assert_eq!(result.amplicons.len(), 1);
+        assert!(result.matches_expected);
+        assert_eq!(result.product.as_deref(), Some(expected.as_str()));
+    }
+
+    #[test]
+    fn differences_to_the_expected_plasmid() {
+        let plasmid = RANDOM_PLASMID.concat();
+        let other = "GATTACAGATTCCAGATTACA";
+        let expected = format!("{}{}{}", &plasmid[..100], other, &plasmid[100..]);
+        let result = simulate(&insertion_request(expected)).unwrap();
+        assert!(!result.matches_expected);
+        let differences: Vec<([usize; 2], &str, &str)> = result
+            .differences
+            .iter()
+            .map(|d| (d.expected_span, d.expected.as_str(), d.observed.as_str()))
+            .collect();
+        assert_eq!(differences, [([112, 112], "C", "A")]);
+    }
+}
@@ -0,0 +1,129 @@
+use std::fs;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::Local;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Data kept in a JSON file of the app data directory, with a format number
+/// raised on incompatible changes.
+pub trait Versioned: Serialize + DeserializeOwned + Default {
+    /// Format written by this version of the app.
+    const FORMAT: u32;
+    /// What the file holds, for error messages.
+    const DESCRIPTION: &'static str;
+
+    /// Format of a file that was read.
+    fn format(&self) -> u32;
+}
+
+/// JSON file of the app data directory. Reads and changes go through a lock,
+/// so that commands running at the same time do not lose each other's edits
+/// or see a file that is still being written.
+pub struct Store<T> {
+    file_name: &'static str,
+    /// Indented output, for files users may share or look at.
+    pretty: bool,
+    lock: Mutex<()>,
+    data: PhantomData<fn() -> T>,
+}
+
+impl<T: Versioned> Store<T> {
+    pub const fn new(file_name: &'static str, pretty: bool) -> Self {
+        Self {
+            file_name,
+            pretty,
+            lock: Mutex::new(()),
+            data: PhantomData,
+        }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        crate::get_appdata_dir().join(self.file_name)
+    }
+
+    /// The stored data, the default if nothing was stored yet.
+    pub fn load(&self) -> Result<T, String> {
+        let _lock = self.lock.lock().map_err(|e| e.to_string())?;
+        read_file(&self.path())
+    }
+
+    pub fn save(&self, value: &T) -> Result<(), String> {
+        let _lock = self.lock.lock().map_err(|e| e.to_string())?;
+        write_file(&self.path(), value, self.pretty)
+    }
+
+    /// Read the data, apply a change and write it back.
+    pub fn modify<R>(&self, change: impl FnOnce(&mut T) -> Result<R, String>) -> Result<R, String> {
+        let _lock = self.lock.lock().map_err(|e| e.to_string())?;
+        let path = self.path();
+        let mut value = read_file(&path)?;
+        let result = change(&mut value)?;
+        write_file(&path, &value, self.pretty)?;
+        Ok(result)
+    }
+
+    /// Copy the stored data to another file, e.g. for sharing.
+    pub fn export(&self, path: &Path) -> Result<(), String> {
+        let _lock = self.lock.lock().map_err(|e| e.to_string())?;
+        write_file(path, &read_file::<T>(&self.path())?, self.pretty)
+    }
+}
+
+/// Read a file of a store, the default if it does not exist yet.
+pub fn read_file<T: Versioned>(path: &Path) -> Result<T, String> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let json = fs::read_to_string(path)
+        .map_err(|e| format!("Could not read \"{}\": {}", path.display(), e))?;
+    let value: T = serde_json::from_str(&json)
+        .map_err(|e| format!("Invalid {} \"{}\": {}", T::DESCRIPTION, path.display(), e))?;
+    if value.format() > T::FORMAT {
+        return Err(format!(
+            "\"{}\" was written by a newer version of IVA Prime.",
+            path.display()
+        ));
+    }
+    Ok(value)
+}
+
+/// Write a JSON file through a temporary file next to it, so that the file
+/// is replaced in one step and never left half written.
+pub fn write_file<T: Serialize>(path: &Path, value: &T, pretty: bool) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Could not create \"{}\": {}", parent.display(), e))?;
+    }
+    let json = if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    }
+    .map_err(|e| e.to_string())?;
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+    fs::write(&temporary, json)
+        .and_then(|_| fs::rename(&temporary, path))
+        .map_err(|e| {
+            let _ = fs::remove_file(&temporary);
+            format!("Could not write \"{}\": {}", path.display(), e)
+        })
+}
+
+/// Local time of a change, as stored in entries.
+pub fn timestamp() -> String {
+    Local::now().format("%Y-%m-%dT%H:%M:%S").to_string()
+}
+
+/// New entry id from the current time, not one `taken` already.
+pub fn new_id(taken: impl Fn(&str) -> bool) -> String {
+    let mut id = Local::now().timestamp_micros();
+    while taken(&format!("{:x}", id)) {
+        id += 1;
+    }
+    format!("{:x}", id)
+}
@@ -0,0 +1,95 @@
+use serde::Serialize;
+
+use crate::sequence::complement_base;
+
+/// Shortest loop a hairpin can close.
+const MIN_HAIRPIN_LOOP: usize = 3;
+
+/// Hairpin found in a single strand.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Hairpin {
+    /// Number of consecutive base pairs in the stem.
+    pub stem_length: usize,
+    /// 0-based start of the 5' arm of the stem.
+    pub start: usize,
+    pub loop_length: usize,
+}
+
+/// Longest perfectly paired hairpin stem in a sequence.
+pub fn longest_hairpin(seq: &str) -> Option<Hairpin> {
+    let s = seq.as_bytes();
+    let n = s.len();
+    let mut best: Option<Hairpin> = None;
+
+    // For every pairing i <-> j, walk inwards while bases keep pairing
+    for i in 0..n {
+        for j in (i + MIN_HAIRPIN_LOOP + 1..n).rev() {
+            let mut stem = 0;
+            while i + stem < j - stem
+                && j - stem - (i + stem) > MIN_HAIRPIN_LOOP
+                && complement_base(s[i + stem]) == s[j - stem]
+            {
+                stem += 1;
+            }
+            if stem > best.as_ref().map_or(0, |h| h.stem_length) {
+                best = Some(Hairpin {
+                    stem_length: stem,
+                    start: i,
+                    loop_length: (j - stem) - (i + stem) + 1,
+                });
+            }
+        }
+    }
+
+    best
+}
+
+/// Length of the longest common substring of two sequences.
+pub fn longest_common_substring(a: &str, b: &str) -> usize {
+    longest_common_run(a.as_bytes(), b.as_bytes())
+}
+
+fn longest_common_run(a: &[u8], b: &[u8]) -> usize {
+    let mut best = 0;
+    let mut prev = vec![0usize; b.len() + 1];
+    for &x in a {
+        let mut curr = vec![0usize; b.len() + 1];
+        for (j, &y) in b.iter().enumerate() {
+            if x == y {
+                curr[j + 1] = prev[j] + 1;
+                best = best.max(curr[j + 1]);
+            }
+        }
+        prev = curr;
+    }
+    best
+}
+
+/// Length of the longest run of a single base.
+pub fn longest_homopolymer(seq: &str) -> usize {
+    let s = seq.as_bytes();
+    let mut best = 0;
+    let mut run = 0;
+    for (i, &b) in s.iter().enumerate() {
+        run = if i > 0 && s[i - 1] == b { run + 1 } else { 1 };
+        best = best.max(run);
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hairpin_loop_covers_unpaired_bases() {
+        let hairpin = longest_hairpin("AAGGGGTTTTCCCCTT").unwrap();
+        assert_eq!(hairpin.stem_length, 6);
+        assert_eq!(hairpin.start, 0);
+        assert_eq!(hairpin.loop_length, 4);
+
+        let hairpin = longest_hairpin("GCGCTTTTTGCGC").unwrap();
+        assert_eq!((hairpin.stem_length, hairpin.loop_length), (4, 5));
+    }
+}
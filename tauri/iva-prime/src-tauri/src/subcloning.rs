@@ -0,0 +1,254 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::export::{consolidate_order, order_sheet_table, table_to_csv, MixedBaseFormat};
+use crate::plasmid_file::{
+    read_plasmid_file, write_genbank, Feature, PlasmidFile, RegionSelection,
+};
+use crate::primers::{generate_subcloning_set, DesignSettings, PrimerSet};
+use crate::sequence::{reverse_complementary, sanitize_sequence, span_sequence, Direction};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubcloningFileRequest {
+    /// Target vector the region is subcloned into.
+    pub recipient_path: PathBuf,
+    pub donor_path: PathBuf,
+    pub region: RegionSelection,
+    /// Orientation of the region in the recipient, `rev` to subclone the
+    /// reverse complement of the donor's top strand.
+    #[serde(default = "default_direction")]
+    pub direction: Direction,
+    pub operation_start: i64,
+    /// Missing to insert without replacing any of the recipient.
+    #[serde(default)]
+    pub operation_end: Option<i64>,
+    /// Extra bases added in front of the subcloned region.
+    #[serde(default)]
+    pub seq_5_prime: String,
+    /// Extra bases added behind the subcloned region.
+    #[serde(default)]
+    pub seq_3_prime: String,
+    /// GenBank file for the resulting plasmid, not written if missing.
+    #[serde(default)]
+    pub output_path: Option<PathBuf>,
+    /// CSV order sheet for the primers, not written if missing.
+    #[serde(default)]
+    pub primers_path: Option<PathBuf>,
+    #[serde(default)]
+    pub mixed_base_format: MixedBaseFormat,
+    #[serde(default)]
+    pub settings: DesignSettings,
+}
+
+fn default_direction() -> Direction {
+    Direction::Fwd
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubcloningFileResult {
+    pub primer_set: PrimerSet,
+    /// Recipient with the subcloned region and the donor's features in it.
+    pub plasmid: PlasmidFile,
+    /// Donor region that was subcloned (1-based, inclusive).
+    pub donor_span: [usize; 2],
+    pub order_sheet_csv: String,
+}
+
+/// 0-based offset of a donor position inside a donor span, if it lies in it.
+fn offset_in_span(position: usize, span: [usize; 2], donor_length: usize) -> Option<usize> {
+    let [start, end] = span;
+    let length = (end + donor_length - start) % donor_length + 1;
+    let offset = (position + donor_length - start) % donor_length;
+    (offset < length).then_some(offset)
+}
+
+/// Features of the resulting plasmid: recipient features clear of the replaced
+/// range, shifted behind the insertion, and donor features lying fully inside
+/// the subcloned region.
+fn result_features(
+    recipient: &PlasmidFile,
+    donor: &PlasmidFile,
+    donor_span: [usize; 2],
+    direction: Direction,
+    [start, end]: [usize; 2],
+    [length_5_prime, target_length, length_3_prime]: [usize; 3],
+) -> Vec<Feature> {
+    let inserted_length = length_5_prime + target_length + length_3_prime;
+    let shift = |position: usize| {
+        if position >= start {
+            position + inserted_length - (end + 1 - start)
+        } else {
+            position
+        }
+    };
+
+    let mut features: Vec<Feature> = recipient
+        .features
+        .iter()
+        .filter(|f| match f.span {
+            [f_start, f_end] if f_start <= f_end => f_end < start || f_start > end,
+            // Across the origin
+            [f_start, f_end] => f_end < start && f_start > end,
+        })
+        .cloned()
+        .map(|mut f| {
+            f.span = [shift(f.span[0]), shift(f.span[1])];
+            for segment in &mut f.segments {
+                *segment = [shift(segment[0]), shift(segment[1])];
+            }
+            f
+        })
+        .collect();
+
+    let target_start = start + length_5_prime;
+    for feature in &donor.features {
+        let [f_start, f_end] = feature.span;
+        let offsets = (
+            offset_in_span(f_start, donor_span, donor.sequence.len()),
+            offset_in_span(f_end, donor_span, donor.sequence.len()),
+        );
+        let (Some(offset_start), Some(offset_end)) = offsets else {
+            continue;
+        };
+        if offset_end < offset_start {
+            continue;
+        }
+        let mut feature = feature.clone();
+        let place = |position: usize| {
+            offset_in_span(position, donor_span, donor.sequence.len()).map(|offset| match direction
+            {
+                Direction::Fwd => target_start + offset,
+                Direction::Rev => target_start + target_length - 1 - offset,
+            })
+        };
+        feature.segments = feature
+            .segments
+            .iter()
+            .filter_map(|[a, b]| Some([place(*a)?, place(*b)?]))
+            .collect();
+        match direction {
+            Direction::Fwd => {
+                feature.span = [target_start + offset_start, target_start + offset_end];
+            }
+            Direction::Rev => {
+                feature.span = [
+                    target_start + target_length - 1 - offset_end,
+                    target_start + target_length - 1 - offset_start,
+                ];
+                // Parts flip along with the region
+                feature.segments = feature
+                    .segments
+                    .iter()
+                    .rev()
+                    .map(|[a, b]| [*b, *a])
+                    .collect();
+                feature.directionality = feature.directionality.map(|d| match d {
+                    Direction::Fwd => Direction::Rev,
+                    Direction::Rev => Direction::Fwd,
+                });
+            }
+        }
+        features.push(feature);
+    }
+
+    features.sort_by_key(|f| f.span[0]);
+    features
+}
+
+/// Subclone a region of a donor plasmid file into a recipient plasmid file.
+///
+/// Primers are designed against the recipient with `generate_subcloning_set`.
+/// The resulting plasmid and the order sheet are written to the requested
+/// paths, if any.
+pub fn subclone_from_files(
+    request: &SubcloningFileRequest,
+) -> Result<SubcloningFileResult, String> {
+    let recipient = read_plasmid_file(&request.recipient_path)?;
+    let donor = read_plasmid_file(&request.donor_path)?;
+
+    let donor_span = donor.resolve_region(&request.region)?;
+    let region = span_sequence(&donor.sequence, donor_span);
+    let subcloning_target = match request.direction {
+        Direction::Fwd => region,
+        Direction::Rev => reverse_complementary(&region),
+    };
+
+    let [start, end] = match request.operation_end {
+        None => [request.operation_start, request.operation_start - 1],
+        Some(end) => [
+            request.operation_start.min(end),
+            request.operation_start.max(end),
+        ],
+    };
+    let recipient_length = recipient.sequence.len() as i64;
+    if start < 1 || start > recipient_length + 1 || end < 0 || end > recipient_length {
+        return Err("Operation range lies outside of the recipient plasmid.".to_string());
+    }
+
+    let seq_5_prime = sanitize_sequence(&request.seq_5_prime);
+    let seq_3_prime = sanitize_sequence(&request.seq_3_prime);
+    let primer_set = generate_subcloning_set(
+        start,
+        request.operation_end,
+        &recipient.sequence,
+        &subcloning_target,
+        &seq_5_prime,
+        &seq_3_prime,
+        recipient.topology,
+        &request.settings,
+    )
+    .map_err(|e| e.to_string())?;
+
+    let features = result_features(
+        &recipient,
+        &donor,
+        donor_span,
+        request.direction,
+        [start as usize, end as usize],
+        [
+            seq_5_prime.len(),
+            subcloning_target.len(),
+            seq_3_prime.len(),
+        ],
+    );
+    let plasmid = PlasmidFile {
+        name: format!("{}_{}", recipient.name, donor.name),
+        sequence: primer_set.current_plasmid_sequence.clone(),
+        topology: recipient.topology,
+        features,
+    };
+
+    let order = consolidate_order(std::slice::from_ref(&primer_set));
+    let order_sheet_csv = table_to_csv(&order_sheet_table(&order, request.mixed_base_format));
+
+    if let Some(path) = &request.output_path {
+        write_genbank(path, &plasmid)?;
+        log::info!("Subcloning result written -> \"{}\"", path.display());
+    }
+    if let Some(path) = &request.primers_path {
+        write_order_sheet(path, &order_sheet_csv)?;
+        log::info!("Subcloning primers written -> \"{}\"", path.display());
+    }
+
+    Ok(SubcloningFileResult {
+        primer_set,
+        plasmid,
+        donor_span,
+        order_sheet_csv,
+    })
+}
+
+fn write_order_sheet(path: &Path, csv: &str) -> Result<(), String> {
+    fs::write(path, csv).map_err(|e| format!("Could not write \"{}\": {}", path.display(), e))
+}
+
+#[tauri::command]
+/// Subclone a region of a plasmid file on disk into another plasmid file,
+/// without either being open in the webview.
+pub fn subclone_from_file(request: SubcloningFileRequest) -> Result<SubcloningFileResult, String> {
+    subclone_from_files(&request)
+}
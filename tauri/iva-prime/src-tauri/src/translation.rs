@@ -0,0 +1,498 @@
+use serde::{Deserialize, Serialize};
+
+use crate::plasmid_file::Feature;
+use crate::sequence::{
+    expand_degenerate, reverse_complementary, sanitize_sequence, span_sequence, Direction, Topology,
+};
+
+/// NCBI translation table. Amino acids and start codons are listed for all 64
+/// codons in NCBI order (TCAG for each position, TTT first).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeneticCode {
+    pub id: u8,
+    pub name: &'static str,
+    pub amino_acids: &'static str,
+    /// `M` for codons that can start translation.
+    pub starts: &'static str,
+}
+
+macro_rules! genetic_code {
+    ($id:expr, $name:expr, $amino_acids:expr, $starts:expr) => {
+        GeneticCode {
+            id: $id,
+            name: $name,
+            amino_acids: $amino_acids,
+            starts: $starts,
+        }
+    };
+}
+
+/// All NCBI translation tables. Stop codons that are read through depending on
+/// their context (tables 27, 28 and 31) translate as their amino acid.
+#[rustfmt::skip]
+pub static GENETIC_CODES: [GeneticCode; 26] = [
+    genetic_code!(1, "Standard",
+        "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "---M------**--*----M---------------M----------------------------"),
+    genetic_code!(2, "Vertebrate Mitochondrial",
+        "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG",
+        "----------**--------------------MMMM----------**---M------------"),
+    genetic_code!(3, "Yeast Mitochondrial",
+        "FFLLSSSSYY**CCWWTTTTPPPPHHQQRRRRIIMMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "----------**----------------------MM---------------M------------"),
+    genetic_code!(4, "Mold, Protozoan, and Coelenterate Mitochondrial and Mycoplasma/Spiroplasma",
+        "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "--MM------**-------M------------MMMM---------------M------------"),
+    genetic_code!(5, "Invertebrate Mitochondrial",
+        "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSSSVVVVAAAADDEEGGGG",
+        "---M------**--------------------MMMM---------------M------------"),
+    genetic_code!(6, "Ciliate, Dasycladacean and Hexamita Nuclear",
+        "FFLLSSSSYYQQCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "--------------*--------------------M----------------------------"),
+    genetic_code!(9, "Echinoderm and Flatworm Mitochondrial",
+        "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        "----------**-----------------------M---------------M------------"),
+    genetic_code!(10, "Euplotid Nuclear",
+        "FFLLSSSSYY**CCCWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "----------**-----------------------M----------------------------"),
+    genetic_code!(11, "Bacterial, Archaeal and Plant Plastid",
+        "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "---M------**--*----M------------MMMM---------------M------------"),
+    genetic_code!(12, "Alternative Yeast Nuclear",
+        "FFLLSSSSYY**CC*WLLLSPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "----------**--*----M---------------M----------------------------"),
+    genetic_code!(13, "Ascidian Mitochondrial",
+        "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSGGVVVVAAAADDEEGGGG",
+        "---M------**----------------------MM---------------M------------"),
+    genetic_code!(14, "Alternative Flatworm Mitochondrial",
+        "FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        "-----------*-----------------------M----------------------------"),
+    genetic_code!(16, "Chlorophycean Mitochondrial",
+        "FFLLSSSSYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "----------*---*--------------------M----------------------------"),
+    genetic_code!(21, "Trematode Mitochondrial",
+        "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        "----------**-----------------------M---------------M------------"),
+    genetic_code!(22, "Scenedesmus obliquus Mitochondrial",
+        "FFLLSS*SYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "------*---*---*--------------------M----------------------------"),
+    genetic_code!(23, "Thraustochytrium Mitochondrial",
+        "FF*LSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "--*-------**--*-----------------M--M---------------M------------"),
+    genetic_code!(24, "Rhabdopleuridae Mitochondrial",
+        "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG",
+        "---M------**-------M---------------M---------------M------------"),
+    genetic_code!(25, "Candidate Division SR1 and Gracilibacteria",
+        "FFLLSSSSYY**CCGWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "---M------**-----------------------M---------------M------------"),
+    genetic_code!(26, "Pachysolen tannophilus Nuclear",
+        "FFLLSSSSYY**CC*WLLLAPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "----------**--*----M---------------M----------------------------"),
+    genetic_code!(27, "Karyorelict Nuclear",
+        "FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "--------------*--------------------M----------------------------"),
+    genetic_code!(28, "Condylostoma Nuclear",
+        "FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "----------**--*--------------------M----------------------------"),
+    genetic_code!(29, "Mesodinium Nuclear",
+        "FFLLSSSSYYYYCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "--------------*--------------------M----------------------------"),
+    genetic_code!(30, "Peritrich Nuclear",
+        "FFLLSSSSYYEECC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "--------------*--------------------M----------------------------"),
+    genetic_code!(31, "Blastocrithidia Nuclear",
+        "FFLLSSSSYYEECCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "----------**-----------------------M----------------------------"),
+    genetic_code!(32, "Balanophoraceae Plastid",
+        "FFLLSSSSYY*WCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "---M------*---*----M------------MMMM---------------M------------"),
+    genetic_code!(33, "Cephalodiscidae Mitochondrial",
+        "FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG",
+        "---M-------*-------M---------------M---------------M------------"),
+];
+
+/// Look up an NCBI translation table by its number.
+pub fn genetic_code(id: u8) -> Option<&'static GeneticCode> {
+    GENETIC_CODES.iter().find(|code| code.id == id)
+}
+
+/// Index of a concrete codon in NCBI order, `None` for anything but ACGT.
+fn codon_index(codon: &[u8]) -> Option<usize> {
+    if codon.len() != 3 {
+        return None;
+    }
+    codon.iter().try_fold(0, |index, base| {
+        let value = match base.to_ascii_uppercase() {
+            b'T' | b'U' => 0,
+            b'C' => 1,
+            b'A' => 2,
+            b'G' => 3,
+            _ => return None,
+        };
+        Some(index * 4 + value)
+    })
+}
+
+impl GeneticCode {
+    /// Translate a codon. Degenerate codons translate if all the codons they
+    /// stand for give the same amino acid, `X` otherwise.
+    pub fn translate_codon(&self, codon: &str) -> char {
+        let expanded = match expand_degenerate(&codon.to_ascii_uppercase(), 64) {
+            Some(expanded) => expanded,
+            None => return 'X',
+        };
+        let mut amino_acids = expanded.iter().map(|c| {
+            codon_index(c.as_bytes())
+                .map(|i| self.amino_acids.as_bytes()[i] as char)
+                .unwrap_or('X')
+        });
+        let first = amino_acids.next().unwrap_or('X');
+        if amino_acids.all(|aa| aa == first) {
+            first
+        } else {
+            'X'
+        }
+    }
+
+    pub fn is_start(&self, codon: &str) -> bool {
+        codon_index(codon.as_bytes()).is_some_and(|i| self.starts.as_bytes()[i] == b'M')
+    }
+
+    pub fn is_stop(&self, codon: &str) -> bool {
+        self.translate_codon(codon) == '*'
+    }
+
+    /// Translate a sequence codon by codon, trailing bases are left out. With
+    /// `initiator` an alternative start codon in front translates as `M`.
+    pub fn translate(&self, sequence: &str, initiator: bool) -> String {
+        sequence
+            .as_bytes()
+            .chunks_exact(3)
+            .enumerate()
+            .map(|(i, codon)| {
+                let codon = String::from_utf8_lossy(codon);
+                if i == 0 && initiator && self.is_start(&codon) {
+                    'M'
+                } else {
+                    self.translate_codon(&codon)
+                }
+            })
+            .collect()
+    }
+}
+
+/// Value of a feature qualifier.
+fn qualifier<'a>(feature: &'a Feature, key: &str) -> Option<&'a str> {
+    feature
+        .qualifiers
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.trim_matches('"'))
+}
+
+/// Translation table of a feature from its `/transl_table` qualifier, table 11
+/// if there is none.
+pub fn feature_genetic_code(feature: &Feature) -> Result<&'static GeneticCode, String> {
+    match qualifier(feature, "transl_table") {
+        Some(value) => value
+            .trim()
+            .parse()
+            .ok()
+            .and_then(genetic_code)
+            .ok_or_else(|| format!("Unknown translation table \"{}\".", value)),
+        None => Ok(genetic_code(11).expect("Table 11 is defined")),
+    }
+}
+
+/// Coding strand sequence of a feature, joining its parts and reading across
+/// the origin where needed.
+pub fn feature_sequence(sequence: &str, feature: &Feature) -> String {
+    let top_strand = if feature.segments.is_empty() {
+        span_sequence(sequence, feature.span)
+    } else {
+        feature
+            .segments
+            .iter()
+            .map(|segment| span_sequence(sequence, *segment))
+            .collect()
+    };
+    match feature.directionality {
+        Some(Direction::Rev) => reverse_complementary(&top_strand),
+        _ => top_strand,
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureTranslationRequest {
+    pub sequence: String,
+    pub feature: Feature,
+    /// Overrides the feature's `/transl_table`.
+    #[serde(default)]
+    pub table: Option<u8>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureTranslation {
+    pub protein: String,
+    pub table: u8,
+    /// Base of the feature the first codon starts at, from `/codon_start`.
+    pub codon_start: usize,
+    /// Bases left over behind the last full codon.
+    pub trailing_bases: usize,
+}
+
+/// Translate a CDS feature, honouring `/transl_table` and `/codon_start`. The
+/// first codon translates as `M` if it is a start codon of the table.
+pub fn translate_feature(
+    sequence: &str,
+    feature: &Feature,
+    table: Option<u8>,
+) -> Result<FeatureTranslation, String> {
+    let code = match table {
+        Some(id) => genetic_code(id).ok_or_else(|| format!("Unknown translation table {}.", id))?,
+        None => feature_genetic_code(feature)?,
+    };
+    let codon_start = match qualifier(feature, "codon_start") {
+        Some(value) => value
+            .trim()
+            .parse()
+            .ok()
+            .filter(|start| (1..=3).contains(start))
+            .ok_or_else(|| format!("Invalid codon start \"{}\".", value))?,
+        None => 1,
+    };
+    let sequence = sanitize_sequence(sequence);
+    let in_bounds = |i: usize| i >= 1 && i <= sequence.len();
+    for [start, end] in std::iter::once(feature.span).chain(feature.segments.iter().copied()) {
+        if !in_bounds(start) || !in_bounds(end) {
+            return Err(format!("Span [{}, {}] is out of bounds.", start, end));
+        }
+    }
+    let coding = feature_sequence(&sequence, feature);
+    let coding = coding.get(codon_start - 1..).unwrap_or_default();
+    Ok(FeatureTranslation {
+        protein: code.translate(coding, codon_start == 1),
+        table: code.id,
+        codon_start,
+        trailing_bases: coding.len() % 3,
+    })
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SixFrameRequest {
+    pub sequence: String,
+    pub topology: Topology,
+    /// Selection to translate (1-based, inclusive). Spans where the end lies
+    /// before the start run across the origin of circular plasmids.
+    pub span: [usize; 2],
+    #[serde(default = "default_table")]
+    pub table: u8,
+}
+
+fn default_table() -> u8 {
+    1
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrameTranslation {
+    /// 1 to 3 on the top strand, -1 to -3 on the bottom strand.
+    pub frame: i8,
+    pub protein: String,
+    /// Plasmid bases of the first and last translated codon (1-based,
+    /// inclusive), in reading direction.
+    pub span: [usize; 2],
+}
+
+/// Translate a selection in all six reading frames. Frames too short to
+/// hold a full codon are left out.
+pub fn six_frame_translation(request: &SixFrameRequest) -> Result<Vec<FrameTranslation>, String> {
+    let sequence = sanitize_sequence(&request.sequence);
+    let code = genetic_code(request.table)
+        .ok_or_else(|| format!("Unknown translation table {}.", request.table))?;
+    let [start, end] = request.span;
+    let in_bounds = |i: usize| i >= 1 && i <= sequence.len();
+    if !in_bounds(start) || !in_bounds(end) {
+        return Err(format!("Span [{}, {}] is out of bounds.", start, end));
+    }
+    if end < start && request.topology == Topology::Linear {
+        return Err("Linear sequences cannot be read across the origin.".to_string());
+    }
+
+    let selection = span_sequence(&sequence, request.span);
+    let bottom_strand = reverse_complementary(&selection);
+    let length = sequence.len() as i64;
+    let wrap = |position: i64| ((position - 1).rem_euclid(length) + 1) as usize;
+    let start = start as i64;
+    let selection_end = start + selection.len() as i64 - 1;
+    let mut frames = Vec::new();
+    for offset in 0..3 {
+        // Frames without a full codon have nothing to translate
+        let codons = selection.len().saturating_sub(offset) / 3;
+        if codons == 0 {
+            continue;
+        }
+        let bases = 3 * codons as i64;
+        frames.push(FrameTranslation {
+            frame: offset as i8 + 1,
+            protein: code.translate(&selection[offset..], false),
+            span: [
+                wrap(start + offset as i64),
+                wrap(start + offset as i64 + bases - 1),
+            ],
+        });
+        frames.push(FrameTranslation {
+            frame: -(offset as i8 + 1),
+            protein: code.translate(&bottom_strand[offset..], false),
+            span: [
+                wrap(selection_end - offset as i64),
+                wrap(selection_end - offset as i64 - bases + 1),
+            ],
+        });
+    }
+    frames.sort_by_key(|f| (f.frame < 0, f.frame.abs()));
+    Ok(frames)
+}
+
+#[tauri::command]
+/// NCBI translation tables.
+pub fn list_genetic_codes() -> Vec<GeneticCode> {
+    GENETIC_CODES.to_vec()
+}
+
+#[tauri::command]
+/// Protein encoded by a CDS feature.
+pub fn translate_cds_feature(
+    request: FeatureTranslationRequest,
+) -> Result<FeatureTranslation, String> {
+    translate_feature(&request.sequence, &request.feature, request.table)
+}
+
+#[tauri::command]
+/// Six-frame translation of a selection.
+pub fn translate_six_frames(request: SixFrameRequest) -> Result<Vec<FrameTranslation>, String> {
+    six_frame_translation(&request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cds(span: [usize; 2], segments: Vec<[usize; 2]>) -> Feature {
+        Feature {
+            label: "CDS".to_string(),
+            feature_type: "CDS".to_string(),
+            span,
+            directionality: Some(Direction::Fwd),
+            segments,
+            qualifiers: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn feature_outside_sequence_is_an_error() {
+        let sequence = "ATGAAATAA".repeat(10);
+        assert!(translate_feature(&sequence, &cds([500, 10], Vec::new()), None).is_err());
+        assert!(translate_feature(&sequence, &cds([1, 9], vec![[1, 6], [95, 99]]), None).is_err());
+
+        let across_origin = translate_feature(&sequence, &cds([82, 6], Vec::new()), None).unwrap();
+        assert_eq!(across_origin.protein, "MK*MK");
+    }
+
+    fn six_frames(sequence: &str, span: [usize; 2]) -> Vec<FrameTranslation> {
+        six_frame_translation(&SixFrameRequest {
+            sequence: sequence.to_string(),
+            topology: Topology::Circular,
+            span,
+            table: 1,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn short_selections_skip_empty_frames() {
+        let sequence = "ATGGCCTGA".repeat(3);
+        assert!(six_frames(&sequence, [1, 1]).is_empty());
+        assert!(six_frames(&sequence, [27, 1]).is_empty());
+
+        let codon = six_frames(&sequence, [1, 3]);
+        assert_eq!(codon.len(), 2);
+        assert_eq!((codon[0].frame, codon[0].protein.as_str()), (1, "M"));
+        assert_eq!(codon[0].span, [1, 3]);
+        assert_eq!((codon[1].frame, codon[1].protein.as_str()), (-1, "H"));
+        assert_eq!(codon[1].span, [3, 1]);
+    }
+
+    #[test]
+    fn six_frames_across_the_origin() {
+        // TGA ATG GCC read across the origin of a 27 bp plasmid
+        let sequence = "ATGGCCTGA".repeat(3);
+        let frames = six_frames(&sequence, [25, 6]);
+        let summary: Vec<(i8, &str, [usize; 2])> = frames
+            .iter()
+            .map(|f| (f.frame, f.protein.as_str(), f.span))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (1, "*MA", [25, 6]),
+                (2, "EW", [26, 4]),
+                (3, "NG", [27, 5]),
+                (-1, "GHS", [6, 25]),
+                (-2, "AI", [5, 27]),
+                (-3, "PF", [4, 26]),
+            ]
+        );
+    }
+
+    #[test]
+    fn genetic_codes_match_ncbi() {
+        let ids: Vec<u8> = GENETIC_CODES.iter().map(|code| code.id).collect();
+        assert_eq!(
+            ids,
+            [
+                1, 2, 3, 4, 5, 6, 9, 10, 11, 12, 13, 14, 16, 21, 22, 23, 24, 25, 26, 27, 28, 29,
+                30, 31, 32, 33
+            ]
+        );
+        for code in &GENETIC_CODES {
+            assert_eq!(code.amino_acids.len(), 64, "table {}", code.id);
+            assert_eq!(code.starts.len(), 64, "table {}", code.id);
+        }
+
+        // Codon reassignments of a few tables
+        let translate = |id: u8, codon: &str| genetic_code(id).unwrap().translate_codon(codon);
+        assert_eq!(translate(1, "TGA"), '*');
+        assert_eq!(translate(2, "TGA"), 'W');
+        assert_eq!(translate(2, "AGA"), '*');
+        assert_eq!(translate(2, "ATA"), 'M');
+        assert_eq!(translate(3, "CTG"), 'T');
+        assert_eq!(translate(4, "TGA"), 'W');
+        assert_eq!(translate(6, "TAA"), 'Q');
+        assert_eq!(translate(6, "TAG"), 'Q');
+        assert_eq!(translate(25, "TGA"), 'G');
+        assert_eq!(translate(1, "GCN"), 'A');
+        assert_eq!(translate(1, "GAN"), 'X');
+
+        let starts = |id: u8| -> Vec<&str> {
+            let code = genetic_code(id).unwrap();
+            ["TTG", "CTG", "ATT", "ATC", "ATA", "ATG", "GTG"]
+                .into_iter()
+                .filter(|codon| code.is_start(codon))
+                .collect()
+        };
+        assert_eq!(starts(1), ["TTG", "CTG", "ATG"]);
+        assert_eq!(
+            starts(11),
+            ["TTG", "CTG", "ATT", "ATC", "ATA", "ATG", "GTG"]
+        );
+
+        let bacterial = genetic_code(11).unwrap();
+        assert_eq!(bacterial.translate("GTGGCCAAGTAAG", true), "MAK*");
+        assert_eq!(bacterial.translate("GTGGCCAAGTAAG", false), "VAK*");
+    }
+}
@@ -0,0 +1,665 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::alignment::{AlignOp, AlignmentScoring, ReferenceIndex};
+use crate::plasmid_file::{Feature, PlasmidFile, RegionSelection};
+use crate::reads::{quality_trim, read_sequencing_reads, SequencingRead};
+use crate::sequence::{sanitize_sequence, Direction, Topology};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct VerificationSettings {
+    /// Error probability limit for trimming read ends (modified Mott algorithm).
+    pub trim_error_limit: f64,
+    pub kmer_length: usize,
+    /// Allowed drift from the seeded diagonal, in bases.
+    pub band: usize,
+    /// Reads below this identity are reported as unmapped.
+    pub min_identity: f64,
+    pub min_aligned_length: usize,
+    /// A difference counts once this many reads show it, or once a read
+    /// shows it with at least `min_quality`.
+    pub min_reads: usize,
+    pub min_quality: u8,
+    pub scoring: AlignmentScoring,
+}
+
+impl Default for VerificationSettings {
+    fn default() -> Self {
+        Self {
+            trim_error_limit: 0.05,
+            kmer_length: 12,
+            band: 40,
+            min_identity: 0.8,
+            min_aligned_length: 50,
+            min_reads: 2,
+            min_quality: 20,
+            scoring: AlignmentScoring::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationRequest {
+    /// Expected construct.
+    pub plasmid: PlasmidFile,
+    /// Sanger reads (`.ab1`, `.fasta`, `.fastq`, `.seq`).
+    pub read_paths: Vec<PathBuf>,
+    /// Region that must be confirmed, the whole plasmid if missing.
+    #[serde(default)]
+    pub target: Option<RegionSelection>,
+    #[serde(default)]
+    pub settings: VerificationSettings,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Verdict {
+    /// Whole target read without differences.
+    Confirmed,
+    /// Confirmed differences to the expected construct were found.
+    Mismatch,
+    /// No confirmed differences, but parts of the target were not read or
+    /// differences need more reads.
+    Incomplete,
+    /// No read could be mapped.
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadAlignment {
+    pub name: String,
+    pub mapped: bool,
+    pub strand: Option<Direction>,
+    /// Part of the read kept after quality trimming (1-based, inclusive).
+    pub trimmed_span: [usize; 2],
+    /// Plasmid region the read aligns to. Spans where the end lies before
+    /// the start run across the origin.
+    pub reference_span: Option<[usize; 2]>,
+    pub identity: f64,
+    pub score: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DifferenceKind {
+    Mismatch,
+    Insertion,
+    Deletion,
+}
+
+/// Difference between the reads and the expected construct.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadDifference {
+    pub kind: DifferenceKind,
+    /// Affected plasmid bases. Insertions sit between the two bases of the span.
+    pub span: [usize; 2],
+    pub expected: String,
+    /// Top strand bases found in the reads.
+    pub observed: String,
+    /// Lowest read quality over the observed bases, if the read has qualities.
+    pub quality: Option<u8>,
+    /// Reads showing the difference.
+    pub reads: Vec<String>,
+    /// Shown by most reads covering the place, and by enough reads or with
+    /// enough quality.
+    pub confirmed: bool,
+    /// Labels of the features the difference lies in.
+    pub features: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnconfirmedRegion {
+    pub span: [usize; 2],
+    pub features: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureVerdict {
+    pub label: String,
+    pub span: [usize; 2],
+    /// Fraction of the feature's bases within the target covered by reads.
+    pub covered: f64,
+    pub differences: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationResult {
+    pub verdict: Verdict,
+    pub target_span: [usize; 2],
+    pub reads: Vec<ReadAlignment>,
+    pub differences: Vec<ReadDifference>,
+    pub unconfirmed: Vec<UnconfirmedRegion>,
+    pub features: Vec<FeatureVerdict>,
+    /// Plain text version of the result.
+    pub report: String,
+}
+
+/// True if a 1-based position lies in a span, spans may run across the origin.
+pub fn span_contains(span: [usize; 2], position: usize) -> bool {
+    let [start, end] = span;
+    if start <= end {
+        (start..=end).contains(&position)
+    } else {
+        position >= start || position <= end
+    }
+}
+
+/// Labels of the features overlapping a span.
+pub fn features_at(features: &[Feature], span: [usize; 2]) -> Vec<String> {
+    features
+        .iter()
+        .filter(|f| {
+            span_contains(f.span, span[0])
+                || span_contains(f.span, span[1])
+                || span_contains(span, f.span[0])
+        })
+        .map(|f| f.label.clone())
+        .collect()
+}
+
+/// Maximal runs (0-based, inclusive) of positions where `keep` holds.
+pub fn runs(length: usize, keep: impl Fn(usize) -> bool) -> Vec<[usize; 2]> {
+    let mut runs = Vec::new();
+    let mut start = None;
+    for i in 0..=length {
+        match (i < length && keep(i), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                runs.push([s, i - 1]);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    runs
+}
+
+/// Join the runs at both ends of a circular plasmid into one run across the
+/// origin, given runs (0-based, inclusive) sorted by position.
+pub fn join_across_origin(
+    mut runs: Vec<[usize; 2]>,
+    length: usize,
+    topology: Topology,
+) -> Vec<[usize; 2]> {
+    if topology == Topology::Circular && runs.len() > 1 {
+        let [first, last] = [runs[0], runs[runs.len() - 1]];
+        if first[0] == 0 && last[1] == length - 1 {
+            runs.pop();
+            runs[0] = [last[0], first[1]];
+            runs.rotate_left(1);
+        }
+    }
+    runs
+}
+
+/// Align every read to the plasmid and collect its differences, one entry per
+/// read and difference before merging.
+struct ReadMapper<'a> {
+    index: ReferenceIndex,
+    sequence: &'a str,
+    settings: &'a VerificationSettings,
+    /// Read depth per plasmid base.
+    depth: Vec<usize>,
+    differences: Vec<ReadDifference>,
+}
+
+impl ReadMapper<'_> {
+    fn map(&mut self, read: &SequencingRead) -> ReadAlignment {
+        let [trim_start, trim_end] = quality_trim(read, self.settings.trim_error_limit);
+        let mut summary = ReadAlignment {
+            name: read.name.clone(),
+            mapped: false,
+            strand: None,
+            trimmed_span: [trim_start + 1, trim_end],
+            reference_span: None,
+            identity: 0.0,
+            score: 0,
+        };
+        if trim_end <= trim_start {
+            return summary;
+        }
+
+        let Some(trimmed) = read.sequence.get(trim_start..trim_end) else {
+            return summary;
+        };
+        let Some(hit) = self
+            .index
+            .align(trimmed, self.settings.band, &self.settings.scoring)
+        else {
+            return summary;
+        };
+        let alignment = &hit.alignment;
+        summary.strand = Some(hit.strand);
+        summary.identity = alignment.identity();
+        summary.score = alignment.score;
+        if alignment.identity() < self.settings.min_identity
+            || alignment.reference_length() < self.settings.min_aligned_length
+        {
+            return summary;
+        }
+        summary.mapped = true;
+
+        let length = self.index.len();
+        let reference_end = (alignment.reference_start + alignment.reference_length() - 1) % length;
+        summary.reference_span = Some([alignment.reference_start + 1, reference_end + 1]);
+
+        // Qualities of the aligned (possibly reverse complemented) query
+        let qualities: Option<Vec<u8>> = read.qualities.as_ref().map(|q| {
+            let trimmed = q[trim_start..trim_end].to_vec();
+            match hit.strand {
+                Direction::Fwd => trimmed,
+                Direction::Rev => trimmed.into_iter().rev().collect(),
+            }
+        });
+        let quality = |i: Option<usize>| qualities.as_ref().zip(i).map(|(q, i)| q[i]);
+
+        let columns = alignment.columns(length);
+        for column in &columns {
+            if column.op != AlignOp::Insertion {
+                self.depth[column.reference] += 1;
+            }
+        }
+
+        // Group consecutive columns of the same kind of difference
+        let mut i = 0;
+        while i < columns.len() {
+            let kind = match columns[i].op {
+                AlignOp::Match => {
+                    i += 1;
+                    continue;
+                }
+                AlignOp::Mismatch => DifferenceKind::Mismatch,
+                AlignOp::Insertion => DifferenceKind::Insertion,
+                AlignOp::Deletion => DifferenceKind::Deletion,
+            };
+            let group_end = columns[i..]
+                .iter()
+                .position(|c| c.op != columns[i].op)
+                .map_or(columns.len(), |p| i + p);
+            let group = &columns[i..group_end];
+
+            let observed: String = group
+                .iter()
+                .filter_map(|c| c.query.map(|q| hit.query[q] as char))
+                .collect();
+            let expected: String = match kind {
+                DifferenceKind::Insertion => String::new(),
+                _ => group
+                    .iter()
+                    .map(|c| self.sequence.as_bytes()[c.reference] as char)
+                    .collect(),
+            };
+            let span = match kind {
+                DifferenceKind::Insertion => {
+                    let next = group[0].reference;
+                    [(next + length - 1) % length + 1, next + 1]
+                }
+                _ => [group[0].reference + 1, group[group.len() - 1].reference + 1],
+            };
+            self.differences.push(ReadDifference {
+                kind,
+                span,
+                expected,
+                observed,
+                quality: group.iter().filter_map(|c| quality(c.query)).min(),
+                reads: vec![read.name.clone()],
+                confirmed: false,
+                features: Vec::new(),
+            });
+            i = group_end;
+        }
+
+        summary
+    }
+}
+
+/// Merge differences found by several reads at the same place.
+fn merge_differences(differences: Vec<ReadDifference>) -> Vec<ReadDifference> {
+    let mut merged: Vec<ReadDifference> = Vec::new();
+    for difference in differences {
+        match merged.iter_mut().find(|d| {
+            d.kind == difference.kind
+                && d.span == difference.span
+                && d.observed == difference.observed
+        }) {
+            Some(existing) => {
+                existing.reads.extend(difference.reads);
+                existing.quality = existing.quality.max(difference.quality);
+            }
+            None => merged.push(difference),
+        }
+    }
+    merged.sort_by_key(|d| d.span[0]);
+    merged
+}
+
+pub fn format_span(span: [usize; 2]) -> String {
+    if span[0] == span[1] {
+        span[0].to_string()
+    } else {
+        format!("{}..{}", span[0], span[1])
+    }
+}
+
+/// Plain text report of a verification result.
+pub fn report_text(plasmid_name: &str, result: &VerificationResult) -> String {
+    let mut lines = vec![
+        format!("Verification of \"{}\"", plasmid_name),
+        format!("Target: {}", format_span(result.target_span)),
+        format!("Verdict: {:?}", result.verdict),
+        String::new(),
+        "Reads:".to_string(),
+    ];
+    for read in &result.reads {
+        lines.push(match (read.mapped, read.reference_span, read.strand) {
+            (true, Some(span), Some(strand)) => format!(
+                "  {}: {} ({:?}), identity {:.1}%",
+                read.name,
+                format_span(span),
+                strand,
+                100.0 * read.identity
+            ),
+            _ => format!("  {}: not mapped", read.name),
+        });
+    }
+    if !result.differences.is_empty() {
+        lines.push(String::new());
+        lines.push("Differences:".to_string());
+        for d in &result.differences {
+            lines.push(format!(
+                "  {:?} at {}: {} -> {}{}{} [{}]{}",
+                d.kind,
+                format_span(d.span),
+                if d.expected.is_empty() {
+                    "-"
+                } else {
+                    &d.expected
+                },
+                if d.observed.is_empty() {
+                    "-"
+                } else {
+                    &d.observed
+                },
+                d.quality.map(|q| format!(", Q{}", q)).unwrap_or_default(),
+                if d.features.is_empty() {
+                    String::new()
+                } else {
+                    format!(", in {}", d.features.join(", "))
+                },
+                d.reads.join(", "),
+                if d.confirmed { "" } else { " unconfirmed" }
+            ));
+        }
+    }
+    if !result.unconfirmed.is_empty() {
+        lines.push(String::new());
+        lines.push("Unconfirmed regions:".to_string());
+        for region in &result.unconfirmed {
+            lines.push(format!(
+                "  {}{}",
+                format_span(region.span),
+                if region.features.is_empty() {
+                    String::new()
+                } else {
+                    format!(" ({})", region.features.join(", "))
+                }
+            ));
+        }
+    }
+    if !result.features.is_empty() {
+        lines.push(String::new());
+        lines.push("Features:".to_string());
+        for f in &result.features {
+            lines.push(format!(
+                "  {}: {:.0}% covered, {} difference(s)",
+                f.label,
+                100.0 * f.covered,
+                f.differences
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Map already loaded reads to the expected construct and judge it.
+pub fn verify_reads(
+    plasmid: &PlasmidFile,
+    reads: &[SequencingRead],
+    target: Option<&RegionSelection>,
+    settings: &VerificationSettings,
+) -> Result<VerificationResult, String> {
+    let sequence = sanitize_sequence(&plasmid.sequence);
+    if sequence.is_empty() {
+        return Err("The plasmid has no sequence.".to_string());
+    }
+    let length = sequence.len();
+    let target_span = match target {
+        Some(target) => plasmid.resolve_region(target)?,
+        None => [1, length],
+    };
+
+    let mut mapper = ReadMapper {
+        index: ReferenceIndex::new(&sequence, plasmid.topology, settings.kmer_length),
+        sequence: &sequence,
+        settings,
+        depth: vec![0; length],
+        differences: Vec::new(),
+    };
+    let read_alignments: Vec<ReadAlignment> = reads.iter().map(|r| mapper.map(r)).collect();
+
+    let in_target = |position: usize| span_contains(target_span, position);
+    let mut differences: Vec<ReadDifference> = merge_differences(mapper.differences)
+        .into_iter()
+        .filter(|d| in_target(d.span[0]) || in_target(d.span[1]))
+        .collect();
+    let depth = &mapper.depth;
+    for difference in &mut differences {
+        difference.features = features_at(&plasmid.features, difference.span);
+        // Reads covering both ends, insertions sit between them
+        let covering = depth[difference.span[0] - 1].min(depth[difference.span[1] - 1]);
+        let support = difference.reads.len();
+        difference.confirmed = 2 * support > covering
+            && (support >= settings.min_reads
+                || difference
+                    .quality
+                    .is_some_and(|q| q >= settings.min_quality));
+    }
+
+    let uncovered = runs(length, |i| in_target(i + 1) && depth[i] == 0);
+    let unconfirmed: Vec<UnconfirmedRegion> =
+        join_across_origin(uncovered, length, plasmid.topology)
+            .into_iter()
+            .map(|[start, end]| [start + 1, end + 1])
+            .map(|span| UnconfirmedRegion {
+                span,
+                features: features_at(&plasmid.features, span),
+            })
+            .collect();
+
+    let features = plasmid
+        .features
+        .iter()
+        .filter_map(|feature| {
+            let positions: Vec<usize> = (1..=length)
+                .filter(|p| span_contains(feature.span, *p) && in_target(*p))
+                .collect();
+            if positions.is_empty() {
+                return None;
+            }
+            let covered = positions.iter().filter(|p| depth[**p - 1] > 0).count();
+            Some(FeatureVerdict {
+                label: feature.label.clone(),
+                span: feature.span,
+                covered: covered as f64 / positions.len() as f64,
+                differences: differences
+                    .iter()
+                    .filter(|d| d.features.contains(&feature.label))
+                    .count(),
+            })
+        })
+        .collect();
+
+    let verdict = if read_alignments.iter().all(|r| !r.mapped) {
+        Verdict::Failed
+    } else if differences.iter().any(|d| d.confirmed) {
+        Verdict::Mismatch
+    } else if !unconfirmed.is_empty() || !differences.is_empty() {
+        Verdict::Incomplete
+    } else {
+        Verdict::Confirmed
+    };
+
+    let mut result = VerificationResult {
+        verdict,
+        target_span,
+        reads: read_alignments,
+        differences,
+        unconfirmed,
+        features,
+        report: String::new(),
+    };
+    result.report = report_text(&plasmid.name, &result);
+    Ok(result)
+}
+
+/// Load Sanger reads from disk and verify the expected construct against them.
+pub fn verify_construct(request: &VerificationRequest) -> Result<VerificationResult, String> {
+    let mut reads = Vec::new();
+    for path in &request.read_paths {
+        reads.extend(read_sequencing_reads(path)?);
+    }
+    if reads.is_empty() {
+        return Err("No reads to verify against.".to_string());
+    }
+    verify_reads(
+        &request.plasmid,
+        &reads,
+        request.target.as_ref(),
+        &request.settings,
+    )
+}
+
+#[tauri::command]
+/// Align Sanger reads to the open plasmid and report whether they confirm it.
+pub fn verify_sanger_reads(request: VerificationRequest) -> Result<VerificationResult, String> {
+    verify_construct(&request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequence::reverse_complementary;
+    use crate::sequence::tests::RANDOM_PLASMID;
+
+    fn plasmid() -> PlasmidFile {
+        PlasmidFile {
+            name: "p".to_string(),
+            sequence: RANDOM_PLASMID.concat(),
+            topology: Topology::Circular,
+            features: Vec::new(),
+        }
+    }
+
+    /// Read of plasmid bases `from..to` with base 151 changed.
+    fn read(name: &str, from: usize, to: usize, quality: Option<u8>) -> SequencingRead {
+        let mut sequence = plasmid().sequence[from..to].to_string();
+        let changed = if &sequence[150 - from..151 - from] == "A" {
+            "C"
+        } else {
+            "A"
+        };
+        sequence.replace_range(150 - from..151 - from, changed);
+        SequencingRead {
+            name: name.to_string(),
+            qualities: quality.map(|q| vec![q; sequence.len()]),
+            sequence,
+        }
+    }
+
+    fn unchanged(name: &str, from: usize, to: usize) -> SequencingRead {
+        SequencingRead {
+            name: name.to_string(),
+            sequence: plasmid().sequence[from..to].to_string(),
+            qualities: None,
+        }
+    }
+
+    fn verdict(reads: &[SequencingRead]) -> (Verdict, Vec<bool>) {
+        let target = RegionSelection::Span([60, 240]);
+        let result = verify_reads(
+            &plasmid(),
+            reads,
+            Some(&target),
+            &VerificationSettings::default(),
+        )
+        .unwrap();
+        let confirmed = result.differences.iter().map(|d| d.confirmed).collect();
+        (result.verdict, confirmed)
+    }
+
+    #[test]
+    fn mismatches_need_several_reads_or_quality() {
+        // One read without qualities is not enough
+        assert_eq!(
+            verdict(&[read("a", 50, 250, None)]),
+            (Verdict::Incomplete, vec![false])
+        );
+        assert_eq!(
+            verdict(&[read("a", 50, 250, Some(15))]),
+            (Verdict::Incomplete, vec![false])
+        );
+        assert_eq!(
+            verdict(&[read("a", 50, 250, Some(40))]),
+            (Verdict::Mismatch, vec![true])
+        );
+
+        let mut reverse = read("b", 40, 260, None);
+        reverse.sequence = reverse_complementary(&reverse.sequence);
+        assert_eq!(
+            verdict(&[read("a", 50, 250, None), reverse]),
+            (Verdict::Mismatch, vec![true])
+        );
+
+        // Most reads covering the base have to agree
+        assert_eq!(
+            verdict(&[
+                read("a", 50, 250, Some(40)),
+                unchanged("b", 40, 260),
+                unchanged("c", 30, 230),
+            ]),
+            (Verdict::Incomplete, vec![false])
+        );
+        assert_eq!(
+            verdict(&[unchanged("b", 40, 260)]),
+            (Verdict::Confirmed, vec![])
+        );
+    }
+
+    #[test]
+    fn unconfirmed_regions_join_across_the_origin() {
+        let result = verify_reads(
+            &plasmid(),
+            &[unchanged("a", 50, 200), unchanged("b", 190, 350)],
+            None,
+            &VerificationSettings::default(),
+        )
+        .unwrap();
+        let spans: Vec<[usize; 2]> = result.unconfirmed.iter().map(|r| r.span).collect();
+        assert_eq!(spans, [[351, 50]]);
+        assert_eq!(result.verdict, Verdict::Incomplete);
+
+        let linear = join_across_origin(vec![[0, 9], [350, 399]], 400, Topology::Linear);
+        assert_eq!(linear, [[0, 9], [350, 399]]);
+        let circular =
+            join_across_origin(vec![[0, 9], [20, 30], [350, 399]], 400, Topology::Circular);
+        assert_eq!(circular, [[20, 30], [350, 9]]);
+    }
+}